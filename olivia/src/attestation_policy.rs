@@ -0,0 +1,127 @@
+//! When the oracle is willing to attest to an event relative to its `expected_outcome_time`.
+use olivia_core::{
+    chrono::{Duration, NaiveDateTime},
+    Event, Path,
+};
+use std::collections::HashMap;
+
+/// How long after an event's `expected_outcome_time` it may be attested to.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AttestationWindow {
+    /// Seconds after the expected outcome time before the event can be attested to.
+    #[serde(default)]
+    pub min_delay: u32,
+    /// Seconds after the expected outcome time after which attesting is reported as late.
+    pub max_lateness: Option<u32>,
+}
+
+/// The [`AttestationWindow`] for each event. Events use the window of the most specific entry in
+/// `paths` they are under or else the default one.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct AttestationPolicy {
+    #[serde(flatten)]
+    pub default: AttestationWindow,
+    #[serde(default)]
+    pub paths: HashMap<Path, AttestationWindow>,
+}
+
+/// Where attesting to an event at some time falls in its window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    OnTime,
+    TooEarly { earliest: NaiveDateTime },
+    Late { by: Duration },
+}
+
+impl AttestationPolicy {
+    pub fn window_for(&self, event: &Event) -> &AttestationWindow {
+        let path = event.id.path();
+        self.paths
+            .iter()
+            .filter(|(prefix, _)| prefix.as_path_ref().is_parent_of(path))
+            .max_by_key(|(prefix, _)| prefix.as_str().len())
+            .map(|(_, window)| window)
+            .unwrap_or(&self.default)
+    }
+
+    /// Events without an expected outcome time can be attested to whenever.
+    pub fn timing(&self, event: &Event, now: NaiveDateTime) -> Timing {
+        let expected = match event.expected_outcome_time {
+            Some(expected) => expected,
+            None => return Timing::OnTime,
+        };
+        let window = self.window_for(event);
+        let earliest = expected + Duration::seconds(window.min_delay.into());
+        if now < earliest {
+            return Timing::TooEarly { earliest };
+        }
+        match window.max_lateness {
+            Some(max_lateness) if now - expected > Duration::seconds(max_lateness.into()) => {
+                Timing::Late { by: now - expected }
+            }
+            _ => Timing::OnTime,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use olivia_core::EventId;
+    use std::str::FromStr;
+
+    #[test]
+    fn most_specific_window_applies() {
+        let policy: AttestationPolicy = serde_yaml::from_str(
+            r#"
+min-delay: 60
+max-lateness: 3600
+paths:
+  "/x": { min-delay: 0 }
+  "/x/BitMEX": { min-delay: 10, max-lateness: 120 }
+"#,
+        )
+        .unwrap();
+        let expected = NaiveDateTime::from_str("2021-10-04T00:00:00").unwrap();
+        let event = |id: &str| Event {
+            id: EventId::from_str(id).unwrap(),
+            expected_outcome_time: Some(expected),
+        };
+        let at = |seconds: i64| expected + Duration::seconds(seconds);
+
+        let random = event("/random/2021-10-04T00:00:00.occur");
+        assert_eq!(
+            policy.timing(&random, at(59)),
+            Timing::TooEarly { earliest: at(60) }
+        );
+        assert_eq!(policy.timing(&random, at(60)), Timing::OnTime);
+        assert_eq!(
+            policy.timing(&random, at(3601)),
+            Timing::Late {
+                by: Duration::seconds(3601)
+            }
+        );
+
+        let other = event("/x/other/2021-10-04T00:00:00.occur");
+        assert_eq!(policy.timing(&other, at(0)), Timing::OnTime);
+        assert_eq!(policy.timing(&other, at(100_000)), Timing::OnTime);
+
+        let bitmex = event("/x/BitMEX/BXBT/2021-10-04T00:00:00.price?n=20");
+        assert!(matches!(
+            policy.timing(&bitmex, at(9)),
+            Timing::TooEarly { .. }
+        ));
+        assert!(matches!(
+            policy.timing(&bitmex, at(121)),
+            Timing::Late { .. }
+        ));
+
+        let untimed = Event {
+            expected_outcome_time: None,
+            ..random
+        };
+        assert_eq!(policy.timing(&untimed, at(-100_000)), Timing::OnTime);
+    }
+}
@@ -12,6 +12,9 @@ extern crate tokio;
 struct Opt {
     #[structopt(short, long, parse(from_os_str), name = "yaml config file")]
     config: PathBuf,
+    /// Allow the publicly known secret-seed from the sample configs
+    #[structopt(long)]
+    allow_sample_seed: bool,
     #[structopt(subcommand)]
     cmd: Command,
 }
@@ -21,12 +24,20 @@ struct Opt {
 pub enum Command {
     Add(cli::add::Entity),
     Run,
+    /// Run the daemon that holds the oracle's keys and signs for it
+    Signer,
+    /// Hold a share of the oracle's keys for signing with a threshold of peers
+    ThresholdPeer(cli::threshold_peer::Peer),
     CheckConfig,
     Derive {
         event: String,
     },
     /// Database commands
     Db(Db),
+    /// Manage the oracle's secret seed
+    Keys(cli::keys::Keys),
+    /// Events whose outcome sources couldn't agree
+    Review(cli::review::Review),
 }
 
 #[derive(Debug, StructOpt)]
@@ -37,7 +48,7 @@ pub enum Db {
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let opt = Opt::from_args();
-    let config: Config = {
+    let mut config: Config = {
         use std::{fs::File, io::Read};
         let file_name = opt.config.to_str().unwrap_or("config file").to_owned();
         let mut file = File::open(opt.config)?;
@@ -47,13 +58,28 @@ async fn main() -> anyhow::Result<()> {
             .context(format!("{} is an invalid configuration file", file_name))?
     };
 
+    if let Command::Add(_)
+    | Command::Run
+    | Command::Signer
+    | Command::ThresholdPeer(_)
+    | Command::Derive { .. }
+    | Command::Keys(cli::keys::Keys::Rotate { .. })
+    | Command::Review(cli::review::Review::Resolve { .. }) = &opt.cmd
+    {
+        config.load_secret_seed(opt.allow_sample_seed)?;
+    }
+
     match opt.cmd {
         Command::Add(entity) => cli::add::add(config, entity).await,
         Command::Run => cli::run::run(config).await,
+        Command::Signer => cli::signer::signer(config).await,
+        Command::ThresholdPeer(peer) => cli::threshold_peer::threshold_peer(config, peer).await,
         Command::Derive { event } => cli::derive::derive(config, EventId::from_str(&event)?),
         Command::Db(db) => match db {
             Db::Init => cli::db_cmd::init(config).await,
         },
+        Command::Keys(keys) => cli::keys::keys(config, keys).await,
+        Command::Review(review) => cli::review::review(config, review).await,
         Command::CheckConfig => Ok(()),
     }
 }
@@ -1,7 +1,11 @@
-use crate::{config::Config, Oracle};
+use crate::{
+    config::{Config, GroupConfig},
+    oracle::Attested,
+    Oracle,
+};
 use olivia_core::{
     chrono::{self, NaiveDateTime},
-    Event, EventId, Outcome, StampedOutcome,
+    BatchSchedule, Event, EventId, EventKind, Group, Outcome, Path, StampedOutcome,
 };
 
 #[derive(Debug, structopt::StructOpt)]
@@ -14,15 +18,39 @@ pub enum Entity {
     Outcome {
         event_id: EventId,
         outcome: String,
+        /// Attest even if the attestation-policy says it's too early
+        #[structopt(long)]
+        force: bool,
+    },
+    /// Cancel the event by attesting that it is void
+    Void { event_id: EventId },
+    /// Commit to the nonces of events that will be created under a path
+    NonceBatch {
+        path: Path,
+        event_kind: EventKind,
+        start: NaiveDateTime,
+        /// seconds between each event
+        interval: u32,
+        /// how many events (at most 10080)
+        n_slots: u32,
     },
 }
 
 pub async fn add(config: Config, entity: Entity) -> anyhow::Result<()> {
-    let secret_seed = config.secret_seed.ok_or(anyhow::anyhow!(
+    match config.group {
+        GroupConfig::Secp256k1 => add_group::<olivia_secp256k1::Secp256k1>(config, entity).await,
+        GroupConfig::Ristretto => add_group::<olivia_ristretto::Ristretto>(config, entity).await,
+    }
+}
+
+async fn add_group<C: Group>(config: Config, entity: Entity) -> anyhow::Result<()> {
+    let signer = config.build_signer::<C>().await?.ok_or(anyhow::anyhow!(
         "Cannot use the add command when oracle is in read-only mode"
     ))?;
-    let db = config.database.connect_database().await?;
-    let oracle = Oracle::new(secret_seed, db.clone()).await?;
+    let db = config.database.connect_database::<C>().await?;
+    let oracle = Oracle::<C>::with_signer(signer, db.clone())
+        .await?
+        .with_policy(config.attestation_policy.clone());
 
     match entity {
         Entity::Event {
@@ -36,15 +64,49 @@ pub async fn add(config: Config, entity: Entity) -> anyhow::Result<()> {
                 })
                 .await?
         }
-        Entity::Outcome { event_id, outcome } => {
-            let outcome = Outcome::try_from_id_and_outcome(event_id, &outcome)?;
+        Entity::Outcome {
+            event_id,
+            outcome,
+            force,
+        } => {
+            let stamped = StampedOutcome {
+                time: chrono::Utc::now().naive_utc(),
+                outcome: Outcome::try_from_id_and_outcome(event_id, &outcome)?,
+            };
+            let attested = match force {
+                true => oracle.force_complete_event(stamped).await?,
+                false => oracle.complete_event(stamped).await?,
+            };
+            if let Attested::Late { by } = attested {
+                eprintln!(
+                    "warning: attested {} seconds after the expected outcome time",
+                    by.num_seconds()
+                );
+            }
+        }
+        Entity::Void { event_id } => {
             oracle
-                .complete_event(StampedOutcome {
-                    time: chrono::Utc::now().naive_utc(),
-                    outcome,
-                })
+                .cancel_event(&event_id, chrono::Utc::now().naive_utc())
                 .await?;
         }
+        Entity::NonceBatch {
+            path,
+            event_kind,
+            start,
+            interval,
+            n_slots,
+        } => {
+            oracle
+                .add_nonce_batch(BatchSchedule {
+                    path,
+                    event_kind,
+                    start,
+                    interval,
+                    n_slots,
+                    epoch: oracle.public_keys().epoch,
+                })
+                .await?
+        }
     }
 
     Ok(())
@@ -1,13 +1,21 @@
-use crate::{config::Config, keychain::KeyChain};
-use olivia_core::EventId;
+use crate::config::{Config, GroupConfig};
+use olivia_core::{EventId, Group};
 
 pub fn derive(config: Config, event: EventId) -> anyhow::Result<()> {
+    match config.group {
+        GroupConfig::Secp256k1 => derive_group::<olivia_secp256k1::Secp256k1>(config, event),
+        GroupConfig::Ristretto => derive_group::<olivia_ristretto::Ristretto>(config, event),
+    }
+}
+
+fn derive_group<C: Group>(config: Config, event: EventId) -> anyhow::Result<()> {
     let secret_seed = config
         .secret_seed
+        .clone()
         .ok_or(anyhow::anyhow!("config file needs secret_seed to run"))?;
-    let keychain = KeyChain::<olivia_secp256k1::Secp256k1>::new(secret_seed);
-    let nonce = keychain.nonces_for_event(&event);
+    let keychain = config.keychain::<C>(secret_seed);
+    let nonce = keychain.for_path(event.path()).nonces_for_event(&event);
 
-    println!("secp256k1: {:?}", nonce);
+    println!("{}: {:?}", C::name(), nonce);
     Ok(())
 }
@@ -0,0 +1,133 @@
+use crate::{
+    config::{Config, GroupConfig},
+    keyfile::{self, KeyMaterial, Keyfile},
+    Oracle,
+};
+use olivia_core::{chrono::Utc, Group, Successor};
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+#[derive(Debug, structopt::StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub enum Keys {
+    /// Create a keyfile holding a new seed encrypted with a passphrase
+    Init {
+        #[structopt(parse(from_os_str))]
+        keyfile: PathBuf,
+        /// Use an existing BIP39 mnemonic rather than creating a new one
+        #[structopt(long)]
+        from_mnemonic: bool,
+    },
+    /// Print the BIP39 mnemonic of the configured secret-seed
+    ExportMnemonic,
+    /// Hand the oracle over from the configured secret-seed to the seed in a new keyfile
+    Rotate {
+        #[structopt(parse(from_os_str))]
+        new_keyfile: PathBuf,
+        /// Rotate even though there are events and nonce batches that the old keys are still needed for
+        #[structopt(long)]
+        force: bool,
+    },
+    /// Print the public keys of the seed in a new keyfile. Signers given them as their successor
+    /// can hand the oracle over to it
+    Successor {
+        #[structopt(parse(from_os_str))]
+        new_keyfile: PathBuf,
+    },
+}
+
+pub async fn keys(config: Config, keys: Keys) -> anyhow::Result<()> {
+    match keys {
+        Keys::Init {
+            keyfile,
+            from_mnemonic,
+        } => {
+            let material = match from_mnemonic {
+                true => KeyMaterial::from_str(&rpassword::prompt_password("mnemonic: ")?)?,
+                false => KeyMaterial::generate(),
+            };
+            let passphrase = match std::env::var(keyfile::PASSPHRASE_ENV) {
+                Ok(passphrase) => passphrase,
+                Err(_) => {
+                    let passphrase = rpassword::prompt_password("new passphrase: ")?;
+                    if passphrase != rpassword::prompt_password("repeat passphrase: ")? {
+                        return Err(anyhow::anyhow!("passphrases didn't match"));
+                    }
+                    passphrase
+                }
+            };
+            Keyfile::encrypt(&material, &passphrase)?.write(&keyfile)?;
+            println!(
+                "wrote keyfile to {}. Use it by putting this in the config:\n\nsecret-seed:\n  keyfile: {:?}",
+                keyfile.display(),
+                keyfile
+            );
+            if !from_mnemonic {
+                println!("\nback up the seed with `olivia keys export-mnemonic`");
+            }
+            Ok(())
+        }
+        Keys::ExportMnemonic => {
+            let seed_source = config
+                .seed_source
+                .ok_or(anyhow::anyhow!("config file has no secret-seed"))?;
+            match seed_source.load()? {
+                KeyMaterial::Mnemonic(mnemonic) => {
+                    println!("{}", mnemonic);
+                    Ok(())
+                }
+                KeyMaterial::Seed(_) => Err(anyhow::anyhow!(
+                    "the secret-seed wasn't created from a mnemonic so it can't be exported as one"
+                )),
+            }
+        }
+        Keys::Rotate { new_keyfile, force } => match config.group {
+            GroupConfig::Secp256k1 => {
+                rotate::<olivia_secp256k1::Secp256k1>(config, new_keyfile, force).await
+            }
+            GroupConfig::Ristretto => {
+                rotate::<olivia_ristretto::Ristretto>(config, new_keyfile, force).await
+            }
+        },
+        Keys::Successor { new_keyfile } => match config.group {
+            GroupConfig::Secp256k1 => successor::<olivia_secp256k1::Secp256k1>(config, new_keyfile),
+            GroupConfig::Ristretto => successor::<olivia_ristretto::Ristretto>(config, new_keyfile),
+        },
+    }
+}
+
+fn successor<C: Group>(config: Config, new_keyfile: PathBuf) -> anyhow::Result<()> {
+    let seed = Keyfile::read(&new_keyfile)?
+        .decrypt(&keyfile::passphrase("new keyfile passphrase: ")?)?
+        .seed();
+    let keychain = config.keychain::<C>(seed);
+    let successor = Successor {
+        keys: keychain.oracle_public_keys(),
+        namespaces: keychain.namespace_public_keys(),
+    };
+    println!("{}", serde_json::to_string_pretty(&successor)?);
+    Ok(())
+}
+
+async fn rotate<C: Group>(config: Config, new_keyfile: PathBuf, force: bool) -> anyhow::Result<()> {
+    let signer = config.build_signer::<C>().await?.ok_or(anyhow::anyhow!(
+        "config has no secret-seed or signer to rotate from"
+    ))?;
+    let successor = Keyfile::read(&new_keyfile)?
+        .decrypt(&keyfile::passphrase("new keyfile passphrase: ")?)?
+        .seed();
+    let db = config.database.connect_database::<C>().await?;
+    let oracle = Oracle::<C>::with_signer(signer, db).await?;
+    let rotated = oracle
+        .rotate_keys(
+            Arc::new(config.keychain::<C>(successor)),
+            Utc::now().naive_utc(),
+            force,
+        )
+        .await?;
+    println!(
+        "the oracle is now on epoch {}. Point the config's secret-seed at {} before running it again",
+        rotated.public_keys().epoch,
+        new_keyfile.display()
+    );
+    Ok(())
+}
@@ -1,4 +1,8 @@
 pub mod add;
 pub mod db_cmd;
 pub mod derive;
+pub mod keys;
+pub mod review;
 pub mod run;
+pub mod signer;
+pub mod threshold_peer;
@@ -0,0 +1,86 @@
+use crate::{
+    config::{Config, GroupConfig},
+    oracle::Attested,
+    Oracle,
+};
+use olivia_core::{chrono, EventId, Group, Outcome, StampedOutcome};
+
+/// Events whose outcome sources couldn't agree on an outcome
+#[derive(Debug, structopt::StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub enum Review {
+    /// List the events waiting for review along with what each source proposed
+    List,
+    /// Attest to the event with an outcome and remove it from review
+    Resolve {
+        event_id: EventId,
+        outcome: String,
+        /// Attest even if the attestation-policy says it's too early
+        #[structopt(long)]
+        force: bool,
+    },
+    /// Remove the event from review without attesting so its sources can propose outcomes again
+    Dismiss { event_id: EventId },
+}
+
+pub async fn review(config: Config, review: Review) -> anyhow::Result<()> {
+    match config.group {
+        GroupConfig::Secp256k1 => review_group::<olivia_secp256k1::Secp256k1>(config, review).await,
+        GroupConfig::Ristretto => review_group::<olivia_ristretto::Ristretto>(config, review).await,
+    }
+}
+
+async fn review_group<C: Group>(config: Config, review: Review) -> anyhow::Result<()> {
+    let db = config.database.connect_database::<C>().await?;
+
+    match review {
+        Review::List => {
+            for review in db.get_outcome_reviews().await? {
+                println!("{}", review.event_id);
+                for proposal in review.proposals {
+                    println!(
+                        "  source {} ({}): {} at {}",
+                        proposal.source, review.path, proposal.outcome, proposal.time
+                    );
+                }
+            }
+        }
+        Review::Resolve {
+            event_id,
+            outcome,
+            force,
+        } => {
+            if db.get_outcome_review(&event_id).await?.is_none() {
+                return Err(anyhow::anyhow!("{} isn't waiting for review", event_id));
+            }
+            let signer = config.build_signer::<C>().await?.ok_or(anyhow::anyhow!(
+                "Cannot resolve reviews when oracle is in read-only mode"
+            ))?;
+            let oracle = Oracle::<C>::with_signer(signer, db.clone())
+                .await?
+                .with_policy(config.attestation_policy.clone());
+            let stamped = StampedOutcome {
+                time: chrono::Utc::now().naive_utc(),
+                outcome: Outcome::try_from_id_and_outcome(event_id.clone(), &outcome)?,
+            };
+            let attested = match force {
+                true => oracle.force_complete_event(stamped).await?,
+                false => oracle.complete_event(stamped).await?,
+            };
+            if let Attested::Late { by } = attested {
+                eprintln!(
+                    "warning: attested {} seconds after the expected outcome time",
+                    by.num_seconds()
+                );
+            }
+            db.remove_outcome_review(&event_id).await?;
+        }
+        Review::Dismiss { event_id } => {
+            if !db.remove_outcome_review(&event_id).await? {
+                return Err(anyhow::anyhow!("{} isn't waiting for review", event_id));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,19 +1,41 @@
-use crate::{config::Config, oracle::Oracle, oracle_loop::OracleLoop};
+use crate::{
+    config::{Config, GroupConfig},
+    oracle::Oracle,
+    oracle_loop::OracleLoop,
+    sources,
+    write_api::WriteApi,
+};
 use core::{
     future::{self, Future},
     pin::Pin,
 };
+use olivia_core::Group;
 
 pub async fn run(config: Config) -> anyhow::Result<()> {
+    match config.group {
+        GroupConfig::Secp256k1 => run_group::<olivia_secp256k1::Secp256k1>(config).await,
+        GroupConfig::Ristretto => run_group::<olivia_ristretto::Ristretto>(config).await,
+    }
+}
+
+async fn run_group<C: Group>(config: Config) -> anyhow::Result<()> {
     let logger = slog::Logger::root(config.loggers.to_slog_drain()?, o!());
-    let db = config.database.connect_database().await?;
+    info!(logger, "using {} group", C::name());
+    let db = config.database.connect_database::<C>().await?;
+    let (write_sender, writes) = sources::http::channel();
+    let write_api = config
+        .rest_api
+        .as_ref()
+        .filter(|rest_config| !rest_config.write_credentials.is_empty())
+        .map(|rest_config| WriteApi::new(rest_config.write_credentials.clone(), write_sender));
 
     let rest_server: Pin<Box<dyn Future<Output = _>>> = match &config.rest_api {
         Some(rest_config) => {
             let logger = logger.new(o!("type" => "http"));
             info!(logger, "starting http server on {}", rest_config.listen);
             let rest_api_server = warp::serve(crate::rest_api::routes(
-                config.database.connect_database_read_group().await?,
+                config.database.connect_database_read_group::<C>().await?,
+                write_api,
                 logger.clone(),
             ))
             .run(rest_config.listen);
@@ -26,25 +48,31 @@ pub async fn run(config: Config) -> anyhow::Result<()> {
         None => Box::pin(future::ready(Ok(()))),
     };
 
-    let oracle_loop: Pin<Box<dyn Future<Output = _>>> = match &config.secret_seed {
-        Some(secret_seed) => {
-            let read_conn = config.database.connect_database_read().await?;
+    let oracle_loop: Pin<Box<dyn Future<Output = _>>> = match config.build_signer::<C>().await? {
+        Some(signer) => {
+            let read_conn = config.database.connect_database_read::<C>().await?;
             let events = config.build_event_streams(read_conn.clone(), logger.clone())?;
-            let outcomes = config.build_outcome_streams(
-                read_conn,
-                &secret_seed.child(b"outcome-seed"),
-                logger.clone(),
-            )?;
+            let outcome_seed = config
+                .secret_seed
+                .as_ref()
+                .map(|secret_seed| secret_seed.child(b"outcome-seed"));
+            let outcomes =
+                config.build_outcome_streams(read_conn, outcome_seed.as_ref(), logger.clone())?;
 
             let nodes = config.build_node_streams(logger.clone())?;
+            let quorums = config.build_outcome_quorums()?;
 
-            let oracle = Oracle::new(secret_seed.clone(), db.clone()).await?;
+            let oracle = Oracle::with_signer(signer, db.clone())
+                .await?
+                .with_policy(config.attestation_policy.clone());
 
             Box::pin(tokio::spawn(
                 OracleLoop {
                     events,
                     outcomes,
                     nodes,
+                    writes,
+                    quorums,
                     oracle,
                     db,
                     logger: logger.clone(),
@@ -0,0 +1,45 @@
+use crate::{
+    config::{read_successor, Config, GroupConfig},
+    remote_signer::SignerDaemon,
+};
+use olivia_core::Group;
+use std::sync::Arc;
+use tokio::net::UnixListener;
+
+pub async fn signer(config: Config) -> anyhow::Result<()> {
+    match config.group {
+        GroupConfig::Secp256k1 => signer_group::<olivia_secp256k1::Secp256k1>(config).await,
+        GroupConfig::Ristretto => signer_group::<olivia_ristretto::Ristretto>(config).await,
+    }
+}
+
+async fn signer_group<C: Group>(config: Config) -> anyhow::Result<()> {
+    let logger = slog::Logger::root(config.loggers.to_slog_drain()?, o!("type" => "signer"));
+    let secret_seed = config.secret_seed.clone().ok_or(anyhow::anyhow!(
+        "config file needs secret_seed to run the signer"
+    ))?;
+    let keychain = config.keychain(secret_seed);
+    let signer_config = config.signer.ok_or(anyhow::anyhow!(
+        "config file needs a signer section to run the signer"
+    ))?;
+    let state_file = signer_config.state_file.ok_or(anyhow::anyhow!(
+        "the signer section needs a state-file to run the signer"
+    ))?;
+    let mut daemon = SignerDaemon::<C>::new(keychain, &state_file, logger.clone())?;
+    if let Some(successor) = &signer_config.successor {
+        daemon = daemon.with_successor(read_successor(successor)?);
+    }
+
+    // a socket left behind by a previous run would stop us from binding
+    if signer_config.socket.exists() {
+        std::fs::remove_file(&signer_config.socket)?;
+    }
+    let listener = UnixListener::bind(&signer_config.socket)?;
+    info!(
+        logger,
+        "{} signer listening on {}",
+        C::name(),
+        signer_config.socket.display()
+    );
+    Arc::new(daemon).serve(listener).await
+}
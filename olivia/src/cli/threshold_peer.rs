@@ -0,0 +1,73 @@
+use crate::{
+    config::{read_successor, Config, GroupConfig},
+    threshold::ThresholdPeer,
+    threshold_peer::PeerDaemon,
+};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+#[derive(Debug, structopt::StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub enum Peer {
+    /// Run the peer so the oracle can sign with its share of the keys
+    Run,
+    /// Print the identity key of the configured secret-seed to put in every config's peers
+    Identity,
+}
+
+pub async fn threshold_peer(config: Config, peer: Peer) -> anyhow::Result<()> {
+    if config.group != GroupConfig::Secp256k1 {
+        return Err(anyhow::anyhow!(
+            "threshold signing only works with the secp256k1 group"
+        ));
+    }
+    let secret_seed = config.secret_seed.clone().ok_or(anyhow::anyhow!(
+        "config file needs secret_seed to run a threshold peer"
+    ))?;
+    match peer {
+        Peer::Identity => {
+            println!("{}", ThresholdPeer::identity_key(&secret_seed));
+            Ok(())
+        }
+        Peer::Run => {
+            let logger = slog::Logger::root(
+                config.loggers.to_slog_drain()?,
+                o!("type" => "threshold-peer"),
+            );
+            let threshold = config.threshold.ok_or(anyhow::anyhow!(
+                "config file needs a threshold section to run a threshold peer"
+            ))?;
+            let params = threshold.params()?;
+            let (index, listen, state_file) =
+                match (threshold.index, threshold.listen, threshold.state_file) {
+                    (Some(index), Some(listen), Some(state_file)) => (index, listen, state_file),
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "the threshold section needs an index, listen and state-file to run a threshold peer"
+                        ))
+                    }
+                };
+            let auth_key = index
+                .checked_sub(1)
+                .and_then(|i| threshold.peers.get(i as usize))
+                .ok_or(anyhow::anyhow!(
+                    "index must be between 1 and the number of peers"
+                ))?
+                .auth_key
+                .clone();
+            let identities = threshold.peers.iter().map(|peer| peer.identity).collect();
+            let mut peer = ThresholdPeer::new(index, secret_seed, params, identities, &state_file)?;
+            if let Some(successor) = &threshold.successor {
+                peer = peer.with_successor(read_successor(successor)?);
+            }
+            let daemon = PeerDaemon::new(peer, auth_key, logger.clone());
+
+            let listener = TcpListener::bind(listen).await?;
+            info!(
+                logger,
+                "threshold peer {} of {} listening on {}", index, params.n_peers, listen
+            );
+            Arc::new(daemon).serve(listener).await
+        }
+    }
+}
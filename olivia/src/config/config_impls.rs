@@ -1,12 +1,22 @@
 use super::*;
 use crate::{
     db::{self, postgres::PgBackendWrite, DbReadEvent, PrefixedDb},
+    keychain::{KeyChain, Signer},
+    keyfile::{self, KeyMaterial, Keyfile, SAMPLE_SEED},
+    quorum::OutcomeQuorums,
+    remote_signer::RemoteSigner,
     sources::{
         self,
-        ticker::{RandomOutcomeCreator, ZeroOutcomeCreator},
+        calendar::Calendar,
+        ticker::{RandomOutcomeCreator, Schedule, SystemClock, ZeroOutcomeCreator},
     },
+    threshold::{PeerClient, ThresholdParams, ThresholdSigner},
+    threshold_peer::RemotePeer,
+};
+use core::any::{Any, TypeId};
+use olivia_core::{
+    chrono, Event, EventKind, Group, Node, NodeKind, Path, StampedOutcome, Successor,
 };
-use olivia_core::{chrono, Event, Node, NodeKind, Path, RangeKind, StampedOutcome};
 use sources::{ticker::TimeOutcomeStream, Update};
 use std::{fs, sync::Arc};
 use tokio_stream as stream;
@@ -35,7 +45,7 @@ impl Config {
     pub fn build_outcome_streams(
         &self,
         db: Arc<dyn DbReadEvent>,
-        secret_seed: &Seed,
+        secret_seed: Option<&Seed>,
         logger: slog::Logger,
     ) -> anyhow::Result<StreamMap<(Path, usize), sources::Stream<StampedOutcome>>> {
         let mut streams = StreamMap::new();
@@ -45,7 +55,7 @@ impl Config {
             let logger = logger.new(o!("path" => parent.to_string()));
             for (i, source) in sources.into_iter().enumerate() {
                 let stream = source.to_outcome_stream(
-                    secret_seed.child(parent.as_str().as_bytes()),
+                    secret_seed.map(|seed| seed.child(parent.as_str().as_bytes())),
                     logger.clone(),
                     db.clone(),
                 )?;
@@ -56,6 +66,61 @@ impl Config {
         Ok(streams)
     }
 
+    pub fn build_outcome_quorums(&self) -> anyhow::Result<OutcomeQuorums> {
+        let mut paths = HashMap::new();
+        for (path, &required) in &self.outcome_quorums {
+            let n_sources = self.outcomes.get(path).map_or(0, Vec::len);
+            if required == 0 || required > n_sources {
+                return Err(anyhow::anyhow!(
+                    "the outcome quorum for {} must be between 1 and the number of outcome sources it has ({}) not {}",
+                    path,
+                    n_sources,
+                    required
+                ));
+            }
+            paths.insert(path.clone(), (required, n_sources));
+        }
+        Ok(OutcomeQuorums::new(paths)
+            .with_timeout(std::time::Duration::from_secs(self.outcome_quorum_timeout)))
+    }
+
+    /// Loads `secret_seed` from `seed_source`. This may prompt for a passphrase. The seed from the
+    /// sample configs is refused unless `allow_sample_seed` is set.
+    pub fn load_secret_seed(&mut self, allow_sample_seed: bool) -> anyhow::Result<()> {
+        if let Some(seed_source) = &self.seed_source {
+            let seed = seed_source.load()?.seed();
+            if seed.as_ref() == SAMPLE_SEED.as_ref() && !allow_sample_seed {
+                return Err(anyhow::anyhow!(
+                    "refusing to use the publicly known seed from the sample configs. Create a keyfile with `olivia keys init` or pass --allow-sample-seed"
+                ));
+            }
+            self.secret_seed = Some(seed);
+        }
+        Ok(())
+    }
+
+    /// The keys derived from `seed` along with the keys of each of the configured `namespaces`.
+    pub fn keychain<C: Group>(&self, seed: Seed) -> KeyChain<C> {
+        KeyChain::new(seed).with_namespaces(self.namespaces.iter().cloned())
+    }
+
+    /// The signer the oracle signs with. This is the `olivia signer` daemon or the threshold peers
+    /// if either is configured and otherwise the keys derived from `secret-seed`. Without any of
+    /// them the oracle is read-only.
+    pub async fn build_signer<C: Group>(&self) -> anyhow::Result<Option<Arc<dyn Signer<C>>>> {
+        Ok(match (&self.signer, &self.threshold, &self.secret_seed) {
+            (Some(_), Some(_), _) => {
+                return Err(anyhow::anyhow!(
+                    "the config can't have both a signer and a threshold section"
+                ))
+            }
+            (Some(signer), None, _) => Some(Arc::new(RemoteSigner::connect(&signer.socket).await?)),
+            (None, Some(threshold), _) => Some(build_threshold_signer::<C>(threshold).await?),
+            (None, None, Some(secret_seed)) => Some(Arc::new(self.keychain(secret_seed.clone()))),
+            (None, None, None) => None,
+        })
+    }
+
     pub fn build_node_streams(
         &self,
         logger: slog::Logger,
@@ -71,6 +136,50 @@ impl Config {
     }
 }
 
+/// Reads the keys written by `olivia keys successor`.
+pub fn read_successor<C: Group>(path: &std::path::Path) -> anyhow::Result<Successor<C>> {
+    use anyhow::Context;
+    let file = fs::File::open(path)
+        .with_context(|| format!("opening successor file {}", path.display()))?;
+    serde_json::from_reader(file)
+        .with_context(|| format!("{} isn't a valid successor file", path.display()))
+}
+
+impl ThresholdConfig {
+    pub fn params(&self) -> anyhow::Result<ThresholdParams> {
+        let params = ThresholdParams {
+            threshold: self.threshold,
+            n_peers: self.peers.len() as u32,
+        };
+        params.check()?;
+        Ok(params)
+    }
+}
+
+/// Sets up the keys with every peer which has to be online.
+async fn build_threshold_signer<C: Group>(
+    threshold: &ThresholdConfig,
+) -> anyhow::Result<Arc<dyn Signer<C>>> {
+    if TypeId::of::<C>() != TypeId::of::<olivia_secp256k1::Secp256k1>() {
+        return Err(anyhow::anyhow!(
+            "threshold signing only works with the secp256k1 group"
+        ));
+    }
+    let peers = threshold
+        .peers
+        .iter()
+        .map(|peer| {
+            Arc::new(RemotePeer::new(peer.address.clone(), peer.auth_key.clone()))
+                as Arc<dyn PeerClient>
+        })
+        .collect();
+    let signer: Arc<dyn Signer<olivia_secp256k1::Secp256k1>> =
+        Arc::new(ThresholdSigner::setup(threshold.params()?, peers).await?);
+    Ok(*(Box::new(signer) as Box<dyn Any>)
+        .downcast::<Arc<dyn Signer<C>>>()
+        .expect("checked the group above"))
+}
+
 impl LoggerConfig {
     pub fn to_slog_drain(&self) -> anyhow::Result<RootDrain> {
         use crate::slog::Drain;
@@ -165,13 +274,28 @@ impl EventSourceConfig {
                     logger.new(o!("type" => "event_source", "source_type" => "redis")),
                 )?)
             }
+            EventSource::RedisStream(config) => {
+                info!(
+                    logger,
+                    "Connecting to redis://{} to receive events on streams {}",
+                    config.connection_info.addr, config.streams.join(",");
+                );
+                let client = redis::Client::open(config.connection_info.clone())?;
+                Box::pin(sources::redis::stream_consumer(
+                    client,
+                    config.into(),
+                    logger.new(o!("type" => "event_source", "source_type" => "redis-stream")),
+                )?)
+            }
             EventSource::Ticker {
                 look_ahead,
                 interval,
+                calendar,
                 initial_time,
                 ends_with,
                 event_kind,
             } => {
+                let schedule = ticker_schedule(interval, calendar.as_ref())?;
                 let initial_time = initial_time.unwrap_or_else(|| {
                     use chrono::Timelike;
                     chrono::Utc::now()
@@ -184,14 +308,14 @@ impl EventSourceConfig {
 
                 let logger = logger.new(o!("type" => "event_source", "source_type" => "ticker"));
                 let look_ahead = chrono::Duration::seconds(look_ahead as i64);
-                let interval = chrono::Duration::seconds(interval as i64);
 
                 Box::pin(
                     sources::ticker::TimeEventStream {
                         db,
                         look_ahead,
-                        interval,
+                        schedule,
                         initial_time,
+                        clock: Box::new(SystemClock),
                         logger,
                         ends_with,
                         event_kind,
@@ -239,12 +363,14 @@ impl EventSourceConfig {
 
     pub fn to_node_stream(&self, _logger: slog::Logger) -> anyhow::Result<sources::Stream<Node>> {
         use EventSource::*;
-        Ok(match self.event_source {
-            Ticker { interval, .. } => Box::pin(stream::iter(vec![Update {
+        Ok(match &self.event_source {
+            Ticker {
+                interval, calendar, ..
+            } => Box::pin(stream::iter(vec![Update {
                 update: Node {
                     path: Path::root(),
                     kind: NodeKind::Range {
-                        range_kind: RangeKind::Time { interval },
+                        range_kind: ticker_schedule(*interval, calendar.as_ref())?.range_kind(),
                     },
                 },
                 processed_notifier: None,
@@ -257,7 +383,7 @@ impl EventSourceConfig {
 impl OutcomeSourceConfig {
     pub fn to_outcome_stream(
         &self,
-        seed: Seed,
+        seed: Option<Seed>,
         logger: slog::Logger,
         db: PrefixedDb,
     ) -> anyhow::Result<sources::Stream<StampedOutcome>> {
@@ -284,6 +410,19 @@ impl OutcomeSourceConfig {
                     logger.new(o!("source_type" => "redis")),
                 )?)
             }
+            RedisStream(config) => {
+                info!(
+                    logger,
+                    "Connecting to redis://{} to receive outcomes on streams {}",
+                    config.connection_info.addr, config.streams.join(",");
+                );
+                let client = redis::Client::open(config.connection_info.clone())?;
+                Box::pin(sources::redis::stream_consumer(
+                    client,
+                    config.into(),
+                    logger.new(o!("source_type" => "redis-stream")),
+                )?)
+            }
             Random {
                 ends_with,
                 event_kind,
@@ -294,10 +433,53 @@ impl OutcomeSourceConfig {
                     logger: logger.new(o!("source_type" => "random")),
                     ends_with,
                     event_kind,
-                    outcome_creator: RandomOutcomeCreator { seed, max },
+                    outcome_creator: RandomOutcomeCreator {
+                        seed: seed
+                            .ok_or_else(|| anyhow::anyhow!("random outcomes need a secret-seed"))?,
+                        max,
+                    },
                 }
                 .start(),
             ),
+            PriceFeed {
+                ends_with,
+                event_kind,
+                endpoints,
+                quorum,
+                max_deviation,
+                max_age,
+                timeout,
+            } => {
+                if !matches!(event_kind, EventKind::Price { .. }) {
+                    return Err(anyhow::anyhow!(
+                        "price-feed outcomes need a price event-kind not {}",
+                        event_kind
+                    ));
+                }
+                let quorum = quorum.unwrap_or(endpoints.len() / 2 + 1);
+                if quorum == 0 || quorum > endpoints.len() {
+                    return Err(anyhow::anyhow!(
+                        "price-feed quorum must be between 1 and the number of endpoints"
+                    ));
+                }
+                let logger = logger.new(o!("source_type" => "price_feed"));
+                let mut price_feed =
+                    sources::price_feed::PriceFeed::new(endpoints, quorum, logger.clone());
+                price_feed.max_deviation = max_deviation;
+                price_feed.max_age =
+                    max_age.map(|max_age| chrono::Duration::seconds(max_age.into()));
+                price_feed.timeout = std::time::Duration::from_secs(timeout.into());
+                Box::pin(
+                    TimeOutcomeStream {
+                        db: db.clone(),
+                        logger,
+                        ends_with,
+                        event_kind: Some(event_kind),
+                        outcome_creator: price_feed,
+                    }
+                    .start(),
+                )
+            }
             Zero {
                 ends_with,
                 event_kind,
@@ -346,41 +528,128 @@ impl OutcomeSourceConfig {
 }
 
 lazy_static::lazy_static! {
-    static ref IN_MEMORY: db::in_memory::InMemory<olivia_secp256k1::Secp256k1> = db::in_memory::InMemory::default();
+    /// There's one in-memory database per group so every connection to it sees the same data.
+    static ref IN_MEMORY: std::sync::Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>> = Default::default();
+}
+
+fn ticker_schedule(
+    interval: Option<u32>,
+    calendar: Option<&CalendarConfig>,
+) -> anyhow::Result<Schedule> {
+    match (interval, calendar) {
+        (Some(interval), None) => Ok(Schedule::Interval(chrono::Duration::seconds(
+            interval as i64,
+        ))),
+        (None, Some(config)) => {
+            let calendar = Calendar::new(&config.cron, &config.time_zone, config.holidays.clone())?;
+            if calendar
+                .next_after(chrono::Utc::now().naive_utc())
+                .is_none()
+            {
+                return Err(anyhow::anyhow!(
+                    "the ticker calendar '{}' never happens",
+                    config.cron
+                ));
+            }
+            Ok(Schedule::Calendar(calendar))
+        }
+        _ => Err(anyhow::anyhow!(
+            "tickers need either an interval or a calendar"
+        )),
+    }
+}
+
+fn in_memory<C: Group>() -> db::in_memory::InMemory<C> {
+    IN_MEMORY
+        .lock()
+        .unwrap()
+        .entry(TypeId::of::<C>())
+        .or_insert_with(|| Box::new(db::in_memory::InMemory::<C>::default()))
+        .downcast_ref::<db::in_memory::InMemory<C>>()
+        .expect("entries are keyed by their group")
+        .clone()
+}
+
+impl SeedSource {
+    pub fn load(&self) -> anyhow::Result<KeyMaterial> {
+        use anyhow::Context;
+        match self {
+            SeedSource::Inline(seed) => Ok(KeyMaterial::Seed(seed.clone())),
+            SeedSource::Location(SeedLocation::Keyfile(path)) => {
+                let keyfile = Keyfile::read(path)?;
+                keyfile.decrypt(&keyfile::passphrase(&format!(
+                    "passphrase for {}: ",
+                    path.display()
+                ))?)
+            }
+            SeedSource::Location(SeedLocation::Mnemonic(mnemonic)) => mnemonic.parse(),
+            SeedSource::Location(SeedLocation::File(path)) => fs::read_to_string(path)
+                .with_context(|| format!("reading secret seed from {}", path.display()))?
+                .parse(),
+            SeedSource::Location(SeedLocation::Env(var)) => std::env::var(var)
+                .with_context(|| format!("reading secret seed from ${}", var))?
+                .parse(),
+        }
+    }
 }
 
 impl DbConfig {
-    pub async fn connect_database_read_group(
+    pub async fn connect_database_read_group<C: Group>(
         &self,
-    ) -> anyhow::Result<Arc<dyn db::DbReadOracle<olivia_secp256k1::Secp256k1>>> {
+    ) -> anyhow::Result<Arc<dyn db::DbReadOracle<C>>> {
         match self {
-            DbConfig::InMemory => Ok(Arc::new(IN_MEMORY.clone())),
+            DbConfig::InMemory => Ok(Arc::new(in_memory::<C>())),
             DbConfig::Postgres { url } => Ok(Arc::new(db::postgres::connect_read(url).await?)),
         }
     }
 
-    pub async fn connect_database_read(&self) -> anyhow::Result<Arc<dyn db::DbReadEvent>> {
+    pub async fn connect_database_read<C: Group>(
+        &self,
+    ) -> anyhow::Result<Arc<dyn db::DbReadEvent>> {
         match self {
-            DbConfig::InMemory => Ok(Arc::new(IN_MEMORY.clone())),
+            DbConfig::InMemory => Ok(Arc::new(in_memory::<C>())),
             DbConfig::Postgres { url } => Ok(Arc::new(db::postgres::connect_read(url).await?)),
         }
     }
 
-    pub async fn connect_database(
-        &self,
-    ) -> anyhow::Result<Arc<dyn db::Db<olivia_secp256k1::Secp256k1>>> {
+    pub async fn connect_database<C: Group>(&self) -> anyhow::Result<Arc<dyn db::Db<C>>> {
         match self {
-            DbConfig::InMemory => Ok(Arc::new(IN_MEMORY.clone())),
-            DbConfig::Postgres { url } => Ok(Arc::new(PgBackendWrite::connect(url).await?)),
+            DbConfig::InMemory => Ok(Arc::new(in_memory::<C>())),
+            DbConfig::Postgres { url } => {
+                let db = PgBackendWrite::connect(url).await?;
+                db.check_version().await?;
+                Ok(Arc::new(db))
+            }
         }
     }
 }
 
-impl From<PredicateKind> for olivia_core::PredicateKind {
+impl From<PredicateKind> for sources::predicate::PredicateKind {
     fn from(from: PredicateKind) -> Self {
+        use olivia_core::BoundKind;
+        let bound = |bound_kind| {
+            sources::predicate::PredicateKind::Single(olivia_core::PredicateKind::Bound(bound_kind))
+        };
         match from {
-            PredicateKind::Eq => olivia_core::PredicateKind::Eq,
-            PredicateKind::Gt => olivia_core::PredicateKind::Bound(olivia_core::BoundKind::Gt),
+            PredicateKind::Eq => {
+                sources::predicate::PredicateKind::Single(olivia_core::PredicateKind::Eq)
+            }
+            PredicateKind::Gt => bound(BoundKind::Gt),
+            PredicateKind::Lt => bound(BoundKind::Lt),
+            PredicateKind::Ge => bound(BoundKind::Ge),
+            PredicateKind::Le => bound(BoundKind::Le),
+            PredicateKind::Range => sources::predicate::PredicateKind::Ranges,
+        }
+    }
+}
+
+impl From<RedisStreamConfig> for sources::redis::StreamConsumerConfig {
+    fn from(config: RedisStreamConfig) -> Self {
+        Self {
+            streams: config.streams,
+            group: config.group,
+            consumer: config.consumer,
+            dead_letter: config.dead_letter,
         }
     }
 }
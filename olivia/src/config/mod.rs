@@ -1,9 +1,18 @@
-use crate::{seed::Seed, sources::predicate::OutcomeFilter};
-use olivia_core::{chrono::NaiveDateTime, Event, EventKind, Path};
+use crate::{
+    attestation_policy::AttestationPolicy,
+    seed::Seed,
+    sources::{predicate::OutcomeFilter, price_feed::PriceEndpoint},
+    write_api::WriteCredential,
+};
+use olivia_core::{
+    chrono::{NaiveDate, NaiveDateTime},
+    Event, EventKind, Path,
+};
 use redis::IntoConnectionInfo;
 use std::{collections::HashMap, str::FromStr};
 
 mod config_impls;
+pub use config_impls::read_successor;
 
 pub type RootDrain = Box<
     dyn slog::SendSyncRefUnwindSafeDrain<Err = slog::Never, Ok = ()>
@@ -19,18 +28,131 @@ pub struct Config {
     pub events: HashMap<Path, Vec<EventSourceConfig>>,
     #[serde(default)]
     pub outcomes: HashMap<Path, Vec<OutcomeSourceConfig>>,
+    /// How many of the outcome sources of a path have to agree before an outcome is attested to.
+    #[serde(default)]
+    pub outcome_quorums: HashMap<Path, usize>,
+    /// Seconds the outcome sources of a path with a quorum have to agree on an event's outcome
+    /// before it's put up for review.
+    #[serde(default = "default_outcome_quorum_timeout")]
+    pub outcome_quorum_timeout: u64,
+    /// Paths whose events get keys of their own rather than the oracle's main keys.
+    #[serde(default)]
+    pub namespaces: Vec<Path>,
+    /// How long after their expected outcome time events can be attested to.
+    #[serde(default)]
+    pub attestation_policy: AttestationPolicy,
     #[serde(default)]
     pub database: DbConfig,
     #[serde(default)]
+    pub group: GroupConfig,
+    #[serde(default)]
     pub loggers: LoggersConfig,
+    #[serde(rename = "secret-seed")]
+    pub seed_source: Option<SeedSource>,
+    /// The seed loaded from `seed_source` by [`Config::load_secret_seed`].
+    #[serde(skip)]
     pub secret_seed: Option<Seed>,
+    pub signer: Option<SignerConfig>,
+    pub threshold: Option<ThresholdConfig>,
     pub rest_api: Option<RestConfig>,
 }
 
+/// Where to get the oracle's secret seed from.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum SeedSource {
+    /// The seed in hex
+    Inline(Seed),
+    Location(SeedLocation),
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum SeedLocation {
+    /// A keyfile created by `olivia keys init`
+    Keyfile(std::path::PathBuf),
+    /// A BIP39 mnemonic
+    Mnemonic(String),
+    /// A file with a hex seed or a BIP39 mnemonic in it
+    File(std::path::PathBuf),
+    /// An environment variable with a hex seed or a BIP39 mnemonic in it
+    Env(String),
+}
+
+impl std::fmt::Debug for SeedLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SeedLocation::Keyfile(path) => f.debug_tuple("Keyfile").field(path).finish(),
+            SeedLocation::Mnemonic(_) => f.debug_tuple("Mnemonic").field(&"XXXX").finish(),
+            SeedLocation::File(path) => f.debug_tuple("File").field(path).finish(),
+            SeedLocation::Env(var) => f.debug_tuple("Env").field(var).finish(),
+        }
+    }
+}
+
+/// Where the `olivia signer` daemon that holds the oracle's keys listens.
+///
+/// When this is set the oracle signs through the daemon rather than with `secret-seed`.
 #[derive(Debug, Clone, Deserialize, PartialEq)]
-#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SignerConfig {
+    pub socket: std::path::PathBuf,
+    /// The file the daemon records what it has signed in. Only the daemon needs it.
+    pub state_file: Option<std::path::PathBuf>,
+    /// The file written by `olivia keys successor` with the keys the daemon may hand the oracle
+    /// over to. Only the daemon needs it.
+    pub successor: Option<std::path::PathBuf>,
+}
+
+/// The `olivia threshold-peer`s holding shares of the oracle's keys.
+///
+/// When this is set the oracle signs with `threshold` of the `peers` rather than with
+/// `secret-seed`. Only secp256k1 keys can be shared.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ThresholdConfig {
+    pub threshold: u32,
+    /// Every peer in order of index.
+    pub peers: Vec<PeerConfig>,
+    /// Which of the `peers` this is (starting from 1). Only the peers need it.
+    pub index: Option<u32>,
+    /// Where the peer listens. Only the peers need it.
+    pub listen: Option<std::net::SocketAddr>,
+    /// The file the peer records its shares and what it has revealed in. Only the peers need it.
+    pub state_file: Option<std::path::PathBuf>,
+    /// The file written by `olivia keys successor` with the keys the peer may help hand the
+    /// oracle over to. Only the peers need it.
+    pub successor: Option<std::path::PathBuf>,
+}
+
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PeerConfig {
+    /// The `host:port` the peer listens on.
+    pub address: String,
+    /// The key printed by `olivia threshold-peer identity`.
+    pub identity: olivia_secp256k1::fun::XOnly,
+    /// The key requests to the peer are authenticated with.
+    pub auth_key: String,
+}
+
+impl std::fmt::Debug for PeerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PeerConfig")
+            .field("address", &self.address)
+            .field("identity", &self.identity)
+            .field("auth_key", &"XXXX")
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct RestConfig {
     pub listen: std::net::SocketAddr,
+    /// Who may post events and outcomes. Posting is only enabled if there are some.
+    #[serde(default)]
+    pub write_credentials: Vec<WriteCredential>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -45,6 +167,39 @@ pub struct RedisConfig {
     pub lists: Vec<String>,
 }
 
+/// Reading from redis streams as part of a consumer group. Entries are only acknowledged once the
+/// oracle has processed them.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RedisStreamConfig {
+    #[serde(
+        deserialize_with = "deser_redis_connection_info",
+        rename = "url",
+        serialize_with = "ser_redis_connection_info"
+    )]
+    pub connection_info: redis::ConnectionInfo,
+    pub streams: Vec<String>,
+    #[serde(default = "default_redis_group")]
+    pub group: String,
+    #[serde(default = "default_redis_group")]
+    pub consumer: String,
+    /// The stream that entries the oracle couldn't deserialize or rejected are moved to. Defaults
+    /// to `<stream>:dead-letter`.
+    pub dead_letter: Option<String>,
+}
+
+fn default_redis_group() -> String {
+    "olivia".into()
+}
+
+fn default_price_feed_timeout() -> u32 {
+    10
+}
+
+fn default_outcome_quorum_timeout() -> u64 {
+    crate::quorum::DEFAULT_TIMEOUT.as_secs()
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case", tag = "backend")]
 #[serde(deny_unknown_fields)]
@@ -59,12 +214,24 @@ impl Default for DbConfig {
     }
 }
 
+/// The group the oracle's keys, nonces and signatures are in.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum GroupConfig {
+    #[default]
+    Secp256k1,
+    Ristretto,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case", tag = "type", deny_unknown_fields)]
 pub enum EventSource {
     #[serde(rename_all = "kebab-case")]
     Ticker {
-        interval: u32,
+        /// Seconds between each event
+        interval: Option<u32>,
+        /// When the events happen if they're not at a fixed `interval`
+        calendar: Option<CalendarConfig>,
         look_ahead: u32,
         initial_time: Option<NaiveDateTime>,
         #[serde(default)]
@@ -72,11 +239,29 @@ pub enum EventSource {
         event_kind: EventKind,
     },
     Redis(RedisConfig),
+    RedisStream(RedisStreamConfig),
     Init {
         events: Vec<Event>,
     },
 }
 
+/// A ticker schedule like "16:00 America/New_York on weekdays that aren't holidays".
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CalendarConfig {
+    /// minute hour day-of-month month day-of-week
+    pub cron: String,
+    #[serde(default = "default_time_zone")]
+    pub time_zone: String,
+    /// Dates (in the time zone) to skip
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+}
+
+fn default_time_zone() -> String {
+    "UTC".into()
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct EventSourceConfig {
@@ -108,6 +293,25 @@ pub enum OutcomeSource {
     },
     /// Get outcomes from redis
     Redis(RedisConfig),
+    /// Get outcomes from redis streams
+    RedisStream(RedisStreamConfig),
+    /// Attest to price events with the median of the prices quoted by some HTTP APIs
+    #[serde(rename_all = "kebab-case")]
+    PriceFeed {
+        #[serde(default)]
+        ends_with: Path,
+        event_kind: EventKind,
+        endpoints: Vec<PriceEndpoint>,
+        /// How many endpoints have to agree on the price. Defaults to a majority of them.
+        quorum: Option<usize>,
+        /// Quotes further than this fraction away from the median are ignored.
+        max_deviation: Option<f64>,
+        /// Quotes more than this many seconds before or after the event's time are ignored.
+        max_age: Option<u32>,
+        /// Seconds to wait for each endpoint.
+        #[serde(default = "default_price_feed_timeout")]
+        timeout: u32,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -132,6 +336,15 @@ pub enum PredicateKind {
     Eq,
     #[serde(rename = "_")]
     Gt,
+    #[serde(rename = "_lt_")]
+    Lt,
+    #[serde(rename = "_ge_")]
+    Ge,
+    #[serde(rename = "_le_")]
+    Le,
+    /// Ranges between consecutive values in the filter
+    #[serde(rename = "_in_")]
+    Range,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -1,9 +1,10 @@
-use crate::db::*;
+use crate::{db::*, quorum::OutcomeReview};
 use anyhow::anyhow;
 use async_trait::async_trait;
 use olivia_core::{
     chrono::NaiveDate, AnnouncedEvent, Attestation, Child, ChildDesc, Event, EventId, Group,
-    OracleKeys, Path, PrefixPath,
+    KeyHistory, NamespaceKeys, OracleKeys, Path, PrefixPath, RawAnnouncement, RawKeySuccession,
+    RawNonceBatch,
 };
 use std::{
     cmp::Reverse,
@@ -11,20 +12,38 @@ use std::{
     str::FromStr,
     sync::{Arc, RwLock},
 };
+use tokio::sync::watch;
 
 #[derive(Clone)]
 pub struct InMemory<C: Group> {
     public_keys: Arc<RwLock<Option<OracleKeys<C>>>>,
+    key_successions: Arc<RwLock<Vec<RawKeySuccession<C>>>>,
+    namespace_keys: Arc<RwLock<BTreeMap<Path, OracleKeys<C>>>>,
     inner: Arc<RwLock<HashMap<EventId, AnnouncedEvent<C>>>>,
     node_kinds: Arc<RwLock<HashMap<Path, NodeKind>>>,
+    announcement_history: Arc<RwLock<HashMap<EventId, Vec<RawAnnouncement<C>>>>>,
+    nonce_batches: Arc<RwLock<HashMap<Path, Vec<RawNonceBatch<C>>>>>,
+    attesting: Arc<RwLock<HashMap<EventId, String>>>,
+    outcome_reviews: Arc<RwLock<BTreeMap<EventId, OutcomeReview>>>,
+    event_changes: (Arc<watch::Sender<()>>, watch::Receiver<()>),
 }
 
 impl<C: Group> Default for InMemory<C> {
     fn default() -> Self {
         Self {
             public_keys: Arc::new(RwLock::new(None)),
+            key_successions: Arc::new(RwLock::new(Vec::new())),
+            namespace_keys: Arc::new(RwLock::new(BTreeMap::default())),
             inner: Arc::new(RwLock::new(HashMap::default())),
             node_kinds: Arc::new(RwLock::new(HashMap::default())),
+            announcement_history: Arc::new(RwLock::new(HashMap::default())),
+            nonce_batches: Arc::new(RwLock::new(HashMap::default())),
+            attesting: Arc::new(RwLock::new(HashMap::default())),
+            outcome_reviews: Arc::new(RwLock::new(BTreeMap::default())),
+            event_changes: {
+                let (sender, receiver) = watch::channel(());
+                (Arc::new(sender), receiver)
+            },
         }
     }
 }
@@ -40,7 +59,72 @@ impl<C: Group> DbReadOracle<C> for InMemory<C> {
     }
 
     async fn get_public_keys(&self) -> Result<Option<OracleKeys<C>>, Error> {
-        Ok(self.public_keys.read().unwrap().as_ref().map(Clone::clone))
+        match self.get_key_history().await? {
+            Some(history) => {
+                Ok(Some(history.current().ok_or_else(|| {
+                    anyhow!("latest key succession in database is invalid")
+                })?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_key_history(&self) -> anyhow::Result<Option<KeyHistory<C>>> {
+        Ok(self
+            .public_keys
+            .read()
+            .unwrap()
+            .clone()
+            .map(|genesis| KeyHistory {
+                genesis,
+                successions: self.key_successions.read().unwrap().clone(),
+            }))
+    }
+
+    async fn get_namespace_keys(&self) -> anyhow::Result<Vec<NamespaceKeys<C>>> {
+        let namespace_keys = &*self.namespace_keys.read().unwrap();
+        Ok(namespace_keys
+            .iter()
+            .map(|(path, keys)| NamespaceKeys {
+                path: path.clone(),
+                keys: keys.clone(),
+            })
+            .collect())
+    }
+
+    async fn get_announcement_history(
+        &self,
+        id: &EventId,
+    ) -> Result<Vec<RawAnnouncement<C>>, Error> {
+        let history = &*self.announcement_history.read().unwrap();
+        Ok(history.get(id).cloned().unwrap_or_default())
+    }
+
+    async fn get_nonce_batches(&self, path: PathRef<'_>) -> Result<Vec<RawNonceBatch<C>>, Error> {
+        let nonce_batches = &*self.nonce_batches.read().unwrap();
+        Ok(nonce_batches
+            .get(&path.to_path())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn get_all_nonce_batches(&self) -> Result<Vec<RawNonceBatch<C>>, Error> {
+        let nonce_batches = &*self.nonce_batches.read().unwrap();
+        Ok(nonce_batches.values().flatten().cloned().collect())
+    }
+
+    async fn get_outcome_reviews(&self) -> anyhow::Result<Vec<OutcomeReview>> {
+        Ok(self
+            .outcome_reviews
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    async fn get_outcome_review(&self, id: &EventId) -> anyhow::Result<Option<OutcomeReview>> {
+        Ok(self.outcome_reviews.read().unwrap().get(id).cloned())
     }
 }
 
@@ -204,6 +288,10 @@ impl<C: Group> DbReadEvent for InMemory<C> {
 
         Ok(events.into_iter().map(|x| x.event).collect())
     }
+
+    fn event_changes(&self) -> Option<watch::Receiver<()>> {
+        Some(self.event_changes.1.clone())
+    }
 }
 
 #[async_trait]
@@ -222,17 +310,40 @@ impl<C: Group> DbWrite<C> for InMemory<C> {
                 v.insert(observed_event);
             }
         }
+        let _ = self.event_changes.0.send(());
         Ok(())
     }
+    async fn begin_attestation(&self, event_id: &EventId, outcome: &str) -> Result<String, Error> {
+        let db = self.inner.read().unwrap();
+        if !db.contains_key(event_id) {
+            return Err(anyhow!("Cannot attest to event that does not exist"));
+        }
+        let mut attesting = self.attesting.write().unwrap();
+        Ok(attesting
+            .entry(event_id.clone())
+            .or_insert_with(|| outcome.to_string())
+            .clone())
+    }
+
     async fn complete_event(
         &self,
         event_id: &EventId,
         attestation: Attestation<C>,
     ) -> Result<(), crate::db::Error> {
         let db = &mut *self.inner.write().unwrap();
+        let attesting = self.attesting.read().unwrap();
         match db.get_mut(&event_id) {
             Some(ref mut event) => match event.attestation {
                 Some(_) => Err(anyhow!("This event has already been attested to")),
+                _ if attesting
+                    .get(event_id)
+                    .map(|outcome| *outcome != attestation.outcome)
+                    .unwrap_or(false) =>
+                {
+                    Err(anyhow!(
+                        "This event is being attested to with a different outcome"
+                    ))
+                }
                 ref mut slot => {
                     *slot = Some(attestation);
                     Ok(())
@@ -242,11 +353,91 @@ impl<C: Group> DbWrite<C> for InMemory<C> {
         }
     }
 
+    async fn reschedule_event(
+        &self,
+        event: Event,
+        announcement: RawAnnouncement<C>,
+    ) -> Result<(), Error> {
+        let db = &mut *self.inner.write().unwrap();
+        let being_attested = self.attesting.read().unwrap().contains_key(&event.id);
+        match db.get_mut(&event.id) {
+            Some(existing) => match existing.attestation {
+                Some(_) => Err(anyhow!(
+                    "Cannot reschedule an event that has been attested to"
+                )),
+                None if being_attested => Err(anyhow!(
+                    "Cannot reschedule an event that is being attested to"
+                )),
+                None => {
+                    let previous = std::mem::replace(&mut existing.announcement, announcement);
+                    existing.event = event;
+                    self.announcement_history
+                        .write()
+                        .unwrap()
+                        .entry(existing.event.id.clone())
+                        .or_default()
+                        .push(previous);
+                    let _ = self.event_changes.0.send(());
+                    Ok(())
+                }
+            },
+            None => Err(anyhow!("Cannot reschedule event that does not exist")),
+        }
+    }
+
     async fn set_public_keys(&self, public_keys: OracleKeys<C>) -> Result<(), Error> {
         *self.public_keys.write().unwrap() = Some(public_keys);
         Ok(())
     }
 
+    async fn insert_key_succession(&self, succession: RawKeySuccession<C>) -> Result<(), Error> {
+        let mut successions = self.key_successions.write().unwrap();
+        let epoch = succession
+            .decode()
+            .ok_or_else(|| anyhow!("key succession is malformed"))?
+            .keys
+            .epoch;
+        if epoch as usize != successions.len() + 1 {
+            return Err(anyhow!(
+                "key succession for epoch {} is out of order",
+                epoch
+            ));
+        }
+        successions.push(succession);
+        Ok(())
+    }
+
+    async fn set_namespace_keys(&self, namespace: NamespaceKeys<C>) -> Result<(), Error> {
+        self.namespace_keys
+            .write()
+            .unwrap()
+            .insert(namespace.path, namespace.keys);
+        Ok(())
+    }
+
+    async fn insert_nonce_batch(&self, path: &Path, batch: RawNonceBatch<C>) -> Result<(), Error> {
+        let mut nonce_batches = self.nonce_batches.write().unwrap();
+        nonce_batches.entry(path.clone()).or_default().push(batch);
+        Ok(())
+    }
+
+    async fn set_outcome_review(&self, review: OutcomeReview) -> Result<(), Error> {
+        self.outcome_reviews
+            .write()
+            .unwrap()
+            .insert(review.event_id.clone(), review);
+        Ok(())
+    }
+
+    async fn remove_outcome_review(&self, event_id: &EventId) -> Result<bool, Error> {
+        Ok(self
+            .outcome_reviews
+            .write()
+            .unwrap()
+            .remove(event_id)
+            .is_some())
+    }
+
     async fn set_node(&self, node: Node) -> Result<(), Error> {
         let mut node_kinds = self.node_kinds.write().unwrap();
         node_kinds.insert(node.path, node.kind);
@@ -266,11 +457,12 @@ impl<C: Group> BorrowDb<C> for InMemory<C> {
 crate::run_rest_api_tests! {
     oracle => oracle,
     routes => routes,
+    db => db,
     curve => olivia_secp256k1::Secp256k1,
     {
-        let db = InMemory::<olivia_secp256k1::Secp256k1>::default();
-        let oracle = crate::oracle::Oracle::new(crate::seed::Seed::new([42u8; 64]), Arc::new(db.clone())).await.unwrap();
-        let routes = crate::rest_api::routes(Arc::new(db), slog::Logger::root(slog::Discard, o!()));
+        let db: Arc<dyn Db<olivia_secp256k1::Secp256k1>> = Arc::new(InMemory::<olivia_secp256k1::Secp256k1>::default());
+        let oracle = crate::oracle::Oracle::new(crate::seed::Seed::new([42u8; 64]), db.clone()).await.unwrap();
+        let routes = crate::rest_api::routes(db.clone(), None, slog::Logger::root(slog::Discard, o!()));
     }
 }
 
@@ -315,5 +507,53 @@ mod test {
         let db = Arc::<InMemory<olivia_secp256k1::Secp256k1>>::default();
         crate::oracle::test::test_oracle_event_lifecycle(db.clone()).await;
         crate::oracle::test::test_price_oracle_event_lifecycle(db.clone()).await;
+        crate::oracle::test::test_void_oracle_event_lifecycle(db.clone()).await;
+        crate::oracle::test::test_reschedule_oracle_event(db.clone()).await;
+        crate::oracle::test::test_racing_outcomes(db.clone()).await;
+        crate::oracle::test::test_attestation_window(db.clone()).await;
+        crate::oracle::test::test_key_rotation(db.clone()).await;
+    }
+
+    #[tokio::test]
+    async fn test_against_ristretto_oracle() {
+        let db = Arc::<InMemory<olivia_ristretto::Ristretto>>::default();
+        crate::oracle::test::test_oracle_event_lifecycle(db.clone()).await;
+        crate::oracle::test::test_price_oracle_event_lifecycle(db.clone()).await;
+        crate::oracle::test::test_void_oracle_event_lifecycle(db.clone()).await;
+        crate::oracle::test::test_reschedule_oracle_event(db.clone()).await;
+    }
+
+    #[tokio::test]
+    async fn root_advertises_group() {
+        let db = InMemory::<olivia_ristretto::Ristretto>::default();
+        let oracle =
+            crate::oracle::Oracle::new(crate::seed::Seed::new([42u8; 64]), Arc::new(db.clone()))
+                .await
+                .unwrap();
+        oracle
+            .add_event(EventId::from_str("/test/one.occur").unwrap().into())
+            .await
+            .unwrap();
+        let routes =
+            crate::rest_api::routes(Arc::new(db), None, slog::Logger::root(slog::Discard, o!()));
+        let res = warp::test::request().path("/").reply(&routes).await;
+        let body = serde_json::from_slice::<serde_json::Value>(res.body()).unwrap();
+        assert_eq!(body["public-keys"]["group"], "ristretto");
+    }
+
+    #[tokio::test]
+    async fn path_is_served_without_public_keys() {
+        let db = InMemory::<olivia_secp256k1::Secp256k1>::default();
+        let event_id = EventId::from_str("/test/one.occur").unwrap();
+        db.insert_event(AnnouncedEvent::test_unattested_instance(event_id.into()))
+            .await
+            .unwrap();
+        let routes =
+            crate::rest_api::routes(Arc::new(db), None, slog::Logger::root(slog::Discard, o!()));
+        let res = warp::test::request().path("/test").reply(&routes).await;
+        assert_eq!(res.status(), 200);
+        let body = serde_json::from_slice::<serde_json::Value>(res.body()).unwrap();
+        assert_eq!(body.get("public-keys"), None);
+        assert_eq!(body["events"], serde_json::json!([]));
     }
 }
@@ -1,6 +1,8 @@
+use crate::quorum::OutcomeReview;
 use olivia_core::{
-    AnnouncedEvent, Attestation, Event, EventId, EventKind, GetPath, Group, Node, NodeKind,
-    OracleKeys, PathRef,
+    AnnouncedEvent, Attestation, Event, EventId, EventKind, GetPath, Group, KeyHistory,
+    NamespaceKeys, Node, NodeKind, OracleKeys, Path, PathRef, RawAnnouncement, RawKeySuccession,
+    RawNonceBatch,
 };
 pub mod in_memory;
 pub mod postgres;
@@ -16,7 +18,24 @@ pub type Error = anyhow::Error;
 #[async_trait]
 pub trait DbReadOracle<C: Group>: Send + Sync + DbReadEvent {
     async fn get_announced_event(&self, id: &EventId) -> anyhow::Result<Option<AnnouncedEvent<C>>>;
+    /// The oracle's keys in the current epoch.
     async fn get_public_keys(&self) -> Result<Option<OracleKeys<C>>, Error>;
+    /// The oracle's original keys and every succession since.
+    async fn get_key_history(&self) -> anyhow::Result<Option<KeyHistory<C>>>;
+    /// The keys of each path that has its own.
+    async fn get_namespace_keys(&self) -> anyhow::Result<Vec<NamespaceKeys<C>>>;
+    /// The announcements an event had before it was rescheduled (oldest first).
+    async fn get_announcement_history(
+        &self,
+        id: &EventId,
+    ) -> anyhow::Result<Vec<RawAnnouncement<C>>>;
+    /// The nonce batches for events directly under `path` (oldest first).
+    async fn get_nonce_batches(&self, path: PathRef<'_>) -> anyhow::Result<Vec<RawNonceBatch<C>>>;
+    /// Every nonce batch under any path.
+    async fn get_all_nonce_batches(&self) -> anyhow::Result<Vec<RawNonceBatch<C>>>;
+    /// The events whose outcome sources couldn't agree on an outcome (ordered by event id).
+    async fn get_outcome_reviews(&self) -> anyhow::Result<Vec<OutcomeReview>>;
+    async fn get_outcome_review(&self, id: &EventId) -> anyhow::Result<Option<OutcomeReview>>;
 }
 
 #[async_trait]
@@ -24,19 +43,48 @@ pub trait DbReadEvent: Send + Sync {
     async fn get_node(&self, path: PathRef<'_>) -> anyhow::Result<Option<GetPath>>;
     async fn query_event(&self, query: EventQuery<'_, '_>) -> anyhow::Result<Option<Event>>;
     async fn query_events(&self, query: EventQuery<'_, '_>) -> anyhow::Result<Vec<Event>>;
+    /// Told whenever an event is added or rescheduled. Databases that can't tell when that
+    /// happens return `None` so anything waiting for an event's time has to check back instead.
+    fn event_changes(&self) -> Option<tokio::sync::watch::Receiver<()>> {
+        None
+    }
 }
 
 #[async_trait]
 pub trait DbWrite<C: Group>: Send + Sync {
     async fn insert_event(&self, observed_event: AnnouncedEvent<C>) -> Result<(), Error>;
     async fn set_node(&self, node: Node) -> Result<(), Error>;
+    /// Records that the event is about to be attested to with `outcome` unless it has already
+    /// been committed to another outcome. Returns the outcome the event is committed to.
+    ///
+    /// This must happen before anything is signed. Once recorded the event can't be completed
+    /// with a different outcome or rescheduled, even if the attestation was never stored.
+    async fn begin_attestation(&self, event_id: &EventId, outcome: &str) -> Result<String, Error>;
     async fn complete_event(
         &self,
         event_id: &EventId,
         outcome: Attestation<C>,
     ) -> Result<(), Error>;
+    /// Replaces the `expected_outcome_time` and announcement of an unattested event, moving its
+    /// current announcement into its history.
+    async fn reschedule_event(
+        &self,
+        event: Event,
+        announcement: RawAnnouncement<C>,
+    ) -> Result<(), Error>;
 
+    /// Sets the oracle's original keys.
     async fn set_public_keys(&self, public_key: OracleKeys<C>) -> Result<(), Error>;
+    /// Moves the oracle on to the next epoch. Fails unless the succession is for the epoch after
+    /// the current one.
+    async fn insert_key_succession(&self, succession: RawKeySuccession<C>) -> Result<(), Error>;
+    /// Sets the keys of the events under `namespace.path`, replacing any it had before.
+    async fn set_namespace_keys(&self, namespace: NamespaceKeys<C>) -> Result<(), Error>;
+    async fn insert_nonce_batch(&self, path: &Path, batch: RawNonceBatch<C>) -> Result<(), Error>;
+    /// Puts the event up for review replacing any review it already had.
+    async fn set_outcome_review(&self, review: OutcomeReview) -> Result<(), Error>;
+    /// Returns whether the event was up for review.
+    async fn remove_outcome_review(&self, event_id: &EventId) -> Result<bool, Error>;
 }
 
 pub trait Db<C: Group>:
@@ -1,11 +1,13 @@
 use super::NodeKind;
-use crate::db::*;
+use crate::{db::*, quorum::OutcomeReview};
 use async_trait::async_trait;
 use olivia_core::{
     attest, chrono::NaiveDate, AnnouncedEvent, Attestation, AttestationSchemes, Child, ChildDesc,
-    Event, EventId, Group, OracleKeys, Path, PathRef, PrefixPath, RawAnnouncement, RawOracleEvent,
+    Event, EventId, Group, KeyHistory, NamespaceKeys, OracleKeys, Path, PathRef, PrefixPath,
+    RawAnnouncement, RawKeySuccession, RawNonceBatch, RawOracleEvent,
 };
 use std::{
+    cmp::Ordering,
     collections::{BTreeMap, HashSet},
     iter::once,
     str::FromStr,
@@ -91,6 +93,18 @@ impl Lquery {
     }
 }
 
+/// The changes made to the schema since `init.sql` in the order they were made. The database's
+/// `version` in `meta` is how many of them have been applied to it.
+const MIGRATIONS: [&str; 7] = [
+    include_str!("postgres/migrations/1_announcement_history.sql"),
+    include_str!("postgres/migrations/2_dlc_v1_signatures.sql"),
+    include_str!("postgres/migrations/3_nonce_batch.sql"),
+    include_str!("postgres/migrations/4_attesting.sql"),
+    include_str!("postgres/migrations/5_key_succession.sql"),
+    include_str!("postgres/migrations/6_namespace_keys.sql"),
+    include_str!("postgres/migrations/7_outcome_review.sql"),
+];
+
 pub struct PgBackendWrite {
     client: RwLock<tokio_postgres::Client>,
     #[allow(dead_code)]
@@ -132,9 +146,67 @@ impl PgBackendWrite {
         )?)
     }
 
+    /// Creates the schema if the database is empty and then applies any migrations it's missing.
     pub async fn setup(&self) -> anyhow::Result<()> {
-        let sql = include_str!("postgres/init.sql");
-        Ok(self.client.read().await.batch_execute(sql).await?)
+        let mut client = self.client.write().await;
+        let tx = client.transaction().await?;
+        let initialized: bool = tx
+            .query_one("SELECT to_regclass('meta') IS NOT NULL", &[])
+            .await?
+            .get(0);
+        if !initialized {
+            tx.batch_execute(include_str!("postgres/init.sql")).await?;
+        }
+
+        let row = tx
+            .query_one(r#"SELECT value FROM meta WHERE key = 'version'"#, &[])
+            .await?;
+        let version: Version = serde_json::from_value(row.get::<_, serde_json::Value>("value"))?;
+        let current = version.version as usize;
+        if current > MIGRATIONS.len() {
+            return Err(anyhow::anyhow!(
+                "the database is at version {} which is newer than this version of olivia ({})",
+                current,
+                MIGRATIONS.len()
+            ));
+        }
+
+        for migration in &MIGRATIONS[current..] {
+            tx.batch_execute(migration).await?;
+        }
+        let version = Version {
+            version: MIGRATIONS.len() as u32,
+        };
+        tx.execute(
+            r#"UPDATE meta SET value = $1 WHERE key = 'version'"#,
+            &[&serde_json::to_value(&version)?],
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Checks the database has exactly the migrations this version of olivia expects.
+    pub async fn check_version(&self) -> anyhow::Result<()> {
+        let version = self.version().await.map_err(|e| {
+            anyhow::anyhow!(
+                "couldn't read the database's version ({}). Run `olivia db init` to set it up",
+                e
+            )
+        })?;
+        match (version.version as usize).cmp(&MIGRATIONS.len()) {
+            Ordering::Less => Err(anyhow::anyhow!(
+                "the database is at version {} but olivia needs version {}. Run `olivia db init` to migrate it",
+                version.version,
+                MIGRATIONS.len()
+            )),
+            Ordering::Greater => Err(anyhow::anyhow!(
+                "the database is at version {} which is newer than this version of olivia ({})",
+                version.version,
+                MIGRATIONS.len()
+            )),
+            Ordering::Equal => Ok(()),
+        }
     }
 }
 
@@ -150,7 +222,9 @@ impl<C: Group> crate::db::DbReadOracle<C> for tokio_postgres::Client {
                       (att).outcome,
                       (att).olivia_v1_scalars,
                       (att).ecdsa_v1_signature,
-                      (att).time
+                      (att).dlc_v1_signatures,
+                      (att).time,
+                      epoch
                FROM event
                  WHERE event.id = $1
             "#,
@@ -170,6 +244,9 @@ impl<C: Group> crate::db::DbReadOracle<C> for tokio_postgres::Client {
                         ecdsa_v1: row
                             .get::<_, Option<_>>("ecdsa_v1_signature")
                             .map(|signature| attest::EcdsaV1 { signature }),
+                        dlc_v1: row
+                            .get::<_, Option<_>>("dlc_v1_signatures")
+                            .map(|signatures| attest::DlcV1 { signatures }),
                     },
                     time: row.get("time"),
                 });
@@ -183,12 +260,30 @@ impl<C: Group> crate::db::DbReadOracle<C> for tokio_postgres::Client {
                         signature: row.get("signature"),
                     },
                     attestation,
+                    epoch: row.get::<_, i32>("epoch") as u32,
                 }))
             }
         }
     }
 
     async fn get_public_keys(&self) -> Result<Option<olivia_core::OracleKeys<C>>, Error> {
+        let latest = self
+            .query_opt(
+                r#"SELECT succession, signature FROM key_succession ORDER BY epoch DESC LIMIT 1"#,
+                &[],
+            )
+            .await?;
+        if let Some(row) = latest {
+            let succession =
+                RawKeySuccession::<C>::from_parts(row.get("succession"), row.get("signature"));
+            return Ok(Some(
+                succession
+                    .decode()
+                    .ok_or_else(|| anyhow::anyhow!("latest key succession in database is invalid"))?
+                    .keys,
+            ));
+        }
+
         let row = self
             .query_opt(r#"SELECT value FROM meta WHERE key = 'public_keys'"#, &[])
             .await?;
@@ -197,6 +292,124 @@ impl<C: Group> crate::db::DbReadOracle<C> for tokio_postgres::Client {
             .map(|row| serde_json::from_value(row.get("value")))
             .transpose()?)
     }
+
+    async fn get_key_history(&self) -> anyhow::Result<Option<KeyHistory<C>>> {
+        let row = self
+            .query_opt(r#"SELECT value FROM meta WHERE key = 'public_keys'"#, &[])
+            .await?;
+        let genesis = match row {
+            Some(row) => serde_json::from_value(row.get("value"))?,
+            None => return Ok(None),
+        };
+        let rows = self
+            .query(
+                r#"SELECT succession, signature FROM key_succession ORDER BY epoch ASC"#,
+                &[],
+            )
+            .await?;
+
+        Ok(Some(KeyHistory {
+            genesis,
+            successions: rows
+                .into_iter()
+                .map(|row| {
+                    RawKeySuccession::from_parts(row.get("succession"), row.get("signature"))
+                })
+                .collect(),
+        }))
+    }
+
+    async fn get_namespace_keys(&self) -> anyhow::Result<Vec<NamespaceKeys<C>>> {
+        let rows = self
+            .query(
+                r#"SELECT path, keys FROM namespace_keys ORDER BY path ASC"#,
+                &[],
+            )
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(NamespaceKeys {
+                    path: Path::from_str(row.get("path"))?,
+                    keys: serde_json::from_value(row.get("keys"))?,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_outcome_reviews(&self) -> anyhow::Result<Vec<OutcomeReview>> {
+        let rows = self
+            .query(r#"SELECT review FROM outcome_review ORDER BY id ASC"#, &[])
+            .await?;
+
+        rows.into_iter()
+            .map(|row| Ok(serde_json::from_value(row.get("review"))?))
+            .collect()
+    }
+
+    async fn get_outcome_review(&self, id: &EventId) -> anyhow::Result<Option<OutcomeReview>> {
+        let row = self
+            .query_opt(
+                r#"SELECT review FROM outcome_review WHERE id = $1"#,
+                &[&id.as_str()],
+            )
+            .await?;
+
+        Ok(match row {
+            Some(row) => Some(serde_json::from_value(row.get("review"))?),
+            None => None,
+        })
+    }
+
+    async fn get_announcement_history(
+        &self,
+        id: &EventId,
+    ) -> Result<Vec<RawAnnouncement<C>>, Error> {
+        let rows = self
+            .query(
+                r#"SELECT (ann).oracle_event, (ann).signature FROM announcement_history
+                 WHERE id = $1
+                 ORDER BY n ASC"#,
+                &[&id.as_str()],
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| RawAnnouncement {
+                oracle_event: RawOracleEvent::from_json_bytes(row.get("oracle_event")),
+                signature: row.get("signature"),
+            })
+            .collect())
+    }
+
+    async fn get_nonce_batches(&self, path: PathRef<'_>) -> Result<Vec<RawNonceBatch<C>>, Error> {
+        let rows = self
+            .query(
+                r#"SELECT batch, signature FROM nonce_batch WHERE path = $1 ORDER BY n ASC"#,
+                &[&path.as_str()],
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| RawNonceBatch::from_parts(row.get("batch"), row.get("signature")))
+            .collect())
+    }
+
+    async fn get_all_nonce_batches(&self) -> Result<Vec<RawNonceBatch<C>>, Error> {
+        let rows = self
+            .query(
+                r#"SELECT batch, signature FROM nonce_batch ORDER BY n ASC"#,
+                &[],
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| RawNonceBatch::from_parts(row.get("batch"), row.get("signature")))
+            .collect())
+    }
 }
 
 #[async_trait]
@@ -455,6 +668,37 @@ impl<C: Group> crate::db::DbReadOracle<C> for PgBackendWrite {
     async fn get_public_keys(&self) -> Result<Option<olivia_core::OracleKeys<C>>, Error> {
         self.client.read().await.get_public_keys().await
     }
+
+    async fn get_key_history(&self) -> anyhow::Result<Option<KeyHistory<C>>> {
+        self.client.read().await.get_key_history().await
+    }
+
+    async fn get_namespace_keys(&self) -> anyhow::Result<Vec<NamespaceKeys<C>>> {
+        self.client.read().await.get_namespace_keys().await
+    }
+
+    async fn get_announcement_history(
+        &self,
+        id: &EventId,
+    ) -> Result<Vec<RawAnnouncement<C>>, Error> {
+        self.client.read().await.get_announcement_history(id).await
+    }
+
+    async fn get_nonce_batches(&self, path: PathRef<'_>) -> Result<Vec<RawNonceBatch<C>>, Error> {
+        self.client.read().await.get_nonce_batches(path).await
+    }
+
+    async fn get_all_nonce_batches(&self) -> Result<Vec<RawNonceBatch<C>>, Error> {
+        self.client.read().await.get_all_nonce_batches().await
+    }
+
+    async fn get_outcome_reviews(&self) -> anyhow::Result<Vec<OutcomeReview>> {
+        DbReadOracle::<C>::get_outcome_reviews(&*self.client.read().await).await
+    }
+
+    async fn get_outcome_review(&self, id: &EventId) -> anyhow::Result<Option<OutcomeReview>> {
+        DbReadOracle::<C>::get_outcome_review(&*self.client.read().await, id).await
+    }
 }
 
 #[async_trait]
@@ -524,13 +768,14 @@ impl<C: Group> crate::db::DbWrite<C> for PgBackendWrite {
         self.set_node_parents(&tx, node).await?;
 
         tx.execute(
-            "INSERT INTO event (id, expected_outcome_time, ann, path) VALUES ($1,$2,ROW($3,$4), $5)",
+            "INSERT INTO event (id, expected_outcome_time, ann, path, epoch) VALUES ($1,$2,ROW($3,$4), $5, $6)",
             &[
                 &event.event.id.as_str(),
                 &event.event.expected_outcome_time,
                 &event.announcement.oracle_event.as_bytes(),
                 &event.announcement.signature,
-                &Ltree::from(event.event.id.path())
+                &Ltree::from(event.event.id.path()),
+                &(event.epoch as i32),
             ],
         )
         .await?;
@@ -542,6 +787,23 @@ impl<C: Group> crate::db::DbWrite<C> for PgBackendWrite {
         Ok(())
     }
 
+    async fn begin_attestation(&self, event_id: &EventId, outcome: &str) -> Result<String, Error> {
+        let client = self.client.read().await;
+        client
+            .execute(
+                "INSERT INTO attesting (id, outcome) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING",
+                &[&event_id.as_str(), &outcome],
+            )
+            .await?;
+        let row = client
+            .query_one(
+                "SELECT outcome FROM attesting WHERE id = $1",
+                &[&event_id.as_str()],
+            )
+            .await?;
+        Ok(row.get(0))
+    }
+
     async fn complete_event(
         &self,
         event_id: &EventId,
@@ -551,6 +813,39 @@ impl<C: Group> crate::db::DbWrite<C> for PgBackendWrite {
         Ok(())
     }
 
+    async fn reschedule_event(
+        &self,
+        event: Event,
+        announcement: RawAnnouncement<C>,
+    ) -> Result<(), Error> {
+        let mut client = self.client.write().await;
+        let tx = client.transaction().await?;
+        let archived = tx
+            .execute(
+                "INSERT INTO announcement_history (id, ann) SELECT id, ann FROM event WHERE id = $1 AND (att).outcome IS NULL AND NOT EXISTS (SELECT 1 FROM attesting WHERE attesting.id = event.id)",
+                &[&event.id.as_str()],
+            )
+            .await?;
+        if archived != 1 {
+            return Err(anyhow::anyhow!(
+                "Cannot reschedule {} as it does not exist or is being attested to",
+                event.id
+            ));
+        }
+        tx.execute(
+            "UPDATE event SET expected_outcome_time = $2, ann = ROW($3,$4) WHERE id = $1",
+            &[
+                &event.id.as_str(),
+                &event.expected_outcome_time,
+                &announcement.oracle_event.as_bytes(),
+                &announcement.signature,
+            ],
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
     async fn set_public_keys(&self, public_keys: OracleKeys<C>) -> Result<(), Error> {
         let value = serde_json::to_value(public_keys).unwrap();
         let key = "public_keys";
@@ -565,6 +860,81 @@ impl<C: Group> crate::db::DbWrite<C> for PgBackendWrite {
         Ok(())
     }
 
+    async fn insert_key_succession(&self, succession: RawKeySuccession<C>) -> Result<(), Error> {
+        let epoch = succession
+            .decode()
+            .ok_or_else(|| anyhow::anyhow!("key succession is malformed"))?
+            .keys
+            .epoch as i32;
+        let inserted = self
+            .client
+            .read()
+            .await
+            .execute(
+                "INSERT INTO key_succession (epoch, succession, signature) SELECT $1, $2, $3 WHERE $1 = (SELECT COALESCE(max(epoch), 0) + 1 FROM key_succession)",
+                &[&epoch, &succession.succession, &succession.signature],
+            )
+            .await?;
+        if inserted != 1 {
+            return Err(anyhow::anyhow!(
+                "key succession for epoch {} is out of order",
+                epoch
+            ));
+        }
+        Ok(())
+    }
+
+    async fn set_namespace_keys(&self, namespace: NamespaceKeys<C>) -> Result<(), Error> {
+        let keys = serde_json::to_value(namespace.keys).unwrap();
+        self.client
+            .read()
+            .await
+            .execute(
+                "INSERT INTO namespace_keys (path, keys) VALUES ($1, $2) ON CONFLICT (path) DO UPDATE SET keys = EXCLUDED.keys",
+                &[&namespace.path.as_str(), &keys],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_nonce_batch(&self, path: &Path, batch: RawNonceBatch<C>) -> Result<(), Error> {
+        self.client
+            .read()
+            .await
+            .execute(
+                "INSERT INTO nonce_batch (path, batch, signature) VALUES ($1, $2, $3)",
+                &[&path.as_str(), &batch.batch, &batch.signature],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn set_outcome_review(&self, review: OutcomeReview) -> Result<(), Error> {
+        let json = serde_json::to_value(&review).unwrap();
+        self.client
+            .read()
+            .await
+            .execute(
+                "INSERT INTO outcome_review (id, review) VALUES ($1, $2) ON CONFLICT (id) DO UPDATE SET review = EXCLUDED.review",
+                &[&review.event_id.as_str(), &json],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_outcome_review(&self, event_id: &EventId) -> Result<bool, Error> {
+        let removed = self
+            .client
+            .read()
+            .await
+            .execute(
+                "DELETE FROM outcome_review WHERE id = $1",
+                &[&event_id.as_str()],
+            )
+            .await?;
+        Ok(removed == 1)
+    }
+
     async fn set_node(&self, node: Node) -> anyhow::Result<()> {
         let kind_json = serde_json::to_value(&node.kind).unwrap();
         let mut client = self.client.write().await;
@@ -585,20 +955,28 @@ async fn _complete_event<Client: tokio_postgres::GenericClient, C: Group>(
     event_id: &EventId,
     attestation: Attestation<C>,
     client: &mut Client,
-) -> Result<(), tokio_postgres::Error> {
+) -> Result<(), Error> {
     let Attestation {
         outcome,
-        schemes: AttestationSchemes {
-            olivia_v1,
-            ecdsa_v1,
-        },
+        schemes:
+            AttestationSchemes {
+                olivia_v1,
+                ecdsa_v1,
+                dlc_v1,
+            },
         time,
     } = attestation;
-    client.execute(
-        "UPDATE event SET att.outcome = $2, att.time = $3, att.olivia_v1_scalars= $4, att.ecdsa_v1_signature = $5 WHERE id = $1",
-        &[&event_id.as_str(), &outcome, &time, &olivia_v1.map(|x| x.scalars), &ecdsa_v1.map(|x| x.signature)],
+    let updated = client.execute(
+        "UPDATE event SET att.outcome = $2, att.time = $3, att.olivia_v1_scalars= $4, att.ecdsa_v1_signature = $5, att.dlc_v1_signatures = $6 WHERE id = $1 AND (att).outcome IS NULL AND NOT EXISTS (SELECT 1 FROM attesting WHERE attesting.id = $1 AND attesting.outcome <> $2)",
+        &[&event_id.as_str(), &outcome, &time, &olivia_v1.map(|x| x.scalars), &ecdsa_v1.map(|x| x.signature), &dlc_v1.map(|x| x.signatures)],
     )
           .await?;
+    if updated != 1 {
+        return Err(anyhow::anyhow!(
+            "Cannot complete {} as it does not exist, has been attested to or is being attested to with a different outcome",
+            event_id
+        ));
+    }
     Ok(())
 }
 
@@ -646,6 +1024,7 @@ crate::run_time_db_tests! {
 crate::run_rest_api_tests! {
     oracle => oracle,
     routes => routes,
+    db => db,
     curve => olivia_secp256k1::Secp256k1,
     {
         use testcontainers::{clients, images, Docker};
@@ -654,9 +1033,10 @@ crate::run_rest_api_tests! {
         let (url, _container) = new_backend!(docker);
         let db_oracle = PgBackendWrite::connect(&url).await.unwrap();
         db_oracle.setup().await.unwrap();
+        let db: Arc<dyn Db<olivia_secp256k1::Secp256k1>> = Arc::new(db_oracle);
         let http_db = connect_read(&url).await.unwrap();
-        let oracle = crate::oracle::Oracle::<olivia_secp256k1::Secp256k1>::new(crate::seed::Seed::new([42u8; 64]), Arc::new(db_oracle)).await.unwrap();
-        let routes = crate::rest_api::routes::<olivia_secp256k1::Secp256k1>(Arc::new(http_db), slog::Logger::root(slog::Discard, o!()));
+        let oracle = crate::oracle::Oracle::<olivia_secp256k1::Secp256k1>::new(crate::seed::Seed::new([42u8; 64]), db.clone()).await.unwrap();
+        let routes = crate::rest_api::routes::<olivia_secp256k1::Secp256k1>(Arc::new(http_db), None, slog::Logger::root(slog::Discard, o!()));
     }
 }
 
@@ -734,6 +1114,18 @@ mod test {
             db.clone(),
         )
         .await;
+        crate::oracle::test::test_void_oracle_event_lifecycle::<olivia_secp256k1::Secp256k1>(
+            db.clone(),
+        )
+        .await;
+        crate::oracle::test::test_reschedule_oracle_event::<olivia_secp256k1::Secp256k1>(
+            db.clone(),
+        )
+        .await;
+        crate::oracle::test::test_racing_outcomes::<olivia_secp256k1::Secp256k1>(db.clone()).await;
+        crate::oracle::test::test_attestation_window::<olivia_secp256k1::Secp256k1>(db.clone())
+            .await;
+        crate::oracle::test::test_key_rotation::<olivia_secp256k1::Secp256k1>(db.clone()).await;
     }
 
     #[tokio::test]
@@ -743,6 +1135,56 @@ mod test {
         let db = PgBackendWrite::connect(&url).await.unwrap();
         db.setup().await.unwrap();
         let version = db.version().await.unwrap();
-        assert_eq!(version.version, 0);
+        assert_eq!(version.version as usize, MIGRATIONS.len());
+        db.check_version().await.unwrap();
+        // running it again doesn't do anything
+        db.setup().await.unwrap();
+        assert_eq!(
+            db.version().await.unwrap().version as usize,
+            MIGRATIONS.len()
+        );
+    }
+
+    #[tokio::test]
+    async fn migrate_version_0_database() {
+        use std::str::FromStr;
+        let docker = clients::Cli::default();
+        let (url, _container) = new_backend!(docker);
+        let db = PgBackendWrite::connect(&url).await.unwrap();
+        assert!(db.check_version().await.is_err());
+        db.client
+            .read()
+            .await
+            .batch_execute(include_str!("postgres/init.sql"))
+            .await
+            .unwrap();
+        assert_eq!(db.version().await.unwrap().version, 0);
+        assert!(db.check_version().await.is_err());
+
+        // an event written before the migrations is still there afterwards
+        let id = EventId::from_str("/test/postgres/migrated.occur").unwrap();
+        let event = AnnouncedEvent::<olivia_secp256k1::Secp256k1>::test_unattested_instance(
+            id.clone().into(),
+        );
+        db.client
+            .read()
+            .await
+            .execute(
+                "INSERT INTO event (id, expected_outcome_time, ann, path) VALUES ($1,$2,ROW($3,$4),$5)",
+                &[
+                    &id.as_str(),
+                    &event.event.expected_outcome_time,
+                    &event.announcement.oracle_event.as_bytes(),
+                    &event.announcement.signature,
+                    &Ltree::from(id.path()),
+                ],
+            )
+            .await
+            .unwrap();
+
+        db.setup().await.unwrap();
+        db.check_version().await.unwrap();
+        let db: Arc<dyn crate::db::Db<olivia_secp256k1::Secp256k1>> = Arc::new(db);
+        assert_eq!(db.get_announced_event(&id).await.unwrap(), Some(event));
     }
 }
@@ -0,0 +1,8 @@
+-- Announcements of events that were replaced when the event was rescheduled
+CREATE TABLE announcement_history (
+       n bigserial PRIMARY KEY,
+       id text NOT NULL REFERENCES event (id),
+       ann announcement NOT NULL
+);
+
+CREATE INDEX idx_announcement_history_id ON announcement_history (id);
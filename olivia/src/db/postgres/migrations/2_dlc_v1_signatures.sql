@@ -0,0 +1 @@
+ALTER TYPE attestation ADD ATTRIBUTE dlc_v1_signatures bytea[];
@@ -0,0 +1,9 @@
+-- Nonces committed to ahead of time for events under path
+CREATE TABLE nonce_batch (
+       n bigserial PRIMARY KEY,
+       path text NOT NULL,
+       batch text NOT NULL,
+       signature bytea NOT NULL
+);
+
+CREATE INDEX idx_nonce_batch_path ON nonce_batch (path);
@@ -0,0 +1,6 @@
+-- The outcome each event is being attested to. It's written before anything is signed so that two
+-- outcomes can never be attested to for the same event (which would reveal the attestation key).
+CREATE TABLE attesting (
+       id text NOT NULL PRIMARY KEY REFERENCES event (id),
+       outcome text NOT NULL
+);
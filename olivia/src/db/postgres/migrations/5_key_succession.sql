@@ -0,0 +1,9 @@
+-- The epoch of the oracle keys the event was announced with
+ALTER TABLE event ADD COLUMN epoch integer NOT NULL DEFAULT 0;
+
+-- The keys the oracle moved on to in each epoch after its original ones (in meta)
+CREATE TABLE key_succession (
+       epoch integer PRIMARY KEY,
+       succession text NOT NULL,
+       signature bytea NOT NULL
+);
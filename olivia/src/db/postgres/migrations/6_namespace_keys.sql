@@ -0,0 +1,5 @@
+-- The keys of the paths whose events aren't signed with the oracle's main keys
+CREATE TABLE namespace_keys (
+       path text NOT NULL PRIMARY KEY,
+       keys jsonb NOT NULL
+);
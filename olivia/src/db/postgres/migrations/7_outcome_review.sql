@@ -0,0 +1,5 @@
+-- Events whose outcome sources couldn't agree on an outcome and are waiting for an operator
+CREATE TABLE outcome_review (
+       id text NOT NULL PRIMARY KEY,
+       review jsonb NOT NULL
+);
@@ -41,4 +41,8 @@ impl DbReadEvent for PrefixedDb {
                 .collect()
         })
     }
+
+    fn event_changes(&self) -> Option<tokio::sync::watch::Receiver<()>> {
+        self.inner.event_changes()
+    }
 }
@@ -101,6 +101,29 @@ macro_rules! run_node_db_tests {
                 );
             }
 
+            #[tokio::test]
+            async fn test_begin_attestation() {
+                $($init)*;
+                let event_id = EventId::from_str("/test/db/test-begin-attestation.occur").unwrap();
+                let mut ann_event = AnnouncedEvent::test_attested_instance(event_id.clone().into());
+                let attestation = ann_event.attestation.take().unwrap();
+                let mut other_attestation = attestation.clone();
+                other_attestation.outcome = "other".into();
+
+                assert!($db.begin_attestation(&event_id, &attestation.outcome).await.is_err(), "event doesn't exist yet");
+                $db.insert_event(ann_event.clone()).await.unwrap();
+
+                assert_eq!($db.begin_attestation(&event_id, &attestation.outcome).await.unwrap(), attestation.outcome);
+                assert_eq!($db.begin_attestation(&event_id, "other").await.unwrap(), attestation.outcome, "first outcome should stick");
+                assert!($db.reschedule_event(ann_event.event.clone(), ann_event.announcement.clone()).await.is_err());
+                assert!($db.complete_event(&event_id, other_attestation).await.is_err());
+                assert_eq!($db.get_announced_event(&event_id).await.unwrap().unwrap().attestation, None);
+
+                $db.complete_event(&event_id, attestation.clone()).await.unwrap();
+                assert!($db.complete_event(&event_id, attestation.clone()).await.is_err(), "can't attest twice");
+                assert_eq!($db.get_announced_event(&event_id).await.unwrap().unwrap().attestation, Some(attestation));
+            }
+
             #[tokio::test]
             async fn test_insert_grandchild_event() {
                 $($init)*;
@@ -175,6 +198,80 @@ macro_rules! run_node_db_tests {
                 assert_eq!(oracle_keys, retrieved_keys);
             }
 
+            #[tokio::test]
+            async fn test_insert_key_successions() {
+                $($init)*;
+                let genesis = <$curve>::test_oracle_keys();
+                $db.set_public_keys(genesis.clone()).await.unwrap();
+                let succession = |epoch| {
+                    let keys = olivia_core::OracleKeys { epoch, ..genesis.clone() };
+                    let succession = olivia_core::KeySuccession {
+                        keys,
+                        time: olivia_core::chrono::NaiveDateTime::from_str("2021-10-04T00:00:00").unwrap(),
+                        namespaces: vec![],
+                    };
+                    olivia_core::RawKeySuccession::<$curve>::create(succession, &<$curve>::test_keypair())
+                };
+                let successions = vec![succession(1), succession(2)];
+                assert!($db.insert_key_succession(successions[1].clone()).await.is_err(), "can't skip an epoch");
+                $db.insert_key_succession(successions[0].clone()).await.unwrap();
+                assert!($db.insert_key_succession(successions[0].clone()).await.is_err(), "can't replace an epoch");
+                $db.insert_key_succession(successions[1].clone()).await.unwrap();
+
+                assert_eq!($db.get_public_keys().await.unwrap().unwrap().epoch, 2);
+                let history = $db.get_key_history().await.unwrap().unwrap();
+                assert_eq!(history.genesis, genesis);
+                assert_eq!(history.successions, successions);
+            }
+
+            #[tokio::test]
+            async fn test_insert_and_get_nonce_batches() {
+                $($init)*;
+                let path = Path::from_str("/test/db/nonce-batches").unwrap();
+                let batches = (0..2)
+                    .map(|i| {
+                        olivia_core::RawNonceBatch::<$curve>::from_parts(
+                            format!("batch {}", i),
+                            <$curve>::sign_announcement(&<$curve>::test_keypair(), b"batch"),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                for batch in &batches {
+                    $db.insert_nonce_batch(&path, batch.clone()).await.unwrap();
+                }
+                assert_eq!($db.get_nonce_batches(path.as_path_ref()).await.unwrap(), batches);
+                assert!($db.get_nonce_batches(path!("/test/db")).await.unwrap().is_empty());
+                let all = $db.get_all_nonce_batches().await.unwrap();
+                assert!(batches.iter().all(|batch| all.contains(batch)));
+            }
+
+            #[tokio::test]
+            async fn test_outcome_reviews() {
+                use $crate::quorum::{OutcomeReview, Proposal};
+                $($init)*;
+                let event_id = EventId::from_str("/test/db/review/MIL_BKN.winner").unwrap();
+                let proposal = |source: usize, outcome: &str| Proposal {
+                    source,
+                    outcome: outcome.into(),
+                    time: olivia_core::chrono::NaiveDateTime::from_str("2021-06-21T02:00:00").unwrap(),
+                };
+                let mut review = OutcomeReview {
+                    event_id: event_id.clone(),
+                    path: Path::from_str("/test/db/review").unwrap(),
+                    proposals: vec![proposal(0, "MIL"), proposal(1, "BKN")],
+                };
+                $db.set_outcome_review(review.clone()).await.unwrap();
+                assert_eq!($db.get_outcome_review(&event_id).await.unwrap(), Some(review.clone()));
+
+                review.propose(proposal(2, "MIL"));
+                $db.set_outcome_review(review.clone()).await.unwrap();
+                assert_eq!($db.get_outcome_reviews().await.unwrap(), vec![review]);
+
+                assert!($db.remove_outcome_review(&event_id).await.unwrap());
+                assert!(!$db.remove_outcome_review(&event_id).await.unwrap());
+                assert_eq!($db.get_outcome_review(&event_id).await.unwrap(), None);
+            }
+
             #[tokio::test]
             async fn test_set_node() {
                 $($init)*;
@@ -1,16 +1,50 @@
 use crate::seed::Seed;
+use async_trait::async_trait;
 use blake2::digest::{Update, VariableOutput};
 use olivia_core::{
-    announce, AnnouncementSchemes, Event, EventId, Group, OracleKeys, Outcome, RawAnnouncement,
-    StampedOutcome,
+    announce, attest, AnnouncementSchemes, AttestationSchemes, BatchSchedule, Descriptor, Event,
+    EventId, Group, KeySuccession, NamespaceKeys, NonceBatch, OracleEvent, OracleKeys, Outcome,
+    Path, PathRef, RawAnnouncement, RawKeySuccession, RawNonceBatch,
 };
 use std::borrow::Borrow;
 
+/// Something that holds the oracle's secret keys and can sign announcements and attestations with
+/// them. [`KeyChain`] holds all the keys itself but they could just as well be split up between
+/// several machines.
+#[async_trait]
+pub trait Signer<C: Group>: Send + Sync {
+    fn oracle_public_keys(&self) -> OracleKeys<C>;
+    /// The keys used instead of the oracle's main ones for the events under particular paths.
+    fn namespace_public_keys(&self) -> Vec<NamespaceKeys<C>> {
+        Vec::new()
+    }
+    async fn create_announcement(&self, event: Event) -> anyhow::Result<RawAnnouncement<C>>;
+    /// Signs the nonces that the events in `schedule` will be announced with ahead of time.
+    async fn create_nonce_batch(&self, schedule: BatchSchedule)
+        -> anyhow::Result<RawNonceBatch<C>>;
+    /// Attests to `outcome` under each scheme `oracle_event` was announced with. An `outcome` of
+    /// `None` voids the event.
+    async fn attest(
+        &self,
+        oracle_event: &OracleEvent<C>,
+        outcome: Option<&Outcome>,
+    ) -> anyhow::Result<AttestationSchemes<C>>;
+    /// Hands the oracle over to the keys in `succession` by signing it with the current
+    /// announcement key.
+    async fn sign_key_succession(
+        &self,
+        succession: KeySuccession<C>,
+    ) -> anyhow::Result<RawKeySuccession<C>>;
+}
+
 pub struct KeyChain<C: Group> {
     announcement_keypair: C::KeyPair,
     olivia_v1_keypair: C::KeyPair,
     ecdsa_v1_keypair: C::KeyPair,
     event_seed: Seed,
+    dlc_v1_event_seed: Seed,
+    namespace_seed: Seed,
+    namespaces: Vec<(Path, KeyChain<C>)>,
 }
 
 impl<C: Group> KeyChain<C> {
@@ -34,23 +68,72 @@ impl<C: Group> KeyChain<C> {
 
         Self {
             event_seed: seed.child(b"oracle-events"),
+            dlc_v1_event_seed: seed.child(b"dlc-v1-events"),
+            namespace_seed: seed.child(b"namespaces"),
+            namespaces: Vec::new(),
             announcement_keypair,
             olivia_v1_keypair,
             ecdsa_v1_keypair,
         }
     }
 
+    /// Gives the events under each of `paths` keys of their own. A namespace's keys are derived
+    /// from the seed and its path alone so they say nothing about the main keys or the keys of
+    /// any other namespace.
+    pub fn with_namespaces(mut self, paths: impl IntoIterator<Item = Path>) -> Self {
+        self.namespaces = paths
+            .into_iter()
+            .map(|path| {
+                let keychain = KeyChain::new(self.namespace_seed.child(path.as_str().as_bytes()));
+                (path, keychain)
+            })
+            .collect();
+        self
+    }
+
+    /// The keys for the events under `path`: those of the most specific namespace it's in or else
+    /// the main ones.
+    pub fn for_path(&self, path: PathRef<'_>) -> &KeyChain<C> {
+        self.namespaces
+            .iter()
+            .filter(|(namespace, _)| namespace.as_path_ref().is_parent_of(path))
+            .max_by_key(|(namespace, _)| namespace.as_str().len())
+            .map(|(_, keychain)| keychain)
+            .unwrap_or(self)
+    }
+
+    pub fn namespace_public_keys(&self) -> Vec<NamespaceKeys<C>> {
+        self.namespaces
+            .iter()
+            .map(|(path, keychain)| NamespaceKeys {
+                path: path.clone(),
+                keys: keychain.oracle_public_keys(),
+            })
+            .collect()
+    }
+
     pub fn oracle_public_keys(&self) -> OracleKeys<C> {
         OracleKeys {
             olivia_v1: Some(self.olivia_v1_keypair.clone().into()),
             ecdsa_v1: Some(self.ecdsa_v1_keypair.clone().into()),
             announcement: self.announcement_keypair.clone().into(),
             group: C::default(),
+            epoch: 0,
         }
     }
 
     pub fn nonces_for_event(&self, event_id: &EventId) -> Vec<C::NonceKeyPair> {
-        let event_seed = self.event_seed.child(event_id.as_bytes());
+        Self::derive_nonces(&self.event_seed, event_id)
+    }
+
+    /// The `dlc_v1` nonces are derived separately from the `olivia_v1` ones so that no nonce is
+    /// ever used by both schemes.
+    pub fn dlc_v1_nonces_for_event(&self, event_id: &EventId) -> Vec<C::NonceKeyPair> {
+        Self::derive_nonces(&self.dlc_v1_event_seed, event_id)
+    }
+
+    fn derive_nonces(seed: &Seed, event_id: &EventId) -> Vec<C::NonceKeyPair> {
+        let event_seed = seed.child(event_id.as_bytes());
         let n = event_id.event_kind().n_nonces();
         let hash = event_seed.to_blake2b_var(C::KEY_MATERIAL_LEN);
         (0..n)
@@ -62,18 +145,29 @@ impl<C: Group> KeyChain<C> {
             .collect()
     }
 
-    pub fn olivia_v1_scalars_for_event_outcome(
+    pub fn olivia_v1_scalars_for_event_outcome(&self, outcome: &Outcome) -> Vec<C::AttestScalar> {
+        let event_id = &outcome.id;
+        let indexes = outcome.attestation_indexes();
+        for (i, index) in indexes.iter().enumerate() {
+            // each digit must be one of the outcomes the nonce was announced with
+            assert!(*index < event_id.n_outcomes_for_nonce(i));
+        }
+        self.olivia_v1_scalars_for_indexes(event_id, &indexes)
+    }
+
+    pub fn olivia_v1_void_scalars_for_event(&self, event_id: &EventId) -> Vec<C::AttestScalar> {
+        self.olivia_v1_scalars_for_indexes(event_id, &event_id.void_attestation_indexes())
+    }
+
+    fn olivia_v1_scalars_for_indexes(
         &self,
-        stamped: &StampedOutcome,
+        event_id: &EventId,
+        indexes: &[u32],
     ) -> Vec<C::AttestScalar> {
-        let event_id = &stamped.outcome.id;
         let event_seed = self.event_seed.child(event_id.as_bytes());
         let hash = event_seed.to_blake2b_var(C::KEY_MATERIAL_LEN);
         let attest_keypair = &self.olivia_v1_keypair;
-        hash.clone().finalize_boxed();
-        stamped
-            .outcome
-            .attestation_indexes()
+        indexes
             .iter()
             .enumerate()
             .map(|(i, index)| {
@@ -95,24 +189,155 @@ impl<C: Group> KeyChain<C> {
             .collect()
     }
 
-    pub fn create_announcement(&self, event: Event) -> RawAnnouncement<C> {
+    pub fn dlc_v1_signatures_for_event_outcome(&self, outcome: &Outcome) -> Vec<C::Signature> {
+        let outcomes = outcome
+            .dlc_outcomes()
+            .expect("dlc-v1 is only announced for events with a descriptor");
+        let public_key = self.announcement_keypair.clone().into();
+        self.dlc_v1_nonces_for_event(&outcome.id)
+            .into_iter()
+            .zip(&outcomes)
+            .map(|(nonce_keypair, outcome)| {
+                let public_nonce = nonce_keypair.clone().into();
+                let signature =
+                    C::dlc_sign_outcome(&self.announcement_keypair, nonce_keypair, outcome);
+                // Always verify the attestation before publishing it
+                assert!(C::dlc_verify_outcome(
+                    &public_key,
+                    &public_nonce,
+                    outcome,
+                    &signature
+                ));
+                signature
+            })
+            .collect()
+    }
+
+    /// The nonces `event_id` is announced with. They only depend on the id so they can be handed
+    /// out before the event is created.
+    pub fn announcement_schemes(&self, event_id: &EventId) -> AnnouncementSchemes<C> {
         let nonces = self
-            .nonces_for_event(&event.id)
+            .nonces_for_event(event_id)
             .into_iter()
             .map(|nonce_kp| nonce_kp.into())
             .collect::<Vec<_>>();
+        // the DLC spec signs with the announcement key so it can only be used for events it can
+        // describe
+        let dlc_v1_nonces = match event_id.descriptor() {
+            Descriptor::MissingDescriptor => vec![],
+            _ => self
+                .dlc_v1_nonces_for_event(event_id)
+                .into_iter()
+                .map(|nonce_kp| nonce_kp.into())
+                .collect(),
+        };
 
-        let schemes = AnnouncementSchemes {
+        AnnouncementSchemes {
             olivia_v1: match nonces.is_empty() {
                 true => None,
                 false => Some(announce::OliviaV1 { nonces }),
             },
             ecdsa_v1: Some(announce::EcdsaV1 {}),
-        };
+            dlc_v1: match dlc_v1_nonces.is_empty() {
+                true => None,
+                false => Some(announce::DlcV1 {
+                    nonces: dlc_v1_nonces,
+                }),
+            },
+        }
+    }
+
+    pub fn create_announcement(&self, event: Event) -> RawAnnouncement<C> {
+        let schemes = self.announcement_schemes(&event.id);
         RawAnnouncement::create(event, &self.announcement_keypair, schemes)
     }
 
+    pub fn create_nonce_batch(&self, schedule: BatchSchedule) -> RawNonceBatch<C> {
+        let slots = (0..schedule.n_slots)
+            .filter_map(|slot| schedule.slot_event_id(slot))
+            .map(|event_id| AnnouncementSchemes {
+                // the batch only commits to nonces
+                ecdsa_v1: None,
+                ..self.announcement_schemes(&event_id)
+            })
+            .collect();
+        RawNonceBatch::create(NonceBatch { schedule, slots }, &self.announcement_keypair)
+    }
+
+    pub fn sign_key_succession(&self, succession: KeySuccession<C>) -> RawKeySuccession<C> {
+        RawKeySuccession::create(succession, &self.announcement_keypair)
+    }
+
     pub fn ecdsa_sign_outcome(&self, outcome: &Outcome) -> C::EcdsaSignature {
-        C::ecdsa_sign(&self.announcement_keypair, &outcome.attestation_string())
+        C::ecdsa_sign(&self.ecdsa_v1_keypair, &outcome.attestation_string())
+    }
+
+    pub fn ecdsa_sign_void(&self, event_id: &EventId) -> C::EcdsaSignature {
+        C::ecdsa_sign(&self.ecdsa_v1_keypair, &event_id.void_attestation_string())
+    }
+}
+
+#[async_trait]
+impl<C: Group> Signer<C> for KeyChain<C> {
+    fn oracle_public_keys(&self) -> OracleKeys<C> {
+        KeyChain::oracle_public_keys(self)
+    }
+
+    fn namespace_public_keys(&self) -> Vec<NamespaceKeys<C>> {
+        KeyChain::namespace_public_keys(self)
+    }
+
+    async fn create_announcement(&self, event: Event) -> anyhow::Result<RawAnnouncement<C>> {
+        let keychain = self.for_path(event.id.path());
+        Ok(KeyChain::create_announcement(keychain, event))
+    }
+
+    async fn create_nonce_batch(
+        &self,
+        schedule: BatchSchedule,
+    ) -> anyhow::Result<RawNonceBatch<C>> {
+        schedule.validate()?;
+        let keychain = self.for_path(schedule.path.as_path_ref());
+        Ok(KeyChain::create_nonce_batch(keychain, schedule))
+    }
+
+    async fn attest(
+        &self,
+        oracle_event: &OracleEvent<C>,
+        outcome: Option<&Outcome>,
+    ) -> anyhow::Result<AttestationSchemes<C>> {
+        let event_id = &oracle_event.event.id;
+        let schemes = &oracle_event.schemes;
+        let keychain = self.for_path(event_id.path());
+        Ok(match outcome {
+            Some(outcome) => AttestationSchemes {
+                olivia_v1: schemes.olivia_v1.as_ref().map(|_| attest::OliviaV1 {
+                    scalars: keychain.olivia_v1_scalars_for_event_outcome(outcome),
+                }),
+                ecdsa_v1: schemes.ecdsa_v1.as_ref().map(|_| attest::EcdsaV1 {
+                    signature: keychain.ecdsa_sign_outcome(outcome),
+                }),
+                dlc_v1: schemes.dlc_v1.as_ref().map(|_| attest::DlcV1 {
+                    signatures: keychain.dlc_v1_signatures_for_event_outcome(outcome),
+                }),
+            },
+            None => AttestationSchemes {
+                olivia_v1: schemes.olivia_v1.as_ref().map(|_| attest::OliviaV1 {
+                    scalars: keychain.olivia_v1_void_scalars_for_event(event_id),
+                }),
+                ecdsa_v1: schemes.ecdsa_v1.as_ref().map(|_| attest::EcdsaV1 {
+                    signature: keychain.ecdsa_sign_void(event_id),
+                }),
+                // the DLC spec has no way of voiding an event
+                dlc_v1: None,
+            },
+        })
+    }
+
+    async fn sign_key_succession(
+        &self,
+        succession: KeySuccession<C>,
+    ) -> anyhow::Result<RawKeySuccession<C>> {
+        Ok(KeyChain::sign_key_succession(self, succession))
     }
 }
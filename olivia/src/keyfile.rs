@@ -0,0 +1,217 @@
+//! Storing the oracle's [`Seed`] somewhere other than in plaintext in the config file.
+use crate::seed::Seed;
+use anyhow::{anyhow, Context};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::RngCore;
+use std::{io::Write, path::Path, str::FromStr};
+
+/// The seed in the sample configs. Anyone can sign as an oracle using it.
+pub const SAMPLE_SEED: Seed = Seed::new([0x42; 64]);
+
+/// The environment variable keyfile passphrases are read from before prompting for them.
+pub const PASSPHRASE_ENV: &str = "OLIVIA_PASSPHRASE";
+
+/// The secret the oracle's seed comes from.
+pub enum KeyMaterial {
+    Seed(Seed),
+    /// The seed is the BIP39 seed of the mnemonic with an empty passphrase.
+    Mnemonic(bip39::Mnemonic),
+}
+
+impl KeyMaterial {
+    /// A new 24 word mnemonic.
+    pub fn generate() -> Self {
+        let mut entropy = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut entropy);
+        KeyMaterial::Mnemonic(bip39::Mnemonic::from_entropy(&entropy).expect("32 bytes is valid"))
+    }
+
+    pub fn seed(&self) -> Seed {
+        match self {
+            KeyMaterial::Seed(seed) => seed.clone(),
+            KeyMaterial::Mnemonic(mnemonic) => Seed::new(mnemonic.to_seed("")),
+        }
+    }
+
+    fn encode(&self) -> String {
+        match self {
+            KeyMaterial::Seed(seed) => crate::hex::encode(seed.as_ref()),
+            KeyMaterial::Mnemonic(mnemonic) => mnemonic.to_string(),
+        }
+    }
+}
+
+impl FromStr for KeyMaterial {
+    type Err = anyhow::Error;
+
+    /// Parses a hex seed or a BIP39 mnemonic.
+    fn from_str(string: &str) -> anyhow::Result<Self> {
+        let string = string.trim();
+        if string.contains(char::is_whitespace) {
+            let mnemonic = bip39::Mnemonic::parse_normalized(string)
+                .map_err(|e| anyhow!("invalid mnemonic: {}", e))?;
+            Ok(KeyMaterial::Mnemonic(mnemonic))
+        } else {
+            let seed = Seed::from_str(string).map_err(|e| anyhow!("invalid seed: {}", e))?;
+            Ok(KeyMaterial::Seed(seed))
+        }
+    }
+}
+
+/// [`KeyMaterial`] encrypted under a passphrase.
+///
+/// The key is derived from the passphrase with argon2id and the material is encrypted with
+/// ChaCha20-Poly1305. The file is JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Keyfile {
+    argon2id: Argon2idParams,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Argon2idParams {
+    salt: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+impl Argon2idParams {
+    fn derive_key(&self, passphrase: &str) -> anyhow::Result<Key> {
+        let salt = crate::hex::decode(&self.salt)?;
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| anyhow!("invalid argon2id parameters: {}", e))?;
+        let mut key = Key::default();
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow!("unable to derive key from passphrase: {}", e))?;
+        Ok(key)
+    }
+}
+
+impl Keyfile {
+    pub fn encrypt(material: &KeyMaterial, passphrase: &str) -> anyhow::Result<Self> {
+        Self::encrypt_with_params(
+            material,
+            passphrase,
+            Params::DEFAULT_M_COST,
+            Params::DEFAULT_T_COST,
+        )
+    }
+
+    fn encrypt_with_params(
+        material: &KeyMaterial,
+        passphrase: &str,
+        m_cost: u32,
+        t_cost: u32,
+    ) -> anyhow::Result<Self> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let argon2id = Argon2idParams {
+            salt: crate::hex::encode(&salt),
+            m_cost,
+            t_cost,
+            p_cost: 1,
+        };
+        let key = argon2id.derive_key(passphrase)?;
+        let ciphertext = ChaCha20Poly1305::new(&key)
+            .encrypt(Nonce::from_slice(&nonce), material.encode().as_bytes())
+            .map_err(|_| anyhow!("unable to encrypt keyfile"))?;
+        Ok(Keyfile {
+            argon2id,
+            nonce: crate::hex::encode(&nonce),
+            ciphertext: crate::hex::encode(&ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> anyhow::Result<KeyMaterial> {
+        let key = self.argon2id.derive_key(passphrase)?;
+        let nonce = crate::hex::decode(&self.nonce)?;
+        if nonce.len() != 12 {
+            return Err(anyhow!("keyfile nonce is the wrong length"));
+        }
+        let plaintext = ChaCha20Poly1305::new(&key)
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                crate::hex::decode(&self.ciphertext)?.as_ref(),
+            )
+            .map_err(|_| anyhow!("wrong passphrase for keyfile"))?;
+        KeyMaterial::from_str(core::str::from_utf8(&plaintext)?)
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("reading keyfile {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("invalid keyfile {}", path.display()))
+    }
+
+    /// Writes the keyfile so only the current user can read it. It won't overwrite an existing file.
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(path)
+            .with_context(|| format!("creating keyfile {}", path.display()))?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Gets a passphrase from [`PASSPHRASE_ENV`] or else by prompting for it.
+pub fn passphrase(prompt: &str) -> anyhow::Result<String> {
+    match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => Ok(rpassword::prompt_password(prompt)?),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keyfile_roundtrip() {
+        let mnemonic = KeyMaterial::generate();
+        let keyfile = Keyfile::encrypt_with_params(&mnemonic, "hunter2", 64, 1).unwrap();
+        let keyfile: Keyfile =
+            serde_json::from_str(&serde_json::to_string(&keyfile).unwrap()).unwrap();
+        let decrypted = keyfile.decrypt("hunter2").unwrap();
+        assert_eq!(decrypted.seed().as_ref(), mnemonic.seed().as_ref());
+        assert!(matches!(decrypted, KeyMaterial::Mnemonic(_)));
+        assert!(keyfile.decrypt("hunter3").is_err());
+
+        let seed = KeyMaterial::Seed(Seed::new([7u8; 64]));
+        let keyfile = Keyfile::encrypt_with_params(&seed, "", 64, 1).unwrap();
+        assert_eq!(keyfile.decrypt("").unwrap().seed().as_ref(), &[7u8; 64]);
+    }
+
+    #[test]
+    fn mnemonic_seed() {
+        // test vector from BIP39
+        let mnemonic = KeyMaterial::from_str(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        )
+        .unwrap();
+        assert_eq!(
+            crate::hex::encode(mnemonic.seed().as_ref()),
+            "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4"
+        );
+        assert!(KeyMaterial::from_str("abandon abandon abandon").is_err());
+        assert!(matches!(
+            KeyMaterial::from_str(&"42".repeat(64)).unwrap(),
+            KeyMaterial::Seed(_)
+        ));
+    }
+}
@@ -6,15 +6,22 @@ mod oracle_loop;
 pub mod seed;
 pub use crate::oracle::Oracle;
 
+pub mod attestation_policy;
 pub mod cli;
 pub mod config;
-mod hex;
+use olivia_core::hex;
 pub mod keychain;
+pub mod keyfile;
 pub mod log;
 mod macros;
+pub mod quorum;
+pub mod remote_signer;
 pub mod rest_api;
 pub mod sources;
+pub mod threshold;
+pub mod threshold_peer;
 mod util;
+pub mod write_api;
 pub use serde;
 
 mod rest_api_tests;
@@ -1,8 +1,8 @@
-use crate::oracle::{EventResult, OutcomeResult};
+use crate::oracle::{Attested, EventResult, OutcomeResult};
 
 pub trait OracleLog {
     fn log_event_result(&self, res: Result<(), EventResult>);
-    fn log_outcome_result(&self, res: Result<(), OutcomeResult>);
+    fn log_outcome_result(&self, res: Result<Attested, OutcomeResult>);
 }
 
 impl OracleLog for slog::Logger {
@@ -14,16 +14,26 @@ impl OracleLog for slog::Logger {
                 Changed => info!(self, "changed"),
                 AlreadyExists => debug!(self, "ignored - already exists"),
                 AlreadyCompleted => debug!(self, "ignored - already completed"),
+                RetiredKeys { epoch } => {
+                    warn!(self, "ignored - announced with retired keys"; "epoch" => epoch)
+                }
+                ReservedPath { segment } => {
+                    warn!(self, "ignored - path is reserved"; "segment" => segment)
+                }
                 DbReadErr(e) => crit!(self,"database read";"error" => format!("{}",e)),
                 DbWriteErr(e) => crit!(self,"database write"; "error" => format!("{}", e)),
+                SignerErr(e) => crit!(self, "signing announcement"; "error" => format!("{}", e)),
             },
         }
     }
 
-    fn log_outcome_result(&self, res: Result<(), OutcomeResult>) {
+    fn log_outcome_result(&self, res: Result<Attested, OutcomeResult>) {
         use OutcomeResult::*;
         match res {
-            Ok(_) => info!(self, "completed"),
+            Ok(Attested::OnTime) => info!(self, "completed"),
+            Ok(Attested::Late { by }) => {
+                error!(self, "completed late"; "seconds_after_expected" => by.num_seconds())
+            }
             Err(e) => match e {
                 AlreadyCompleted => debug!(self, "already completed"),
                 OutcomeChanged { existing, new } => {
@@ -33,6 +43,13 @@ impl OracleLog for slog::Logger {
                 DbReadErr(e) => crit!(self, "database read"; "error" => format!("{}", e)),
                 DbWriteErr(e) => crit!(self, "database write"; "error" => format!("{}", e)),
                 AnnouncementWasBogus => crit!(self, "bogus announcement"),
+                TooEarly { earliest } => {
+                    error!(self, "refused to attest before attestation window"; "earliest" => earliest.to_string())
+                }
+                RetiredKeys { epoch } => {
+                    error!(self, "announced with retired keys"; "epoch" => epoch)
+                }
+                SignerErr(e) => crit!(self, "signing attestation"; "error" => format!("{}", e)),
             },
         }
     }
@@ -1,8 +1,14 @@
-use crate::{keychain::KeyChain, seed::Seed};
+use crate::{
+    attestation_policy::{AttestationPolicy, Timing},
+    db::EventQuery,
+    keychain::{KeyChain, Signer},
+    seed::Seed,
+};
 use anyhow::anyhow;
 use olivia_core::{
-    attest, AnnouncedEvent, Attestation, AttestationSchemes, Event, Group, OracleKeys,
-    StampedOutcome,
+    chrono::{Duration, NaiveDateTime, Utc},
+    AnnouncedEvent, Attestation, BatchSchedule, Event, EventId, Group, KeySuccession,
+    NamespaceKeys, OracleKeys, Outcome, PathRef, StampedOutcome, VOID_OUTCOME,
 };
 use std::sync::Arc;
 
@@ -14,10 +20,20 @@ pub enum EventResult {
     AlreadyCompleted,
     #[error("event already exists but was updated")]
     Changed,
+    #[error(
+        "event was announced with the oracle's keys from epoch {epoch} which have been rotated out"
+    )]
+    RetiredKeys { epoch: u32 },
+    #[error(
+        "events can't be created under /{segment} because the REST API serves something else there"
+    )]
+    ReservedPath { segment: String },
     #[error("unable to read from database: {0}")]
     DbReadErr(crate::db::Error),
     #[error("unable to write to database: {0}")]
     DbWriteErr(crate::db::Error),
+    #[error("unable to sign announcement: {0}")]
+    SignerErr(anyhow::Error),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -34,51 +50,312 @@ pub enum OutcomeResult {
     DbWriteErr(crate::db::Error),
     #[error("the announcement for this event was no longer valid when read from database")]
     AnnouncementWasBogus,
+    #[error(
+        "event was announced with the oracle's keys from epoch {epoch} which have been rotated out"
+    )]
+    RetiredKeys { epoch: u32 },
+    #[error("the attestation policy doesn't allow attesting to the event until {earliest}")]
+    TooEarly { earliest: NaiveDateTime },
+    #[error("unable to sign attestation: {0}")]
+    SignerErr(anyhow::Error),
+}
+
+/// How an attestation went relative to the event's [`AttestationWindow`].
+///
+/// [`AttestationWindow`]: crate::attestation_policy::AttestationWindow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attested {
+    OnTime,
+    /// The attestation was made `by` after the event's expected outcome time which is later than
+    /// the window allows.
+    Late {
+        by: Duration,
+    },
 }
 
 pub struct Oracle<C: Group> {
     db: Arc<dyn crate::db::Db<C>>,
-    keychain: KeyChain<C>,
+    signer: Arc<dyn Signer<C>>,
+    /// The signer's keys along with the epoch they are for.
+    keys: OracleKeys<C>,
+    namespaces: Vec<NamespaceKeys<C>>,
+    policy: AttestationPolicy,
 }
 
 impl<C: Group> Oracle<C> {
     pub async fn new(seed: Seed, db: Arc<dyn crate::db::Db<C>>) -> anyhow::Result<Self> {
-        let keychain = KeyChain::new(seed);
-        let public_keys = keychain.oracle_public_keys();
-        if let Some(db_pubkeys) = db.get_public_keys().await? {
-            if public_keys != db_pubkeys {
-                return Err(anyhow!(
-                    "public key derived from seed does not match database"
-                ));
+        Self::with_signer(Arc::new(KeyChain::new(seed)), db).await
+    }
+
+    pub async fn with_signer(
+        signer: Arc<dyn Signer<C>>,
+        db: Arc<dyn crate::db::Db<C>>,
+    ) -> anyhow::Result<Self> {
+        let mut keys = signer.oracle_public_keys();
+        match db.get_key_history().await? {
+            Some(history) => {
+                let current = history
+                    .current()
+                    .ok_or_else(|| anyhow!("latest key succession in database is invalid"))?;
+                // signers don't know which epoch their keys belong to
+                keys.epoch = current.epoch;
+                if keys != current {
+                    let retired = history
+                        .verify()
+                        .into_iter()
+                        .flatten()
+                        .find(|old| old.announcement == keys.announcement);
+                    return Err(match retired {
+                        Some(old) => anyhow!(
+                            "public key derived from seed is from epoch {} but the oracle has moved on to epoch {}",
+                            old.epoch,
+                            current.epoch
+                        ),
+                        None => anyhow!("public key derived from seed does not match database"),
+                    });
+                }
             }
-        } else {
-            db.set_public_keys(public_keys).await?
+            None => db.set_public_keys(keys.clone()).await?,
         }
+        let namespaces = Self::store_namespaces(db.as_ref(), signer.as_ref(), &keys, false).await?;
 
-        Ok(Self { db, keychain })
+        Ok(Self {
+            db,
+            signer,
+            keys,
+            namespaces,
+            policy: AttestationPolicy::default(),
+        })
+    }
+
+    /// Refuse to attest to events earlier than `policy` allows.
+    pub fn with_policy(mut self, policy: AttestationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The signer's namespace keys for the epoch of `keys`.
+    fn namespace_keys(signer: &dyn Signer<C>, keys: &OracleKeys<C>) -> Vec<NamespaceKeys<C>> {
+        let mut namespaces = signer.namespace_public_keys();
+        for namespace in &mut namespaces {
+            namespace.keys.epoch = keys.epoch;
+        }
+        namespaces
+    }
+
+    /// Records the signer's namespace keys in the database. Events that were announced under a
+    /// namespace can only be verified with its keys so a namespace in the database has to stay
+    /// with the same keys unless they are being `replace`d by a rotation. For the same reason a
+    /// new namespace can't be added over events that were announced with other keys and are yet
+    /// to be attested to.
+    async fn store_namespaces(
+        db: &dyn crate::db::Db<C>,
+        signer: &dyn Signer<C>,
+        keys: &OracleKeys<C>,
+        replace: bool,
+    ) -> anyhow::Result<Vec<NamespaceKeys<C>>> {
+        let namespaces = Self::namespace_keys(signer, keys);
+        let existing = db.get_namespace_keys().await?;
+        for old in &existing {
+            match namespaces
+                .iter()
+                .find(|namespace| namespace.path == old.path)
+            {
+                Some(namespace) if replace || namespace.keys == old.keys => {}
+                Some(_) => {
+                    return Err(anyhow!(
+                        "keys for namespace {} derived from seed do not match database",
+                        old.path
+                    ))
+                }
+                None => {
+                    return Err(anyhow!(
+                        "namespace {} is in the database but isn't configured",
+                        old.path
+                    ))
+                }
+            }
+        }
+        if !replace {
+            for namespace in &namespaces {
+                if existing.iter().any(|old| old.path == namespace.path) {
+                    continue;
+                }
+                let unattested = db
+                    .query_events(EventQuery {
+                        path: Some(namespace.path.as_path_ref()),
+                        attested: Some(false),
+                        ..Default::default()
+                    })
+                    .await?;
+                let moved = unattested.iter().find(|event| {
+                    NamespaceKeys::find(&namespaces, event.id.path())
+                        .is_some_and(|found| found.path == namespace.path)
+                });
+                if let Some(event) = moved {
+                    return Err(anyhow!(
+                        "namespace {} can't be added while events under it that were announced with other keys (like {}) haven't been attested to",
+                        namespace.path,
+                        event.id
+                    ));
+                }
+            }
+        }
+        for namespace in &namespaces {
+            if replace || !existing.contains(namespace) {
+                db.set_namespace_keys(namespace.clone()).await?;
+            }
+        }
+        Ok(namespaces)
     }
 
     pub fn public_keys(&self) -> OracleKeys<C> {
-        self.keychain.oracle_public_keys()
+        self.keys.clone()
+    }
+
+    /// The keys the events under `path` are announced and attested to with.
+    pub fn public_keys_for_path(&self, path: PathRef<'_>) -> OracleKeys<C> {
+        match NamespaceKeys::find(&self.namespaces, path) {
+            Some(namespace) => namespace.keys.clone(),
+            None => self.keys.clone(),
+        }
+    }
+
+    /// Hands the oracle over to the keys of `successor` by recording a [`KeySuccession`] signed
+    /// with the current announcement key. The succession includes the successor's namespace keys
+    /// so they can be trusted as much as its main keys. Returns the oracle running with the new
+    /// keys.
+    ///
+    /// Events announced before the rotation can't be rescheduled or attested to afterwards and the
+    /// slots of nonce batches after `time` won't match the events created for them so this refuses
+    /// to rotate while there are any unless `force` is set. When it is forced the rest of each
+    /// batch is committed to again with the new keys.
+    pub async fn rotate_keys(
+        &self,
+        successor: Arc<dyn Signer<C>>,
+        time: NaiveDateTime,
+        force: bool,
+    ) -> anyhow::Result<Oracle<C>> {
+        let mut keys = successor.oracle_public_keys();
+        keys.epoch = self
+            .keys
+            .epoch
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("no epochs left"))?;
+        if keys.announcement == self.keys.announcement {
+            return Err(anyhow!("new keys are the same as the current ones"));
+        }
+
+        let mut unattested = vec![];
+        for event in self
+            .db
+            .query_events(EventQuery {
+                attested: Some(false),
+                ..Default::default()
+            })
+            .await?
+        {
+            match self.db.get_announced_event(&event.id).await? {
+                Some(announced) if announced.epoch == self.keys.epoch => unattested.push(event.id),
+                _ => {}
+            }
+        }
+        let mut batches = vec![];
+        for batch in self.db.get_all_nonce_batches().await? {
+            let schedule = batch
+                .decode()
+                .ok_or_else(|| anyhow!("nonce batch in database is invalid"))?
+                .schedule;
+            if schedule.epoch != self.keys.epoch {
+                continue;
+            }
+            if let Some(remaining) = schedule.remaining_after(time) {
+                batches.push(remaining);
+            }
+        }
+        if !force && (!unattested.is_empty() || !batches.is_empty()) {
+            return Err(anyhow!(
+                "{} events still need to be attested to with the current keys and {} nonce batches have slots that haven't happened yet. Use --force to rotate anyway",
+                unattested.len(),
+                batches.len()
+            ));
+        }
+
+        let succession = self
+            .signer
+            .sign_key_succession(KeySuccession {
+                keys: keys.clone(),
+                time,
+                namespaces: Self::namespace_keys(successor.as_ref(), &keys),
+            })
+            .await?;
+        if succession.verify(&self.keys).is_none() {
+            return Err(anyhow!("signer produced an invalid key succession"));
+        }
+        self.db.insert_key_succession(succession).await?;
+        let namespaces =
+            Self::store_namespaces(self.db.as_ref(), successor.as_ref(), &keys, true).await?;
+        let rotated = Oracle {
+            db: self.db.clone(),
+            signer: successor,
+            keys,
+            namespaces,
+            policy: self.policy.clone(),
+        };
+        for schedule in batches {
+            rotated.add_nonce_batch(schedule).await?;
+        }
+        Ok(rotated)
     }
 
     pub async fn add_event(&self, new_event: Event) -> Result<(), EventResult> {
+        if let Some(segment) = new_event.id.path().first() {
+            if crate::rest_api::RESERVED_PATHS.contains(&segment) {
+                return Err(EventResult::ReservedPath {
+                    segment: segment.into(),
+                });
+            }
+        }
         match self.db.get_announced_event(&new_event.id).await {
             Ok(Some(AnnouncedEvent {
                 attestation: Some(_),
                 ..
             })) => Err(EventResult::AlreadyCompleted),
-            Ok(Some(AnnouncedEvent { .. })) => {
-                // TODO: update exected_outcome_time
-                Err(EventResult::AlreadyExists)
+            Ok(Some(AnnouncedEvent { event, epoch, .. })) => {
+                // An event without a time doesn't tell us anything about when it will happen so it
+                // never unschedules an existing one.
+                if new_event.expected_outcome_time.is_none()
+                    || new_event.expected_outcome_time == event.expected_outcome_time
+                {
+                    return Err(EventResult::AlreadyExists);
+                }
+                if epoch != self.keys.epoch {
+                    return Err(EventResult::RetiredKeys { epoch });
+                }
+                // The announcement has to be re-signed since it commits to the time but the nonces
+                // stay the same because they only depend on the event id.
+                let announcement = self
+                    .signer
+                    .create_announcement(new_event.clone())
+                    .await
+                    .map_err(EventResult::SignerErr)?;
+                match self.db.reschedule_event(new_event, announcement).await {
+                    Ok(()) => Err(EventResult::Changed),
+                    Err(e) => Err(EventResult::DbWriteErr(e)),
+                }
             }
             Ok(None) => {
-                let announcement = self.keychain.create_announcement(new_event.clone());
+                let announcement = self
+                    .signer
+                    .create_announcement(new_event.clone())
+                    .await
+                    .map_err(EventResult::SignerErr)?;
                 self.db
                     .insert_event(AnnouncedEvent {
                         event: new_event,
                         announcement,
                         attestation: None,
+                        epoch: self.keys.epoch,
                     })
                     .await
                     .map_err(EventResult::DbWriteErr)
@@ -87,10 +364,73 @@ impl<C: Group> Oracle<C> {
         }
     }
 
-    pub async fn complete_event(&self, stamped: StampedOutcome) -> Result<(), OutcomeResult> {
-        let existing = self.db.get_announced_event(&stamped.outcome.id).await;
-        let outcome_val_str = stamped.outcome.outcome_string();
-        match existing {
+    /// Commits to the nonces of the events in `schedule` before they exist. The nonces only depend
+    /// on the event id so when one of them is added later it's announced with the same ones.
+    /// The batch is for the oracle's current keys whatever epoch `schedule` says.
+    pub async fn add_nonce_batch(&self, mut schedule: BatchSchedule) -> Result<(), EventResult> {
+        schedule.epoch = self.keys.epoch;
+        let path = schedule.path.clone();
+        let batch = self
+            .signer
+            .create_nonce_batch(schedule)
+            .await
+            .map_err(EventResult::SignerErr)?;
+        self.db
+            .insert_nonce_batch(&path, batch)
+            .await
+            .map_err(EventResult::DbWriteErr)
+    }
+
+    pub async fn complete_event(&self, stamped: StampedOutcome) -> Result<Attested, OutcomeResult> {
+        let outcome = &stamped.outcome;
+        self.attest(
+            &outcome.id,
+            outcome.outcome_string(),
+            stamped.time,
+            Some(outcome),
+            true,
+        )
+        .await
+    }
+
+    /// Like [`complete_event`](Self::complete_event) but attests even if the attestation policy
+    /// says it's too early.
+    pub async fn force_complete_event(
+        &self,
+        stamped: StampedOutcome,
+    ) -> Result<Attested, OutcomeResult> {
+        let outcome = &stamped.outcome;
+        self.attest(
+            &outcome.id,
+            outcome.outcome_string(),
+            stamped.time,
+            Some(outcome),
+            false,
+        )
+        .await
+    }
+
+    /// Voids an announced event instead of attesting to one of its outcomes (e.g. the match was
+    /// postponed). The resulting attestation has [`VOID_OUTCOME`] as its outcome. Events can be
+    /// voided before their expected outcome time.
+    pub async fn cancel_event(
+        &self,
+        event_id: &EventId,
+        time: NaiveDateTime,
+    ) -> Result<Attested, OutcomeResult> {
+        self.attest(event_id, VOID_OUTCOME.to_string(), time, None, false)
+            .await
+    }
+
+    async fn attest(
+        &self,
+        event_id: &EventId,
+        outcome_val_str: String,
+        time: NaiveDateTime,
+        outcome: Option<&Outcome>,
+        enforce_policy: bool,
+    ) -> Result<Attested, OutcomeResult> {
+        match self.db.get_announced_event(event_id).await {
             Ok(None) => Err(OutcomeResult::EventNotExist),
             Ok(Some(AnnouncedEvent {
                 attestation: Some(attestation),
@@ -105,37 +445,51 @@ impl<C: Group> Oracle<C> {
                     })
                 }
             }
+            Ok(Some(AnnouncedEvent { epoch, .. })) if epoch != self.keys.epoch => {
+                Err(OutcomeResult::RetiredKeys { epoch })
+            }
             Ok(Some(AnnouncedEvent {
                 event,
                 announcement,
                 ..
             })) => {
-                if let Some(oracle_event) = announcement.verify_against_id(
-                    &stamped.outcome.id,
-                    &self.keychain.oracle_public_keys().announcement,
-                ) {
-                    let att_schemes =
-                        AttestationSchemes {
-                            olivia_v1: oracle_event.schemes.olivia_v1.as_ref().map(|_| {
-                                attest::OliviaV1 {
-                                    scalars: self
-                                        .keychain
-                                        .olivia_v1_scalars_for_event_outcome(&stamped),
-                                }
-                            }),
-                            ecdsa_v1: oracle_event.schemes.ecdsa_v1.as_ref().map(|_| {
-                                attest::EcdsaV1 {
-                                    signature: self.keychain.ecdsa_sign_outcome(&stamped.outcome),
-                                }
-                            }),
-                        };
-
-                    let attestation = Attestation::new(outcome_val_str, stamped.time, att_schemes);
+                let attested = match self.policy.timing(&event, Utc::now().naive_utc()) {
+                    Timing::TooEarly { earliest } if enforce_policy => {
+                        return Err(OutcomeResult::TooEarly { earliest })
+                    }
+                    Timing::Late { by } => Attested::Late { by },
+                    _ => Attested::OnTime,
+                };
+                let keys = self.public_keys_for_path(event_id.path());
+                if let Some(oracle_event) =
+                    announcement.verify_against_id(event_id, &keys.announcement)
+                {
+                    // Another outcome may have started being attested to since we read the event.
+                    // Signing a second outcome would reveal the attestation key so this has to be
+                    // settled by the database before the signer sees anything.
+                    let committed = self
+                        .db
+                        .begin_attestation(&event.id, &outcome_val_str)
+                        .await
+                        .map_err(OutcomeResult::DbWriteErr)?;
+                    if committed != outcome_val_str {
+                        return Err(OutcomeResult::OutcomeChanged {
+                            existing: committed,
+                            new: outcome_val_str,
+                        });
+                    }
+                    let schemes = self
+                        .signer
+                        .attest(&oracle_event, outcome)
+                        .await
+                        .map_err(OutcomeResult::SignerErr)?;
+                    let attestation = Attestation::new(outcome_val_str, time, schemes);
 
                     self.db
                         .complete_event(&event.id, attestation)
                         .await
-                        .map_err(OutcomeResult::DbWriteErr)
+                        .map_err(OutcomeResult::DbWriteErr)?;
+                    Ok(attested)
                 } else {
                     Err(OutcomeResult::AnnouncementWasBogus)
                 }
@@ -150,7 +504,9 @@ pub mod test {
     use super::*;
     use crate::db::Db;
     use core::{convert::TryInto, str::FromStr};
-    use olivia_core::{EventId, WireEventOutcome};
+    use olivia_core::{
+        chrono::NaiveDateTime, AttestationInvalid, EventId, EventKind, Path, WireEventOutcome,
+    };
 
     pub async fn test_oracle_event_lifecycle<C: Group>(db: Arc<dyn Db<C>>) {
         let oracle = Oracle::new(crate::seed::Seed::new([42u8; 64]), db.clone())
@@ -174,6 +530,7 @@ pub mod test {
             .announcement
             .verify_against_id(&event_id, &public_keys.announcement)
             .expect("announcement signature should be valid");
+        assert!(oracle_event.schemes.dlc_v1.is_some());
 
         let outcome: StampedOutcome = WireEventOutcome {
             event_id: event_id.clone(),
@@ -196,6 +553,14 @@ pub mod test {
             attestation.verify_olivia_v1_attestation(&oracle_event, &public_keys),
             Ok(())
         );
+        assert_eq!(
+            attestation.verify_ecdsa_v1_attestation(&oracle_event, &public_keys),
+            Ok(())
+        );
+        assert_eq!(
+            attestation.verify_dlc_v1_attestation(&oracle_event, &public_keys),
+            Ok(())
+        );
     }
 
     pub async fn test_price_oracle_event_lifecycle<C: Group>(db: Arc<dyn Db<C>>) {
@@ -207,40 +572,405 @@ pub mod test {
             .await
             .unwrap()
             .expect("creating oracle should have set public keys");
-        let event_id = EventId::from_str("/foo/bar/baz.price?n=6").unwrap();
-        assert!(oracle.add_event(event_id.clone().into()).await.is_ok());
+        for (event_id, outcome) in [
+            ("/foo/bar/baz.price?n=6", "23"),
+            ("/foo/bar/baz.price?n=3&base=10", "23"),
+            (
+                "/foo/bar/baz.price?n=3&base=10&signed&unit=usd&exp=-1",
+                "-2.3",
+            ),
+        ] {
+            let event_id = EventId::from_str(event_id).unwrap();
+            assert!(oracle.add_event(event_id.clone().into()).await.is_ok());
 
-        let event = db
-            .get_announced_event(&event_id)
+            let event = db
+                .get_announced_event(&event_id)
+                .await
+                .unwrap()
+                .expect("event should be there");
+
+            let oracle_event = event
+                .announcement
+                .verify_against_id(&event_id, &public_keys.announcement)
+                .expect("announcement signature should be valid");
+
+            let outcome: StampedOutcome = WireEventOutcome {
+                event_id: event_id.clone(),
+                outcome: outcome.into(),
+                time: None,
+            }
+            .try_into()
+            .unwrap();
+
+            assert!(oracle.complete_event(outcome.clone()).await.is_ok());
+
+            let attested_event = db
+                .get_announced_event(&event_id)
+                .await
+                .unwrap()
+                .expect("event should still be there");
+
+            let attestation = attested_event.attestation.expect("should be attested to");
+            assert_eq!(
+                attestation.verify_olivia_v1_attestation(&oracle_event, &public_keys),
+                Ok(())
+            );
+            assert_eq!(
+                attestation.verify_dlc_v1_attestation(&oracle_event, &public_keys),
+                Ok(())
+            );
+        }
+    }
+
+    pub async fn test_void_oracle_event_lifecycle<C: Group>(db: Arc<dyn Db<C>>) {
+        let oracle = Oracle::new(crate::seed::Seed::new([42u8; 64]), db.clone())
+            .await
+            .expect("should be able to create oracle");
+        let public_keys = db
+            .get_public_keys()
             .await
             .unwrap()
-            .expect("event should be there");
+            .expect("creating oracle should have set public keys");
+        for event_id in [
+            "/foo/bar/postponed/FOO_BAR.vs",
+            "/foo/bar/void.price?n=3&base=10&signed",
+        ] {
+            let event_id = EventId::from_str(event_id).unwrap();
+            let time = olivia_core::chrono::Utc::now().naive_utc();
+            assert!(matches!(
+                oracle.cancel_event(&event_id, time).await,
+                Err(OutcomeResult::EventNotExist)
+            ));
+            assert!(oracle.add_event(event_id.clone().into()).await.is_ok());
 
-        let oracle_event = event
-            .announcement
-            .verify_against_id(&event_id, &public_keys.announcement)
-            .expect("announcement signature should be valid");
+            let oracle_event = db
+                .get_announced_event(&event_id)
+                .await
+                .unwrap()
+                .expect("event should be there")
+                .announcement
+                .verify_against_id(&event_id, &public_keys.announcement)
+                .expect("announcement signature should be valid");
 
-        let outcome: StampedOutcome = WireEventOutcome {
-            event_id: event_id.clone(),
-            outcome: "23".into(),
-            time: None,
+            assert!(oracle.cancel_event(&event_id, time).await.is_ok());
+
+            let attestation = db
+                .get_announced_event(&event_id)
+                .await
+                .unwrap()
+                .expect("event should still be there")
+                .attestation
+                .expect("should be attested to");
+
+            assert!(attestation.is_void());
+            assert_eq!(
+                attestation.verify_olivia_v1_attestation(&oracle_event, &public_keys),
+                Ok(())
+            );
+            assert_eq!(
+                attestation.verify_ecdsa_v1_attestation(&oracle_event, &public_keys),
+                Ok(())
+            );
+            // dlcspecs has no way to void an event
+            assert_eq!(
+                attestation.verify_dlc_v1_attestation(&oracle_event, &public_keys),
+                Err(AttestationInvalid::Missing)
+            );
+
+            assert!(matches!(
+                oracle.cancel_event(&event_id, time).await,
+                Err(OutcomeResult::AlreadyCompleted)
+            ));
+            assert!(matches!(
+                oracle
+                    .complete_event(StampedOutcome::test_instance(&event_id))
+                    .await,
+                Err(OutcomeResult::OutcomeChanged { .. })
+            ));
         }
-        .try_into()
-        .unwrap();
+    }
 
-        assert!(oracle.complete_event(outcome.clone()).await.is_ok());
+    pub async fn test_racing_outcomes<C: Group>(db: Arc<dyn Db<C>>) {
+        let oracle = Arc::new(
+            Oracle::new(crate::seed::Seed::new([42u8; 64]), db.clone())
+                .await
+                .expect("should be able to create oracle"),
+        );
+        let event_id = EventId::from_str("/foo/bar/race/FOO_BAR.vs").unwrap();
+        assert!(oracle.add_event(event_id.clone().into()).await.is_ok());
 
-        let attested_event = db
+        let attempts = ["FOO_win", "BAR_win", "draw", "FOO_win"]
+            .iter()
+            .map(|outcome| {
+                let oracle = oracle.clone();
+                let stamped: StampedOutcome = WireEventOutcome {
+                    event_id: event_id.clone(),
+                    outcome: outcome.to_string(),
+                    time: None,
+                }
+                .try_into()
+                .unwrap();
+                tokio::spawn(async move { oracle.complete_event(stamped).await })
+            })
+            .collect::<Vec<_>>();
+
+        let mut n_completed = 0;
+        for attempt in attempts {
+            match attempt.await.unwrap() {
+                Ok(_) => n_completed += 1,
+                Err(OutcomeResult::OutcomeChanged { .. })
+                | Err(OutcomeResult::AlreadyCompleted)
+                | Err(OutcomeResult::DbWriteErr(_)) => {}
+                Err(e) => panic!("unexpected error {}", e),
+            }
+        }
+        assert_eq!(n_completed, 1);
+        assert!(db
             .get_announced_event(&event_id)
             .await
             .unwrap()
-            .expect("event should still be there");
+            .unwrap()
+            .attestation
+            .is_some());
+    }
 
-        let attestation = attested_event.attestation.expect("should be attested to");
+    pub async fn test_attestation_window<C: Group>(db: Arc<dyn Db<C>>) {
+        let policy: AttestationPolicy = serde_yaml::from_str(
+            "{ min-delay: 60, max-lateness: 3600, paths: { /foo/bar/window/late: { max-lateness: 60 } } }",
+        )
+        .unwrap();
+        let oracle = Oracle::new(crate::seed::Seed::new([42u8; 64]), db.clone())
+            .await
+            .expect("should be able to create oracle")
+            .with_policy(policy);
+        let now = Utc::now().naive_utc();
+        let event_at = |id: &str, time| Event {
+            id: EventId::from_str(id).unwrap(),
+            expected_outcome_time: Some(time),
+        };
+
+        let early = event_at("/foo/bar/window/early/FOO_BAR.vs", now);
+        assert!(oracle.add_event(early.clone()).await.is_ok());
+        assert!(matches!(
+            oracle
+                .complete_event(StampedOutcome::test_instance(&early.id))
+                .await,
+            Err(OutcomeResult::TooEarly { earliest }) if earliest == now + Duration::seconds(60)
+        ));
+        assert!(
+            db.get_announced_event(&early.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .attestation
+                .is_none(),
+            "refusing shouldn't commit the event to an outcome"
+        );
+        // voiding is allowed since the event may have been called off
+        let postponed = event_at("/foo/bar/window/postponed/FOO_BAR.vs", now);
+        assert!(oracle.add_event(postponed.clone()).await.is_ok());
         assert_eq!(
-            attestation.verify_olivia_v1_attestation(&oracle_event, &public_keys),
+            oracle.cancel_event(&postponed.id, now).await.unwrap(),
+            Attested::OnTime
+        );
+        assert_eq!(
+            oracle
+                .force_complete_event(StampedOutcome::test_instance(&early.id))
+                .await
+                .unwrap(),
+            Attested::OnTime
+        );
+
+        let late = event_at("/foo/bar/window/late/FOO_BAR.vs", now - Duration::hours(2));
+        assert!(oracle.add_event(late.clone()).await.is_ok());
+        assert!(matches!(
+            oracle
+                .complete_event(StampedOutcome::test_instance(&late.id))
+                .await,
+            Ok(Attested::Late { by }) if by >= Duration::hours(2)
+        ));
+    }
+
+    /// Rotates the oracle's keys so it has to come after every other test sharing `db`.
+    pub async fn test_key_rotation<C: Group>(db: Arc<dyn Db<C>>) {
+        let oracle = Oracle::new(crate::seed::Seed::new([42u8; 64]), db.clone())
+            .await
+            .expect("should be able to create oracle");
+        let old_event = EventId::from_str("/foo/bar/rotation/old.occur").unwrap();
+        let new_event = EventId::from_str("/foo/bar/rotation/new.occur").unwrap();
+        assert!(oracle.add_event(old_event.clone().into()).await.is_ok());
+        let schedule = BatchSchedule {
+            path: Path::from_str("/foo/bar/rotation/batch").unwrap(),
+            event_kind: EventKind::SingleOccurrence,
+            start: NaiveDateTime::from_str("2021-10-04T11:00:00").unwrap(),
+            interval: 3600,
+            n_slots: 3,
+            epoch: 0,
+        };
+        oracle.add_nonce_batch(schedule.clone()).await.unwrap();
+
+        let successor = Arc::new(KeyChain::new(crate::seed::Seed::new([43u8; 64])));
+        let time = NaiveDateTime::from_str("2021-10-04T12:00:00").unwrap();
+        assert!(
+            oracle
+                .rotate_keys(successor.clone(), time, false)
+                .await
+                .is_err(),
+            "shouldn't rotate while there are unattested events and nonce batches"
+        );
+        let rotated = oracle.rotate_keys(successor, time, true).await.unwrap();
+        assert_eq!(rotated.public_keys().epoch, oracle.public_keys().epoch + 1);
+        assert_eq!(
+            db.get_public_keys().await.unwrap(),
+            Some(rotated.public_keys())
+        );
+
+        assert!(
+            Oracle::new(crate::seed::Seed::new([42u8; 64]), db.clone())
+                .await
+                .is_err(),
+            "retired keys shouldn't be able to run the oracle"
+        );
+        let restarted = Oracle::new(crate::seed::Seed::new([43u8; 64]), db.clone())
+            .await
+            .expect("successor keys should be able to run the oracle");
+        assert_eq!(restarted.public_keys(), rotated.public_keys());
+
+        assert!(matches!(
+            rotated
+                .complete_event(StampedOutcome::test_instance(&old_event))
+                .await,
+            Err(OutcomeResult::RetiredKeys { .. })
+        ));
+        assert!(matches!(
+            rotated
+                .add_event(Event {
+                    id: old_event.clone(),
+                    expected_outcome_time: Some(time),
+                })
+                .await,
+            Err(EventResult::RetiredKeys { .. })
+        ));
+
+        assert!(rotated.add_event(new_event.clone().into()).await.is_ok());
+        assert!(rotated
+            .complete_event(StampedOutcome::test_instance(&new_event))
+            .await
+            .is_ok());
+        let event = db.get_announced_event(&new_event).await.unwrap().unwrap();
+        assert_eq!(event.epoch, rotated.public_keys().epoch);
+        let oracle_event = event
+            .announcement
+            .verify_against_id(&new_event, &rotated.public_keys().announcement)
+            .expect("new events should be announced with the new keys");
+        assert_eq!(
+            event
+                .attestation
+                .unwrap()
+                .verify_olivia_v1_attestation(&oracle_event, &rotated.public_keys()),
             Ok(())
         );
+
+        let keys = db
+            .get_key_history()
+            .await
+            .unwrap()
+            .unwrap()
+            .verify()
+            .expect("key history should verify");
+        assert_eq!(keys.last(), Some(&rotated.public_keys()));
+
+        // the rest of the nonce batch is committed to again with the new keys
+        let batches = db
+            .get_nonce_batches(schedule.path.as_path_ref())
+            .await
+            .unwrap();
+        assert_eq!(batches.len(), 2);
+        let recommitted = batches[1]
+            .verify(&rotated.public_keys().announcement)
+            .expect("should be signed with the new keys");
+        assert_eq!(
+            recommitted.schedule,
+            BatchSchedule {
+                start: NaiveDateTime::from_str("2021-10-04T13:00:00").unwrap(),
+                n_slots: 1,
+                epoch: rotated.public_keys().epoch,
+                ..schedule.clone()
+            }
+        );
+        let slot_event = schedule.slot_event_id(2).unwrap();
+        rotated.add_event(slot_event.clone().into()).await.unwrap();
+        let oracle_event = db
+            .get_announced_event(&slot_event)
+            .await
+            .unwrap()
+            .unwrap()
+            .announcement
+            .verify_against_id(&slot_event, &rotated.public_keys().announcement)
+            .unwrap();
+        assert!(recommitted.nonces_for_event(&slot_event).is_some());
+        assert!(recommitted.is_consistent_with(&oracle_event));
+    }
+
+    pub async fn test_reschedule_oracle_event<C: Group>(db: Arc<dyn Db<C>>) {
+        let oracle = Oracle::new(crate::seed::Seed::new([42u8; 64]), db.clone())
+            .await
+            .expect("should be able to create oracle");
+        let public_keys = oracle.public_keys();
+        let event_id = EventId::from_str("/foo/bar/rescheduled/FOO_BAR.vs").unwrap();
+        let original_time = NaiveDateTime::from_str("2021-10-04T12:00:00").unwrap();
+        let new_time = NaiveDateTime::from_str("2021-10-05T15:30:00").unwrap();
+        let event_at = |time| Event {
+            id: event_id.clone(),
+            expected_outcome_time: time,
+        };
+
+        assert!(oracle
+            .add_event(event_at(Some(original_time)))
+            .await
+            .is_ok());
+        let original = db.get_announced_event(&event_id).await.unwrap().unwrap();
+
+        assert!(matches!(
+            oracle.add_event(event_at(Some(original_time))).await,
+            Err(EventResult::AlreadyExists)
+        ));
+        assert!(matches!(
+            oracle.add_event(event_at(None)).await,
+            Err(EventResult::AlreadyExists)
+        ));
+        assert!(matches!(
+            oracle.add_event(event_at(Some(new_time))).await,
+            Err(EventResult::Changed)
+        ));
+
+        let rescheduled = db.get_announced_event(&event_id).await.unwrap().unwrap();
+        assert_eq!(rescheduled.event, event_at(Some(new_time)));
+        let oracle_event = rescheduled
+            .announcement
+            .verify_against_id(&event_id, &public_keys.announcement)
+            .expect("new announcement should be valid");
+        assert_eq!(oracle_event.event.expected_outcome_time, Some(new_time));
+        let original_oracle_event = original
+            .announcement
+            .verify_against_id(&event_id, &public_keys.announcement)
+            .unwrap();
+        assert_eq!(
+            oracle_event.schemes.olivia_v1, original_oracle_event.schemes.olivia_v1,
+            "nonces should not change"
+        );
+        assert_eq!(
+            db.get_announcement_history(&event_id).await.unwrap(),
+            vec![original.announcement]
+        );
+
+        assert!(oracle
+            .complete_event(StampedOutcome::test_instance(&event_id))
+            .await
+            .is_ok());
+        assert!(matches!(
+            oracle.add_event(event_at(Some(original_time))).await,
+            Err(EventResult::AlreadyCompleted)
+        ));
     }
 }
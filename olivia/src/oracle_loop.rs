@@ -1,17 +1,30 @@
 use crate::{
     db::Db,
     log::OracleLog,
-    sources::{self, Update},
+    quorum::{Decision, OutcomeQuorums, Proposal},
+    sources::{
+        self,
+        http::{Write, WriteReceiver, WriteResult},
+        Processed, Update,
+    },
     Oracle,
 };
-use olivia_core::{Event, Group, Node, Path, PrefixPath, StampedOutcome};
+use olivia_core::{AnnouncedEvent, Event, Group, Node, Path, PrefixPath, StampedOutcome};
 use std::sync::Arc;
+use tokio::{
+    sync::oneshot,
+    time::{sleep_until, Instant},
+};
 use tokio_stream::{StreamExt, StreamMap};
 
 pub struct OracleLoop<G: Group> {
     pub events: StreamMap<(Path, usize), sources::Stream<Event>>,
     pub outcomes: StreamMap<(Path, usize), sources::Stream<StampedOutcome>>,
     pub nodes: StreamMap<(Path, usize), sources::Stream<Node>>,
+    /// Events and outcomes posted to the REST API.
+    pub writes: WriteReceiver,
+    /// The paths whose outcomes are only attested to once enough of their sources agree.
+    pub quorums: OutcomeQuorums,
     pub oracle: Oracle<G>,
     pub db: Arc<dyn Db<G>>,
     pub logger: slog::Logger,
@@ -23,20 +36,21 @@ impl<G: Group> OracleLoop<G> {
             mut events,
             mut outcomes,
             mut nodes,
+            mut writes,
+            mut quorums,
             oracle,
             db,
             logger,
         } = self;
         loop {
+            let next_deadline = quorums.next_deadline();
             tokio::select! {
                 Some((parent, Update { update: event, processed_notifier })) = events.next() => {
                     let event = event.prefix_path(parent.0.as_path_ref());
                     let logger = logger
                         .new(o!("type" => "new_event", "event_id" => event.id.to_string()));
                     let res = oracle.add_event(event).await;
-                    if let Some(processed_notifier) = processed_notifier {
-                        let _ = processed_notifier.send(res.is_err());
-                    }
+                    notify(processed_notifier, Processed::from_event_result(&res));
                     logger.log_event_result(res)
                 },
                 Some((parent, Update { update: stamped, processed_notifier })) = outcomes.next() => {
@@ -44,26 +58,73 @@ impl<G: Group> OracleLoop<G> {
                     let logger = logger.new(
                             o!("type" => "new_outcome", "event_id" => stamped.outcome.id.to_string(), "value" => stamped.outcome.outcome_string()),
                         );
-                    let res = oracle.complete_event(stamped.clone()).await;
-                    if let Some(processed_notifier) = processed_notifier {
-                        let _ = processed_notifier.send(res.is_err());
+                    match quorums.quorum_path(&stamped.outcome.id).cloned() {
+                        Some(path) if path == parent.0 => {
+                            propose_outcome(&oracle, db.as_ref(), &mut quorums, &parent, stamped, processed_notifier, &logger).await
+                        }
+                        // a source configured under some other path isn't one of the quorum's sources
+                        Some(path) => {
+                            notify(processed_notifier, Processed::Rejected);
+                            warn!(logger, "rejected - the event is under another path's outcome quorum"; "quorum_path" => path.to_string())
+                        }
+                        None => {
+                            let res = oracle.complete_event(stamped.clone()).await;
+                            notify(processed_notifier, Processed::from_outcome_result(&res));
+                            logger.log_outcome_result(res)
+                        }
                     }
-                    logger.log_outcome_result(res)
                 },
                 Some((parent, Update { update: node, processed_notifier })) = nodes.next() => {
                     let node = node.prefix_path(parent.0.as_path_ref());
                     let logger =
                         logger.new(o!("type" => "new_node", "path" => node.path.to_string()));
                     let res = db.set_node(node.clone()).await;
-                    if let Some(processed_notifier) = processed_notifier {
-                        let _ = processed_notifier.send(res.is_err());
-                    }
+                    notify(processed_notifier, match res {
+                        Ok(()) => Processed::Done,
+                        Err(_) => Processed::Retry,
+                    });
 
                     match res {
                         Ok(()) => info!(logger, "added"),
                         Err(e) => error!(logger, "failed to add"; "error" => e.to_string()),
                     }
                 },
+                Some(write) = writes.recv() => match write {
+                    Write::Event { event, result } => {
+                        let logger = logger
+                            .new(o!("type" => "new_event", "source_type" => "http", "event_id" => event.id.to_string()));
+                        let id = event.id.clone();
+                        let res = oracle.add_event(event).await;
+                        let _ = result.send(WriteResult::from_event_result(id, &res));
+                        logger.log_event_result(res)
+                    }
+                    Write::Outcome { outcome: stamped, result } => {
+                        let logger = logger.new(
+                            o!("type" => "new_outcome", "source_type" => "http", "event_id" => stamped.outcome.id.to_string(), "value" => stamped.outcome.outcome_string()),
+                        );
+                        let id = stamped.outcome.id.clone();
+                        if let Some(path) = quorums.quorum_path(&id) {
+                            let _ = result.send(WriteResult::needs_quorum(id, path));
+                            warn!(logger, "rejected - the path has an outcome quorum");
+                        } else {
+                            let res = oracle.complete_event(stamped).await;
+                            let _ = result.send(WriteResult::from_outcome_result(id, &res));
+                            logger.log_outcome_result(res)
+                        }
+                    }
+                },
+                _ = sleep_until(next_deadline.unwrap_or_else(Instant::now)), if next_deadline.is_some() => {
+                    for decision in quorums.expire(Instant::now()) {
+                        let logger = match &decision {
+                            Decision::Disagreed { review, .. } => logger.new(
+                                o!("type" => "outcome_quorum_timeout", "event_id" => review.event_id.to_string()),
+                            ),
+                            _ => logger.clone(),
+                        };
+                        crit!(logger, "outcome sources didn't reach a quorum in time");
+                        decide(&oracle, db.as_ref(), decision, &logger).await
+                    }
+                },
                 else =>  {
                     info!(logger, "stopping oracle loop");
                     break;
@@ -72,3 +133,113 @@ impl<G: Group> OracleLoop<G> {
         }
     }
 }
+
+fn notify(processed_notifier: Option<oneshot::Sender<Processed>>, processed: Processed) {
+    if let Some(processed_notifier) = processed_notifier {
+        let _ = processed_notifier.send(processed);
+    }
+}
+
+/// Handles an outcome from the `source`th outcome source of a path with a quorum. It's only
+/// attested to once enough sources agree and is put up for review if they can't.
+async fn propose_outcome<G: Group>(
+    oracle: &Oracle<G>,
+    db: &dyn Db<G>,
+    quorums: &mut OutcomeQuorums,
+    (path, source): &(Path, usize),
+    stamped: StampedOutcome,
+    processed_notifier: Option<oneshot::Sender<Processed>>,
+    logger: &slog::Logger,
+) {
+    let logger = logger.new(o!("source" => *source));
+    let id = &stamped.outcome.id;
+    match db.get_announced_event(id).await {
+        Ok(Some(AnnouncedEvent {
+            attestation: None, ..
+        })) => {}
+        // there's nothing to agree on so let the oracle say why it can't be attested to
+        Ok(_) => {
+            let res = oracle.complete_event(stamped).await;
+            notify(processed_notifier, Processed::from_outcome_result(&res));
+            return logger.log_outcome_result(res);
+        }
+        Err(e) => {
+            notify(processed_notifier, Processed::Retry);
+            return crit!(logger, "database read"; "error" => e.to_string());
+        }
+    }
+
+    match db.get_outcome_review(id).await {
+        Ok(None) => {}
+        Ok(Some(mut review)) => {
+            review.propose(Proposal::new(*source, &stamped));
+            let res = db.set_outcome_review(review).await;
+            return match res {
+                Ok(()) => {
+                    notify(processed_notifier, Processed::Rejected);
+                    warn!(logger, "ignored - waiting for review")
+                }
+                Err(e) => {
+                    notify(processed_notifier, Processed::Retry);
+                    crit!(logger, "database write"; "error" => e.to_string())
+                }
+            };
+        }
+        Err(e) => {
+            notify(processed_notifier, Processed::Retry);
+            return crit!(logger, "database read"; "error" => e.to_string());
+        }
+    }
+
+    let decision = quorums.propose(path, *source, stamped, processed_notifier);
+    decide(oracle, db, decision, &logger).await
+}
+
+/// Acts on what the quorum decided about an event.
+async fn decide<G: Group>(
+    oracle: &Oracle<G>,
+    db: &dyn Db<G>,
+    decision: Decision,
+    logger: &slog::Logger,
+) {
+    match decision {
+        Decision::Waiting => debug!(logger, "waiting for quorum"),
+        Decision::Agreed {
+            stamped,
+            notifiers,
+            dissent,
+        } => {
+            for (proposal, processed_notifier) in dissent {
+                warn!(logger, "outcome source disagreed with quorum"; "dissenting_source" => proposal.source, "proposed" => proposal.outcome);
+                notify(processed_notifier, Processed::Rejected);
+            }
+            let res = oracle.complete_event(stamped).await;
+            for processed_notifier in notifiers {
+                let _ = processed_notifier.send(Processed::from_outcome_result(&res));
+            }
+            logger.log_outcome_result(res)
+        }
+        Decision::Disagreed { review, notifiers } => {
+            let proposals = review
+                .proposals
+                .iter()
+                .map(|proposal| format!("{}: {}", proposal.source, proposal.outcome))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let res = db.set_outcome_review(review).await;
+            let processed = match res {
+                Ok(()) => Processed::Rejected,
+                Err(_) => Processed::Retry,
+            };
+            for processed_notifier in notifiers {
+                let _ = processed_notifier.send(processed);
+            }
+            match res {
+                Ok(()) => {
+                    crit!(logger, "outcome sources disagree - waiting for review"; "proposals" => proposals)
+                }
+                Err(e) => crit!(logger, "database write"; "error" => e.to_string()),
+            }
+        }
+    }
+}
@@ -0,0 +1,513 @@
+//! Only attesting to the outcome of an event once enough of the outcome sources for its path agree
+//! on it.
+use crate::sources::Processed;
+use olivia_core::{chrono::NaiveDateTime, EventId, Path, StampedOutcome};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+use tokio::{sync::oneshot::Sender, time::Instant};
+
+/// How long the sources of a path have to reach a quorum after the first of them proposes an
+/// outcome for an event.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// An outcome proposed by one of the outcome sources of a path.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Proposal {
+    /// The index of the source in the path's list of outcome sources in the config.
+    pub source: usize,
+    pub outcome: String,
+    pub time: NaiveDateTime,
+}
+
+/// An event whose outcome sources couldn't reach a quorum. It won't be attested to until an
+/// operator resolves it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct OutcomeReview {
+    pub event_id: EventId,
+    /// The path the outcome sources are configured under.
+    pub path: Path,
+    pub proposals: Vec<Proposal>,
+}
+
+impl Proposal {
+    pub fn new(source: usize, stamped: &StampedOutcome) -> Self {
+        Proposal {
+            source,
+            outcome: stamped.outcome.outcome_string(),
+            time: stamped.time,
+        }
+    }
+}
+
+impl OutcomeReview {
+    /// Adds the proposal replacing any earlier one from the same source.
+    pub fn propose(&mut self, proposal: Proposal) {
+        self.proposals
+            .retain(|existing| existing.source != proposal.source);
+        self.proposals.push(proposal);
+        self.proposals.sort_by_key(|proposal| proposal.source);
+    }
+}
+
+struct Pending {
+    path: Path,
+    proposals: BTreeMap<usize, (StampedOutcome, Option<Sender<Processed>>)>,
+    /// When the event is put up for review if there's still no quorum.
+    deadline: Instant,
+}
+
+impl Pending {
+    fn escalate(self, event_id: EventId) -> Decision {
+        let mut notifiers = vec![];
+        let mut proposals = vec![];
+        for (source, (stamped, notifier)) in self.proposals {
+            notifiers.extend(notifier);
+            proposals.push(Proposal::new(source, &stamped));
+        }
+        Decision::Disagreed {
+            review: OutcomeReview {
+                event_id,
+                path: self.path,
+                proposals,
+            },
+            notifiers,
+        }
+    }
+}
+
+/// What to do about an event after a source proposes an outcome for it.
+///
+/// The processed notifiers of the proposals are held on to until a decision is reached so that
+/// sources which wait on them don't move on to the next event.
+pub enum Decision {
+    /// More sources have to propose an outcome before there can be a quorum.
+    Waiting,
+    /// Enough sources agree on `stamped` to attest to it.
+    Agreed {
+        stamped: StampedOutcome,
+        notifiers: Vec<Sender<Processed>>,
+        /// The proposals from sources that disagreed.
+        dissent: Vec<(Proposal, Option<Sender<Processed>>)>,
+    },
+    /// There can no longer be a quorum (or there wasn't one in time).
+    Disagreed {
+        review: OutcomeReview,
+        notifiers: Vec<Sender<Processed>>,
+    },
+}
+
+/// The proposals for events under paths with a quorum that haven't been decided yet.
+pub struct OutcomeQuorums {
+    /// How many sources have to agree and how many sources there are for each path.
+    paths: HashMap<Path, (usize, usize)>,
+    timeout: Duration,
+    pending: HashMap<EventId, Pending>,
+}
+
+impl Default for OutcomeQuorums {
+    fn default() -> Self {
+        Self::new(HashMap::new())
+    }
+}
+
+impl OutcomeQuorums {
+    pub fn new(paths: HashMap<Path, (usize, usize)>) -> Self {
+        Self {
+            paths,
+            timeout: DEFAULT_TIMEOUT,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The deepest path with a quorum that the event is under (if any).
+    pub fn quorum_path(&self, event_id: &EventId) -> Option<&Path> {
+        self.paths
+            .keys()
+            .filter(|path| path.as_path_ref().is_parent_of(event_id.path()))
+            .max_by_key(|path| path.as_str().len())
+    }
+
+    /// The earliest time an undecided event has to be put up for review.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().map(|pending| pending.deadline).min()
+    }
+
+    /// Gives up on the events whose sources haven't reached a quorum by `now`.
+    pub fn expire(&mut self, now: Instant) -> Vec<Decision> {
+        let expired = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        expired
+            .into_iter()
+            .map(|id| {
+                let pending = self.pending.remove(&id).expect("it's pending");
+                pending.escalate(id)
+            })
+            .collect()
+    }
+
+    /// Records an outcome proposed by the `source`th outcome source of `path` (which must have a
+    /// quorum). A source's proposal replaces any it made before.
+    pub fn propose(
+        &mut self,
+        path: &Path,
+        source: usize,
+        stamped: StampedOutcome,
+        notifier: Option<Sender<Processed>>,
+    ) -> Decision {
+        let (required, n_sources) = self.paths[path];
+        let deadline = Instant::now() + self.timeout;
+        let id = stamped.outcome.id.clone();
+        let pending = self.pending.entry(id.clone()).or_insert_with(|| Pending {
+            path: path.clone(),
+            proposals: BTreeMap::new(),
+            deadline,
+        });
+        pending.proposals.insert(source, (stamped, notifier));
+
+        let mut counts = BTreeMap::<u64, usize>::new();
+        for (stamped, _) in pending.proposals.values() {
+            *counts.entry(stamped.outcome.value).or_default() += 1;
+        }
+        let (value, agreeing) = counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .expect("there's at least one proposal");
+        let undecided = n_sources.saturating_sub(pending.proposals.len());
+
+        if agreeing >= required {
+            let pending = self.pending.remove(&id).expect("it was just inserted");
+            let mut agreed = None;
+            let mut notifiers = vec![];
+            let mut dissent = vec![];
+            for (source, (stamped, notifier)) in pending.proposals {
+                if stamped.outcome.value == value {
+                    notifiers.extend(notifier);
+                    // attest with the time the latest agreeing source figured out the outcome
+                    if agreed
+                        .as_ref()
+                        .is_none_or(|agreed: &StampedOutcome| stamped.time > agreed.time)
+                    {
+                        agreed = Some(stamped);
+                    }
+                } else {
+                    dissent.push((Proposal::new(source, &stamped), notifier));
+                }
+            }
+            Decision::Agreed {
+                stamped: agreed.expect("at least one source agreed"),
+                notifiers,
+                dissent,
+            }
+        } else if agreeing + undecided < required {
+            let pending = self.pending.remove(&id).expect("it was just inserted");
+            pending.escalate(id)
+        } else {
+            Decision::Waiting
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use olivia_core::Outcome;
+    use std::str::FromStr;
+
+    fn stamped(id: &str, outcome: &str) -> StampedOutcome {
+        StampedOutcome {
+            outcome: Outcome::try_from_id_and_outcome(EventId::from_str(id).unwrap(), outcome)
+                .unwrap(),
+            time: NaiveDateTime::from_str("2021-10-04T00:00:00").unwrap(),
+        }
+    }
+
+    #[test]
+    fn two_of_three_must_agree() {
+        let path = Path::from_str("/NBA/match").unwrap();
+        let mut quorums = OutcomeQuorums::new([(path.clone(), (2, 3))].into_iter().collect());
+        let one = "/NBA/match/2021-06-20/MIL_BKN.winner";
+        let two = "/NBA/match/2021-06-22/MIL_BKN.vs";
+
+        assert!(matches!(
+            quorums.propose(&path, 0, stamped(one, "MIL"), None),
+            Decision::Waiting
+        ));
+        assert!(matches!(
+            quorums.propose(&path, 1, stamped(one, "BKN"), None),
+            Decision::Waiting
+        ));
+        match quorums.propose(&path, 2, stamped(one, "MIL"), None) {
+            Decision::Agreed {
+                stamped: agreed,
+                dissent,
+                ..
+            } => {
+                assert_eq!(agreed, stamped(one, "MIL"));
+                assert_eq!(
+                    dissent
+                        .into_iter()
+                        .map(|(proposal, _)| (proposal.source, proposal.outcome))
+                        .collect::<Vec<_>>(),
+                    vec![(1, "BKN".to_string())]
+                );
+            }
+            _ => panic!("two sources agree"),
+        }
+
+        assert!(matches!(
+            quorums.propose(&path, 0, stamped(two, "MIL_win"), None),
+            Decision::Waiting
+        ));
+        // a source changing its mind replaces its proposal
+        assert!(matches!(
+            quorums.propose(&path, 0, stamped(two, "BKN_win"), None),
+            Decision::Waiting
+        ));
+        assert!(matches!(
+            quorums.propose(&path, 1, stamped(two, "MIL_win"), None),
+            Decision::Waiting
+        ));
+        match quorums.propose(&path, 2, stamped(two, "draw"), None) {
+            Decision::Disagreed { review, .. } => {
+                assert_eq!(review.path, path);
+                assert_eq!(
+                    review
+                        .proposals
+                        .iter()
+                        .map(|proposal| proposal.outcome.as_str())
+                        .collect::<Vec<_>>(),
+                    vec!["BKN_win", "MIL_win", "draw"]
+                );
+            }
+            _ => panic!("no two sources agree"),
+        }
+    }
+
+    #[tokio::test]
+    async fn undecided_events_time_out() {
+        let path = Path::from_str("/NBA/match").unwrap();
+        let mut quorums = OutcomeQuorums::new([(path.clone(), (2, 3))].into_iter().collect())
+            .with_timeout(Duration::from_secs(60));
+        let one = "/NBA/match/2021-06-20/MIL_BKN.winner";
+        assert_eq!(
+            quorums.quorum_path(&EventId::from_str(one).unwrap()),
+            Some(&path)
+        );
+        assert_eq!(
+            quorums.quorum_path(&EventId::from_str("/NBA/2021-06-20/MIL_BKN.winner").unwrap()),
+            None
+        );
+        let nested = Path::from_str("/NBA/match/playoffs").unwrap();
+        let nested_quorums = OutcomeQuorums::new(
+            [(path.clone(), (2, 3)), (nested.clone(), (1, 1))]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(
+            nested_quorums
+                .quorum_path(&EventId::from_str("/NBA/match/playoffs/MIL_BKN.winner").unwrap()),
+            Some(&nested),
+            "the deepest quorum wins"
+        );
+        assert_eq!(quorums.next_deadline(), None);
+
+        let start = Instant::now();
+        assert!(matches!(
+            quorums.propose(&path, 0, stamped(one, "MIL"), None),
+            Decision::Waiting
+        ));
+        let deadline = quorums.next_deadline().unwrap();
+        assert!(deadline >= start + Duration::from_secs(60));
+        assert!(quorums.expire(deadline - Duration::from_secs(1)).is_empty());
+        match &quorums.expire(deadline)[..] {
+            [Decision::Disagreed { review, .. }] => {
+                assert_eq!(
+                    review.proposals,
+                    vec![Proposal::new(0, &stamped(one, "MIL"))]
+                )
+            }
+            _ => panic!("the event should be put up for review"),
+        }
+        assert_eq!(quorums.next_deadline(), None);
+    }
+
+    #[tokio::test]
+    async fn outcome_quorum_attests_or_escalates() {
+        use crate::{
+            db::DbReadOracle,
+            sources::{Stream, Update},
+        };
+        use olivia_core::Event;
+        use olivia_secp256k1::Secp256k1;
+        use std::sync::Arc;
+        use tokio::sync::{mpsc, oneshot};
+        use tokio_stream::{wrappers::UnboundedReceiverStream, StreamMap};
+        let db = Arc::new(crate::db::in_memory::InMemory::<Secp256k1>::default());
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let oracle = crate::oracle::Oracle::new(crate::seed::Seed::new([42u8; 64]), db.clone())
+            .await
+            .unwrap();
+        let agreed = EventId::from_str("/test/quorum/MIL_BKN.winner").unwrap();
+        let disputed = EventId::from_str("/test/quorum/MIL_BKN.vs").unwrap();
+        let late = EventId::from_str("/test/quorum/LAL_BOS.winner").unwrap();
+        let bypassed = EventId::from_str("/test/quorum/NYK_ATL.winner").unwrap();
+        for id in [&agreed, &disputed, &late, &bypassed] {
+            oracle
+                .add_event(Event {
+                    id: id.clone(),
+                    expected_outcome_time: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        let path = Path::from_str("/test/quorum").unwrap();
+        let mut outcomes = StreamMap::new();
+        let mut senders = vec![];
+        for source in 0..3 {
+            let (sender, receiver) = mpsc::unbounded_channel::<Update<StampedOutcome>>();
+            outcomes.insert(
+                (path.clone(), source),
+                Box::pin(UnboundedReceiverStream::new(receiver)) as Stream<StampedOutcome>,
+            );
+            senders.push(sender);
+        }
+        // a source for a path above the quorum's
+        let (sender, receiver) = mpsc::unbounded_channel::<Update<StampedOutcome>>();
+        outcomes.insert(
+            (Path::from_str("/test").unwrap(), 0),
+            Box::pin(UnboundedReceiverStream::new(receiver)) as Stream<StampedOutcome>,
+        );
+        senders.push(sender);
+        let (write_sender, writes) = crate::sources::http::channel();
+        tokio::spawn(
+            crate::oracle_loop::OracleLoop {
+                events: StreamMap::new(),
+                outcomes,
+                nodes: StreamMap::new(),
+                writes,
+                quorums: OutcomeQuorums::new([(path.clone(), (2, 3))].into_iter().collect())
+                    .with_timeout(Duration::from_millis(500)),
+                oracle,
+                db: db.clone(),
+                logger,
+            }
+            .start(),
+        );
+        let propose = |source: usize, id: &str, outcome: &str| {
+            let (processed_notifier, processed) = oneshot::channel();
+            senders[source]
+                .send(Update {
+                    update: StampedOutcome {
+                        outcome: Outcome::try_from_id_and_outcome(
+                            EventId::from_str(id).unwrap(),
+                            outcome,
+                        )
+                        .unwrap(),
+                        time: olivia_core::chrono::Utc::now().naive_utc(),
+                    },
+                    processed_notifier: Some(processed_notifier),
+                })
+                .unwrap_or_else(|_| panic!("oracle loop stopped"));
+            processed
+        };
+        let attestation = |id: EventId| {
+            let db = db.clone();
+            async move {
+                db.get_announced_event(&id)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .attestation
+                    .map(|attestation| attestation.outcome)
+            }
+        };
+
+        let first = propose(0, "/MIL_BKN.winner", "MIL");
+        let dissent = propose(1, "/MIL_BKN.winner", "BKN");
+        let second = propose(2, "/MIL_BKN.winner", "MIL");
+        assert_eq!(first.await, Ok(Processed::Done));
+        assert_eq!(second.await, Ok(Processed::Done));
+        assert_eq!(
+            dissent.await,
+            Ok(Processed::Rejected),
+            "the dissenting source is told it failed"
+        );
+        assert_eq!(attestation(agreed).await, Some("MIL".to_string()));
+
+        let proposed = vec![
+            propose(0, "/MIL_BKN.vs", "MIL_win"),
+            propose(1, "/MIL_BKN.vs", "BKN_win"),
+            propose(2, "/MIL_BKN.vs", "draw"),
+        ];
+        for processed in proposed {
+            assert_eq!(processed.await, Ok(Processed::Rejected));
+        }
+        assert_eq!(attestation(disputed.clone()).await, None);
+        let review = db.get_outcome_review(&disputed).await.unwrap().unwrap();
+        assert_eq!(review.path, path);
+        assert_eq!(review.proposals.len(), 3);
+
+        // sources that try again are added to the review rather than attested to
+        assert_eq!(
+            propose(1, "/MIL_BKN.vs", "MIL_win").await,
+            Ok(Processed::Rejected)
+        );
+        assert_eq!(attestation(disputed.clone()).await, None);
+        assert_eq!(
+            db.get_outcome_review(&disputed)
+                .await
+                .unwrap()
+                .unwrap()
+                .proposals[1]
+                .outcome,
+            "MIL_win"
+        );
+
+        // nor can sources of other paths
+        assert_eq!(
+            propose(3, "/quorum/NYK_ATL.winner", "NYK").await,
+            Ok(Processed::Rejected)
+        );
+        assert_eq!(attestation(bypassed).await, None);
+
+        // outcomes posted to the REST API can't skip the quorum
+        let (result, posted) = oneshot::channel();
+        write_sender
+            .send(crate::sources::http::Write::Outcome {
+                outcome: StampedOutcome::test_instance(&late),
+                result,
+            })
+            .unwrap_or_else(|_| panic!("oracle loop stopped"));
+        assert_eq!(posted.await.unwrap().status, 409);
+
+        assert_eq!(
+            propose(0, "/LAL_BOS.winner", "LAL").await,
+            Ok(Processed::Rejected),
+            "the other sources never proposed an outcome so it timed out"
+        );
+        assert_eq!(attestation(late.clone()).await, None);
+        assert_eq!(
+            db.get_outcome_review(&late)
+                .await
+                .unwrap()
+                .unwrap()
+                .proposals
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,703 @@
+//! Keeps the oracle's secret keys in a separate `olivia signer` process.
+//!
+//! The process that talks to the database and the outside world uses a [`RemoteSigner`] which
+//! sends each request over a unix socket to a [`SignerDaemon`]. The daemon holds the
+//! [`KeyChain`] and refuses requests that break its policy no matter who sends them:
+//!
+//! - it never attests to two different outcomes of an event (including voiding it after attesting
+//!   to an outcome or vice versa) since that would reveal the attestation key.
+//! - it never attests to an outcome before the event's `expected_outcome_time`.
+//! - it only attests to events it announced. The time it checks against is the one it last signed
+//!   for the event, not the one in the request.
+//! - it never re-announces an event it has attested to or moves an event's
+//!   `expected_outcome_time` earlier or into the past.
+//! - it only hands the oracle over to the [`Successor`] it was configured with and only does so
+//!   once.
+//!
+//! Every announcement, attestation and key succession is written to the daemon's state file before it is made so
+//! the policy holds across restarts.
+//!
+//! Each request and response is a line of JSON.
+use crate::keychain::{KeyChain, Signer};
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use olivia_core::{
+    chrono::{NaiveDateTime, Utc},
+    AttestationSchemes, BatchSchedule, Event, EventId, Group, KeySuccession, NamespaceKeys,
+    OracleEvent, OracleKeys, Outcome, RawAnnouncement, RawKeySuccession, RawNonceBatch, Successor,
+};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt},
+    net::{UnixListener, UnixStream},
+    sync::Mutex,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "method", bound = "C: Group")]
+enum Request<C: Group> {
+    PublicKeys,
+    NamespacePublicKeys,
+    CreateAnnouncement {
+        event: Event,
+    },
+    CreateNonceBatch {
+        schedule: BatchSchedule,
+    },
+    Attest {
+        oracle_event: OracleEvent<C>,
+        /// `None` voids the event
+        outcome: Option<String>,
+    },
+    SignKeySuccession {
+        succession: KeySuccession<C>,
+    },
+}
+
+type Response = Result<serde_json::Value, String>;
+
+/// A [`Signer`] that forwards everything to a [`SignerDaemon`] listening on `socket`.
+pub struct RemoteSigner<C: Group> {
+    socket: PathBuf,
+    public_keys: OracleKeys<C>,
+    namespace_public_keys: Vec<NamespaceKeys<C>>,
+}
+
+impl<C: Group> RemoteSigner<C> {
+    /// Connects to the daemon to get its public keys.
+    pub async fn connect(socket: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let socket = socket.into();
+        let public_keys = request(&socket, &Request::<C>::PublicKeys)
+            .await
+            .with_context(|| format!("connecting to signer at {}", socket.display()))?;
+        let namespace_public_keys = request(&socket, &Request::<C>::NamespacePublicKeys).await?;
+        Ok(Self {
+            socket,
+            public_keys,
+            namespace_public_keys,
+        })
+    }
+}
+
+async fn request<C: Group, T: serde::de::DeserializeOwned>(
+    socket: &Path,
+    request: &Request<C>,
+) -> anyhow::Result<T> {
+    let mut stream = tokio::io::BufStream::new(UnixStream::connect(socket).await?);
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).await?;
+    stream.flush().await?;
+    let mut line = String::new();
+    stream.read_line(&mut line).await?;
+    let response: Response = serde_json::from_str(&line)
+        .with_context(|| format!("signer sent an invalid response '{}'", line.trim_end()))?;
+    let value = response.map_err(|e| anyhow!("signer refused: {}", e))?;
+    Ok(serde_json::from_value(value)?)
+}
+
+#[async_trait]
+impl<C: Group> Signer<C> for RemoteSigner<C> {
+    fn oracle_public_keys(&self) -> OracleKeys<C> {
+        self.public_keys.clone()
+    }
+
+    fn namespace_public_keys(&self) -> Vec<NamespaceKeys<C>> {
+        self.namespace_public_keys.clone()
+    }
+
+    async fn create_announcement(&self, event: Event) -> anyhow::Result<RawAnnouncement<C>> {
+        request(&self.socket, &Request::<C>::CreateAnnouncement { event }).await
+    }
+
+    async fn create_nonce_batch(
+        &self,
+        schedule: BatchSchedule,
+    ) -> anyhow::Result<RawNonceBatch<C>> {
+        request(&self.socket, &Request::<C>::CreateNonceBatch { schedule }).await
+    }
+
+    async fn attest(
+        &self,
+        oracle_event: &OracleEvent<C>,
+        outcome: Option<&Outcome>,
+    ) -> anyhow::Result<AttestationSchemes<C>> {
+        request(
+            &self.socket,
+            &Request::Attest {
+                oracle_event: oracle_event.clone(),
+                outcome: outcome.map(Outcome::outcome_string),
+            },
+        )
+        .await
+    }
+
+    async fn sign_key_succession(
+        &self,
+        succession: KeySuccession<C>,
+    ) -> anyhow::Result<RawKeySuccession<C>> {
+        request(&self.socket, &Request::SignKeySuccession { succession }).await
+    }
+}
+
+/// A line in the daemon's state file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+enum Record {
+    Announced {
+        event_id: EventId,
+        expected_outcome_time: Option<NaiveDateTime>,
+    },
+    Attested {
+        event_id: EventId,
+        outcome: String,
+    },
+    Succeeded {
+        keys: serde_json::Value,
+    },
+}
+
+struct Policy {
+    state_file: File,
+    announced: HashMap<EventId, Option<NaiveDateTime>>,
+    attested: HashMap<EventId, String>,
+    /// The keys we handed over to.
+    succeeded: Option<serde_json::Value>,
+}
+
+impl Policy {
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let state_file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("opening signer state file {}", path.display()))?;
+        let records = BufReader::new(&state_file)
+            .lines()
+            .map(|line| {
+                serde_json::from_str(&line?)
+                    .with_context(|| format!("corrupt signer state file {}", path.display()))
+            })
+            .collect::<anyhow::Result<Vec<Record>>>()?;
+        let mut policy = Policy {
+            state_file,
+            announced: HashMap::default(),
+            attested: HashMap::default(),
+            succeeded: None,
+        };
+        for record in records {
+            policy.apply(record);
+        }
+        Ok(policy)
+    }
+
+    fn apply(&mut self, record: Record) {
+        match record {
+            Record::Announced {
+                event_id,
+                expected_outcome_time,
+            } => {
+                self.announced.insert(event_id, expected_outcome_time);
+            }
+            Record::Attested { event_id, outcome } => {
+                self.attested.insert(event_id, outcome);
+            }
+            Record::Succeeded { keys } => self.succeeded = Some(keys),
+        }
+    }
+
+    /// Writes the record to disk before applying it.
+    fn record(&mut self, record: Record) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        self.state_file.write_all(line.as_bytes())?;
+        self.state_file.sync_data()?;
+        self.apply(record);
+        Ok(())
+    }
+
+    fn check_announce(&self, event: &Event) -> Result<(), String> {
+        if self.attested.contains_key(&event.id) {
+            return Err(format!(
+                "{} has already been attested to so it can't be announced again",
+                event.id
+            ));
+        }
+        let recorded = match self.announced.get(&event.id) {
+            Some(Some(recorded)) => recorded,
+            _ => return Ok(()),
+        };
+        match event.expected_outcome_time {
+            Some(time) if time == *recorded => Ok(()),
+            Some(time) if time < *recorded || time < Utc::now().naive_utc() => Err(format!(
+                "{} can't be moved from {} to {}",
+                event.id, recorded, time
+            )),
+            Some(_) => Ok(()),
+            None => Err(format!(
+                "{} can't be announced again without its expected outcome time {}",
+                event.id, recorded
+            )),
+        }
+    }
+
+    fn check_attest(&self, event_id: &EventId, outcome: &str, is_void: bool) -> Result<(), String> {
+        let expected_outcome_time = self
+            .announced
+            .get(event_id)
+            .ok_or_else(|| format!("{} was never announced by this signer", event_id))?;
+        match self.attested.get(event_id) {
+            Some(existing) if existing != outcome => {
+                return Err(format!(
+                    "{} has already been attested to with '{}'",
+                    event_id, existing
+                ))
+            }
+            Some(_) => return Ok(()),
+            None => {}
+        }
+        // Voiding an event reveals nothing about its outcome so it can happen at any time (e.g.
+        // when a match is postponed).
+        if let (Some(expected_outcome_time), false) = (expected_outcome_time, is_void) {
+            if Utc::now().naive_utc() < *expected_outcome_time {
+                return Err(format!(
+                    "{} can't be attested to before {}",
+                    event_id, expected_outcome_time
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_succession(&self, keys: &serde_json::Value) -> Result<(), String> {
+        match &self.succeeded {
+            Some(succeeded) if succeeded != keys => {
+                Err("this signer has already handed over to other keys".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Holds the oracle's keys and serves [`RemoteSigner`]s.
+pub struct SignerDaemon<C: Group> {
+    keychain: KeyChain<C>,
+    successor: Option<Successor<C>>,
+    policy: Mutex<Policy>,
+    logger: slog::Logger,
+}
+
+impl<C: Group> SignerDaemon<C> {
+    /// Creates the daemon, loading what it has already signed from `state_file`.
+    pub fn new(
+        keychain: KeyChain<C>,
+        state_file: &Path,
+        logger: slog::Logger,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            keychain,
+            successor: None,
+            policy: Mutex::new(Policy::open(state_file)?),
+            logger,
+        })
+    }
+
+    /// The keys the daemon may hand the oracle over to. Without one it refuses to sign any key
+    /// succession.
+    pub fn with_successor(self, successor: Successor<C>) -> Self {
+        Self {
+            successor: Some(successor),
+            ..self
+        }
+    }
+
+    pub async fn serve(self: Arc<Self>, listener: UnixListener) -> anyhow::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let daemon = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = daemon.handle_connection(stream).await {
+                    error!(daemon.logger, "signer connection failed"; "error" => e.to_string());
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, stream: UnixStream) -> anyhow::Result<()> {
+        let mut stream = tokio::io::BufStream::new(stream);
+        let mut line = String::new();
+        while stream.read_line(&mut line).await? > 0 {
+            let response: Response = match serde_json::from_str::<Request<C>>(&line) {
+                Ok(request) => self.handle_request(request).await,
+                Err(e) => Err(format!("invalid request: {}", e)),
+            };
+            if let Err(e) = &response {
+                warn!(self.logger, "refused request"; "reason" => e);
+            }
+            let mut response = serde_json::to_string(&response)?;
+            response.push('\n');
+            stream.write_all(response.as_bytes()).await?;
+            stream.flush().await?;
+            line.clear();
+        }
+        Ok(())
+    }
+
+    async fn handle_request(&self, request: Request<C>) -> Response {
+        match request {
+            Request::PublicKeys => to_response(Ok(self.keychain.oracle_public_keys())),
+            Request::NamespacePublicKeys => to_response(Ok(self.keychain.namespace_public_keys())),
+            Request::CreateAnnouncement { event } => {
+                let mut policy = self.policy.lock().await;
+                policy.check_announce(&event)?;
+                policy
+                    .record(Record::Announced {
+                        event_id: event.id.clone(),
+                        expected_outcome_time: event.expected_outcome_time,
+                    })
+                    .map_err(|e| e.to_string())?;
+                to_response(Signer::create_announcement(&self.keychain, event).await)
+            }
+            Request::CreateNonceBatch { schedule } => {
+                to_response(Signer::create_nonce_batch(&self.keychain, schedule).await)
+            }
+            Request::Attest {
+                oracle_event,
+                outcome,
+            } => {
+                let event_id = &oracle_event.event.id;
+                let outcome = match outcome {
+                    Some(outcome) => Some(
+                        Outcome::try_from_id_and_outcome(event_id.clone(), &outcome)
+                            .map_err(|e| e.to_string())?,
+                    ),
+                    None => None,
+                };
+                let outcome_string = match &outcome {
+                    Some(outcome) => outcome.outcome_string(),
+                    None => olivia_core::VOID_OUTCOME.to_string(),
+                };
+                let mut policy = self.policy.lock().await;
+                policy.check_attest(event_id, &outcome_string, outcome.is_none())?;
+                policy
+                    .record(Record::Attested {
+                        event_id: event_id.clone(),
+                        outcome: outcome_string,
+                    })
+                    .map_err(|e| e.to_string())?;
+                info!(self.logger, "attesting"; "event_id" => event_id.as_str());
+                to_response(Signer::attest(&self.keychain, &oracle_event, outcome.as_ref()).await)
+            }
+            Request::SignKeySuccession { succession } => {
+                let successor = self
+                    .successor
+                    .as_ref()
+                    .ok_or_else(|| "no successor is configured for this signer".to_string())?;
+                if !successor.matches(&succession) {
+                    return Err(
+                        "the keys aren't the successor this signer is configured with".to_string(),
+                    );
+                }
+                let keys = serde_json::to_value(&succession.keys).map_err(|e| e.to_string())?;
+                let mut policy = self.policy.lock().await;
+                policy.check_succession(&keys)?;
+                policy
+                    .record(Record::Succeeded { keys })
+                    .map_err(|e| e.to_string())?;
+                info!(self.logger, "handing over to new keys"; "epoch" => succession.keys.epoch);
+                to_response(Signer::sign_key_succession(&self.keychain, succession).await)
+            }
+        }
+    }
+}
+
+fn to_response<T: serde::Serialize>(result: anyhow::Result<T>) -> Response {
+    result
+        .map(|value| serde_json::to_value(value).expect("signer responses serialize"))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::seed::Seed;
+    use core::str::FromStr;
+    use olivia_core::{chrono::Duration, OracleKeys};
+    use olivia_secp256k1::Secp256k1;
+    use tempfile::TempDir;
+
+    fn successor_keychain() -> KeyChain<Secp256k1> {
+        KeyChain::new(Seed::new([43u8; 64]))
+    }
+
+    async fn start_daemon(socket: &Path, state_file: &Path) {
+        let successor = successor_keychain();
+        let daemon = SignerDaemon::<Secp256k1>::new(
+            KeyChain::new(Seed::new([42u8; 64])),
+            state_file,
+            slog::Logger::root(slog::Discard, o!()),
+        )
+        .unwrap()
+        .with_successor(Successor {
+            keys: successor.oracle_public_keys(),
+            namespaces: successor.namespace_public_keys(),
+        });
+        let listener = UnixListener::bind(socket).unwrap();
+        tokio::spawn(Arc::new(daemon).serve(listener));
+    }
+
+    async fn announce(
+        signer: &RemoteSigner<Secp256k1>,
+        public_keys: &OracleKeys<Secp256k1>,
+        event: Event,
+    ) -> OracleEvent<Secp256k1> {
+        signer
+            .create_announcement(event.clone())
+            .await
+            .unwrap()
+            .verify_against_id(&event.id, &public_keys.announcement)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn remote_signer_enforces_policy() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("signer.sock");
+        let state_file = dir.path().join("signer.state");
+        start_daemon(&socket, &state_file).await;
+
+        let signer = RemoteSigner::<Secp256k1>::connect(&socket).await.unwrap();
+        let keychain = KeyChain::<Secp256k1>::new(Seed::new([42u8; 64]));
+        let public_keys = signer.oracle_public_keys();
+        assert_eq!(public_keys, keychain.oracle_public_keys());
+
+        let outcome = |id: &EventId, outcome: &str| {
+            Outcome::try_from_id_and_outcome(id.clone(), outcome).unwrap()
+        };
+
+        let past = EventId::from_str("/test/signer/FOO_BAR.vs").unwrap();
+        let oracle_event = announce(
+            &signer,
+            &public_keys,
+            Event {
+                id: past.clone(),
+                expected_outcome_time: Some(Utc::now().naive_utc() - Duration::hours(1)),
+            },
+        )
+        .await;
+        let schemes = signer
+            .attest(&oracle_event, Some(&outcome(&past, "FOO_win")))
+            .await
+            .unwrap();
+        assert_eq!(
+            schemes.olivia_v1,
+            Signer::attest(&keychain, &oracle_event, Some(&outcome(&past, "FOO_win")))
+                .await
+                .unwrap()
+                .olivia_v1
+        );
+        assert!(
+            signer
+                .attest(&oracle_event, Some(&outcome(&past, "FOO_win")))
+                .await
+                .is_ok(),
+            "attesting to the same outcome again is fine"
+        );
+        assert!(signer
+            .attest(&oracle_event, Some(&outcome(&past, "BAR_win")))
+            .await
+            .is_err());
+        assert!(signer.attest(&oracle_event, None).await.is_err());
+
+        let future = EventId::from_str("/test/signer/BAZ_QUX.vs").unwrap();
+        let future_event = announce(
+            &signer,
+            &public_keys,
+            Event {
+                id: future.clone(),
+                expected_outcome_time: Some(Utc::now().naive_utc() + Duration::hours(1)),
+            },
+        )
+        .await;
+        assert!(signer
+            .attest(&future_event, Some(&outcome(&future, "BAZ_win")))
+            .await
+            .is_err());
+        let overdue = EventId::from_str("/test/signer/overdue.occur").unwrap();
+        announce(
+            &signer,
+            &public_keys,
+            Event {
+                id: overdue.clone(),
+                expected_outcome_time: Some(Utc::now().naive_utc() - Duration::hours(2)),
+            },
+        )
+        .await;
+        assert!(
+            signer
+                .create_announcement(Event {
+                    id: overdue.clone(),
+                    expected_outcome_time: Some(Utc::now().naive_utc() - Duration::hours(1)),
+                })
+                .await
+                .is_err(),
+            "can't reschedule into the past"
+        );
+
+        let reschedule = |hours: i64| {
+            signer.create_announcement(Event {
+                id: future.clone(),
+                expected_outcome_time: Some(Utc::now().naive_utc() + Duration::hours(hours)),
+            })
+        };
+        assert!(reschedule(2).await.is_ok(), "postponing is fine");
+        assert!(
+            reschedule(1).await.is_err(),
+            "can't bring the outcome time forward"
+        );
+        assert!(signer
+            .create_announcement(Event {
+                id: future.clone(),
+                expected_outcome_time: None,
+            })
+            .await
+            .is_err());
+        assert!(
+            signer.attest(&future_event, None).await.is_ok(),
+            "voiding early is fine"
+        );
+        assert!(
+            reschedule(3).await.is_err(),
+            "can't announce an event that has been attested to"
+        );
+        assert!(signer
+            .create_announcement(Event {
+                id: past.clone(),
+                expected_outcome_time: Some(Utc::now().naive_utc() + Duration::hours(1)),
+            })
+            .await
+            .is_err());
+
+        let unknown = EventId::from_str("/test/signer/unknown.occur").unwrap();
+        let unknown_event = OracleEvent {
+            event: unknown.clone().into(),
+            schemes: keychain.announcement_schemes(&unknown),
+        };
+        assert!(signer
+            .attest(&unknown_event, Some(&outcome(&unknown, "true")))
+            .await
+            .is_err());
+
+        // a new daemon with the same state file remembers what was attested to
+        let socket = dir.path().join("restarted.sock");
+        start_daemon(&socket, &state_file).await;
+        let signer = RemoteSigner::<Secp256k1>::connect(&socket).await.unwrap();
+        assert!(signer
+            .attest(&oracle_event, Some(&outcome(&past, "BAR_win")))
+            .await
+            .is_err());
+        assert!(signer
+            .attest(&oracle_event, Some(&outcome(&past, "FOO_win")))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn signer_only_hands_over_to_its_successor() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("signer.sock");
+        let state_file = dir.path().join("signer.state");
+        start_daemon(&socket, &state_file).await;
+        let signer = RemoteSigner::<Secp256k1>::connect(&socket).await.unwrap();
+        let succession = |keys: OracleKeys<Secp256k1>, epoch: u32| KeySuccession {
+            keys: OracleKeys { epoch, ..keys },
+            time: Utc::now().naive_utc(),
+            namespaces: vec![],
+        };
+        let successor = successor_keychain().oracle_public_keys();
+        let impostor = KeyChain::<Secp256k1>::new(Seed::new([44u8; 64])).oracle_public_keys();
+
+        assert!(signer
+            .sign_key_succession(succession(impostor.clone(), 1))
+            .await
+            .is_err());
+        let signed = signer
+            .sign_key_succession(succession(successor.clone(), 1))
+            .await
+            .unwrap();
+        assert_eq!(
+            signed
+                .verify(&signer.oracle_public_keys())
+                .map(|succession| succession.keys),
+            Some(OracleKeys {
+                epoch: 1,
+                ..successor.clone()
+            })
+        );
+        assert!(
+            signer
+                .sign_key_succession(succession(successor.clone(), 1))
+                .await
+                .is_ok(),
+            "signing the same succession again is fine"
+        );
+        assert!(
+            signer
+                .sign_key_succession(succession(successor.clone(), 2))
+                .await
+                .is_err(),
+            "can't hand over twice"
+        );
+
+        // a new daemon with the same state file remembers the handover
+        let socket = dir.path().join("restarted.sock");
+        start_daemon(&socket, &state_file).await;
+        let signer = RemoteSigner::<Secp256k1>::connect(&socket).await.unwrap();
+        assert!(signer
+            .sign_key_succession(succession(successor, 2))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn oracle_with_remote_signer() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("signer.sock");
+        let state_file = dir.path().join("signer.state");
+        start_daemon(&socket, &state_file).await;
+        let signer = RemoteSigner::<Secp256k1>::connect(&socket).await.unwrap();
+        let db = Arc::<crate::db::in_memory::InMemory<Secp256k1>>::default();
+        let oracle = crate::Oracle::with_signer(Arc::new(signer), db.clone())
+            .await
+            .unwrap();
+        let event_id = EventId::from_str("/test/signer/oracle.occur").unwrap();
+        oracle.add_event(event_id.clone().into()).await.unwrap();
+        oracle
+            .complete_event(olivia_core::StampedOutcome::test_instance(&event_id))
+            .await
+            .unwrap();
+        let public_keys = oracle.public_keys();
+        let announced = crate::db::DbReadOracle::get_announced_event(&*db, &event_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let oracle_event = announced
+            .announcement
+            .verify_against_id(&event_id, &public_keys.announcement)
+            .unwrap();
+        let attestation = announced.attestation.unwrap();
+        assert!(attestation
+            .verify_olivia_v1_attestation(&oracle_event, &public_keys)
+            .is_ok());
+        assert!(attestation
+            .verify_ecdsa_v1_attestation(&oracle_event, &public_keys)
+            .is_ok());
+    }
+}
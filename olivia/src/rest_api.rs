@@ -1,6 +1,6 @@
-use crate::db::DbReadOracle;
+use crate::{db::DbReadOracle, write_api::WriteApi};
 use core::{convert::TryFrom, str::FromStr};
-use olivia_core::{http::*, EventId, GetPath, Group, Path, PathRef};
+use olivia_core::{http::*, EventId, GetPath, Group, KeyHistory, NamespaceKeys, Path, PathRef};
 use serde::Serialize;
 use std::{convert::Infallible, sync::Arc};
 use warp::{self, http, Filter};
@@ -128,10 +128,20 @@ async fn get_event<C: Group>(
 
 pub async fn get_root<C: Group>(db: Arc<dyn DbReadOracle<C>>) -> ApiReply<RootResponse<C>> {
     let public_keys = db.get_public_keys().await;
+    let namespaces = match db.get_namespace_keys().await {
+        Ok(namespaces) => namespaces,
+        Err(_) => {
+            return ApiReply::Err(
+                ErrorMessage::internal_server_error()
+                    .with_message("namespace keys could not be retrieved from the database"),
+            )
+        }
+    };
     match public_keys {
         Ok(Some(public_keys)) => match db.get_node(PathRef::root()).await {
             Ok(Some(node)) => ApiReply::Ok(RootResponse {
                 public_keys,
+                namespaces,
                 node: GetPath {
                     events: node.events,
                     child_desc: node.child_desc,
@@ -152,7 +162,7 @@ pub async fn get_root<C: Group>(db: Arc<dyn DbReadOracle<C>>) -> ApiReply<RootRe
 async fn get_path<C: Group>(
     tail: ApiReply<String>,
     db: Arc<dyn DbReadOracle<C>>,
-) -> ApiReply<PathResponse> {
+) -> ApiReply<PathResponse<C>> {
     let tail = match tail {
         ApiReply::Ok(tail) => tail,
         ApiReply::Err(e) => return ApiReply::Err(e),
@@ -167,9 +177,18 @@ async fn get_path<C: Group>(
             )
         }
     };
+    // the keys are just a convenience so the node is still served without them
+    let public_keys = match db.get_namespace_keys().await {
+        Ok(namespaces) => match NamespaceKeys::find(&namespaces, path.as_path_ref()) {
+            Some(namespace) => Some(namespace.keys.clone()),
+            None => db.get_public_keys().await.ok().flatten(),
+        },
+        Err(_) => None,
+    };
     let node = db.get_node(path.as_path_ref()).await;
     match node {
         Ok(Some(node)) => ApiReply::Ok(PathResponse {
+            public_keys,
             node: GetPath {
                 events: node.events,
                 child_desc: node.child_desc,
@@ -180,6 +199,41 @@ async fn get_path<C: Group>(
     }
 }
 
+async fn get_nonce_batches<C: Group>(
+    tail: ApiReply<String>,
+    db: Arc<dyn DbReadOracle<C>>,
+) -> ApiReply<NonceBatchesResponse<C>> {
+    let tail = match tail {
+        ApiReply::Ok(tail) => tail,
+        ApiReply::Err(e) => return ApiReply::Err(e),
+    };
+    let tail = tail.as_str().strip_suffix('/').unwrap_or(tail.as_str());
+    let path = match Path::from_str(&format!("/{}", tail)) {
+        Ok(path) => path,
+        Err(e) => {
+            return ApiReply::Err(
+                ErrorMessage::bad_request()
+                    .with_message(format!("'/{}' is not a valid path: {}", tail, e)),
+            )
+        }
+    };
+    match db.get_nonce_batches(path.as_path_ref()).await {
+        Ok(nonce_batches) => ApiReply::Ok(NonceBatchesResponse { nonce_batches }),
+        Err(_e) => ApiReply::Err(ErrorMessage::internal_server_error()),
+    }
+}
+
+/// Every key the oracle has had so that announcements made with retired keys can still be checked.
+async fn get_key_history<C: Group>(db: Arc<dyn DbReadOracle<C>>) -> ApiReply<KeyHistory<C>> {
+    match db.get_key_history().await {
+        Ok(Some(history)) => ApiReply::Ok(history),
+        Err(_) | Ok(None) => ApiReply::Err(
+            ErrorMessage::internal_server_error()
+                .with_message("oracle key history could not be retrieved from the database"),
+        ),
+    }
+}
+
 async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
     Ok(ApiReply::<()>::Err(
         ErrorMessage::internal_server_error()
@@ -187,9 +241,15 @@ async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, warp
     ))
 }
 
+/// The read-only API along with `POST /events` and `POST /outcomes` if `writes` is set.
+/// The first segments of the paths the REST API serves something other than events under. The
+/// oracle won't create events under them so they can't be shadowed.
+pub const RESERVED_PATHS: &[&str] = &["keys", "nonce-batches"];
+
 pub fn routes<C: Group>(
     db: Arc<dyn DbReadOracle<C>>,
-    _logger: slog::Logger,
+    writes: Option<WriteApi>,
+    logger: slog::Logger,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::reject::Rejection> + Clone {
     let event = warp::get()
         .and(percent_decoded_tail())
@@ -213,12 +273,28 @@ pub fn routes<C: Group>(
         .and(with_db(db.clone()))
         .and_then(|tail, db| async { Ok::<_, Infallible>(get_path(tail, db).await) });
 
+    let nonce_batches = warp::get()
+        .and(warp::path("nonce-batches"))
+        .and(percent_decoded_tail())
+        .and(with_db(db.clone()))
+        .and_then(|tail, db| async { Ok::<_, Infallible>(get_nonce_batches(tail, db).await) });
+
+    let keys = warp::get()
+        .and(warp::path("keys"))
+        .and(warp::path::end())
+        .and(with_db(db.clone()))
+        .and_then(|db| async { Ok::<_, Infallible>(get_key_history(db).await) });
+
     let cors = warp::cors()
         .allow_any_origin()
         .allow_methods(vec!["OPTIONS", "GET", "POST", "DELETE", "PUT"])
-        .allow_headers(vec!["content-type"]);
+        .allow_headers(vec!["content-type", "authorization"]);
 
-    root.or(event_with_query)
+    crate::write_api::routes(writes, logger)
+        .or(root)
+        .or(keys)
+        .or(nonce_batches)
+        .or(event_with_query)
         .or(event)
         .or(path)
         .with(cors)
@@ -4,6 +4,7 @@ macro_rules! run_rest_api_tests {
     (
         oracle => $oracle:ident,
         routes => $routes:ident,
+        db => $db:ident,
         curve => $curve:ty,
      { $($init:tt)* }) => {
         #[cfg(test)]
@@ -142,6 +143,139 @@ macro_rules! run_rest_api_tests {
                         .is_some())
             }
 
+            #[tokio::test]
+            async fn get_void_event(){
+                $($init)*;
+                let event_id = EventId::from_str("/test/one/two/FOO_BAR.vs").unwrap();
+
+                $oracle
+                    .add_event(event_id.clone().into())
+                    .await
+                    .unwrap();
+                $oracle
+                    .cancel_event(&event_id, olivia_core::chrono::Utc::now().naive_utc())
+                    .await
+                    .unwrap();
+
+                let res = warp::test::request()
+                    .path(event_id.as_str())
+                    .reply(&$routes)
+                    .await;
+
+                assert_eq!(res.status(), 200);
+                let body = j::<EventResponse<$curve>>(&res.body()).unwrap();
+                let attestation = body.attestation.expect("voided event should be attested to");
+                assert!(attestation.is_void());
+                assert_eq!(attestation.outcome, olivia_core::VOID_OUTCOME);
+            }
+
+            #[tokio::test]
+            async fn get_nonce_batches(){
+                $($init)*;
+                let schedule = olivia_core::BatchSchedule {
+                    path: olivia_core::Path::from_str("/test/batch/BXBT").unwrap(),
+                    event_kind: olivia_core::EventKind::from_str("price?n=4").unwrap(),
+                    start: olivia_core::chrono::NaiveDateTime::from_str("2021-10-04T00:00:00").unwrap(),
+                    interval: 60,
+                    n_slots: 10,
+                    epoch: $oracle.public_keys().epoch,
+                };
+                $oracle.add_nonce_batch(schedule.clone()).await.unwrap();
+
+                let public_keys = $oracle.public_keys();
+                let res = warp::test::request()
+                    .path("/nonce-batches/test/batch/BXBT")
+                    .reply(&$routes)
+                    .await;
+                assert_eq!(res.status(), 200);
+                let body = j::<NonceBatchesResponse<$curve>>(&res.body()).unwrap();
+                assert_eq!(body.nonce_batches.len(), 1);
+                let batch = body.nonce_batches[0]
+                    .verify(&public_keys.announcement)
+                    .expect("batch should be signed by the oracle");
+                assert_eq!(batch.schedule, schedule);
+
+                // an event created later in one of the slots is announced with the batch's nonces
+                let event_id = EventId::from_str("/test/batch/BXBT/2021-10-04T00:05:00.price?n=4").unwrap();
+                assert_eq!(schedule.slot_for_event(&event_id), Some(5));
+                $oracle.add_event(event_id.clone().into()).await.unwrap();
+                let res = warp::test::request()
+                    .path(event_id.as_str())
+                    .reply(&$routes)
+                    .await;
+                let oracle_event = j::<EventResponse<$curve>>(&res.body())
+                    .unwrap()
+                    .announcement
+                    .verify_against_id(&event_id, &public_keys.announcement)
+                    .unwrap();
+                assert!(oracle_event.schemes.olivia_v1.is_some());
+                assert!(batch.is_consistent_with(&oracle_event));
+
+                let res = warp::test::request()
+                    .path("/nonce-batches/test/batch")
+                    .reply(&$routes)
+                    .await;
+                let body = j::<NonceBatchesResponse<$curve>>(&res.body()).unwrap();
+                assert!(body.nonce_batches.is_empty());
+
+                // events under /nonce-batches would be hidden by the batches
+                let reserved = EventId::from_str("/nonce-batches/test/batch.occur").unwrap();
+                assert!(matches!(
+                    $oracle.add_event(reserved.into()).await,
+                    Err(crate::oracle::EventResult::ReservedPath { .. })
+                ));
+            }
+
+            #[tokio::test]
+            async fn get_key_history(){
+                $($init)*;
+                let old_event = EventId::from_str("/test/keys/old.occur").unwrap();
+                $oracle.add_event(old_event.clone().into()).await.unwrap();
+                let rotated = $oracle
+                    .rotate_keys(
+                        std::sync::Arc::new(crate::keychain::KeyChain::new(crate::seed::Seed::new([43u8; 64]))),
+                        olivia_core::chrono::NaiveDateTime::from_str("2021-10-04T00:00:00").unwrap(),
+                        true,
+                    )
+                    .await
+                    .unwrap();
+                let new_event = EventId::from_str("/test/keys/new.occur").unwrap();
+                rotated.add_event(new_event.clone().into()).await.unwrap();
+
+                let res = warp::test::request().path("/keys").reply(&$routes).await;
+                assert_eq!(res.status(), 200);
+                let history = j::<olivia_core::KeyHistory<$curve>>(&res.body()).unwrap();
+                let keys = history.verify().expect("key history should be valid");
+                assert_eq!(keys.len(), 2);
+                assert_eq!(keys[0], $oracle.public_keys());
+                assert_eq!(keys[1], rotated.public_keys());
+
+                let root = warp::test::request().path("/").reply(&$routes).await;
+                assert_eq!(j::<RootResponse<$curve>>(&root.body()).unwrap().public_keys, keys[1]);
+
+                // an event tree at /keys would be hidden by the key history
+                let reserved = EventId::from_str("/keys/foo.occur").unwrap();
+                assert!(matches!(
+                    rotated.add_event(reserved.into()).await,
+                    Err(crate::oracle::EventResult::ReservedPath { .. })
+                ));
+                let not_reserved = EventId::from_str("/foo/keys.occur").unwrap();
+                assert!(rotated.add_event(not_reserved.into()).await.is_ok());
+
+                for (event_id, epoch) in [(old_event, 0), (new_event, 1)] {
+                    let res = warp::test::request()
+                        .path(event_id.as_str())
+                        .reply(&$routes)
+                        .await;
+                    let body = j::<EventResponse<$curve>>(&res.body()).unwrap();
+                    assert_eq!(body.epoch, epoch);
+                    assert!(body
+                        .announcement
+                        .verify_against_id(&event_id, &keys[epoch as usize].announcement)
+                        .is_some());
+                }
+            }
+
             #[tokio::test]
             async fn get_event_with_param(){
                 $($init)*;
@@ -205,6 +339,126 @@ macro_rules! run_rest_api_tests {
                         .verify_against_id(&event_id, &public_keys.announcement)
                         .is_some())
             }
+
+            #[tokio::test]
+            async fn namespaces_have_their_own_keys() {
+                $($init)*;
+                use olivia_core::{NamespaceKeys, StampedOutcome};
+                let keychain = crate::keychain::KeyChain::new(crate::seed::Seed::new([42u8; 64])).with_namespaces([
+                    Path::from_str("/x/BitMEX").unwrap(),
+                    Path::from_str("/x/BitMEX/ETHUSD").unwrap(),
+                ]);
+                let namespaces = keychain.namespace_public_keys();
+                let namespaced = crate::oracle::Oracle::with_signer(std::sync::Arc::new(keychain), $db.clone())
+                    .await
+                    .unwrap();
+
+                let pending = EventId::from_str("/y/pending.occur").unwrap();
+                $oracle.add_event(pending.clone().into()).await.unwrap();
+                let over_pending = std::sync::Arc::new(crate::keychain::KeyChain::new(crate::seed::Seed::new([42u8; 64])).with_namespaces([
+                    Path::from_str("/x/BitMEX").unwrap(),
+                    Path::from_str("/x/BitMEX/ETHUSD").unwrap(),
+                    Path::from_str("/y").unwrap(),
+                ]));
+                assert!(
+                    crate::oracle::Oracle::with_signer(over_pending.clone(), $db.clone())
+                        .await
+                        .is_err(),
+                    "can't add a namespace over events that haven't been attested to"
+                );
+                $oracle
+                    .complete_event(StampedOutcome::test_instance(&pending))
+                    .await
+                    .unwrap();
+
+                for (event_id, namespace) in [
+                    ("/x/BitMEX/BXBT/2021-10-04T00:00:00.price?n=20", Some(0)),
+                    ("/x/BitMEX/ETHUSD/2021-10-04T00:00:00.price?n=20", Some(1)),
+                    ("/random/2021-10-04T00:00:00.price?n=20", None),
+                ] {
+                    let event_id = EventId::from_str(event_id).unwrap();
+                    let keys = match namespace {
+                        Some(i) => namespaces[i].keys.clone(),
+                        None => $oracle.public_keys(),
+                    };
+                    assert_eq!(
+                        NamespaceKeys::find(&namespaces, event_id.path()).map(|ns| &ns.keys),
+                        namespace.map(|i| &namespaces[i].keys)
+                    );
+                    namespaced.add_event(event_id.clone().into()).await.unwrap();
+                    namespaced
+                        .complete_event(StampedOutcome::test_instance(&event_id))
+                        .await
+                        .unwrap();
+
+                    let res = warp::test::request()
+                        .path(event_id.path().as_str())
+                        .reply(&$routes)
+                        .await;
+                    let node = j::<PathResponse<$curve>>(&res.body()).unwrap();
+                    assert_eq!(node.public_keys, Some(keys.clone()));
+
+                    let res = warp::test::request()
+                        .path(event_id.as_str())
+                        .reply(&$routes)
+                        .await;
+                    let event = j::<EventResponse<$curve>>(&res.body()).unwrap();
+                    let oracle_event = event
+                        .announcement
+                        .verify_against_id(&event_id, &keys.announcement)
+                        .expect("announcement should be signed with the namespace's key");
+                    assert_eq!(
+                        event
+                            .attestation
+                            .unwrap()
+                            .verify_olivia_v1_attestation(&oracle_event, &keys),
+                        Ok(())
+                    );
+                    if namespace.is_some() {
+                        assert!(event
+                            .announcement
+                            .verify_against_id(&event_id, &$oracle.public_keys().announcement)
+                            .is_none());
+                    }
+                }
+
+                assert!(
+                    crate::oracle::Oracle::new(crate::seed::Seed::new([42u8; 64]), $db.clone())
+                        .await
+                        .is_err(),
+                    "can't drop a namespace that events were announced under"
+                );
+                let namespaced = crate::oracle::Oracle::with_signer(over_pending, $db.clone())
+                    .await
+                    .expect("the event under the new namespace has been attested to");
+
+                let successor = crate::keychain::KeyChain::new(crate::seed::Seed::new([43u8; 64])).with_namespaces([
+                    Path::from_str("/x/BitMEX").unwrap(),
+                    Path::from_str("/x/BitMEX/ETHUSD").unwrap(),
+                    Path::from_str("/y").unwrap(),
+                ]);
+                let mut successor_namespaces = successor.namespace_public_keys();
+                let rotated = namespaced
+                    .rotate_keys(
+                        std::sync::Arc::new(successor),
+                        olivia_core::chrono::NaiveDateTime::from_str("2021-10-05T00:00:00").unwrap(),
+                        true,
+                    )
+                    .await
+                    .unwrap();
+                for namespace in &mut successor_namespaces {
+                    namespace.keys.epoch = rotated.public_keys().epoch;
+                }
+                let history = $db.get_key_history().await.unwrap().unwrap();
+                let succession = history
+                    .successions
+                    .last()
+                    .unwrap()
+                    .verify(&namespaced.public_keys())
+                    .expect("the succession is signed with the old keys");
+                assert_eq!(succession.namespaces, successor_namespaces);
+                assert_eq!($db.get_namespace_keys().await.unwrap(), successor_namespaces);
+            }
         }
     }
 }
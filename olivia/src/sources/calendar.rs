@@ -0,0 +1,325 @@
+//! Calendar schedules for tickers like "16:00 America/New_York on trading days" written as cron
+//! expressions.
+use chrono_tz::Tz;
+use olivia_core::{
+    chrono::{Datelike, NaiveDate, NaiveDateTime, TimeZone},
+    RangeKind,
+};
+use std::{collections::BTreeSet, str::FromStr};
+
+/// How many days ahead to look for the next time before giving up. Long enough for schedules that
+/// only happen on the 29th of February.
+const MAX_DAYS: u32 = 366 * 8;
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// The times matching a five field cron expression (minute hour day-of-month month day-of-week)
+/// in a time zone except on the holidays.
+///
+/// Fields are `*`, numbers or names (`JAN`, `MON`), ranges (`MON-FRI`) and steps (`*/15`, `0-30/10`)
+/// separated by commas. `L` as the day of the month is the last day of the month. Like cron a day
+/// matches if either the day of the month or the day of the week does when both are restricted.
+///
+/// Times that happen twice when the clocks go back are only scheduled the first time and times
+/// that don't happen when the clocks go forward are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calendar {
+    cron: String,
+    time_zone: Tz,
+    holidays: BTreeSet<NaiveDate>,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    last_day: bool,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+fn parse_value(value: &str, min: u32, max: u32, names: &[&str]) -> anyhow::Result<u32> {
+    let value = match names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(value))
+    {
+        Some(i) => i as u32 + min,
+        None => u32::from_str(value).map_err(|_| anyhow::anyhow!("'{}' isn't valid", value))?,
+    };
+    if value < min || value > max {
+        return Err(anyhow::anyhow!(
+            "{} isn't between {} and {}",
+            value,
+            min,
+            max
+        ));
+    }
+    Ok(value)
+}
+
+/// Parses a cron field into a bitmask of the values it matches.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> anyhow::Result<u64> {
+    let mut mask = 0u64;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, Some(u32::from_str(step)?)),
+            None => (item, None),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (
+                parse_value(start, min, max, names)?,
+                parse_value(end, min, max, names)?,
+            ),
+            // `5/15` means every 15 starting from 5
+            None if step.is_some() => (parse_value(range, min, max, names)?, max),
+            None => {
+                let value = parse_value(range, min, max, names)?;
+                (value, value)
+            }
+        };
+        if start > end {
+            return Err(anyhow::anyhow!("the range {} is backwards", range));
+        }
+        let step = match step {
+            Some(0) => return Err(anyhow::anyhow!("steps can't be 0")),
+            Some(step) => step as usize,
+            None => 1,
+        };
+        for value in (start..=end).step_by(step) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn bits(mask: u64) -> impl Iterator<Item = u32> {
+    (0..64).filter(move |i| mask & (1 << i) != 0)
+}
+
+impl Calendar {
+    pub fn new(cron: &str, time_zone: &str, holidays: Vec<NaiveDate>) -> anyhow::Result<Self> {
+        let time_zone = Tz::from_str(time_zone)
+            .map_err(|e| anyhow::anyhow!("'{}' isn't a time zone: {}", time_zone, e))?;
+        let fields = cron.split_whitespace().collect::<Vec<_>>();
+        let (minutes, hours, days, months, weekdays) = match fields[..] {
+            [minutes, hours, days, months, weekdays] => (minutes, hours, days, months, weekdays),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "'{}' should have five fields (minute hour day-of-month month day-of-week)",
+                    cron
+                ))
+            }
+        };
+        let field = |name: &str, field: &str, min: u32, max: u32, names: &[&str]| {
+            parse_field(field, min, max, names)
+                .map_err(|e| anyhow::anyhow!("invalid {} field in '{}': {}", name, cron, e))
+        };
+        let (days, last_day) = match days.strip_suffix(",L").or_else(|| days.strip_prefix("L,")) {
+            _ if days == "L" => (0, true),
+            Some(days) => (field("day-of-month", days, 1, 31, &[])?, true),
+            None => (field("day-of-month", days, 1, 31, &[])?, false),
+        };
+        let mut weekdays = field("day-of-week", weekdays, 0, 7, &WEEKDAYS)?;
+        // 7 is Sunday too
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        let calendar = Calendar {
+            cron: cron.to_string(),
+            time_zone,
+            holidays: holidays.into_iter().collect(),
+            minutes: field("minute", minutes, 0, 59, &[])?,
+            hours: field("hour", hours, 0, 23, &[])?,
+            days,
+            last_day,
+            months: field("month", months, 1, 12, &MONTHS)?,
+            weekdays,
+            days_restricted: !fields[2].starts_with('*'),
+            weekdays_restricted: !fields[4].starts_with('*'),
+        };
+
+        Ok(calendar)
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 || self.holidays.contains(&date) {
+            return false;
+        }
+        let is_last_day = date
+            .succ_opt()
+            .is_none_or(|next| next.month() != date.month());
+        let day = self.days & (1 << date.day()) != 0 || (self.last_day && is_last_day);
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+
+    fn next(&self, time: NaiveDateTime, inclusive: bool) -> Option<NaiveDateTime> {
+        let mut date = self.time_zone.from_utc_datetime(&time).naive_local().date();
+        for _ in 0..MAX_DAYS {
+            if self.matches_date(date) {
+                for hour in bits(self.hours) {
+                    for minute in bits(self.minutes) {
+                        let scheduled = match self
+                            .time_zone
+                            .from_local_datetime(&date.and_hms_opt(hour, minute, 0)?)
+                            .earliest()
+                        {
+                            Some(scheduled) => scheduled.naive_utc(),
+                            None => continue,
+                        };
+                        if scheduled > time || (inclusive && scheduled == time) {
+                            return Some(scheduled);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    /// The first scheduled time (in UTC) after `time`.
+    pub fn next_after(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.next(time, false)
+    }
+
+    /// The first scheduled time (in UTC) at or after `time`.
+    pub fn first_from(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.next(time, true)
+    }
+
+    pub fn range_kind(&self) -> RangeKind {
+        RangeKind::Calendar {
+            cron: self.cron.clone(),
+            time_zone: self.time_zone.name().to_string(),
+            holidays: self.holidays.iter().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dt(dt: &str) -> NaiveDateTime {
+        NaiveDateTime::from_str(dt).unwrap()
+    }
+
+    fn times(calendar: &Calendar, from: &str, n: usize) -> Vec<NaiveDateTime> {
+        let mut times = vec![calendar.first_from(dt(from)).unwrap()];
+        while times.len() < n {
+            let next = calendar.next_after(*times.last().unwrap()).unwrap();
+            times.push(next);
+        }
+        times
+    }
+
+    #[test]
+    fn trading_day_close_in_new_york() {
+        let holidays = vec![NaiveDate::from_ymd_opt(2021, 12, 31).unwrap()];
+        let calendar = Calendar::new("0 16 * * MON-FRI", "America/New_York", holidays).unwrap();
+        assert_eq!(
+            times(&calendar, "2021-12-30T12:00:00", 3),
+            vec![
+                dt("2021-12-30T21:00:00"),
+                // the 31st is a holiday and then it's the weekend
+                dt("2022-01-03T21:00:00"),
+                dt("2022-01-04T21:00:00"),
+            ]
+        );
+        assert_eq!(
+            times(&calendar, "2022-03-11T21:00:00", 2),
+            vec![
+                dt("2022-03-11T21:00:00"),
+                // the clocks went forward over the weekend
+                dt("2022-03-14T20:00:00"),
+            ]
+        );
+        assert_eq!(
+            calendar.range_kind(),
+            RangeKind::Calendar {
+                cron: "0 16 * * MON-FRI".into(),
+                time_zone: "America/New_York".into(),
+                holidays: vec![NaiveDate::from_ymd_opt(2021, 12, 31).unwrap()],
+            }
+        );
+    }
+
+    #[test]
+    fn weekly_and_month_end() {
+        let fridays = Calendar::new("0 8 * * FRI", "UTC", vec![]).unwrap();
+        assert_eq!(
+            times(&fridays, "2021-10-01T08:00:01", 2),
+            vec![dt("2021-10-08T08:00:00"), dt("2021-10-15T08:00:00")]
+        );
+
+        let month_end = Calendar::new("0 0 L * *", "UTC", vec![]).unwrap();
+        assert_eq!(
+            times(&month_end, "2024-01-15T00:00:00", 3),
+            vec![
+                dt("2024-01-31T00:00:00"),
+                dt("2024-02-29T00:00:00"),
+                dt("2024-03-31T00:00:00"),
+            ]
+        );
+
+        // the 15th or any Monday
+        let either = Calendar::new("30 9 15 * 1", "UTC", vec![]).unwrap();
+        assert_eq!(
+            times(&either, "2021-11-12T00:00:00", 3),
+            vec![
+                dt("2021-11-15T09:30:00"),
+                dt("2021-11-22T09:30:00"),
+                dt("2021-11-29T09:30:00"),
+            ]
+        );
+
+        let quarter_hours = Calendar::new("*/15 23 * * 7", "UTC", vec![]).unwrap();
+        assert_eq!(
+            times(&quarter_hours, "2021-10-03T23:40:00", 3),
+            vec![
+                dt("2021-10-03T23:45:00"),
+                dt("2021-10-10T23:00:00"),
+                dt("2021-10-10T23:15:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn daylight_saving_changes() {
+        let calendar = Calendar::new("30 1,2 * * *", "America/New_York", vec![]).unwrap();
+        // 01:30 happens twice when the clocks go back
+        assert_eq!(
+            times(&calendar, "2021-11-07T00:00:00", 3),
+            vec![
+                dt("2021-11-07T05:30:00"),
+                dt("2021-11-07T07:30:00"),
+                dt("2021-11-08T06:30:00"),
+            ]
+        );
+        // 02:30 doesn't happen when they go forward
+        assert_eq!(
+            times(&calendar, "2022-03-13T00:00:00", 2),
+            vec![dt("2022-03-13T06:30:00"), dt("2022-03-14T05:30:00")]
+        );
+    }
+
+    #[test]
+    fn invalid_calendars() {
+        assert!(Calendar::new("0 16 * *", "UTC", vec![]).is_err());
+        assert!(Calendar::new("60 16 * * *", "UTC", vec![]).is_err());
+        assert!(Calendar::new("0 16 * * FRI-MON", "UTC", vec![]).is_err());
+        assert!(Calendar::new("*/0 16 * * *", "UTC", vec![]).is_err());
+        assert!(Calendar::new("0 16 * * *", "Mars/Olympus_Mons", vec![]).is_err());
+        let never = Calendar::new("0 0 30 2 *", "UTC", vec![]).unwrap();
+        assert_eq!(never.next_after(dt("2021-01-01T00:00:00")), None);
+    }
+}
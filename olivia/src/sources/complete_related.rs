@@ -1,4 +1,5 @@
-use olivia_core::{EventKind, Outcome};
+use core::cmp::Ordering;
+use olivia_core::{EventId, EventKind, Outcome, ScoreKind, VsMatchKind, Win, WinOrDraw};
 
 use crate::db::{DbReadEvent, EventQuery, PrefixedDb};
 
@@ -12,7 +13,6 @@ impl CompleteRelated {
             // things with less than 3 outcomes won't be depended on by anything.
             return Ok(vec![]);
         }
-        let outcome_event_kind = outcome.id.event_kind();
 
         let related_events = self
             .db
@@ -25,28 +25,79 @@ impl CompleteRelated {
 
         Ok(related_events
             .into_iter()
-            .filter_map(|related| match related.id.event_kind() {
-                // If we have the outcome for the event we also have it for the predicated event.
-                EventKind::Predicate { inner, predicate }
-                    if inner.eq_fuzzy(&outcome_event_kind) =>
-                {
-                    let outcome_value = predicate.predicate_outcome(&outcome.outcome_string());
-                    Some(Outcome {
-                        id: related.id,
-                        value: outcome_value,
-                    })
-                }
-                // If we have a price outcome we don't care about nonces
-                EventKind::Price { n_digits: _ }
-                    if matches!(outcome_event_kind, EventKind::Price { n_digits: _ }) =>
-                {
-                    Some(Outcome {
-                        id: related.id,
-                        value: outcome.value,
-                    })
-                }
-                _ => None,
-            })
+            .filter_map(|related| related_outcome(outcome, related.id))
             .collect())
     }
 }
+
+/// The outcome of the sibling event `related` implied by `outcome` (if it implies one).
+fn related_outcome(outcome: &Outcome, related: EventId) -> Option<Outcome> {
+    let outcome_event_kind = outcome.id.event_kind();
+    let value = match related.event_kind() {
+        // If we have the outcome for the event we also have it for the predicated event.
+        EventKind::Predicate { inner, predicate } if inner.eq_fuzzy(&outcome_event_kind) => {
+            predicate.predicate_outcome(outcome)
+        }
+        // The predicate might be on something we can work out from the outcome e.g. the total of
+        // a score.
+        EventKind::Predicate { inner, predicate } => {
+            let inner_outcome = related_outcome(outcome, related.replace_kind(*inner))?;
+            predicate.predicate_outcome(&inner_outcome)
+        }
+        // If we have a price outcome we don't care about nonces
+        price @ EventKind::Price { .. } if price.eq_fuzzy(&outcome_event_kind) => outcome.value,
+        kind => {
+            let (left, right) = outcome.score()?;
+            match kind {
+                EventKind::VsMatch(VsMatchKind::WinOrDraw) => match left.cmp(&right) {
+                    Ordering::Greater => WinOrDraw::Left as u64,
+                    Ordering::Less => WinOrDraw::Right as u64,
+                    Ordering::Equal => WinOrDraw::Draw as u64,
+                },
+                EventKind::VsMatch(VsMatchKind::Win) => match left.cmp(&right) {
+                    Ordering::Greater => Win::Left as u64,
+                    Ordering::Less => Win::Right as u64,
+                    // there's no winner so the event can't be completed
+                    Ordering::Equal => return None,
+                },
+                EventKind::Score(ScoreKind::Total) => left + right,
+                EventKind::Score(ScoreKind::Margin) => (left as i64 - right as i64) as u64,
+                _ => return None,
+            }
+        }
+    };
+
+    Some(Outcome { id: related, value })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::str::FromStr;
+
+    fn related(outcome: &Outcome, kind: &str) -> Option<String> {
+        let related = outcome.id.replace_kind(EventKind::from_str(kind).unwrap());
+        related_outcome(outcome, related).map(|outcome| outcome.outcome_string())
+    }
+
+    #[test]
+    fn related_to_score() {
+        let id = EventId::from_str("/s/EPL/match/2021-08-13/BRE_ARS.score").unwrap();
+        let home_win = Outcome::try_from_id_and_outcome(id.clone(), "3-1").unwrap();
+        let draw = Outcome::try_from_id_and_outcome(id, "1-1").unwrap();
+
+        assert_eq!(related(&home_win, "vs").as_deref(), Some("BRE_win"));
+        assert_eq!(related(&draw, "vs").as_deref(), Some("draw"));
+        assert_eq!(related(&home_win, "winner").as_deref(), Some("BRE"));
+        assert_eq!(related(&draw, "winner"), None);
+        assert_eq!(related(&home_win, "vs=ARS_win").as_deref(), Some("false"));
+        assert_eq!(related(&home_win, "total").as_deref(), Some("4"));
+        assert_eq!(related(&home_win, "margin").as_deref(), Some("2"));
+        assert_eq!(related(&home_win, "total_3").as_deref(), Some("true"));
+        assert_eq!(related(&draw, "total_3").as_deref(), Some("false"));
+        assert_eq!(related(&home_win, "margin_ge_2").as_deref(), Some("true"));
+        assert_eq!(related(&draw, "margin_lt_-1").as_deref(), Some("false"));
+        assert_eq!(related(&home_win, "occur"), None);
+        assert_eq!(related(&home_win, "score=3-1").as_deref(), Some("true"));
+    }
+}
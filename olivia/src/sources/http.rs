@@ -0,0 +1,100 @@
+//! Events and outcomes posted to the REST API.
+//!
+//! Unlike the other sources each item comes with a channel the oracle loop reports the result of
+//! processing it on so the HTTP response can say what happened.
+use crate::oracle::{Attested, EventResult, OutcomeResult};
+use olivia_core::{Event, EventId, Path, StampedOutcome};
+use tokio::sync::{mpsc, oneshot};
+use warp::http::StatusCode;
+
+pub enum Write {
+    Event {
+        event: Event,
+        result: oneshot::Sender<WriteResult>,
+    },
+    Outcome {
+        outcome: StampedOutcome,
+        result: oneshot::Sender<WriteResult>,
+    },
+}
+
+pub type WriteSender = mpsc::UnboundedSender<Write>;
+pub type WriteReceiver = mpsc::UnboundedReceiver<Write>;
+
+pub fn channel() -> (WriteSender, WriteReceiver) {
+    mpsc::unbounded_channel()
+}
+
+/// What happened to a posted event or outcome.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteResult {
+    pub id: EventId,
+    /// The HTTP status code the result would have on its own.
+    pub status: u16,
+    pub result: String,
+}
+
+impl WriteResult {
+    pub fn from_event_result(id: EventId, res: &Result<(), EventResult>) -> Self {
+        use EventResult::*;
+        let status = match res {
+            Ok(()) => StatusCode::CREATED,
+            Err(AlreadyExists) | Err(Changed) => StatusCode::OK,
+            Err(AlreadyCompleted) | Err(RetiredKeys { .. }) => StatusCode::CONFLICT,
+            Err(ReservedPath { .. }) => StatusCode::BAD_REQUEST,
+            Err(DbReadErr(_)) | Err(DbWriteErr(_)) | Err(SignerErr(_)) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        let result = match res {
+            Ok(()) => "event created".into(),
+            Err(e) => e.to_string(),
+        };
+        Self {
+            id,
+            status: status.as_u16(),
+            result,
+        }
+    }
+
+    /// The outcome of an event under a path with an outcome quorum which can only be attested to
+    /// once the path's outcome sources agree.
+    pub fn needs_quorum(id: EventId, path: &Path) -> Self {
+        Self {
+            id,
+            status: StatusCode::CONFLICT.as_u16(),
+            result: format!(
+                "outcomes under {} are only attested to once its outcome sources agree",
+                path
+            ),
+        }
+    }
+
+    pub fn from_outcome_result(id: EventId, res: &Result<Attested, OutcomeResult>) -> Self {
+        use OutcomeResult::*;
+        let status = match res {
+            Ok(_) | Err(AlreadyCompleted) => StatusCode::OK,
+            Err(OutcomeChanged { .. }) | Err(RetiredKeys { .. }) => StatusCode::CONFLICT,
+            Err(EventNotExist) => StatusCode::NOT_FOUND,
+            // 425 Too Early
+            Err(TooEarly { .. }) => StatusCode::from_u16(425).unwrap(),
+            Err(DbReadErr(_))
+            | Err(DbWriteErr(_))
+            | Err(AnnouncementWasBogus)
+            | Err(SignerErr(_)) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let result = match res {
+            Ok(Attested::OnTime) => "event attested to".into(),
+            Ok(Attested::Late { by }) => format!(
+                "event attested to {} seconds after its expected outcome time which is later than the attestation policy allows",
+                by.num_seconds()
+            ),
+            Err(e) => e.to_string(),
+        };
+        Self {
+            id,
+            status: status.as_u16(),
+            result,
+        }
+    }
+}
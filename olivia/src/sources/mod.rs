@@ -1,8 +1,12 @@
+use crate::oracle::{Attested, EventResult, OutcomeResult};
 use olivia_core::{Event, EventId, PathRef, PrefixPath};
 use tokio::sync::oneshot::Sender;
 use tokio_stream as stream;
+pub mod calendar;
 pub mod complete_related;
+pub mod http;
 pub mod predicate;
+pub mod price_feed;
 pub mod redis;
 pub mod ticker;
 #[cfg(test)]
@@ -10,7 +14,44 @@ mod time_tests;
 
 pub struct Update<E> {
     pub update: E, // An Event or EventOutcome
-    pub processed_notifier: Option<Sender<bool>>,
+    pub processed_notifier: Option<Sender<Processed>>,
+}
+
+/// What the oracle made of an [`Update`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Processed {
+    /// It was applied or it had been already.
+    Done,
+    /// It couldn't be applied for a reason that might go away (e.g. the database was down or the
+    /// outcome came too early) so it should be sent again later.
+    Retry,
+    /// It was rejected and sending it again won't help.
+    Rejected,
+}
+
+impl Processed {
+    pub fn from_event_result(res: &Result<(), EventResult>) -> Self {
+        use EventResult::*;
+        match res {
+            Ok(()) | Err(AlreadyExists) | Err(AlreadyCompleted) | Err(Changed) => Processed::Done,
+            Err(RetiredKeys { .. }) | Err(ReservedPath { .. }) => Processed::Rejected,
+            Err(DbReadErr(_)) | Err(DbWriteErr(_)) | Err(SignerErr(_)) => Processed::Retry,
+        }
+    }
+
+    pub fn from_outcome_result(res: &Result<Attested, OutcomeResult>) -> Self {
+        use OutcomeResult::*;
+        match res {
+            Ok(_) | Err(AlreadyCompleted) => Processed::Done,
+            Err(OutcomeChanged { .. })
+            | Err(EventNotExist)
+            | Err(AnnouncementWasBogus)
+            | Err(RetiredKeys { .. }) => Processed::Rejected,
+            Err(DbReadErr(_)) | Err(DbWriteErr(_)) | Err(TooEarly { .. }) | Err(SignerErr(_)) => {
+                Processed::Retry
+            }
+        }
+    }
 }
 
 impl<E> From<E> for Update<E> {
@@ -1,4 +1,4 @@
-use olivia_core::{EventId, PredicateKind};
+use olivia_core::EventId;
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(untagged)]
@@ -22,6 +22,15 @@ pub enum Pattern {
     All,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PredicateKind {
+    /// Apply the predicate to each of the outcomes
+    Single(olivia_core::PredicateKind),
+    /// Create a range predicate between each consecutive pair of outcomes. The upper end of each
+    /// range is one less than the next outcome so the ranges don't overlap.
+    Ranges,
+}
+
 #[derive(Clone, Debug)]
 pub struct Predicate {
     pub outcome_filter: OutcomeFilter,
@@ -30,10 +39,54 @@ pub struct Predicate {
 
 impl Predicate {
     pub fn apply_to_event_id(&self, id: &EventId) -> Vec<EventId> {
-        self.outcome_filter
-            .outcomes_for(id)
-            .into_iter()
-            .map(move |value| id.predicate(self.predicate_kind, value))
-            .collect()
+        let outcomes = self.outcome_filter.outcomes_for(id);
+        match self.predicate_kind {
+            PredicateKind::Single(predicate_kind) => outcomes
+                .into_iter()
+                .map(move |value| id.predicate(predicate_kind, value))
+                .collect(),
+            PredicateKind::Ranges => outcomes
+                .windows(2)
+                .filter(|pair| pair[0] < pair[1])
+                .map(|pair| id.range_predicate(pair[0], pair[1] - 1))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::str::FromStr;
+    use olivia_core::BoundKind;
+
+    #[test]
+    fn apply_predicates() {
+        let id = EventId::from_str("/x/BitMEX/BXBT/2021-10-05T05:00:00.price?n=20").unwrap();
+        let lt = Predicate {
+            outcome_filter: OutcomeFilter::Indexes(vec![30000, 50000]),
+            predicate_kind: PredicateKind::Single(olivia_core::PredicateKind::Bound(BoundKind::Lt)),
+        };
+        assert_eq!(
+            lt.apply_to_event_id(&id),
+            vec![
+                EventId::from_str("/x/BitMEX/BXBT/2021-10-05T05:00:00.price_lt_30000").unwrap(),
+                EventId::from_str("/x/BitMEX/BXBT/2021-10-05T05:00:00.price_lt_50000").unwrap(),
+            ]
+        );
+
+        let ranges = Predicate {
+            outcome_filter: OutcomeFilter::Indexes(vec![30000, 40000, 50000]),
+            predicate_kind: PredicateKind::Ranges,
+        };
+        assert_eq!(
+            ranges.apply_to_event_id(&id),
+            vec![
+                EventId::from_str("/x/BitMEX/BXBT/2021-10-05T05:00:00.price_in_30000..39999")
+                    .unwrap(),
+                EventId::from_str("/x/BitMEX/BXBT/2021-10-05T05:00:00.price_in_40000..49999")
+                    .unwrap(),
+            ]
+        );
     }
 }
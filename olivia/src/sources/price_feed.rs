@@ -0,0 +1,489 @@
+//! Completing price events with the median of the prices quoted by some HTTP JSON APIs.
+use crate::sources::ticker::OutcomeCreator;
+use async_trait::async_trait;
+use core::{convert::TryFrom, str::FromStr};
+use olivia_core::{
+    chrono::{self, NaiveDateTime},
+    EventId, EventKind,
+};
+use serde_json::Value;
+use std::time::Duration;
+
+/// A location in a JSON document like `$.data[0].price`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct JsonPath(Vec<JsonPathSegment>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum JsonPathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl JsonPath {
+    pub fn get<'a>(&self, mut value: &'a Value) -> Option<&'a Value> {
+        for segment in &self.0 {
+            value = match segment {
+                JsonPathSegment::Key(key) => value.get(key)?,
+                JsonPathSegment::Index(index) => value.get(index)?,
+            };
+        }
+        Some(value)
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' is not a valid JSON path", path);
+        let mut rest = path.strip_prefix('$').unwrap_or(path);
+        let mut segments = vec![];
+        while !rest.is_empty() {
+            if let Some(tail) = rest.strip_prefix('[') {
+                let (index, tail) = tail.split_once(']').ok_or_else(invalid)?;
+                let segment = match index
+                    .strip_prefix('"')
+                    .and_then(|key| key.strip_suffix('"'))
+                {
+                    Some(key) => JsonPathSegment::Key(key.into()),
+                    None => JsonPathSegment::Index(index.parse().map_err(|_| invalid())?),
+                };
+                segments.push(segment);
+                rest = tail;
+            } else {
+                let tail = match rest.strip_prefix('.') {
+                    Some(tail) => tail,
+                    // the first key doesn't need a '.' before it
+                    None if segments.is_empty() && rest.len() == path.len() => rest,
+                    None => return Err(invalid()),
+                };
+                let end = tail.find(['.', '[']).unwrap_or(tail.len());
+                if end == 0 {
+                    return Err(invalid());
+                }
+                segments.push(JsonPathSegment::Key(tail[..end].into()));
+                rest = &tail[end..];
+            }
+        }
+        Ok(JsonPath(segments))
+    }
+}
+
+impl TryFrom<String> for JsonPath {
+    type Error = String;
+
+    fn try_from(path: String) -> Result<Self, Self::Error> {
+        JsonPath::from_str(&path)
+    }
+}
+
+impl From<JsonPath> for String {
+    fn from(path: JsonPath) -> Self {
+        let mut string = String::from("$");
+        for segment in path.0 {
+            match segment {
+                JsonPathSegment::Key(key) if key.contains(['.', '[']) => {
+                    string.push_str(&format!("[\"{}\"]", key))
+                }
+                JsonPathSegment::Key(key) => {
+                    string.push('.');
+                    string.push_str(&key);
+                }
+                JsonPathSegment::Index(index) => string.push_str(&format!("[{}]", index)),
+            }
+        }
+        string
+    }
+}
+
+/// An HTTP API that quotes a price.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PriceEndpoint {
+    /// An `https://` or `http://` URL to GET.
+    pub url: String,
+    /// Where the price is in the response. It can be a number or a string.
+    pub path: JsonPath,
+    /// What to multiply the price by to get it in the event's unit.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Where the time of the quote is in the response. It can be a unix timestamp in seconds or
+    /// milliseconds or an RFC 3339 time.
+    pub timestamp_path: Option<JsonPath>,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+pub struct PriceFeed {
+    pub endpoints: Vec<PriceEndpoint>,
+    /// How many quotes there have to be (after removing outliers) to attest to their median.
+    pub quorum: usize,
+    /// Quotes further than this fraction away from the median of all the quotes are ignored.
+    pub max_deviation: Option<f64>,
+    /// Quotes further than this from the event's expected outcome time are ignored. Quotes from
+    /// endpoints without a `timestamp_path` are as old as when they were fetched.
+    pub max_age: Option<chrono::Duration>,
+    pub timeout: Duration,
+    pub logger: slog::Logger,
+    client: hyper::Client<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum QuoteError {
+    #[error("request failed: {0}")]
+    Request(String),
+    #[error("request timed out")]
+    Timeout,
+    #[error("response was {0}")]
+    Status(u16),
+    #[error("response wasn't JSON: {0}")]
+    NotJson(String),
+    #[error("no price in the response")]
+    NoPrice,
+    #[error("no time in the response")]
+    NoTimestamp,
+    #[error("quote is {} seconds from the event's time", .0.num_seconds())]
+    Stale(chrono::Duration),
+}
+
+impl PriceFeed {
+    pub fn new(endpoints: Vec<PriceEndpoint>, quorum: usize, logger: slog::Logger) -> Self {
+        Self {
+            endpoints,
+            quorum,
+            max_deviation: None,
+            max_age: None,
+            timeout: Duration::from_secs(10),
+            logger,
+            client: hyper::Client::builder().build(
+                hyper_rustls::HttpsConnectorBuilder::new()
+                    .with_webpki_roots()
+                    .https_or_http()
+                    .enable_http1()
+                    .build(),
+            ),
+        }
+    }
+
+    async fn fetch(
+        &self,
+        endpoint: &PriceEndpoint,
+        event_time: NaiveDateTime,
+    ) -> Result<f64, QuoteError> {
+        let uri =
+            hyper::Uri::from_str(&endpoint.url).map_err(|e| QuoteError::Request(e.to_string()))?;
+        let response = tokio::time::timeout(self.timeout, async {
+            let response = self.client.get(uri).await?;
+            let status = response.status();
+            let body = hyper::body::to_bytes(response.into_body()).await?;
+            Ok::<_, hyper::Error>((status, body))
+        })
+        .await
+        .map_err(|_| QuoteError::Timeout)?
+        .map_err(|e| QuoteError::Request(e.to_string()))?;
+        let fetched_at = chrono::Utc::now().naive_utc();
+        let (status, body) = response;
+        if !status.is_success() {
+            return Err(QuoteError::Status(status.as_u16()));
+        }
+        let json = serde_json::from_slice::<Value>(&body)
+            .map_err(|e| QuoteError::NotJson(e.to_string()))?;
+        quote_from_json(endpoint, &json, self.max_age, event_time, fetched_at)
+    }
+}
+
+/// The expected outcome time of an event made by a ticker which is the last segment of its path.
+pub fn event_time(id: &EventId) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(id.path().last(), "%FT%T").ok()
+}
+
+fn json_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn json_time(value: &Value) -> Option<NaiveDateTime> {
+    if let Value::String(string) = value {
+        if let Ok(time) = chrono::DateTime::parse_from_rfc3339(string) {
+            return Some(time.naive_utc());
+        }
+    }
+    let timestamp = json_number(value)?;
+    // anything this big must be in milliseconds
+    let millis = if timestamp > 1e11 {
+        timestamp
+    } else {
+        timestamp * 1000.0
+    };
+    NaiveDateTime::from_timestamp_opt(
+        (millis / 1000.0).floor() as i64,
+        ((millis % 1000.0) * 1_000_000.0) as u32,
+    )
+}
+
+fn quote_from_json(
+    endpoint: &PriceEndpoint,
+    json: &Value,
+    max_age: Option<chrono::Duration>,
+    event_time: NaiveDateTime,
+    fetched_at: NaiveDateTime,
+) -> Result<f64, QuoteError> {
+    let price = endpoint
+        .path
+        .get(json)
+        .and_then(json_number)
+        .ok_or(QuoteError::NoPrice)?;
+    if let Some(max_age) = max_age {
+        let time = match &endpoint.timestamp_path {
+            Some(timestamp_path) => timestamp_path
+                .get(json)
+                .and_then(json_time)
+                .ok_or(QuoteError::NoTimestamp)?,
+            None => fetched_at,
+        };
+        let distance = match time > event_time {
+            true => time - event_time,
+            false => event_time - time,
+        };
+        if distance > max_age {
+            return Err(QuoteError::Stale(distance));
+        }
+    }
+    Ok(price * endpoint.scale)
+}
+
+fn median(sorted: &[f64]) -> f64 {
+    let middle = sorted.len() / 2;
+    match sorted.len() % 2 {
+        0 => (sorted[middle - 1] + sorted[middle]) / 2.0,
+        _ => sorted[middle],
+    }
+}
+
+/// The median of the `quotes` that are within `max_deviation` of the median of all of them as long
+/// as there are at least `quorum` of them.
+pub fn aggregate(
+    mut quotes: Vec<f64>,
+    max_deviation: Option<f64>,
+    quorum: usize,
+) -> Result<f64, String> {
+    quotes.retain(|quote| quote.is_finite());
+    if quotes.is_empty() || quotes.len() < quorum {
+        return Err(format!(
+            "only {} of the {} quotes needed",
+            quotes.len(),
+            quorum
+        ));
+    }
+    quotes.sort_by(|a, b| a.partial_cmp(b).expect("they're finite"));
+    if let Some(max_deviation) = max_deviation {
+        let overall = median(&quotes);
+        quotes.retain(|quote| (quote - overall).abs() <= (overall * max_deviation).abs());
+        if quotes.is_empty() || quotes.len() < quorum {
+            return Err(format!(
+                "only {} of the {} quotes needed agree with each other",
+                quotes.len(),
+                quorum
+            ));
+        }
+    }
+    Ok(median(&quotes))
+}
+
+/// The outcome value of a price event for `price` which is rounded to the event's precision.
+pub fn price_to_value(kind: &EventKind, price: f64) -> Option<u64> {
+    let (is_signed, exp) = match kind {
+        EventKind::Price { is_signed, exp, .. } => (*is_signed, *exp),
+        _ => return None,
+    };
+    let units = (price / 10f64.powi(exp)).round();
+    if !units.is_finite() || (!is_signed && units < 0.0) {
+        return None;
+    }
+    Some(match is_signed {
+        true => units as i64 as u64,
+        false => units as u64,
+    })
+}
+
+#[async_trait]
+impl OutcomeCreator for PriceFeed {
+    async fn create_outcome(&self, id: &EventId) -> Option<u64> {
+        let event_time = match event_time(id) {
+            Some(event_time) => event_time,
+            None => {
+                error!(self.logger, "price event's path doesn't end with its time"; "id" => id.as_str());
+                return None;
+            }
+        };
+        let fetches = self
+            .endpoints
+            .iter()
+            .map(|endpoint| self.fetch(endpoint, event_time));
+        let mut quotes = vec![];
+        for (endpoint, quote) in self
+            .endpoints
+            .iter()
+            .zip(futures::future::join_all(fetches).await)
+        {
+            match quote {
+                Ok(quote) => quotes.push(quote),
+                Err(e) => {
+                    warn!(self.logger, "ignoring price quote"; "id" => id.as_str(), "url" => &endpoint.url, "error" => e.to_string())
+                }
+            }
+        }
+        let price = match aggregate(quotes, self.max_deviation, self.quorum) {
+            Ok(price) => price,
+            Err(e) => {
+                error!(self.logger, "no price quorum"; "id" => id.as_str(), "error" => e);
+                return None;
+            }
+        };
+        let value = price_to_value(&id.event_kind(), price);
+        if value.is_none() {
+            error!(self.logger, "price can't be the outcome of the event"; "id" => id.as_str(), "price" => price);
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use warp::Filter;
+
+    #[test]
+    fn json_paths() {
+        let json = serde_json::json!({
+            "data": [{ "price": "43210.5", "time": 1633305600 }],
+            "weird.key": { "last": 42 }
+        });
+        let get = |path: &str| JsonPath::from_str(path).unwrap().get(&json).cloned();
+        assert_eq!(get("$.data[0].price"), Some("43210.5".into()));
+        assert_eq!(get("data[0].time"), Some(1633305600.into()));
+        assert_eq!(get(r#"$["weird.key"].last"#), Some(42.into()));
+        assert_eq!(get("$.data[1].price"), None);
+        assert_eq!(get("$"), Some(json.clone()));
+        assert!(JsonPath::from_str("$.data[x]").is_err());
+        assert!(JsonPath::from_str("$..price").is_err());
+        for (path, normalized) in [
+            ("data[0].price", "$.data[0].price"),
+            (r#"$["weird.key"].last"#, r#"$["weird.key"].last"#),
+        ] {
+            assert_eq!(String::from(JsonPath::from_str(path).unwrap()), normalized);
+        }
+    }
+
+    #[test]
+    fn aggregate_rejects_outliers() {
+        assert_eq!(aggregate(vec![100.0, 102.0, 101.0], None, 2), Ok(101.0));
+        assert_eq!(aggregate(vec![100.0, 102.0], None, 2), Ok(101.0));
+        assert_eq!(
+            aggregate(vec![100.0, 101.0, 150.0, 100.5], Some(0.01), 3),
+            Ok(100.5)
+        );
+        assert!(aggregate(vec![100.0, 101.0, 150.0], Some(0.01), 3).is_err());
+        assert!(aggregate(vec![100.0], None, 2).is_err());
+        assert!(aggregate(vec![], None, 0).is_err());
+    }
+
+    #[test]
+    fn quotes_without_a_timestamp_are_as_old_as_their_fetch() {
+        let endpoint = PriceEndpoint {
+            url: "http://localhost/quote".into(),
+            path: JsonPath::from_str("$.price").unwrap(),
+            scale: 1.0,
+            timestamp_path: None,
+        };
+        let json = serde_json::json!({ "price": 42 });
+        let event_time = NaiveDateTime::from_str("2021-10-04T00:00:00").unwrap();
+        let max_age = Some(chrono::Duration::seconds(60));
+        let fetched_after = |seconds| event_time + chrono::Duration::seconds(seconds);
+        assert_eq!(
+            quote_from_json(&endpoint, &json, max_age, event_time, fetched_after(30)),
+            Ok(42.0)
+        );
+        assert!(matches!(
+            quote_from_json(&endpoint, &json, max_age, event_time, fetched_after(3600)),
+            Err(QuoteError::Stale(_))
+        ));
+        assert_eq!(
+            quote_from_json(&endpoint, &json, None, event_time, fetched_after(3600)),
+            Ok(42.0)
+        );
+    }
+
+    #[test]
+    fn prices_are_rounded_to_the_events_precision() {
+        let kind = |kind: &str| EventKind::from_str(kind).unwrap();
+        assert_eq!(price_to_value(&kind("price?n=20"), 43210.5), Some(43211));
+        assert_eq!(price_to_value(&kind("price?n=20"), -1.0), None);
+        assert_eq!(
+            price_to_value(&kind("price?n=20&exp=-2"), 43210.504),
+            Some(4321050)
+        );
+        assert_eq!(
+            price_to_value(&kind("price?n=20&signed"), -3.0),
+            Some(-3i64 as u64)
+        );
+        assert_eq!(price_to_value(&kind("occur"), 1.0), None);
+    }
+
+    #[tokio::test]
+    async fn attests_median_of_fresh_quotes() {
+        let id = EventId::from_str("/x/BitMEX/BXBT/2021-10-04T00:00:00.price?n=20").unwrap();
+        let fresh = event_time(&id).unwrap().timestamp();
+        assert_eq!(
+            event_time(&EventId::from_str("/x/BitMEX/BXBT/latest.price?n=20").unwrap()),
+            None
+        );
+        let quotes = warp::path!("quote" / u32).map(move |n: u32| {
+            warp::reply::json(&match n {
+                0 => serde_json::json!({ "result": { "price": 43200.0, "time": fresh } }),
+                1 => serde_json::json!({ "result": { "price": "43210", "time": fresh * 1000 } }),
+                // stale
+                2 => serde_json::json!({ "result": { "price": 1.0, "time": fresh - 3600 } }),
+                // from long after the event
+                5 => serde_json::json!({ "result": { "price": 2.0, "time": fresh + 3600 } }),
+                // outlier
+                3 => serde_json::json!({ "result": { "price": 50000.0, "time": fresh } }),
+                _ => serde_json::json!({ "result": { "time": fresh } }),
+            })
+        });
+        let (addr, server) = warp::serve(quotes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        let endpoint = |n: u32| PriceEndpoint {
+            url: format!("http://{}/quote/{}", addr, n),
+            path: JsonPath::from_str("$.result.price").unwrap(),
+            scale: 1.0,
+            timestamp_path: Some(JsonPath::from_str("$.result.time").unwrap()),
+        };
+        let logger = slog::Logger::root(slog::Discard, o!());
+
+        let mut feed = PriceFeed::new((0..6).map(endpoint).collect(), 2, logger);
+        feed.max_age = Some(chrono::Duration::seconds(60));
+        feed.max_deviation = Some(0.01);
+        assert_eq!(feed.create_outcome(&id).await, Some(43205));
+
+        feed.quorum = 3;
+        assert_eq!(feed.create_outcome(&id).await, None);
+
+        feed.endpoints.push(PriceEndpoint {
+            url: "http://127.0.0.1:1/nothing-here".into(),
+            ..endpoint(0)
+        });
+        feed.quorum = 2;
+        assert_eq!(
+            feed.create_outcome(&id).await,
+            Some(43205),
+            "unreachable endpoints are ignored"
+        );
+    }
+}
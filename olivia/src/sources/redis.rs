@@ -1,14 +1,18 @@
-use crate::sources::Update;
-use redis::RedisResult;
+use crate::sources::{Processed, Update};
+use redis::{
+    streams::{StreamId, StreamReadOptions, StreamReadReply},
+    Commands, RedisResult,
+};
 use serde::de::DeserializeOwned;
 use serde_json;
 use std::{
     collections::{hash_map::RandomState, HashSet},
     iter::FromIterator,
     thread,
+    time::{Duration, Instant},
 };
 use stream::wrappers::UnboundedReceiverStream;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use tokio_stream as stream;
 
 pub fn event_stream<StrList: IntoIterator<Item = String>, I: DeserializeOwned + Send + 'static>(
@@ -87,3 +91,334 @@ pub fn event_stream<StrList: IntoIterator<Item = String>, I: DeserializeOwned +
 
     Ok(UnboundedReceiverStream::new(receiver))
 }
+
+/// Consumes entries from redis streams as part of a consumer group.
+///
+/// Each entry's `json` field is deserialized and sent on. It's only acknowledged (`XACK`) once the
+/// oracle reports it's been processed. Entries that can't be deserialized or that the oracle
+/// rejects are moved to a dead-letter stream first. Entries that were delivered to `consumer` but
+/// never acknowledged (e.g. because olivia crashed or the oracle couldn't process them right then)
+/// are delivered again every [`RETRY_INTERVAL`].
+pub fn stream_consumer<I: DeserializeOwned + Send + 'static>(
+    client: redis::Client,
+    config: StreamConsumerConfig,
+    logger: slog::Logger,
+) -> Result<impl stream::Stream<Item = Update<I>>, redis::RedisError> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let conn = client.get_connection()?;
+    let consumer = StreamConsumer {
+        client,
+        conn,
+        config,
+        sender,
+        logger,
+    };
+    thread::spawn(move || consumer.run());
+    Ok(UnboundedReceiverStream::new(receiver))
+}
+
+/// How often entries that haven't been acknowledged are delivered again.
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct StreamConsumerConfig {
+    pub streams: Vec<String>,
+    pub group: String,
+    pub consumer: String,
+    pub dead_letter: Option<String>,
+}
+
+struct StreamConsumer<I> {
+    client: redis::Client,
+    conn: redis::Connection,
+    config: StreamConsumerConfig,
+    sender: mpsc::UnboundedSender<Update<I>>,
+    logger: slog::Logger,
+}
+
+enum Stop {
+    Shutdown,
+    Redis(redis::RedisError),
+}
+
+impl From<redis::RedisError> for Stop {
+    fn from(e: redis::RedisError) -> Self {
+        Stop::Redis(e)
+    }
+}
+
+impl<I: DeserializeOwned> StreamConsumer<I> {
+    fn run(mut self) {
+        loop {
+            match self.consume() {
+                Ok(()) | Err(Stop::Shutdown) => {
+                    info!(
+                        self.logger,
+                        "Redis stream loop has shut down because channel has been dropped"
+                    );
+                    break;
+                }
+                Err(Stop::Redis(e)) => {
+                    crit!(self.logger, "Unable to handle Error from Redis. Reconnecting."; "error" => format!("{}", e));
+                    loop {
+                        match self.client.get_connection() {
+                            Ok(new_conn) => {
+                                self.conn = new_conn;
+                                info!(self.logger, "Reconnected to Redis");
+                                break;
+                            }
+                            Err(e) => {
+                                error!(self.logger,"Failed to re-connect to Redis. Trying again in 5 seconds"; "error" => format!("{}", e));
+                                thread::sleep(std::time::Duration::from_millis(5_000));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn consume(&mut self) -> Result<(), Stop> {
+        for stream in &self.config.streams {
+            let created: RedisResult<()> =
+                self.conn
+                    .xgroup_create_mkstream(stream, &self.config.group, "0");
+            match created {
+                Ok(()) => {}
+                Err(e) if e.code() == Some("BUSYGROUP") => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        loop {
+            self.retry_pending()?;
+            let retry_at = Instant::now() + RETRY_INTERVAL;
+            loop {
+                let block = retry_at
+                    .saturating_duration_since(Instant::now())
+                    .as_millis() as usize;
+                // blocking for 0ms would block forever
+                if block == 0 {
+                    break;
+                }
+                let streams = self.config.streams.clone();
+                for (stream, entry) in self.read(&streams, ">", Some(block))? {
+                    self.handle(&stream, entry)?;
+                }
+            }
+        }
+    }
+
+    /// Handles each entry that was delivered to us before but never acknowledged.
+    fn retry_pending(&mut self) -> Result<(), Stop> {
+        for stream in self.config.streams.clone() {
+            let mut after = "0".to_string();
+            loop {
+                let pending = self.read(std::slice::from_ref(&stream), &after, None)?;
+                match pending.last() {
+                    Some((_, entry)) => after = entry.id.clone(),
+                    None => break,
+                }
+                for (stream, entry) in pending {
+                    self.handle(&stream, entry)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read(
+        &mut self,
+        streams: &[String],
+        id: &str,
+        block: Option<usize>,
+    ) -> RedisResult<Vec<(String, StreamId)>> {
+        let mut options = StreamReadOptions::default()
+            .group(&self.config.group, &self.config.consumer)
+            .count(10);
+        if let Some(block) = block {
+            options = options.block(block);
+        }
+        let ids = vec![id; streams.len()];
+        let reply: Option<StreamReadReply> = self.conn.xread_options(streams, &ids, &options)?;
+        Ok(reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| {
+                let stream = key.key;
+                key.ids
+                    .into_iter()
+                    .map(move |entry| (stream.clone(), entry))
+            })
+            .collect())
+    }
+
+    fn handle(&mut self, stream: &str, entry: StreamId) -> Result<(), Stop> {
+        let json: Option<String> = entry.get("json");
+        let item = match json.as_deref().map(serde_json::from_str::<I>) {
+            Some(Ok(item)) => item,
+            Some(Err(e)) => {
+                error!(
+                    self.logger,
+                    "Failed to deserialize entry";
+                    "stream" => stream,
+                    "id" => &entry.id,
+                    "error" => format!("{}",e),
+                    "json" => json.as_deref().unwrap_or_default(),
+                );
+                return self.dead_letter(stream, &entry, json, "undeserialisable");
+            }
+            None => {
+                error!(self.logger, "Entry has no json field"; "stream" => stream, "id" => &entry.id);
+                return self.dead_letter(stream, &entry, json, "undeserialisable");
+            }
+        };
+
+        let (processed_notifier, processed) = oneshot::channel();
+        self.sender
+            .send(Update {
+                update: item,
+                processed_notifier: Some(processed_notifier),
+            })
+            .map_err(|_| Stop::Shutdown)?;
+
+        // The entry stays pending if the oracle goes away before processing it.
+        match processed.blocking_recv().map_err(|_| Stop::Shutdown)? {
+            Processed::Done => self
+                .conn
+                .xack(stream, &self.config.group, &[&entry.id])
+                .map_err(Stop::Redis),
+            Processed::Retry => {
+                warn!(self.logger, "Entry couldn't be processed (will try again)"; "stream" => stream, "id" => &entry.id);
+                Ok(())
+            }
+            Processed::Rejected => self.dead_letter(stream, &entry, json, "rejected"),
+        }
+    }
+
+    fn dead_letter(
+        &mut self,
+        stream: &str,
+        entry: &StreamId,
+        json: Option<String>,
+        reason: &str,
+    ) -> Result<(), Stop> {
+        let dead_letter = self
+            .config
+            .dead_letter
+            .clone()
+            .unwrap_or_else(|| format!("{}:dead-letter", stream));
+        warn!(self.logger, "Moving entry to dead-letter stream"; "stream" => stream, "id" => &entry.id, "dead_letter" => &dead_letter, "reason" => reason);
+        let mut fields = vec![("stream", stream), ("id", &entry.id), ("reason", reason)];
+        if let Some(json) = &json {
+            fields.push(("json", json));
+        }
+        redis::pipe()
+            .atomic()
+            .xadd(&dead_letter, "*", &fields)
+            .ignore()
+            .xack(stream, &self.config.group, &[&entry.id])
+            .ignore()
+            .query(&mut self.conn)
+            .map_err(Stop::Redis)
+    }
+}
+
+#[cfg(all(test, feature = "docker_tests"))]
+mod test {
+    use super::*;
+    use olivia_core::Event;
+    use stream::StreamExt;
+    use testcontainers::{clients, images, Docker};
+
+    fn config() -> StreamConsumerConfig {
+        StreamConsumerConfig {
+            streams: vec!["events".into()],
+            group: "olivia".into(),
+            consumer: "olivia".into(),
+            dead_letter: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_consumer_acks_retries_and_dead_letters() {
+        let docker = clients::Cli::default();
+        let container = docker.run(images::redis::Redis::default());
+        let client = redis::Client::open(format!(
+            "redis://localhost:{}",
+            container.get_host_port(6379).unwrap()
+        ))
+        .unwrap();
+        let mut conn = client.get_connection().unwrap();
+        let logger = slog::Logger::root(slog::Discard, o!());
+
+        let _: String = conn
+            .xadd("events", "*", &[("json", r#"{"id": "/test/one.occur"}"#)])
+            .unwrap();
+        let _: String = conn.xadd("events", "*", &[("json", "garbage")]).unwrap();
+        let _: String = conn
+            .xadd("events", "*", &[("json", r#"{"id": "/test/two.occur"}"#)])
+            .unwrap();
+
+        let mut events =
+            Box::pin(stream_consumer::<Event>(client.clone(), config(), logger.clone()).unwrap());
+        let one = events.next().await.unwrap();
+        assert_eq!(one.update.id.as_str(), "/test/one.occur");
+        one.processed_notifier
+            .unwrap()
+            .send(Processed::Done)
+            .unwrap();
+
+        // the oracle goes away before processing "two"
+        let two = events.next().await.unwrap();
+        assert_eq!(two.update.id.as_str(), "/test/two.occur");
+        drop(two);
+        drop(events);
+
+        let mut events =
+            Box::pin(stream_consumer::<Event>(client.clone(), config(), logger).unwrap());
+        let two = events.next().await.unwrap();
+        assert_eq!(
+            two.update.id.as_str(),
+            "/test/two.occur",
+            "unacknowledged entry is delivered again"
+        );
+        two.processed_notifier
+            .unwrap()
+            .send(Processed::Rejected)
+            .unwrap();
+
+        let _: String = conn
+            .xadd("events", "*", &[("json", r#"{"id": "/test/three.occur"}"#)])
+            .unwrap();
+        let three = events.next().await.unwrap();
+        assert_eq!(three.update.id.as_str(), "/test/three.occur");
+        three
+            .processed_notifier
+            .unwrap()
+            .send(Processed::Retry)
+            .unwrap();
+        let three = events.next().await.unwrap();
+        assert_eq!(
+            three.update.id.as_str(),
+            "/test/three.occur",
+            "entries that should be retried are delivered again"
+        );
+        three
+            .processed_notifier
+            .unwrap()
+            .send(Processed::Done)
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let pending: redis::streams::StreamPendingReply =
+            conn.xpending("events", "olivia").unwrap();
+        assert_eq!(pending.count(), 0);
+        let dead: StreamReadReply = conn.xread(&["events:dead-letter"], &["0"]).unwrap();
+        let reasons = dead.keys[0]
+            .ids
+            .iter()
+            .map(|entry| entry.get::<String>("reason").unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(reasons, vec!["undeserialisable", "rejected"]);
+    }
+}
@@ -1,21 +1,82 @@
 use crate::{
     db::{DbReadEvent, EventQuery, Order, PrefixedDb},
     seed::Seed,
-    sources::Update,
+    sources::{calendar::Calendar, Processed, Update},
 };
+use async_trait::async_trait;
 use olivia_core::{
     chrono,
     chrono::{Duration, NaiveDateTime},
-    Event, EventId, EventKind, Outcome, Path, PrefixPath, StampedOutcome,
+    Event, EventId, EventKind, Outcome, Path, PrefixPath, RangeKind, StampedOutcome,
+};
+use tokio::{
+    sync::{oneshot, watch},
+    time,
 };
-use tokio::{sync::oneshot, time};
 use tokio_stream as stream;
 
+/// When a ticker's events happen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    /// Every interval from the initial time.
+    Interval(Duration),
+    Calendar(Calendar),
+}
+
+impl Schedule {
+    /// The first event time at or after `time`.
+    pub fn first_from(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Schedule::Interval(_) => Some(time),
+            Schedule::Calendar(calendar) => calendar.first_from(time),
+        }
+    }
+
+    /// The event time that comes after the event at `time`.
+    pub fn next_after(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Schedule::Interval(interval) => Some(time + *interval),
+            Schedule::Calendar(calendar) => calendar.next_after(time),
+        }
+    }
+
+    pub fn range_kind(&self) -> RangeKind {
+        match self {
+            Schedule::Interval(interval) => RangeKind::Time {
+                interval: interval.num_seconds() as u32,
+            },
+            Schedule::Calendar(calendar) => calendar.range_kind(),
+        }
+    }
+}
+
+/// Where tickers get the current time from.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> NaiveDateTime;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        now()
+    }
+}
+
+/// A clock stopped at the time.
+impl Clock for NaiveDateTime {
+    fn now(&self) -> NaiveDateTime {
+        *self
+    }
+}
+
 pub struct TimeEventStream {
     pub db: PrefixedDb,
     pub look_ahead: Duration,
-    pub interval: Duration,
+    pub schedule: Schedule,
+    /// The time of the first event if the schedule allows and otherwise the first time after it.
     pub initial_time: NaiveDateTime,
+    pub clock: Box<dyn Clock>,
     pub logger: slog::Logger,
     pub ends_with: Path,
     pub event_kind: EventKind,
@@ -26,8 +87,9 @@ impl TimeEventStream {
         let TimeEventStream {
             db,
             look_ahead,
-            interval,
+            schedule,
             initial_time,
+            clock,
             logger,
             ends_with,
             event_kind,
@@ -56,23 +118,18 @@ impl TimeEventStream {
                     order: Order::Latest,
                     ..Default::default()
                 }).await;
-                let (update, waiting) = match latest {
+                let next_event = match latest {
                     Ok(Some(latest)) => {
                         let latest = latest
                             .expected_outcome_time
                             .expect("time events always have this");
                         // If the latest event we have in the DB is 19:36 and our interval is 1min
                         // then the next event we want is 19:37.
-                        let next_event = latest + interval;
-                        // But we should add it at 18:36 if our look_ahead is 1hr
-                        let add_when = next_event - look_ahead;
-                        // wait until then before returning it
-                        delay_until(add_when).await;
-                        create_update(next_event)
+                        schedule.next_after(latest)
                     }
                     Ok(None) => {
                         // This means this is our first run against this backend, we add a new event to get us started.
-                        create_update(initial_time)
+                        schedule.first_from(initial_time)
                     }
                     Err(err) => {
                         crit!(
@@ -83,11 +140,23 @@ impl TimeEventStream {
                         break;
                     }
                 };
+                let next_event = match next_event {
+                    Some(next_event) => next_event,
+                    None => {
+                        crit!(logger, "Stopping emitting new time events as the schedule has no more times");
+                        break;
+                    }
+                };
+                // But we should add it at 18:36 if our look_ahead is 1hr
+                let add_when = next_event - look_ahead;
+                // wait until then before returning it
+                delay_until(clock.as_ref(), add_when).await;
+                let (update, waiting) = create_update(next_event);
                 let event_id = update.update.id.clone();
 
                 yield update;
 
-                if let Err(_) | Ok(true) = waiting.await {
+                if waiting.await != Ok(Processed::Done) {
                     error!(logger, "processing of new ticker failed (will try again)"; "id" => event_id.as_str());
                     time::sleep(std::time::Duration::from_secs(10)).await;
                 }
@@ -116,6 +185,7 @@ where
             ends_with,
             event_kind,
         } = self;
+        let mut changes = db.event_changes();
         async_stream::stream! {
             loop {
                 let event = db.query_event(EventQuery {
@@ -137,7 +207,7 @@ where
                         continue;
                     }
                     Ok(None) => {
-                        time::sleep(std::time::Duration::from_secs(1)).await;
+                        wait_for_changes(&mut changes, now() + Duration::seconds(1)).await;
                         continue;
                     }
                 };
@@ -146,7 +216,28 @@ where
                     .expected_outcome_time
                     .expect("time events always have this");
 
-                delay_until(event_complete_time).await;
+                // The event might be rescheduled (or an earlier one added) while we wait. If the
+                // database can't tell us check back after a tenth of the wait.
+                let check_back = match changes {
+                    Some(_) => event_complete_time,
+                    None => {
+                        let wait = (event_complete_time - now()) / 10;
+                        event_complete_time.min(now() + wait.max(Duration::seconds(1)).min(Duration::seconds(60)))
+                    }
+                };
+                wait_for_changes(&mut changes, check_back).await;
+                if event_complete_time > now() {
+                    continue;
+                }
+
+                let value = match outcome_creator.create_outcome(&event.id).await {
+                    Some(value) => value,
+                    None => {
+                        error!(logger, "unable to determine outcome (will try again)"; "id" => event.id.as_str());
+                        time::sleep(std::time::Duration::from_secs(10)).await;
+                        continue;
+                    }
+                };
 
                 let (sender, waiting) = oneshot::channel();
 
@@ -154,14 +245,14 @@ where
                     update: StampedOutcome {
                         outcome: Outcome {
                             id: event.id.clone(),
-                            value: outcome_creator.create_outcome(&event.id),
+                            value,
                         },
                         time: now(), // tell the actual truth about when we actually figured it was done
                     },
                     processed_notifier: Some(sender),
                 };
 
-                if let Err(_) | Ok(true) = waiting.await {
+                if waiting.await != Ok(Processed::Done) {
                     error!(logger, "processing of ticker outcome failed (will try again)"; "id" => event.id.as_str());
                     time::sleep(std::time::Duration::from_secs(10)).await;
                 }
@@ -170,8 +261,21 @@ where
     }
 }
 
-async fn delay_until(until: NaiveDateTime) {
-    let delta = until - now();
+/// Waits until `until` or until the database says its events have changed.
+async fn wait_for_changes(changes: &mut Option<watch::Receiver<()>>, until: NaiveDateTime) {
+    match changes {
+        Some(receiver) => {
+            tokio::select! {
+                _ = delay_until(&SystemClock, until) => {},
+                _ = receiver.changed() => {},
+            }
+        }
+        None => delay_until(&SystemClock, until).await,
+    }
+}
+
+async fn delay_until(clock: &dyn Clock, until: NaiveDateTime) {
+    let delta = until - clock.now();
     if delta > Duration::zero() {
         time::sleep(delta.to_std().unwrap().into()).await;
     }
@@ -181,8 +285,10 @@ fn now() -> NaiveDateTime {
     chrono::Utc::now().naive_utc()
 }
 
+#[async_trait]
 pub trait OutcomeCreator {
-    fn create_outcome(&self, id: &EventId) -> u64;
+    /// The outcome of the event or `None` if it can't be determined right now.
+    async fn create_outcome(&self, id: &EventId) -> Option<u64>;
 }
 
 pub struct RandomOutcomeCreator {
@@ -190,8 +296,9 @@ pub struct RandomOutcomeCreator {
     pub max: Option<u64>,
 }
 
+#[async_trait]
 impl OutcomeCreator for RandomOutcomeCreator {
-    fn create_outcome(&self, id: &EventId) -> u64 {
+    async fn create_outcome(&self, id: &EventId) -> Option<u64> {
         use rand::{Rng, SeedableRng};
         let event_randomness = self.seed.child(id.as_bytes());
         let mut chacha_bytes = [0u8; 32];
@@ -199,15 +306,16 @@ impl OutcomeCreator for RandomOutcomeCreator {
         let mut rng = chacha20::ChaCha20Rng::from_seed(chacha_bytes);
         let n_outcomes = id.n_outcomes();
         let max = self.max.unwrap_or(n_outcomes).min(n_outcomes);
-        rng.gen_range(0..max)
+        Some(rng.gen_range(0..max))
     }
 }
 
 pub struct ZeroOutcomeCreator;
 
+#[async_trait]
 impl OutcomeCreator for ZeroOutcomeCreator {
-    fn create_outcome(&self, _: &EventId) -> u64 {
-        0
+    async fn create_outcome(&self, _: &EventId) -> Option<u64> {
+        Some(0)
     }
 }
 
@@ -216,18 +324,21 @@ mod test {
     use super::*;
     use std::str::FromStr;
 
-    #[test]
-    fn random_outcome_creator() {
+    #[tokio::test]
+    async fn random_outcome_creator() {
         let random_outcome_creator = RandomOutcomeCreator {
             seed: Seed::new([42u8; 64]),
             max: None,
         };
-        let random_outcomes = (0..10)
-            .map(|i| {
+        let mut random_outcomes = vec![];
+        for i in 0..10 {
+            random_outcomes.push(
                 random_outcome_creator
                     .create_outcome(&EventId::from_str(&format!("/{}/foo_bar.vs", i)).unwrap())
-            })
-            .collect::<Vec<_>>();
+                    .await
+                    .unwrap(),
+            );
+        }
         assert_eq!(random_outcomes, [0, 2, 2, 1, 2, 0, 1, 2, 0, 0].to_vec())
     }
 }
@@ -11,6 +11,7 @@ macro_rules! run_time_db_tests {
             use olivia_core::{AnnouncedEvent, EventKind, Event, EventId, path, PrefixPath, Path, Outcome, chrono::{NaiveDateTime, Duration, Utc}};
             use crate::db::Order;
             use crate::sources::ticker::*;
+            use crate::sources::Processed;
             use core::str::FromStr;
             use tokio_stream::StreamExt;
             use std::sync::Arc;
@@ -34,8 +35,9 @@ macro_rules! run_time_db_tests {
                 let mut stream = Box::pin(TimeEventStream {
                     db: PrefixedDb::new($event_db, Path::from_str("/time").unwrap()),
                     look_ahead,
-                    interval,
+                    schedule: Schedule::Interval(interval),
                     initial_time,
+                    clock: Box::new(SystemClock),
                     logger: logger(),
                     ends_with: Path::root(),
                     event_kind: EventKind::SingleOccurrence,
@@ -49,7 +51,7 @@ macro_rules! run_time_db_tests {
                     $db.insert_event(AnnouncedEvent::test_unattested_instance(event.prefix_path(path!("/time"))))
                        .await
                        .unwrap();
-                    let _ = update.processed_notifier.unwrap().send(false);
+                    let _ = update.processed_notifier.unwrap().send(Processed::Done);
                 }
 
                 cur += interval;
@@ -61,7 +63,7 @@ macro_rules! run_time_db_tests {
                     $db.insert_event(AnnouncedEvent::test_unattested_instance(event.prefix_path(path!("/time"))))
                        .await
                        .unwrap();
-                    let _ = update.processed_notifier.unwrap().send(false);
+                    let _ = update.processed_notifier.unwrap().send(Processed::Done);
                 }
 
                 cur += interval;
@@ -73,7 +75,7 @@ macro_rules! run_time_db_tests {
                     $db.insert_event(AnnouncedEvent::test_unattested_instance(event.prefix_path(path!("/time"))))
                        .await
                        .unwrap();
-                    let _ = update.processed_notifier.unwrap().send(false);
+                    let _ = update.processed_notifier.unwrap().send(Processed::Done);
                 }
                 assert!(
                     now() < initial_time + fudge,
@@ -88,7 +90,7 @@ macro_rules! run_time_db_tests {
                     $db.insert_event(AnnouncedEvent::test_unattested_instance(event.prefix_path(path!("/time"))))
                        .await
                        .unwrap();
-                    let _ = update.processed_notifier.unwrap().send(false);
+                    let _ = update.processed_notifier.unwrap().send(Processed::Done);
                 }
 
                 assert!(
@@ -101,6 +103,64 @@ macro_rules! run_time_db_tests {
                 );
             }
 
+            #[tokio::test]
+            async fn time_ticker_calendar_events_stream() {
+                $($init)*;
+                use crate::sources::calendar::Calendar;
+                use olivia_core::{chrono::NaiveDate, GetPath, Node, NodeKind, ChildDesc};
+                let holidays = vec![NaiveDate::from_ymd_opt(2021, 12, 31).unwrap()];
+                let calendar = Calendar::new("0 16 * * MON-FRI", "America/New_York", holidays).unwrap();
+                let schedule = Schedule::Calendar(calendar.clone());
+                let clock = NaiveDateTime::from_str("2021-12-30T12:00:00").unwrap();
+
+                let mut stream = Box::pin(TimeEventStream {
+                    db: PrefixedDb::new($event_db, Path::from_str("/close").unwrap()),
+                    look_ahead: Duration::days(7),
+                    schedule: schedule.clone(),
+                    initial_time: clock,
+                    clock: Box::new(clock),
+                    logger: logger(),
+                    ends_with: Path::root(),
+                    event_kind: EventKind::SingleOccurrence,
+                }.start());
+
+                // every trading day within a week of the stopped clock
+                for expected in ["2021-12-30T21:00:00", "2022-01-03T21:00:00", "2022-01-04T21:00:00", "2022-01-05T21:00:00"] {
+                    let update = stream.next().await.expect("Not None");
+                    let event = update.update;
+                    let expected = NaiveDateTime::from_str(expected).unwrap();
+                    assert_eq!(event.id, EventId::occur_from_dt(expected));
+                    assert_eq!(event.expected_outcome_time, Some(expected));
+                    $db.insert_event(AnnouncedEvent::test_unattested_instance(event.prefix_path(path!("/close"))))
+                       .await
+                       .unwrap();
+                    let _ = update.processed_notifier.unwrap().send(Processed::Done);
+                }
+
+                assert!(
+                    tokio::time::timeout(std::time::Duration::from_millis(100), stream.next())
+                        .await
+                        .is_err(),
+                    "the next close is more than a week away"
+                );
+
+                $db.set_node(Node {
+                    path: Path::from_str("/close").unwrap(),
+                    kind: NodeKind::Range { range_kind: schedule.range_kind() },
+                })
+                   .await
+                   .unwrap();
+                assert_eq!(
+                    $db.get_node(path!("/close")).await.unwrap().unwrap().child_desc,
+                    ChildDesc::Range {
+                        range_kind: calendar.range_kind(),
+                        start: Some("2021-12-30T21:00:00".into()),
+                        next_unattested: Some("2021-12-30T21:00:00".into()),
+                        end: Some("2022-01-05T21:00:00".into()),
+                    }
+                );
+            }
+
             fn time_outcome_stream(db: Arc<dyn DbReadEvent>) -> std::pin::Pin<Box<dyn tokio_stream::Stream<Item = crate::sources::Update<olivia_core::StampedOutcome>>>> {
                 Box::pin(TimeOutcomeStream { outcome_creator: ZeroOutcomeCreator, db: PrefixedDb::new(db, Path::from_str("/time").unwrap()), logger: logger(), ends_with: Path::root(), event_kind: Some(EventKind::SingleOccurrence) }.start())
             }
@@ -176,6 +236,37 @@ macro_rules! run_time_db_tests {
                 assert!(stamped.time <= now(), "should not be in the future");
             }
 
+            #[tokio::test]
+            async fn time_ticker_outcome_rescheduled() {
+                $($init)*;
+                let start = now();
+                let event = Event::occur_event_from_dt(start + Duration::seconds(3)).prefix_path(path!("/time"));
+
+                $db.insert_event(AnnouncedEvent::test_unattested_instance(event.clone()))
+                   .await
+                   .unwrap();
+
+                let mut stream = time_outcome_stream($event_db);
+                let future = stream.next();
+                tokio::pin!(future);
+                assert!(
+                    tokio::time::timeout(std::time::Duration::from_millis(100), &mut future)
+                        .await
+                        .is_err(),
+                    "should wait for the event"
+                );
+
+                let rescheduled = Event { expected_outcome_time: Some(start + Duration::seconds(1)), ..event.clone() };
+                $db.reschedule_event(rescheduled.clone(), AnnouncedEvent::<$curve>::test_unattested_instance(rescheduled).announcement)
+                   .await
+                   .unwrap();
+
+                let item = future.await.expect("stream shouldn't stop");
+                assert_eq!(item.update.outcome.id, event.id.strip_prefix_path(path!("/time")));
+                assert!(now() >= start + Duration::seconds(1), "should wait for the new time");
+                assert!(now() < start + Duration::seconds(3), "should not wait for the old time");
+            }
+
             #[tokio::test]
             async fn time_ticker_wait_for_event_outcomes() {
                 $($init)*;
@@ -216,7 +307,7 @@ macro_rules! run_time_db_tests {
                 )
                    .await
                    .unwrap();
-                first.processed_notifier.unwrap().send(false).unwrap();
+                first.processed_notifier.unwrap().send(Processed::Done).unwrap();
 
                 let second = stream.next().await.unwrap();
                 assert_eq!(
@@ -231,7 +322,7 @@ macro_rules! run_time_db_tests {
                 )
                    .await
                    .unwrap();
-                second.processed_notifier.unwrap().send(false).unwrap();
+                second.processed_notifier.unwrap().send(Processed::Done).unwrap();
 
                 let third = stream.next().await.unwrap();
                 assert_eq!(
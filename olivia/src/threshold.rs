@@ -0,0 +1,1291 @@
+//! Running the oracle with its secret keys split between several peers.
+//!
+//! Each of the `n_peers` peers has its own [`Seed`]. The announcement and `olivia_v1` keys are
+//! generated with a joint verifiable secret sharing: every peer deals a Shamir sharing of a secret
+//! derived from its seed and a peer's share of the key is the sum of the shares dealt to it. No
+//! single peer ever learns the keys. The `olivia_v1` nonces for each event are generated the same
+//! way when the event is announced. After that any `threshold` of the peers can:
+//!
+//! - sign an announcement with a FROST style two round signing protocol.
+//! - reveal their shares of the attestation scalars which are interpolated into the real ones.
+//!
+//! Shares are encrypted to the peer they are dealt to so whatever relays the messages (the
+//! [`ThresholdSigner`]) learns nothing from them. The outputs are ordinary announcements and
+//! attestations that verify under the combined [`OracleKeys`]. Only the `olivia_v1` scheme is
+//! supported.
+//!
+//! The threshold has to be a majority of the peers. Each peer refuses to reveal its shares for two
+//! different outcomes of an event but with a smaller threshold two groups of peers that don't
+//! overlap could reveal different outcomes between them, which would reveal the `olivia_v1` key.
+//!
+//! Each peer writes the shares dealt to it and the outcomes it has revealed to its state file
+//! before acting on them so its shares and what it has revealed survive restarts. Peers only sign
+//! a key succession to the [`Successor`] they were configured with and only do so once.
+//!
+//! Note that every peer has to take part in dealing nonces so all of them need to be online to
+//! announce an event.
+use crate::{keychain::Signer, seed::Seed};
+use anyhow::{anyhow, ensure, Context};
+use async_trait::async_trait;
+use blake2::{
+    digest::{Update, VariableOutput},
+    VarBlake2b,
+};
+use olivia_core::{
+    announce, attest, AnnouncementSchemes, AttestationSchemes, BatchSchedule, Event, EventId,
+    Group, KeySuccession, NonceBatch, OracleEvent, OracleKeys, Outcome, RawAnnouncement,
+    RawKeySuccession, RawNonceBatch, RawOracleEvent, SignatureKind, Successor, VOID_OUTCOME,
+};
+use olivia_secp256k1::{
+    fun::{g, marker::*, s, Point, Scalar, XOnly, G},
+    schnorr_fun::{self, Message},
+    KeyPair, Secp256k1, SCHNORR,
+};
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// A peer's index in the sharing. Indexes start at `1` since the shared secret is at `0`.
+pub type PeerIndex = u32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThresholdParams {
+    /// The number of peers needed to sign announcements and attestations.
+    pub threshold: u32,
+    pub n_peers: u32,
+}
+
+impl ThresholdParams {
+    /// Checks that the threshold is a majority of the peers.
+    pub fn check(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.threshold <= self.n_peers && self.threshold > self.n_peers / 2,
+            "threshold must be more than half the number of peers"
+        );
+        Ok(())
+    }
+}
+
+/// What is being dealt.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Deal {
+    AnnouncementKey,
+    OliviaV1Key,
+    /// The `olivia_v1` nonces for an event.
+    Nonces(EventId),
+}
+
+impl Deal {
+    fn tag(&self) -> Vec<u8> {
+        match self {
+            Deal::AnnouncementKey => b"announcement-key".to_vec(),
+            Deal::OliviaV1Key => b"olivia-v1-key".to_vec(),
+            Deal::Nonces(event_id) => [&b"event-nonces/"[..], event_id.as_bytes()].concat(),
+        }
+    }
+
+    fn n_secrets(&self) -> usize {
+        match self {
+            Deal::Nonces(event_id) => event_id.n_nonces() as usize,
+            _ => 1,
+        }
+    }
+}
+
+/// A peer's contribution to the sharing of one or more secrets.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dealing {
+    pub dealer: PeerIndex,
+    /// Commitments to the coefficients of the polynomial for each secret.
+    pub commitments: Vec<Vec<Point>>,
+    /// The shares of each secret for each peer (in order of index) encrypted to that peer.
+    pub encrypted_shares: Vec<Vec<[u8; 32]>>,
+}
+
+/// A peer's nonces for the first round of signing an announcement.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NonceCommitment {
+    pub D: Point,
+    pub E: Point,
+}
+
+/// How the [`ThresholdSigner`] talks to a peer.
+#[async_trait]
+pub trait PeerClient: Send + Sync {
+    async fn deal(&self, deal: &Deal) -> anyhow::Result<Dealing>;
+    /// Gives the peer every peer's dealing. Returns the public key of each secret that was dealt.
+    async fn receive_dealings(
+        &self,
+        deal: &Deal,
+        dealings: &[Dealing],
+    ) -> anyhow::Result<Vec<XOnly>>;
+    async fn announcement_nonce(&self) -> anyhow::Result<NonceCommitment>;
+    /// Signs the JSON encoded oracle event, key succession or nonce batch (depending on `kind`)
+    /// with the announcement key share. `nonces` are the nonces of every peer taking part
+    /// (including this one).
+    async fn sign_with_announcement_key(
+        &self,
+        kind: SignatureKind,
+        message: &[u8],
+        nonces: &BTreeMap<PeerIndex, NonceCommitment>,
+    ) -> anyhow::Result<Scalar<Public, Zero>>;
+    /// Reveals the peer's shares of the `olivia_v1` attestation scalars for `outcome` (which may be
+    /// [`VOID_OUTCOME`]).
+    async fn reveal_olivia_v1(
+        &self,
+        event_id: &EventId,
+        outcome: &str,
+    ) -> anyhow::Result<Vec<Scalar<Public, Zero>>>;
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SecretShare {
+    share: Scalar<Secret, Zero>,
+    public_key: XOnly,
+}
+
+/// A line in a peer's state file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+enum Record {
+    Dealt {
+        deal: Deal,
+        shares: Vec<SecretShare>,
+    },
+    Revealed {
+        event_id: EventId,
+        outcome: String,
+    },
+    Succeeded {
+        keys: OracleKeys<Secp256k1>,
+    },
+}
+
+struct PeerState {
+    state_file: File,
+    announcement_key: Option<SecretShare>,
+    olivia_v1_key: Option<SecretShare>,
+    nonces: HashMap<EventId, Vec<SecretShare>>,
+    /// These are only kept in memory. Losing them just fails the signing sessions they were for.
+    announcement_nonces: HashMap<[u8; 33], (Scalar, Scalar)>,
+    revealed: HashMap<EventId, String>,
+    /// The keys we handed over to.
+    succeeded: Option<OracleKeys<Secp256k1>>,
+}
+
+impl PeerState {
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        // the file has our key shares in it
+        let state_file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .mode(0o600)
+            .open(path)
+            .with_context(|| format!("opening peer state file {}", path.display()))?;
+        let records = BufReader::new(&state_file)
+            .lines()
+            .map(|line| {
+                serde_json::from_str(&line?)
+                    .with_context(|| format!("corrupt peer state file {}", path.display()))
+            })
+            .collect::<anyhow::Result<Vec<Record>>>()?;
+        let mut state = PeerState {
+            state_file,
+            announcement_key: None,
+            olivia_v1_key: None,
+            nonces: HashMap::default(),
+            announcement_nonces: HashMap::default(),
+            revealed: HashMap::default(),
+            succeeded: None,
+        };
+        for record in records {
+            state.apply(record);
+        }
+        Ok(state)
+    }
+
+    fn apply(&mut self, record: Record) {
+        match record {
+            Record::Dealt { deal, mut shares } => match deal {
+                Deal::AnnouncementKey => self.announcement_key = shares.pop(),
+                Deal::OliviaV1Key => self.olivia_v1_key = shares.pop(),
+                Deal::Nonces(event_id) => {
+                    self.nonces.insert(event_id, shares);
+                }
+            },
+            Record::Revealed { event_id, outcome } => {
+                self.revealed.insert(event_id, outcome);
+            }
+            Record::Succeeded { keys } => self.succeeded = Some(keys),
+        }
+    }
+
+    /// Writes the record to disk before applying it.
+    fn record(&mut self, record: Record) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        self.state_file.write_all(line.as_bytes())?;
+        self.state_file.sync_data()?;
+        self.apply(record);
+        Ok(())
+    }
+
+    /// The public keys of the secrets we already have shares of from `deal`.
+    fn dealt(&self, deal: &Deal) -> Option<Vec<XOnly>> {
+        let shares = match deal {
+            Deal::AnnouncementKey => core::slice::from_ref(self.announcement_key.as_ref()?),
+            Deal::OliviaV1Key => core::slice::from_ref(self.olivia_v1_key.as_ref()?),
+            Deal::Nonces(event_id) => self.nonces.get(event_id)?.as_slice(),
+        };
+        Some(shares.iter().map(|share| share.public_key).collect())
+    }
+
+    /// Checks that the `olivia_v1` nonces in `schemes` are the ones we dealt for `event_id`.
+    fn check_nonces(
+        &self,
+        event_id: &EventId,
+        schemes: &AnnouncementSchemes<Secp256k1>,
+    ) -> anyhow::Result<()> {
+        if let Some(olivia_v1) = &schemes.olivia_v1 {
+            let our_nonces = self
+                .nonces
+                .get(event_id)
+                .ok_or_else(|| anyhow!("nonces for {} haven't been dealt", event_id))?;
+            ensure!(
+                olivia_v1.nonces.len() == our_nonces.len()
+                    && olivia_v1
+                        .nonces
+                        .iter()
+                        .zip(our_nonces)
+                        .all(|(nonce, ours)| XOnly::from(nonce.clone()) == ours.public_key),
+                "{} has the wrong nonces",
+                event_id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// One of the peers holding a share of the oracle's keys.
+pub struct ThresholdPeer {
+    index: PeerIndex,
+    params: ThresholdParams,
+    seed: Seed,
+    identity: KeyPair,
+    peer_identities: Vec<XOnly>,
+    successor: Option<Successor<Secp256k1>>,
+    state: Mutex<PeerState>,
+}
+
+impl ThresholdPeer {
+    /// `peer_identities` are the [`identity_key`](Self::identity_key)s of every peer in order of
+    /// index. The shares dealt to the peer and the outcomes it has revealed are loaded from and
+    /// recorded in `state_file`.
+    pub fn new(
+        index: PeerIndex,
+        seed: Seed,
+        params: ThresholdParams,
+        peer_identities: Vec<XOnly>,
+        state_file: &Path,
+    ) -> anyhow::Result<Self> {
+        params.check()?;
+        ensure!(
+            peer_identities.len() == params.n_peers as usize,
+            "need the identity of every peer"
+        );
+        ensure!(
+            index > 0 && index <= params.n_peers,
+            "peer index must be between 1 and the number of peers"
+        );
+        let seed = seed.child(b"threshold");
+        let identity = identity_keypair(&seed);
+        ensure!(
+            peer_identities[index as usize - 1] == identity.public_key(),
+            "our identity doesn't match the one for our index"
+        );
+        Ok(Self {
+            index,
+            params,
+            seed,
+            identity,
+            peer_identities,
+            successor: None,
+            state: Mutex::new(PeerState::open(state_file)?),
+        })
+    }
+
+    /// The keys the peer may help hand the oracle over to. Without one it refuses to sign any key
+    /// succession.
+    pub fn with_successor(self, successor: Successor<Secp256k1>) -> Self {
+        Self {
+            successor: Some(successor),
+            ..self
+        }
+    }
+
+    /// The key that shares dealt to the peer with this seed are encrypted to.
+    pub fn identity_key(seed: &Seed) -> XOnly {
+        identity_keypair(&seed.child(b"threshold")).public_key()
+    }
+
+    fn share_pad(
+        &self,
+        deal: &Deal,
+        dealer: PeerIndex,
+        recipient: PeerIndex,
+        secret: usize,
+    ) -> [u8; 32] {
+        let other = match self.index == dealer {
+            true => recipient,
+            false => dealer,
+        };
+        let shared_secret = {
+            let other_key = self.peer_identities[other as usize - 1].to_point();
+            g!({ self.identity.secret_key() } * other_key).mark::<Normal>()
+        };
+        let mut hash = VarBlake2b::new_keyed(&shared_secret.to_bytes(), 32);
+        hash.update(deal.tag());
+        hash.update(dealer.to_be_bytes());
+        hash.update(recipient.to_be_bytes());
+        hash.update((secret as u32).to_be_bytes());
+        let mut pad = [0u8; 32];
+        pad.copy_from_slice(hash.finalize_boxed().borrow());
+        pad
+    }
+
+    fn polynomial(&self, deal: &Deal, secret: usize) -> Vec<Scalar> {
+        let hash = self.seed.child(&deal.tag()).to_blake2b_var(32);
+        (0..self.params.threshold)
+            .map(|k| {
+                let mut hash = hash.clone();
+                hash.update((secret as u32).to_be_bytes());
+                hash.update(k.to_be_bytes());
+                Scalar::from_slice_mod_order(hash.finalize_boxed().borrow())
+                    .expect("hash output is 32-bytes long")
+                    .mark::<NonZero>()
+                    .expect("will not be zero")
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PeerState> {
+        self.state.lock().expect("peer state lock poisoned")
+    }
+}
+
+#[async_trait]
+impl PeerClient for ThresholdPeer {
+    async fn deal(&self, deal: &Deal) -> anyhow::Result<Dealing> {
+        let polynomials = (0..deal.n_secrets())
+            .map(|secret| self.polynomial(deal, secret))
+            .collect::<Vec<_>>();
+        let commitments = polynomials
+            .iter()
+            .map(|poly| {
+                poly.iter()
+                    .map(|coeff| g!(coeff * G).normalize().mark::<Public>())
+                    .collect()
+            })
+            .collect();
+        let encrypted_shares = (1..=self.params.n_peers)
+            .map(|recipient| {
+                polynomials
+                    .iter()
+                    .enumerate()
+                    .map(|(secret, poly)| {
+                        let pad = self.share_pad(deal, self.index, recipient, secret);
+                        let mut share = evaluate(poly, recipient).to_bytes();
+                        share
+                            .iter_mut()
+                            .zip(pad)
+                            .for_each(|(byte, pad)| *byte ^= pad);
+                        share
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Dealing {
+            dealer: self.index,
+            commitments,
+            encrypted_shares,
+        })
+    }
+
+    async fn receive_dealings(
+        &self,
+        deal: &Deal,
+        dealings: &[Dealing],
+    ) -> anyhow::Result<Vec<XOnly>> {
+        ensure!(
+            dealings.len() == self.params.n_peers as usize
+                && dealings
+                    .iter()
+                    .enumerate()
+                    .all(|(i, dealing)| dealing.dealer == i as PeerIndex + 1),
+            "need exactly one dealing from every peer in order"
+        );
+        let mut secret_shares = vec![];
+        for secret in 0..deal.n_secrets() {
+            let mut share = Scalar::zero();
+            let mut public_key = Point::zero().mark::<Jacobian>();
+            for dealing in dealings {
+                let commitments = dealing
+                    .commitments
+                    .get(secret)
+                    .filter(|commitments| commitments.len() == self.params.threshold as usize)
+                    .ok_or_else(|| anyhow!("peer {} dealt the wrong thing", dealing.dealer))?;
+                let mut encrypted = *dealing
+                    .encrypted_shares
+                    .get(self.index as usize - 1)
+                    .and_then(|shares| shares.get(secret))
+                    .ok_or_else(|| anyhow!("peer {} didn't deal us a share", dealing.dealer))?;
+                let pad = self.share_pad(deal, dealing.dealer, self.index, secret);
+                encrypted
+                    .iter_mut()
+                    .zip(pad)
+                    .for_each(|(byte, pad)| *byte ^= pad);
+                let dealt = Scalar::from_bytes(encrypted)
+                    .filter(|dealt| g!(dealt * G) == evaluate_commitments(commitments, self.index))
+                    .ok_or_else(|| anyhow!("peer {} dealt us an invalid share", dealing.dealer))?;
+                share = s!(share + dealt);
+                public_key = g!(public_key + { commitments[0] });
+            }
+            let (public_key, needs_negation) = public_key
+                .mark::<NonZero>()
+                .ok_or_else(|| anyhow!("the dealt secret was zero"))?
+                .into_point_with_even_y();
+            share.conditional_negate(needs_negation);
+            secret_shares.push(SecretShare {
+                share,
+                public_key: public_key.to_xonly(),
+            });
+        }
+
+        let public_keys = secret_shares
+            .iter()
+            .map(|secret_share| secret_share.public_key)
+            .collect::<Vec<_>>();
+        let mut state = self.lock();
+        // dealing is deterministic so dealing again (e.g. when the oracle restarts) gives the same
+        // secrets unless the peers have been set up differently
+        match state.dealt(deal) {
+            Some(dealt) => ensure!(
+                dealt == public_keys,
+                "{:?} has already been dealt with different public keys",
+                deal
+            ),
+            None => state.record(Record::Dealt {
+                deal: deal.clone(),
+                shares: secret_shares,
+            })?,
+        }
+        Ok(public_keys)
+    }
+
+    async fn announcement_nonce(&self) -> anyhow::Result<NonceCommitment> {
+        let d = Scalar::random(&mut rand::thread_rng());
+        let e = Scalar::random(&mut rand::thread_rng());
+        let nonce = NonceCommitment {
+            D: g!(d * G).normalize().mark::<Public>(),
+            E: g!(e * G).normalize().mark::<Public>(),
+        };
+        self.lock()
+            .announcement_nonces
+            .insert(nonce.D.to_bytes(), (d, e));
+        Ok(nonce)
+    }
+
+    async fn sign_with_announcement_key(
+        &self,
+        kind: SignatureKind,
+        message: &[u8],
+        nonces: &BTreeMap<PeerIndex, NonceCommitment>,
+    ) -> anyhow::Result<Scalar<Public, Zero>> {
+        let mut state = self.lock();
+        let key = state
+            .announcement_key
+            .clone()
+            .ok_or_else(|| anyhow!("announcement key hasn't been dealt"))?;
+        let (d, e) = nonces
+            .get(&self.index)
+            .and_then(|nonce| state.announcement_nonces.remove(&nonce.D.to_bytes()))
+            .ok_or_else(|| anyhow!("we didn't create a nonce for this signing session"))?;
+
+        // only sign announcements and nonce batches with nonces we helped create
+        match kind {
+            SignatureKind::Announcement => {
+                let decoded = RawOracleEvent::<Secp256k1>::from_json_bytes(message.to_vec())
+                    .decode()
+                    .ok_or_else(|| anyhow!("announcement isn't a valid oracle event"))?;
+                state.check_nonces(&decoded.event.id, &decoded.schemes)?;
+            }
+            SignatureKind::NonceBatch => {
+                let batch = serde_json::from_slice::<NonceBatch<Secp256k1>>(message)
+                    .map_err(|e| anyhow!("nonce batch is invalid: {}", e))?;
+                ensure!(
+                    batch.slots.len() == batch.schedule.n_slots as usize,
+                    "nonce batch has the wrong number of slots"
+                );
+                for (slot, schemes) in batch.slots.iter().enumerate() {
+                    let event_id = batch
+                        .schedule
+                        .slot_event_id(slot as u32)
+                        .ok_or_else(|| anyhow!("nonce batch slot {} is invalid", slot))?;
+                    state.check_nonces(&event_id, schemes)?;
+                }
+            }
+            SignatureKind::DlcAnnouncement => {
+                return Err(anyhow!("only JSON encoded announcements can be signed"))
+            }
+            SignatureKind::KeySuccession => {
+                let succession = serde_json::from_slice::<KeySuccession<Secp256k1>>(message)
+                    .map_err(|e| anyhow!("key succession is invalid: {}", e))?;
+                let successor = self
+                    .successor
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("no successor is configured for this peer"))?;
+                ensure!(
+                    successor.matches(&succession),
+                    "the keys aren't the successor this peer is configured with"
+                );
+                match &state.succeeded {
+                    Some(keys) => ensure!(
+                        *keys == succession.keys,
+                        "this peer has already handed over to other keys"
+                    ),
+                    None => state.record(Record::Succeeded {
+                        keys: succession.keys,
+                    })?,
+                }
+            }
+        }
+
+        let (R, binding_factors) = group_commitment(kind, message, nonces)?;
+        let (R, needs_negation) = R.into_point_with_even_y();
+        let rho = &binding_factors[&self.index];
+        let mut k = s!(d + rho * e);
+        k.conditional_negate(needs_negation);
+        let c = SCHNORR.challenge(
+            R.to_xonly(),
+            key.public_key,
+            Message::<Public>::plain(kind.tag(), message),
+        );
+        let lambda = lagrange_coefficient(self.index, nonces.keys().copied());
+        Ok(s!(k + c * lambda * { &key.share }).mark::<Public>())
+    }
+
+    async fn reveal_olivia_v1(
+        &self,
+        event_id: &EventId,
+        outcome: &str,
+    ) -> anyhow::Result<Vec<Scalar<Public, Zero>>> {
+        let indexes = match outcome {
+            VOID_OUTCOME => event_id.void_attestation_indexes(),
+            outcome => {
+                Outcome::try_from_id_and_outcome(event_id.clone(), outcome)?.attestation_indexes()
+            }
+        };
+        let mut state = self.lock();
+        let key = state
+            .olivia_v1_key
+            .clone()
+            .ok_or_else(|| anyhow!("olivia-v1 key hasn't been dealt"))?;
+        let nonces = state
+            .nonces
+            .get(event_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("nonces for {} haven't been dealt", event_id))?;
+        ensure!(nonces.len() == indexes.len(), "wrong number of nonces");
+        // revealing for two different outcomes would reveal our key share
+        match state.revealed.get(event_id) {
+            Some(revealed) => ensure!(
+                revealed == outcome,
+                "already revealed {} for {}",
+                revealed,
+                event_id
+            ),
+            None => state.record(Record::Revealed {
+                event_id: event_id.clone(),
+                outcome: outcome.to_string(),
+            })?,
+        }
+
+        Ok(nonces
+            .iter()
+            .zip(indexes)
+            .map(|(nonce, index)| {
+                let c_plus_1 = Scalar::from(index + 1);
+                s!(c_plus_1 * { &nonce.share } + { &key.share }).mark::<Public>()
+            })
+            .collect())
+    }
+}
+
+/// Drives the threshold protocol on behalf of the oracle.
+pub struct ThresholdSigner {
+    params: ThresholdParams,
+    peers: Vec<Arc<dyn PeerClient>>,
+    announcement_key: XOnly,
+    olivia_v1_key: XOnly,
+}
+
+impl ThresholdSigner {
+    /// Generates the keys with `peers` (in order of index). All of them have to take part.
+    pub async fn setup(
+        params: ThresholdParams,
+        peers: Vec<Arc<dyn PeerClient>>,
+    ) -> anyhow::Result<Self> {
+        params.check()?;
+        ensure!(
+            peers.len() == params.n_peers as usize,
+            "need a client for every peer"
+        );
+        let announcement_key = deal(&peers, &Deal::AnnouncementKey).await?[0];
+        let olivia_v1_key = deal(&peers, &Deal::OliviaV1Key).await?[0];
+        Ok(Self {
+            params,
+            peers,
+            announcement_key,
+            olivia_v1_key,
+        })
+    }
+
+    /// Gets `threshold` of the peers to do `f`. Peers that fail are skipped.
+    async fn with_threshold<T, F, Fut>(&self, f: F) -> anyhow::Result<BTreeMap<PeerIndex, T>>
+    where
+        F: Fn(Arc<dyn PeerClient>) -> Fut,
+        Fut: std::future::Future<Output = anyhow::Result<T>>,
+    {
+        let mut results = BTreeMap::new();
+        let mut errors = vec![];
+        for (i, peer) in self.peers.iter().enumerate() {
+            if results.len() == self.params.threshold as usize {
+                break;
+            }
+            match f(peer.clone()).await {
+                Ok(result) => {
+                    results.insert(i as PeerIndex + 1, result);
+                }
+                Err(e) => errors.push(format!("peer {}: {}", i + 1, e)),
+            }
+        }
+        ensure!(
+            results.len() == self.params.threshold as usize,
+            "not enough peers responded: {}",
+            errors.join(", ")
+        );
+        Ok(results)
+    }
+
+    /// Makes a FROST signature on `message` with the peers' shares of the announcement key.
+    async fn sign_with_announcement_key(
+        &self,
+        kind: SignatureKind,
+        message: &[u8],
+    ) -> anyhow::Result<olivia_secp256k1::Signature> {
+        let nonces = self
+            .with_threshold(|peer| async move { peer.announcement_nonce().await })
+            .await?;
+        let mut s = Scalar::zero();
+        for (index, peer_s) in self
+            .with_threshold(|peer| {
+                let nonces = &nonces;
+                async move { peer.sign_with_announcement_key(kind, message, nonces).await }
+            })
+            .await?
+        {
+            ensure!(
+                nonces.contains_key(&index),
+                "a different peer signed the announcement"
+            );
+            s = s!(s + peer_s);
+        }
+        let (R, _) = group_commitment(kind, message, &nonces)?;
+        let signature: olivia_secp256k1::Signature = schnorr_fun::Signature {
+            R: R.into_point_with_even_y().0.to_xonly(),
+            s: s.mark::<Public>(),
+        }
+        .into();
+        ensure!(
+            Secp256k1::verify_announcement_key_signature(
+                kind,
+                &self.announcement_key.into(),
+                message,
+                &signature
+            ),
+            "peers produced an invalid announcement signature"
+        );
+        Ok(signature)
+    }
+}
+
+async fn deal(peers: &[Arc<dyn PeerClient>], deal: &Deal) -> anyhow::Result<Vec<XOnly>> {
+    let mut dealings = vec![];
+    for peer in peers {
+        dealings.push(peer.deal(deal).await?);
+    }
+    let mut public_keys = None;
+    for (i, peer) in peers.iter().enumerate() {
+        let peer_public_keys = peer.receive_dealings(deal, &dealings).await?;
+        match &public_keys {
+            Some(public_keys) => ensure!(
+                *public_keys == peer_public_keys,
+                "peer {} disagrees about the dealt public keys",
+                i + 1
+            ),
+            None => public_keys = Some(peer_public_keys),
+        }
+    }
+    Ok(public_keys.unwrap_or_default())
+}
+
+#[async_trait]
+impl Signer<Secp256k1> for ThresholdSigner {
+    fn oracle_public_keys(&self) -> OracleKeys<Secp256k1> {
+        OracleKeys {
+            olivia_v1: Some(self.olivia_v1_key.into()),
+            ecdsa_v1: None,
+            announcement: self.announcement_key.into(),
+            group: Secp256k1,
+            epoch: 0,
+        }
+    }
+
+    async fn create_announcement(
+        &self,
+        event: Event,
+    ) -> anyhow::Result<RawAnnouncement<Secp256k1>> {
+        let nonces = match event.id.n_nonces() {
+            0 => vec![],
+            _ => deal(&self.peers, &Deal::Nonces(event.id.clone())).await?,
+        };
+        let oracle_event = OracleEvent {
+            event,
+            schemes: AnnouncementSchemes {
+                olivia_v1: match nonces.is_empty() {
+                    true => None,
+                    false => Some(announce::OliviaV1 {
+                        nonces: nonces.into_iter().map(Into::into).collect(),
+                    }),
+                },
+                ..Default::default()
+            },
+        }
+        .encode_json();
+        let signature = self
+            .sign_with_announcement_key(SignatureKind::Announcement, oracle_event.as_bytes())
+            .await?;
+
+        Ok(RawAnnouncement {
+            oracle_event,
+            signature,
+        })
+    }
+
+    async fn create_nonce_batch(
+        &self,
+        schedule: BatchSchedule,
+    ) -> anyhow::Result<RawNonceBatch<Secp256k1>> {
+        schedule.validate()?;
+        let mut slots = Vec::<AnnouncementSchemes<Secp256k1>>::new();
+        for slot in 0..schedule.n_slots {
+            let event_id = schedule
+                .slot_event_id(slot)
+                .expect("slot is within the schedule");
+            // dealing is deterministic so the event gets the same nonces when it's created
+            let nonces = match event_id.n_nonces() {
+                0 => vec![],
+                _ => deal(&self.peers, &Deal::Nonces(event_id)).await?,
+            };
+            slots.push(AnnouncementSchemes {
+                olivia_v1: match nonces.is_empty() {
+                    true => None,
+                    false => Some(announce::OliviaV1 {
+                        nonces: nonces.into_iter().map(Into::into).collect(),
+                    }),
+                },
+                ..Default::default()
+            });
+        }
+        let batch = NonceBatch { schedule, slots }.encode_json();
+        let signature = self
+            .sign_with_announcement_key(SignatureKind::NonceBatch, batch.as_bytes())
+            .await?;
+        Ok(RawNonceBatch::from_parts(batch, signature))
+    }
+
+    async fn sign_key_succession(
+        &self,
+        succession: KeySuccession<Secp256k1>,
+    ) -> anyhow::Result<RawKeySuccession<Secp256k1>> {
+        let succession = succession.encode_json();
+        let signature = self
+            .sign_with_announcement_key(SignatureKind::KeySuccession, succession.as_bytes())
+            .await?;
+        Ok(RawKeySuccession::from_parts(succession, signature))
+    }
+
+    async fn attest(
+        &self,
+        oracle_event: &OracleEvent<Secp256k1>,
+        outcome: Option<&Outcome>,
+    ) -> anyhow::Result<AttestationSchemes<Secp256k1>> {
+        let olivia_v1 = match &oracle_event.schemes.olivia_v1 {
+            Some(olivia_v1) => olivia_v1,
+            None => return Ok(AttestationSchemes::default()),
+        };
+        let event_id = &oracle_event.event.id;
+        let (outcome_string, indexes) = match outcome {
+            Some(outcome) => (outcome.outcome_string(), outcome.attestation_indexes()),
+            None => (
+                VOID_OUTCOME.to_string(),
+                event_id.void_attestation_indexes(),
+            ),
+        };
+        let outcome_string = &outcome_string;
+        let n_scalars = indexes.len();
+
+        let shares = self
+            .with_threshold(|peer| async move {
+                let shares = peer.reveal_olivia_v1(event_id, outcome_string).await?;
+                ensure!(shares.len() == n_scalars, "wrong number of scalars");
+                Ok(shares)
+            })
+            .await?;
+
+        let scalars = (0..n_scalars)
+            .map(|i| {
+                let zero = Scalar::zero().mark::<Public>();
+                shares.iter().fold(zero, |scalar, (index, peer_shares)| {
+                    let lambda = lagrange_coefficient(*index, shares.keys().copied());
+                    s!(scalar + lambda * { &peer_shares[i] }).mark::<Public>()
+                })
+            })
+            .map(olivia_secp256k1::AttestScalar::from)
+            .collect::<Vec<_>>();
+
+        let public_key = self.olivia_v1_key.into();
+        for ((nonce, index), scalar) in olivia_v1.nonces.iter().zip(&indexes).zip(&scalars) {
+            ensure!(
+                Secp256k1::verify_attest_scalar(&public_key, nonce, *index, scalar),
+                "peers produced an invalid attestation"
+            );
+        }
+
+        Ok(AttestationSchemes {
+            olivia_v1: Some(attest::OliviaV1 { scalars }),
+            ..Default::default()
+        })
+    }
+}
+
+fn identity_keypair(seed: &Seed) -> KeyPair {
+    let hash = seed.child(b"identity").to_blake2b_var(32);
+    Secp256k1::keypair_from_secret_bytes(hash.finalize_boxed().borrow())
+}
+
+fn evaluate(polynomial: &[Scalar], index: PeerIndex) -> Scalar<Secret, Zero> {
+    let x = Scalar::from(index);
+    polynomial
+        .iter()
+        .rev()
+        .fold(Scalar::zero(), |acc, coeff| s!(acc * x + coeff))
+}
+
+fn evaluate_commitments(commitments: &[Point], index: PeerIndex) -> Point<Jacobian, Public, Zero> {
+    let x = Scalar::from(index).mark::<Public>();
+    commitments
+        .iter()
+        .rev()
+        .fold(Point::zero().mark::<Jacobian>(), |acc, commitment| {
+            g!(x * acc + commitment)
+        })
+}
+
+fn lagrange_coefficient(
+    index: PeerIndex,
+    indexes: impl Iterator<Item = PeerIndex>,
+) -> Scalar<Public, Zero> {
+    let x_i = Scalar::from(index).mark::<Public>();
+    let (numerator, denominator) = indexes.filter(|j| *j != index).fold(
+        (
+            Scalar::one().mark::<(Public, Zero)>(),
+            Scalar::one().mark::<(Public, Zero)>(),
+        ),
+        |(numerator, denominator), j| {
+            let x_j = Scalar::from(j).mark::<Public>();
+            (
+                s!(numerator * x_j).mark::<Public>(),
+                s!(denominator * (x_j - x_i)).mark::<Public>(),
+            )
+        },
+    );
+    let denominator = denominator.mark::<NonZero>().expect("indexes are distinct");
+    s!(numerator * { denominator.invert() }).mark::<Public>()
+}
+
+type BindingFactors = BTreeMap<PeerIndex, Scalar<Public, Zero>>;
+
+/// The FROST group commitment `R` and each signer's binding factor.
+fn group_commitment(
+    kind: SignatureKind,
+    message: &[u8],
+    nonces: &BTreeMap<PeerIndex, NonceCommitment>,
+) -> anyhow::Result<(Point<Normal, Public, NonZero>, BindingFactors)> {
+    let mut hash = VarBlake2b::new(32).expect("32 is a valid output length");
+    hash.update(kind.tag().as_bytes());
+    hash.update(message);
+    for (index, nonce) in nonces {
+        hash.update(index.to_be_bytes());
+        hash.update(nonce.D.to_bytes());
+        hash.update(nonce.E.to_bytes());
+    }
+    let mut R = Point::zero().mark::<Jacobian>();
+    let mut binding_factors = BTreeMap::new();
+    for (index, nonce) in nonces {
+        let mut hash = hash.clone();
+        hash.update(b"binding");
+        hash.update(index.to_be_bytes());
+        let rho = Scalar::from_slice_mod_order(hash.finalize_boxed().borrow())
+            .expect("hash output is 32-bytes long")
+            .mark::<Public>();
+        R = g!(R + { nonce.D } + rho * { nonce.E });
+        binding_factors.insert(*index, rho);
+    }
+    let R = R
+        .mark::<(Normal, NonZero)>()
+        .ok_or_else(|| anyhow!("nonces added up to zero"))?;
+    Ok((R, binding_factors))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        db::{in_memory::InMemory, Db},
+        oracle::Oracle,
+    };
+    use core::str::FromStr;
+    use olivia_core::{chrono::NaiveDateTime, StampedOutcome};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::TempDir;
+
+    /// A peer that can be taken offline.
+    struct Switch {
+        peer: Arc<ThresholdPeer>,
+        online: AtomicBool,
+    }
+
+    impl Switch {
+        fn check(&self) -> anyhow::Result<()> {
+            ensure!(self.online.load(Ordering::SeqCst), "offline");
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl PeerClient for Switch {
+        async fn deal(&self, deal: &Deal) -> anyhow::Result<Dealing> {
+            self.check()?;
+            self.peer.deal(deal).await
+        }
+
+        async fn receive_dealings(
+            &self,
+            deal: &Deal,
+            dealings: &[Dealing],
+        ) -> anyhow::Result<Vec<XOnly>> {
+            self.check()?;
+            self.peer.receive_dealings(deal, dealings).await
+        }
+
+        async fn announcement_nonce(&self) -> anyhow::Result<NonceCommitment> {
+            self.check()?;
+            self.peer.announcement_nonce().await
+        }
+
+        async fn sign_with_announcement_key(
+            &self,
+            kind: SignatureKind,
+            message: &[u8],
+            nonces: &BTreeMap<PeerIndex, NonceCommitment>,
+        ) -> anyhow::Result<Scalar<Public, Zero>> {
+            self.check()?;
+            self.peer
+                .sign_with_announcement_key(kind, message, nonces)
+                .await
+        }
+
+        async fn reveal_olivia_v1(
+            &self,
+            event_id: &EventId,
+            outcome: &str,
+        ) -> anyhow::Result<Vec<Scalar<Public, Zero>>> {
+            self.check()?;
+            self.peer.reveal_olivia_v1(event_id, outcome).await
+        }
+    }
+
+    fn state_file(dir: &TempDir, index: PeerIndex) -> std::path::PathBuf {
+        dir.path().join(format!("peer-{}.state", index))
+    }
+
+    fn new_peer(params: ThresholdParams, index: PeerIndex, state_file: &Path) -> ThresholdPeer {
+        let identities = (1..=params.n_peers)
+            .map(|i| ThresholdPeer::identity_key(&Seed::new([i as u8; 64])))
+            .collect();
+        ThresholdPeer::new(
+            index,
+            Seed::new([index as u8; 64]),
+            params,
+            identities,
+            state_file,
+        )
+        .unwrap()
+        .with_successor(Successor {
+            keys: Secp256k1::test_oracle_keys(),
+            namespaces: vec![],
+        })
+    }
+
+    /// The peers' state files are in the returned directory which is removed when it's dropped.
+    async fn setup(params: ThresholdParams) -> (TempDir, Vec<Arc<Switch>>, ThresholdSigner) {
+        let dir = TempDir::new().unwrap();
+        let switches = (1..=params.n_peers)
+            .map(|index| {
+                let peer = new_peer(params, index, &state_file(&dir, index));
+                Arc::new(Switch {
+                    peer: Arc::new(peer),
+                    online: AtomicBool::new(true),
+                })
+            })
+            .collect::<Vec<_>>();
+        let peers = switches
+            .iter()
+            .map(|switch| switch.clone() as Arc<dyn PeerClient>)
+            .collect();
+        let signer = ThresholdSigner::setup(params, peers).await.unwrap();
+        (dir, switches, signer)
+    }
+
+    #[test]
+    fn lagrange_interpolation() {
+        let poly = (1..=3u32)
+            .map(|i| Scalar::from(i).mark::<NonZero>().unwrap())
+            .collect::<Vec<_>>();
+        for indexes in [[1, 2, 3], [2, 4, 5], [5, 1, 3]] {
+            let secret = indexes.iter().fold(Scalar::zero(), |acc, index| {
+                let lambda = lagrange_coefficient(*index, indexes.iter().copied());
+                s!(acc + lambda * { evaluate(&poly, *index) })
+            });
+            assert_eq!(secret, poly[0]);
+        }
+    }
+
+    #[tokio::test]
+    async fn threshold_oracle_lifecycle() {
+        let params = ThresholdParams {
+            threshold: 2,
+            n_peers: 3,
+        };
+        let (_dir, peers, signer) = setup(params).await;
+        let db: Arc<dyn Db<Secp256k1>> = Arc::new(InMemory::default());
+        let oracle = Oracle::with_signer(Arc::new(signer), db.clone())
+            .await
+            .unwrap();
+        let public_keys = oracle.public_keys();
+        let time = NaiveDateTime::from_str("2021-10-05T05:00:00").unwrap();
+
+        for (id, outcome) in [
+            ("/foo/bar/baz.occur", Some("true")),
+            ("/x/BitMEX/BXBT/2021-10-05T05:00:00.price?n=8", Some("42")),
+            ("/s/EPL/match/2021-08-13/BRE_ARS.vs", None),
+        ] {
+            let event_id = EventId::from_str(id).unwrap();
+            oracle.add_event(event_id.clone().into()).await.unwrap();
+            let oracle_event = db
+                .get_announced_event(&event_id)
+                .await
+                .unwrap()
+                .expect("event should be there")
+                .announcement
+                .verify_against_id(&event_id, &public_keys.announcement)
+                .expect("announcement signature should be valid");
+
+            // only a threshold of peers is needed to attest
+            peers[0].online.store(false, Ordering::SeqCst);
+            match outcome {
+                Some(outcome) => oracle
+                    .complete_event(StampedOutcome {
+                        outcome: Outcome::try_from_id_and_outcome(event_id.clone(), outcome)
+                            .unwrap(),
+                        time,
+                    })
+                    .await
+                    .unwrap(),
+                None => oracle.cancel_event(&event_id, time).await.unwrap(),
+            };
+            peers[0].online.store(true, Ordering::SeqCst);
+
+            let attestation = db
+                .get_announced_event(&event_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .attestation
+                .expect("should be attested to");
+            assert_eq!(
+                attestation.verify_olivia_v1_attestation(&oracle_event, &public_keys),
+                Ok(())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn threshold_nonce_batches_and_successions() {
+        let params = ThresholdParams {
+            threshold: 2,
+            n_peers: 3,
+        };
+        let (_dir, _, signer) = setup(params).await;
+        let keys = signer.oracle_public_keys();
+        let schedule = BatchSchedule {
+            path: olivia_core::Path::from_str("/x/BitMEX/BXBT").unwrap(),
+            event_kind: olivia_core::EventKind::from_str("price?n=2").unwrap(),
+            start: NaiveDateTime::from_str("2021-10-05T05:00:00").unwrap(),
+            interval: 60,
+            n_slots: 2,
+            epoch: 0,
+        };
+        let batch = signer.create_nonce_batch(schedule.clone()).await.unwrap();
+        assert_eq!(
+            batch.verify(&keys.announcement).map(|batch| batch.schedule),
+            Some(schedule)
+        );
+        assert!(
+            !Secp256k1::verify_announcement_signature(
+                &keys.announcement,
+                batch.batch.as_bytes(),
+                &batch.signature
+            ),
+            "a nonce batch signature isn't an announcement signature"
+        );
+
+        let succession = |keys: &OracleKeys<Secp256k1>, epoch: u32| KeySuccession {
+            keys: OracleKeys {
+                epoch,
+                ..keys.clone()
+            },
+            time: NaiveDateTime::from_str("2021-10-05T05:00:00").unwrap(),
+            namespaces: vec![],
+        };
+        let successor = Secp256k1::test_oracle_keys();
+        assert!(
+            signer
+                .sign_key_succession(succession(&keys, 1))
+                .await
+                .is_err(),
+            "peers only hand over to their configured successor"
+        );
+        let signed = signer
+            .sign_key_succession(succession(&successor, 1))
+            .await
+            .unwrap();
+        assert_eq!(
+            signed.verify(&keys).map(|succession| succession.keys),
+            Some(succession(&successor, 1).keys)
+        );
+        assert!(
+            signer
+                .sign_key_succession(succession(&successor, 2))
+                .await
+                .is_err(),
+            "peers only hand over once"
+        );
+    }
+
+    #[tokio::test]
+    async fn not_enough_peers() {
+        let params = ThresholdParams {
+            threshold: 2,
+            n_peers: 3,
+        };
+        let (_dir, peers, signer) = setup(params).await;
+        let event_id = EventId::from_str("/foo/bar/baz.occur").unwrap();
+        let oracle_event = signer
+            .create_announcement(event_id.clone().into())
+            .await
+            .unwrap()
+            .verify_against_id(&event_id, &signer.oracle_public_keys().announcement)
+            .unwrap();
+        let outcome = Outcome::try_from_id_and_outcome(event_id, "true").unwrap();
+        peers[0].online.store(false, Ordering::SeqCst);
+        peers[2].online.store(false, Ordering::SeqCst);
+        assert!(signer.attest(&oracle_event, Some(&outcome)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn peer_only_reveals_one_outcome() {
+        let params = ThresholdParams {
+            threshold: 2,
+            n_peers: 2,
+        };
+        let (dir, peers, signer) = setup(params).await;
+        let event_id = EventId::from_str("/foo/bar/baz.occur").unwrap();
+        signer
+            .create_announcement(event_id.clone().into())
+            .await
+            .unwrap();
+        let peer = &peers[0].peer;
+        let revealed = peer.reveal_olivia_v1(&event_id, "true").await.unwrap();
+        assert_eq!(
+            peer.reveal_olivia_v1(&event_id, "true").await.unwrap(),
+            revealed
+        );
+        assert!(peer.reveal_olivia_v1(&event_id, "false").await.is_err());
+        assert!(peer
+            .reveal_olivia_v1(&event_id, VOID_OUTCOME)
+            .await
+            .is_err());
+
+        // the shares and what was revealed are still there after a restart
+        let restarted = new_peer(params, 1, &state_file(&dir, 1));
+        assert_eq!(
+            restarted.reveal_olivia_v1(&event_id, "true").await.unwrap(),
+            revealed
+        );
+        assert!(restarted
+            .reveal_olivia_v1(&event_id, "false")
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn threshold_must_be_a_majority() {
+        for (threshold, n_peers, ok) in [
+            (2, 3, true),
+            (3, 4, true),
+            (1, 1, true),
+            (2, 4, false),
+            (1, 2, false),
+            (0, 0, false),
+            (4, 3, false),
+        ] {
+            assert_eq!(
+                ThresholdParams { threshold, n_peers }.check().is_ok(),
+                ok,
+                "{} of {}",
+                threshold,
+                n_peers
+            );
+        }
+    }
+}
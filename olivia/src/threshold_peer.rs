@@ -0,0 +1,321 @@
+//! Runs each [`ThresholdPeer`] on its own machine.
+//!
+//! The oracle reaches each peer with a [`RemotePeer`] which sends requests over TCP to the
+//! [`PeerDaemon`] serving it. Every peer shares an `auth-key` with the oracle and each request is
+//! sent as the hex HMAC-SHA256 of the request keyed with it, a space and then the request so only
+//! the oracle can ask a peer to sign or reveal anything. Replaying a request doesn't get a peer to
+//! do anything it didn't do the first time.
+//!
+//! Each request and response is a line of JSON.
+use crate::threshold::{Deal, Dealing, NonceCommitment, PeerClient, PeerIndex, ThresholdPeer};
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use olivia_core::{EventId, SignatureKind};
+use olivia_secp256k1::fun::{marker::*, Scalar, XOnly};
+use sha2::Sha256;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// How long to wait for a peer to respond before giving up on it.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "method")]
+enum Request {
+    Deal {
+        deal: Deal,
+    },
+    ReceiveDealings {
+        deal: Deal,
+        dealings: Vec<Dealing>,
+    },
+    AnnouncementNonce,
+    SignWithAnnouncementKey {
+        kind: SignatureKind,
+        /// hex encoded
+        message: String,
+        nonces: Vec<(PeerIndex, NonceCommitment)>,
+    },
+    RevealOliviaV1 {
+        event_id: EventId,
+        outcome: String,
+    },
+}
+
+type Response = Result<serde_json::Value, String>;
+
+fn mac(auth_key: &str, request: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(auth_key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(request.as_bytes());
+    mac
+}
+
+/// A [`PeerClient`] for a [`PeerDaemon`] listening on `address`.
+pub struct RemotePeer {
+    address: String,
+    auth_key: String,
+}
+
+impl RemotePeer {
+    /// `address` is the `host:port` the peer listens on.
+    pub fn new(address: impl Into<String>, auth_key: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            auth_key: auth_key.into(),
+        }
+    }
+
+    async fn request<T: serde::de::DeserializeOwned>(
+        &self,
+        request: &Request,
+    ) -> anyhow::Result<T> {
+        tokio::time::timeout(REQUEST_TIMEOUT, self.send(request))
+            .await
+            .map_err(|_| anyhow!("peer at {} timed out", self.address))?
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(&self, request: &Request) -> anyhow::Result<T> {
+        let stream = TcpStream::connect(&self.address)
+            .await
+            .with_context(|| format!("connecting to peer at {}", self.address))?;
+        let mut stream = tokio::io::BufStream::new(stream);
+        let request = serde_json::to_string(request)?;
+        let signature = crate::hex::encode(&mac(&self.auth_key, &request).finalize().into_bytes());
+        let line = format!("{} {}\n", signature, request);
+        stream.write_all(line.as_bytes()).await?;
+        stream.flush().await?;
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+        let response: Response = serde_json::from_str(&line)
+            .with_context(|| format!("peer sent an invalid response '{}'", line.trim_end()))?;
+        let value = response.map_err(|e| anyhow!("peer refused: {}", e))?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[async_trait]
+impl PeerClient for RemotePeer {
+    async fn deal(&self, deal: &Deal) -> anyhow::Result<Dealing> {
+        self.request(&Request::Deal { deal: deal.clone() }).await
+    }
+
+    async fn receive_dealings(
+        &self,
+        deal: &Deal,
+        dealings: &[Dealing],
+    ) -> anyhow::Result<Vec<XOnly>> {
+        self.request(&Request::ReceiveDealings {
+            deal: deal.clone(),
+            dealings: dealings.to_vec(),
+        })
+        .await
+    }
+
+    async fn announcement_nonce(&self) -> anyhow::Result<NonceCommitment> {
+        self.request(&Request::AnnouncementNonce).await
+    }
+
+    async fn sign_with_announcement_key(
+        &self,
+        kind: SignatureKind,
+        message: &[u8],
+        nonces: &BTreeMap<PeerIndex, NonceCommitment>,
+    ) -> anyhow::Result<Scalar<Public, Zero>> {
+        self.request(&Request::SignWithAnnouncementKey {
+            kind,
+            message: crate::hex::encode(message),
+            nonces: nonces.clone().into_iter().collect(),
+        })
+        .await
+    }
+
+    async fn reveal_olivia_v1(
+        &self,
+        event_id: &EventId,
+        outcome: &str,
+    ) -> anyhow::Result<Vec<Scalar<Public, Zero>>> {
+        self.request(&Request::RevealOliviaV1 {
+            event_id: event_id.clone(),
+            outcome: outcome.to_string(),
+        })
+        .await
+    }
+}
+
+/// Serves a [`ThresholdPeer`] to the oracle's [`RemotePeer`]s.
+pub struct PeerDaemon {
+    peer: ThresholdPeer,
+    auth_key: String,
+    logger: slog::Logger,
+}
+
+impl PeerDaemon {
+    pub fn new(peer: ThresholdPeer, auth_key: impl Into<String>, logger: slog::Logger) -> Self {
+        Self {
+            peer,
+            auth_key: auth_key.into(),
+            logger,
+        }
+    }
+
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> anyhow::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let daemon = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = daemon.handle_connection(stream).await {
+                    error!(daemon.logger, "peer connection failed"; "error" => e.to_string());
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, stream: TcpStream) -> anyhow::Result<()> {
+        let mut stream = tokio::io::BufStream::new(stream);
+        let mut line = String::new();
+        while stream.read_line(&mut line).await? > 0 {
+            let response: Response = match self.authenticate(line.trim_end()) {
+                Ok(request) => self.handle_request(request).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = &response {
+                warn!(self.logger, "refused request"; "reason" => e);
+            }
+            let mut response = serde_json::to_string(&response)?;
+            response.push('\n');
+            stream.write_all(response.as_bytes()).await?;
+            stream.flush().await?;
+            line.clear();
+        }
+        Ok(())
+    }
+
+    fn authenticate(&self, line: &str) -> Result<Request, String> {
+        let (signature, request) = line
+            .split_once(' ')
+            .ok_or_else(|| "request isn't authenticated".to_string())?;
+        let signature =
+            crate::hex::decode(signature).map_err(|_| "request isn't authenticated".to_string())?;
+        mac(&self.auth_key, request)
+            .verify_slice(&signature)
+            .map_err(|_| "request has an invalid HMAC".to_string())?;
+        serde_json::from_str(request).map_err(|e| format!("invalid request: {}", e))
+    }
+
+    async fn handle_request(&self, request: Request) -> Response {
+        match request {
+            Request::Deal { deal } => to_response(self.peer.deal(&deal).await),
+            Request::ReceiveDealings { deal, dealings } => {
+                to_response(self.peer.receive_dealings(&deal, &dealings).await)
+            }
+            Request::AnnouncementNonce => to_response(self.peer.announcement_nonce().await),
+            Request::SignWithAnnouncementKey {
+                kind,
+                message,
+                nonces,
+            } => {
+                let message = crate::hex::decode(&message).map_err(|e| e.to_string())?;
+                to_response(
+                    self.peer
+                        .sign_with_announcement_key(kind, &message, &nonces.into_iter().collect())
+                        .await,
+                )
+            }
+            Request::RevealOliviaV1 { event_id, outcome } => {
+                info!(self.logger, "revealing"; "event_id" => event_id.as_str(), "outcome" => &outcome);
+                to_response(self.peer.reveal_olivia_v1(&event_id, &outcome).await)
+            }
+        }
+    }
+}
+
+fn to_response<T: serde::Serialize>(result: anyhow::Result<T>) -> Response {
+    result
+        .map(|value| serde_json::to_value(value).expect("peer responses serialize"))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        keychain::Signer,
+        seed::Seed,
+        threshold::{ThresholdParams, ThresholdSigner},
+    };
+    use core::str::FromStr;
+    use olivia_core::{Outcome, VOID_OUTCOME};
+    use tempfile::TempDir;
+
+    async fn start_peer(params: ThresholdParams, index: PeerIndex, dir: &TempDir) -> String {
+        let identities = (1..=params.n_peers)
+            .map(|i| ThresholdPeer::identity_key(&Seed::new([i as u8; 64])))
+            .collect();
+        let peer = ThresholdPeer::new(
+            index,
+            Seed::new([index as u8; 64]),
+            params,
+            identities,
+            &dir.path().join(format!("peer-{}.state", index)),
+        )
+        .unwrap();
+        let daemon = PeerDaemon::new(
+            peer,
+            format!("key-{}", index),
+            slog::Logger::root(slog::Discard, o!()),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(Arc::new(daemon).serve(listener));
+        address
+    }
+
+    #[tokio::test]
+    async fn remote_peers() {
+        let params = ThresholdParams {
+            threshold: 2,
+            n_peers: 3,
+        };
+        let dir = TempDir::new().unwrap();
+        let mut addresses = vec![];
+        for index in 1..=params.n_peers {
+            addresses.push(start_peer(params, index, &dir).await);
+        }
+        let peers = addresses
+            .iter()
+            .enumerate()
+            .map(|(i, address)| {
+                Arc::new(RemotePeer::new(address.clone(), format!("key-{}", i + 1)))
+                    as Arc<dyn PeerClient>
+            })
+            .collect();
+        let signer = ThresholdSigner::setup(params, peers).await.unwrap();
+        let public_keys = signer.oracle_public_keys();
+
+        let event_id = EventId::from_str("/foo/bar/baz.occur").unwrap();
+        let oracle_event = signer
+            .create_announcement(event_id.clone().into())
+            .await
+            .unwrap()
+            .verify_against_id(&event_id, &public_keys.announcement)
+            .expect("announcement signature should be valid");
+        let outcome = Outcome::try_from_id_and_outcome(event_id.clone(), "true").unwrap();
+        let attestation = signer.attest(&oracle_event, Some(&outcome)).await.unwrap();
+        assert!(attestation.olivia_v1.is_some());
+
+        // someone without the auth key can't get a peer to reveal anything
+        let peer = RemotePeer::new(addresses[0].clone(), "key-1");
+        let impostor = RemotePeer::new(addresses[0].clone(), "key-2");
+        assert!(peer.reveal_olivia_v1(&event_id, "true").await.is_ok());
+        assert!(impostor.reveal_olivia_v1(&event_id, "true").await.is_err());
+        assert!(peer
+            .reveal_olivia_v1(&event_id, VOID_OUTCOME)
+            .await
+            .is_err());
+    }
+}
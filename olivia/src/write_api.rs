@@ -0,0 +1,398 @@
+//! Posting events and outcomes to the REST API.
+//!
+//! `POST /events` takes an [`Event`] and `POST /outcomes` takes an outcome like
+//! `{"id": "/NBA/match/2021-06-20/MIL_BKN.winner", "outcome": "MIL"}`, or an array of either. Each
+//! request has to be authorized by a [`WriteCredential`] that covers the path of every event in it.
+use crate::{
+    rest_api::ErrorMessage,
+    sources::http::{Write, WriteResult, WriteSender},
+};
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use olivia_core::{Event, EventId, Path, StampedOutcome};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+use std::{convert::Infallible, sync::Arc};
+use subtle::ConstantTimeEq;
+use tokio::sync::oneshot;
+use warp::{
+    http::StatusCode,
+    reply::{self, Reply, Response},
+    Filter,
+};
+
+/// The largest request body accepted.
+const MAX_BODY: u64 = 1024 * 1024;
+
+/// Something that may post events and outcomes under `paths`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WriteCredential {
+    /// Identifies the credential in the logs and in HMAC signed requests.
+    pub name: String,
+    pub auth: WriteAuth,
+    pub paths: Vec<Path>,
+}
+
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WriteAuth {
+    /// Requests have the header `Authorization: Bearer <token>`.
+    Bearer(String),
+    /// Requests have the header `Authorization: HMAC-SHA256 <name>:<signature>` where the signature
+    /// is the hex HMAC-SHA256 of the request body keyed with this.
+    HmacSha256(String),
+}
+
+impl std::fmt::Debug for WriteAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteAuth::Bearer(_) => f.debug_tuple("Bearer").field(&"XXXX").finish(),
+            WriteAuth::HmacSha256(_) => f.debug_tuple("HmacSha256").field(&"XXXX").finish(),
+        }
+    }
+}
+
+impl WriteCredential {
+    fn authorizes(&self, authorization: &str, body: &[u8]) -> bool {
+        match &self.auth {
+            WriteAuth::Bearer(token) => match authorization.strip_prefix("Bearer ") {
+                Some(given) => token.as_bytes().ct_eq(given.trim().as_bytes()).into(),
+                None => false,
+            },
+            WriteAuth::HmacSha256(key) => {
+                let signed = authorization
+                    .strip_prefix("HMAC-SHA256 ")
+                    .and_then(|signed| signed.trim().split_once(':'));
+                match signed {
+                    Some((name, signature)) if name == self.name => {
+                        let signature = match crate::hex::decode(signature) {
+                            Ok(signature) => signature,
+                            Err(_) => return false,
+                        };
+                        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+                            .expect("HMAC takes keys of any length");
+                        mac.update(body);
+                        mac.verify_slice(&signature).is_ok()
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    pub fn allows(&self, id: &EventId) -> bool {
+        self.paths
+            .iter()
+            .any(|path| path.as_path_ref().is_parent_of(id.path()))
+    }
+}
+
+/// Where posted events and outcomes are sent along with who may post them.
+#[derive(Clone)]
+pub struct WriteApi {
+    credentials: Arc<Vec<WriteCredential>>,
+    writes: WriteSender,
+}
+
+impl WriteApi {
+    pub fn new(credentials: Vec<WriteCredential>, writes: WriteSender) -> Self {
+        Self {
+            credentials: Arc::new(credentials),
+            writes,
+        }
+    }
+
+    fn authorize(&self, authorization: &str, body: &[u8]) -> Option<&WriteCredential> {
+        self.credentials
+            .iter()
+            .find(|credential| credential.authorizes(authorization, body))
+    }
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> Response {
+    reply::with_status(
+        reply::json(&ErrorMessage::from_status(status).with_message(message)),
+        status,
+    )
+    .into_response()
+}
+
+/// Parses a single item or an array of them. Returns whether it was an array too.
+fn parse_items<T: DeserializeOwned>(body: &[u8]) -> Result<(Vec<T>, bool), serde_json::Error> {
+    match serde_json::from_slice(body)? {
+        serde_json::Value::Array(items) => Ok((
+            items
+                .into_iter()
+                .map(serde_json::from_value)
+                .collect::<Result<_, _>>()?,
+            true,
+        )),
+        item => Ok((vec![serde_json::from_value(item)?], false)),
+    }
+}
+
+async fn post_writes<T: DeserializeOwned>(
+    api: Option<WriteApi>,
+    authorization: Option<String>,
+    body: Bytes,
+    logger: slog::Logger,
+    id: fn(&T) -> &EventId,
+    to_write: fn(T, oneshot::Sender<WriteResult>) -> Write,
+) -> Response {
+    let api = match api {
+        Some(api) => api,
+        None => {
+            return error(
+                StatusCode::NOT_FOUND,
+                "posting events and outcomes isn't enabled",
+            )
+        }
+    };
+    let credential = match authorization
+        .as_deref()
+        .and_then(|authorization| api.authorize(authorization, &body))
+    {
+        Some(credential) => credential,
+        None => {
+            warn!(logger, "rejected unauthorized write request");
+            return error(
+                StatusCode::UNAUTHORIZED,
+                "missing or invalid Authorization header",
+            );
+        }
+    };
+    let (items, batch) = match parse_items::<T>(&body) {
+        Ok(items) => items,
+        Err(e) => return error(StatusCode::BAD_REQUEST, format!("invalid request: {}", e)),
+    };
+    if let Some(forbidden) = items.iter().map(id).find(|id| !credential.allows(id)) {
+        warn!(logger, "rejected write outside of credential's paths"; "credential" => &credential.name, "event_id" => forbidden.as_str());
+        return error(
+            StatusCode::FORBIDDEN,
+            format!("'{}' may not write to {}", credential.name, forbidden),
+        );
+    }
+    info!(logger, "write request"; "credential" => &credential.name, "items" => items.len());
+
+    let mut results = Vec::with_capacity(items.len());
+    for item in items {
+        let (sender, receiver) = oneshot::channel();
+        if api.writes.send(to_write(item, sender)).is_err() {
+            return error(StatusCode::SERVICE_UNAVAILABLE, "the oracle isn't running");
+        }
+        match receiver.await {
+            Ok(result) => results.push(result),
+            Err(_) => return error(StatusCode::SERVICE_UNAVAILABLE, "the oracle isn't running"),
+        }
+    }
+
+    if batch {
+        reply::json(&results).into_response()
+    } else {
+        let result = results.remove(0);
+        let status = StatusCode::from_u16(result.status).expect("always a valid status");
+        reply::with_status(reply::json(&result), status).into_response()
+    }
+}
+
+pub fn routes(
+    api: Option<WriteApi>,
+    logger: slog::Logger,
+) -> impl Filter<Extract = (Response,), Error = warp::Rejection> + Clone {
+    let request = warp::post()
+        .and(warp::any().map(move || api.clone()))
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::body::content_length_limit(MAX_BODY))
+        .and(warp::body::bytes())
+        .and(warp::any().map(move || logger.clone()));
+
+    let events = warp::path("events")
+        .and(warp::path::end())
+        .and(request.clone())
+        .and_then(|api, authorization, body, logger| async move {
+            Ok::<_, Infallible>(
+                post_writes::<Event>(
+                    api,
+                    authorization,
+                    body,
+                    logger,
+                    |event| &event.id,
+                    |event, result| Write::Event { event, result },
+                )
+                .await,
+            )
+        });
+
+    let outcomes = warp::path("outcomes")
+        .and(warp::path::end())
+        .and(request)
+        .and_then(|api, authorization, body, logger| async move {
+            Ok::<_, Infallible>(
+                post_writes::<StampedOutcome>(
+                    api,
+                    authorization,
+                    body,
+                    logger,
+                    |stamped| &stamped.outcome.id,
+                    |outcome, result| Write::Outcome { outcome, result },
+                )
+                .await,
+            )
+        });
+
+    events.or(outcomes).unify()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn credentials_authorize_and_scope() {
+        let bearer = WriteCredential {
+            name: "feed".into(),
+            auth: WriteAuth::Bearer("s3cret".into()),
+            paths: vec![Path::from_str("/NBA").unwrap()],
+        };
+        assert!(bearer.authorizes("Bearer s3cret", b""));
+        assert!(!bearer.authorizes("Bearer s3cre", b""));
+        assert!(!bearer.authorizes("s3cret", b""));
+        assert!(bearer.allows(&EventId::from_str("/NBA/match/2021-06-20/MIL_BKN.winner").unwrap()));
+        assert!(!bearer.allows(&EventId::from_str("/NBAX/2021-06-20/MIL_BKN.winner").unwrap()));
+
+        let hmac = WriteCredential {
+            name: "bitmex".into(),
+            auth: WriteAuth::HmacSha256("key".into()),
+            paths: vec![Path::root()],
+        };
+        let body = b"The quick brown fox jumps over the lazy dog";
+        // test vector from wikipedia
+        let signature = "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8";
+        assert!(hmac.authorizes(&format!("HMAC-SHA256 bitmex:{}", signature), body));
+        assert!(!hmac.authorizes(&format!("HMAC-SHA256 other:{}", signature), body));
+        assert!(!hmac.authorizes(&format!("HMAC-SHA256 bitmex:{}", signature), b"tampered"));
+        assert!(!hmac.authorizes(&format!("Bearer {}", signature), body));
+        assert!(hmac.allows(&EventId::from_str("/NBA/match/2021-06-20/MIL_BKN.winner").unwrap()));
+    }
+
+    #[tokio::test]
+    async fn write_api_reports_results() {
+        use crate::{db::DbReadOracle, sources::http::WriteResult};
+        use olivia_secp256k1::Secp256k1;
+        use tokio_stream::StreamMap;
+        let db = Arc::new(crate::db::in_memory::InMemory::<Secp256k1>::default());
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let oracle = crate::oracle::Oracle::new(crate::seed::Seed::new([42u8; 64]), db.clone())
+            .await
+            .unwrap();
+        let (write_sender, writes) = crate::sources::http::channel();
+        tokio::spawn(
+            crate::oracle_loop::OracleLoop {
+                events: StreamMap::new(),
+                outcomes: StreamMap::new(),
+                nodes: StreamMap::new(),
+                writes,
+                quorums: Default::default(),
+                oracle,
+                db: db.clone(),
+                logger: logger.clone(),
+            }
+            .start(),
+        );
+        let write_api = WriteApi::new(
+            vec![WriteCredential {
+                name: "feed".into(),
+                auth: WriteAuth::Bearer("s3cret".into()),
+                paths: vec![Path::from_str("/test").unwrap()],
+            }],
+            write_sender,
+        );
+        let routes = crate::rest_api::routes(db.clone(), Some(write_api), logger);
+        let post = |path: &str, auth: &str, body: &str| {
+            warp::test::request()
+                .method("POST")
+                .path(path)
+                .header("authorization", auth)
+                .body(body)
+        };
+
+        let res = post("/events", "Bearer s3cret", r#"{"id": "/test/one.occur"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 201);
+        assert!(db
+            .get_announced_event(&EventId::from_str("/test/one.occur").unwrap())
+            .await
+            .unwrap()
+            .is_some());
+
+        let res = post("/events", "Bearer wrong", r#"{"id": "/test/two.occur"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 401);
+        let res = post("/events", "Bearer s3cret", r#"{"id": "/other/two.occur"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 403);
+        let res = post("/events", "Bearer s3cret", r#"{"idd": "/test/two.occur"}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), 400);
+
+        let res = post(
+            "/outcomes",
+            "Bearer s3cret",
+            r#"[{"id": "/test/one.occur", "outcome": "true"}, {"id": "/test/two.occur", "outcome": "true"}]"#,
+        )
+        .reply(&routes)
+        .await;
+        assert_eq!(res.status(), 200);
+        let results = serde_json::from_slice::<Vec<WriteResult>>(res.body()).unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result.status)
+                .collect::<Vec<_>>(),
+            vec![200, 404]
+        );
+        assert_eq!(
+            results[1].result,
+            "the event being attested to does not exist"
+        );
+
+        let res = post(
+            "/outcomes",
+            "Bearer s3cret",
+            r#"{"id": "/test/one.occur", "outcome": "false"}"#,
+        )
+        .reply(&routes)
+        .await;
+        assert_eq!(
+            res.status(),
+            400,
+            "invalid outcomes aren't sent to the oracle"
+        );
+
+        let price = "/test/price.price?n=4";
+        let res = post(
+            "/events",
+            "Bearer s3cret",
+            &format!(r#"{{"id": "{}"}}"#, price),
+        )
+        .reply(&routes)
+        .await;
+        assert_eq!(res.status(), 201);
+        for (outcome, status) in [("3", 200), ("3", 200), ("4", 409)] {
+            let res = post(
+                "/outcomes",
+                "Bearer s3cret",
+                &format!(r#"{{"id": "{}", "outcome": "{}"}}"#, price, outcome),
+            )
+            .reply(&routes)
+            .await;
+            assert_eq!(res.status(), status);
+        }
+    }
+}
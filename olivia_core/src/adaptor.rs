@@ -0,0 +1,388 @@
+use crate::{Attestation, EventId, EventKind, Group, OracleEvent, OracleKeys, Outcome};
+
+/// The anticipated `olivia_v1` attestation for every outcome whose attestation indexes start with
+/// `prefix`.
+///
+/// A DLC client uses `point` as the adaptor point for the CET that pays out on those outcomes.
+/// Once the oracle attests [`Attestation::olivia_v1_adaptor_secret`] gives its discrete log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptorPoint<C: Group> {
+    pub prefix: Vec<u32>,
+    pub point: C::AnticipatedAttestation,
+}
+
+/// The minimal set of attestation index prefixes that covers every outcome between `start` and
+/// `end` (inclusive).
+///
+/// The prefixes are never empty. An empty prefix would need no attestation at all (not even one
+/// voiding the event) so a range covering every outcome gets one prefix for each first digit.
+///
+/// Signed price events are ordered by their signed value. Values that can't be represented by the
+/// event's digits are clamped the same way they are when attested to. Returns `None` if `start`
+/// and `end` are outcomes of different events.
+pub fn outcome_range_prefixes(start: &Outcome, end: &Outcome) -> Option<Vec<Vec<u32>>> {
+    if start.id != end.id {
+        return None;
+    }
+    let id = &start.id;
+    let bases = nonce_bases(id);
+    let indexes = |value| {
+        Outcome {
+            id: id.clone(),
+            value,
+        }
+        .attestation_indexes()
+    };
+    let mut prefixes = vec![];
+
+    match id.event_kind() {
+        EventKind::Price {
+            is_signed: true,
+            n_digits,
+            ..
+        } if n_digits > 0 => {
+            let (start, end) = (start.value as i64, end.value as i64);
+            // negative values have larger attestation indexes the further they are from zero so
+            // they are covered separately with their ends swapped.
+            if start < 0 && start <= end {
+                let mut lo = indexes(end.min(-1) as u64);
+                if end >= -1 {
+                    // "-0" is never attested to so it can be covered too if it helps
+                    lo[1..].iter_mut().for_each(|digit| *digit = 0);
+                }
+                let hi = indexes(start as u64);
+                cover_range(&lo, &hi, &bases, &mut vec![], &mut prefixes);
+            }
+            if end >= 0 && start <= end {
+                let lo = indexes(start.max(0) as u64);
+                let hi = indexes(end as u64);
+                cover_range(&lo, &hi, &bases, &mut vec![], &mut prefixes);
+            }
+        }
+        _ => {
+            if start.value <= end.value {
+                let (lo, hi) = (indexes(start.value), indexes(end.value));
+                cover_range(&lo, &hi, &bases, &mut vec![], &mut prefixes);
+            }
+        }
+    }
+
+    Some(prefixes)
+}
+
+/// The minimal set of attestation index prefixes that covers exactly the given outcomes.
+///
+/// Like [`outcome_range_prefixes`] the prefixes are never empty. Returns `None` if the outcomes are not all from the same event.
+pub fn outcome_set_prefixes(outcomes: &[Outcome]) -> Option<Vec<Vec<u32>>> {
+    let id = match outcomes.first() {
+        Some(outcome) => &outcome.id,
+        None => return Some(vec![]),
+    };
+    if outcomes.iter().any(|outcome| &outcome.id != id) {
+        return None;
+    }
+    let mut indexes = outcomes
+        .iter()
+        .map(Outcome::attestation_indexes)
+        .collect::<Vec<_>>();
+    indexes.sort();
+    indexes.dedup();
+    let suffixes = indexes.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    let mut prefixes = vec![];
+    cover_set(&suffixes, &nonce_bases(id), &mut vec![], &mut prefixes);
+    Some(prefixes)
+}
+
+fn nonce_bases(id: &EventId) -> Vec<u32> {
+    (0..id.n_nonces() as usize)
+        .map(|i| id.n_outcomes_for_nonce(i))
+        .collect()
+}
+
+/// Pushes `prefix` onto `out` unless it's empty in which case it's split up by the first digit.
+fn push_prefix(prefix: &[u32], bases: &[u32], out: &mut Vec<Vec<u32>>) {
+    match (prefix.is_empty(), bases.first()) {
+        (true, Some(base)) => out.extend((0..*base).map(|digit| vec![digit])),
+        (true, None) => {}
+        (false, _) => out.push(prefix.to_vec()),
+    }
+}
+
+/// Pushes the prefixes covering every digit string between `lo` and `hi` (inclusive) onto `out`.
+fn cover_range(
+    lo: &[u32],
+    hi: &[u32],
+    bases: &[u32],
+    prefix: &mut Vec<u32>,
+    out: &mut Vec<Vec<u32>>,
+) {
+    let is_min = lo.iter().all(|digit| *digit == 0);
+    let is_max = hi
+        .iter()
+        .zip(bases)
+        .all(|(digit, base)| *digit + 1 == *base);
+    if is_min && is_max {
+        push_prefix(prefix, bases, out);
+        return;
+    }
+
+    let (first_lo, first_hi) = (lo[0], hi[0]);
+    if first_lo == first_hi {
+        prefix.push(first_lo);
+        cover_range(&lo[1..], &hi[1..], &bases[1..], prefix, out);
+        prefix.pop();
+        return;
+    }
+
+    let rest = &bases[1..];
+    let max = rest.iter().map(|base| base - 1).collect::<Vec<_>>();
+    let min = vec![0; rest.len()];
+
+    prefix.push(first_lo);
+    cover_range(&lo[1..], &max, rest, prefix, out);
+    prefix.pop();
+    for digit in first_lo + 1..first_hi {
+        prefix.push(digit);
+        out.push(prefix.clone());
+        prefix.pop();
+    }
+    prefix.push(first_hi);
+    cover_range(&min, &hi[1..], rest, prefix, out);
+    prefix.pop();
+}
+
+/// Pushes the prefixes covering exactly `suffixes` (which must be sorted and unique) onto `out`.
+fn cover_set(suffixes: &[&[u32]], bases: &[u32], prefix: &mut Vec<u32>, out: &mut Vec<Vec<u32>>) {
+    let n_possible = bases
+        .iter()
+        .try_fold(1u64, |n, base| n.checked_mul(*base as u64));
+    if n_possible == Some(suffixes.len() as u64) {
+        push_prefix(prefix, bases, out);
+        return;
+    }
+
+    let mut rest = suffixes;
+    while let Some(first) = rest.first() {
+        let digit = first[0];
+        let len = rest
+            .iter()
+            .position(|suffix| suffix[0] != digit)
+            .unwrap_or(rest.len());
+        let group = rest[..len]
+            .iter()
+            .map(|suffix| &suffix[1..])
+            .collect::<Vec<_>>();
+        prefix.push(digit);
+        cover_set(&group, &bases[1..], prefix, out);
+        prefix.pop();
+        rest = &rest[len..];
+    }
+}
+
+impl<C: Group> OracleEvent<C> {
+    /// The `olivia_v1` adaptor points for the outcomes between `start` and `end` (inclusive).
+    ///
+    /// See [`outcome_range_prefixes`] for how the range is split up. Returns `None` if the event
+    /// or the keys have no `olivia_v1` scheme or the outcomes are not for this event.
+    pub fn adaptor_points_for_range_olivia_v1(
+        &self,
+        oracle_keys: &OracleKeys<C>,
+        start: &Outcome,
+        end: &Outcome,
+    ) -> Option<Vec<AdaptorPoint<C>>> {
+        if start.id != self.event.id {
+            return None;
+        }
+        let prefixes = outcome_range_prefixes(start, end)?;
+        self.adaptor_points_olivia_v1(oracle_keys, prefixes)
+    }
+
+    /// The `olivia_v1` adaptor points for exactly the outcomes in `outcomes`.
+    ///
+    /// Returns `None` if the event or the keys have no `olivia_v1` scheme or the outcomes are not
+    /// for this event.
+    pub fn adaptor_points_for_outcomes_olivia_v1(
+        &self,
+        oracle_keys: &OracleKeys<C>,
+        outcomes: &[Outcome],
+    ) -> Option<Vec<AdaptorPoint<C>>> {
+        if outcomes.iter().any(|outcome| outcome.id != self.event.id) {
+            return None;
+        }
+        let prefixes = outcome_set_prefixes(outcomes)?;
+        self.adaptor_points_olivia_v1(oracle_keys, prefixes)
+    }
+
+    fn adaptor_points_olivia_v1(
+        &self,
+        oracle_keys: &OracleKeys<C>,
+        prefixes: Vec<Vec<u32>>,
+    ) -> Option<Vec<AdaptorPoint<C>>> {
+        let public_key = oracle_keys.olivia_v1.as_ref()?;
+        let n_nonces = self.schemes.olivia_v1.as_ref()?.nonces.len();
+        if n_nonces != self.event.id.n_nonces() as usize {
+            return None;
+        }
+        let anticipated = (0..n_nonces)
+            .map(|i| self.anticipate_attestations_olivia_v1(public_key, i))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(
+            prefixes
+                .into_iter()
+                .map(|prefix| {
+                    let points = prefix
+                        .iter()
+                        .enumerate()
+                        .map(|(i, index)| anticipated[i][*index as usize].clone())
+                        .collect::<Vec<_>>();
+                    AdaptorPoint {
+                        point: C::combine_anticipated_attestations(&points),
+                        prefix,
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<C: Group> Attestation<C> {
+    /// Combines the `olivia_v1` scalars into the secret for the adaptor point of `prefix`.
+    ///
+    /// Returns `None` if the attested outcome isn't covered by `prefix` (the CET can't be
+    /// completed), `prefix` is empty or there are no `olivia_v1` scalars.
+    pub fn olivia_v1_adaptor_secret(
+        &self,
+        event_id: &EventId,
+        prefix: &[u32],
+    ) -> Option<C::AttestScalar> {
+        if self.is_void() || prefix.is_empty() {
+            return None;
+        }
+        let outcome = Outcome::try_from_id_and_outcome(event_id.clone(), &self.outcome).ok()?;
+        if !outcome.attestation_indexes().starts_with(prefix) {
+            return None;
+        }
+        let scalars = &self.schemes.olivia_v1.as_ref()?.scalars;
+        Some(C::combine_attest_scalars(scalars.get(..prefix.len())?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::str::FromStr;
+
+    fn outcome(id: &str, outcome: &str) -> Outcome {
+        Outcome::try_from_id_and_outcome(EventId::from_str(id).unwrap(), outcome).unwrap()
+    }
+
+    fn range(id: &str, start: &str, end: &str) -> Vec<Vec<u32>> {
+        outcome_range_prefixes(&outcome(id, start), &outcome(id, end)).unwrap()
+    }
+
+    /// Checks the prefixes cover exactly the values in the range by brute force.
+    fn covers_exactly(id: &str, prefixes: &[Vec<u32>], values: impl Iterator<Item = (i64, bool)>) {
+        let id = EventId::from_str(id).unwrap();
+        for (value, in_range) in values {
+            let indexes = Outcome {
+                id: id.clone(),
+                value: value as u64,
+            }
+            .attestation_indexes();
+            let n_covering = prefixes
+                .iter()
+                .filter(|prefix| indexes.starts_with(prefix))
+                .count();
+            assert_eq!(n_covering, in_range as usize, "value {}", value);
+        }
+    }
+
+    #[test]
+    fn binary_range() {
+        let id = "/x/BitMEX/BXBT/2021-10-05T05:00:00.price?n=4";
+        assert_eq!(
+            range(id, "3", "12"),
+            vec![vec![0, 0, 1, 1], vec![0, 1], vec![1, 0], vec![1, 1, 0, 0]]
+        );
+        // the whole range still needs an attestation to one of the outcomes
+        assert_eq!(range(id, "0", "15"), vec![vec![0], vec![1]]);
+        assert_eq!(range(id, "8", "100"), vec![vec![1]]);
+        assert_eq!(range(id, "5", "5"), vec![vec![0, 1, 0, 1]]);
+        assert!(range(id, "6", "5").is_empty());
+        for (start, end) in [(0, 7), (1, 14), (4, 11), (9, 9)] {
+            let prefixes = range(id, &start.to_string(), &end.to_string());
+            covers_exactly(id, &prefixes, (0..16).map(|v| (v, start <= v && v <= end)));
+        }
+    }
+
+    #[test]
+    fn base_10_range() {
+        let id = "/x/BitMEX/BXBT/2021-10-05T05:00:00.price?n=3&base=10";
+        assert_eq!(
+            range(id, "95", "210"),
+            vec![
+                vec![0, 9, 5],
+                vec![0, 9, 6],
+                vec![0, 9, 7],
+                vec![0, 9, 8],
+                vec![0, 9, 9],
+                vec![1],
+                vec![2, 0],
+                vec![2, 1, 0]
+            ]
+        );
+    }
+
+    #[test]
+    fn signed_range() {
+        let id = "/x/BitMEX/BXBT/2021-10-05T05:00:00.price?n=3&signed";
+        let prefixes = range(id, "-5", "2");
+        covers_exactly(id, &prefixes, (-7..8).map(|v| (v, (-5..=2).contains(&v))));
+        assert_eq!(
+            prefixes,
+            vec![vec![1, 0], vec![1, 1, 0], vec![0, 0, 0], vec![0, 0, 1, 0]]
+        );
+        assert_eq!(range(id, "-7", "-1"), vec![vec![1]]);
+        assert_eq!(range(id, "0", "7"), vec![vec![0]]);
+        assert_eq!(range(id, "-7", "7"), vec![vec![1], vec![0]]);
+    }
+
+    #[test]
+    fn enum_range_and_set() {
+        let id = "/s/EPL/match/2021-08-13/BRE_ARS.vs";
+        assert_eq!(range(id, "BRE_win", "ARS_win"), vec![vec![0], vec![1]]);
+        let set = outcome_set_prefixes(&[outcome(id, "draw"), outcome(id, "BRE_win")]).unwrap();
+        assert_eq!(set, vec![vec![0], vec![2]]);
+        let all = ["BRE_win", "ARS_win", "draw"].map(|o| outcome(id, o));
+        assert_eq!(
+            outcome_set_prefixes(&all).unwrap(),
+            vec![vec![0], vec![1], vec![2]]
+        );
+    }
+
+    #[test]
+    fn digit_set() {
+        let id = "/x/BitMEX/BXBT/2021-10-05T05:00:00.price?n=3";
+        let values = [0, 1, 2, 3, 5, 5, 7];
+        let outcomes = values
+            .iter()
+            .map(|v| outcome(id, &v.to_string()))
+            .collect::<Vec<_>>();
+        let prefixes = outcome_set_prefixes(&outcomes).unwrap();
+        assert_eq!(prefixes, vec![vec![0], vec![1, 0, 1], vec![1, 1, 1]]);
+        covers_exactly(id, &prefixes, (0..8).map(|v| (v, values.contains(&v))));
+        let all = (0..8)
+            .map(|v| outcome(id, &v.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(outcome_set_prefixes(&all).unwrap(), vec![vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn mismatched_events() {
+        let a = outcome("/foo/bar.price?n=3", "1");
+        let b = outcome("/foo/baz.price?n=3", "2");
+        assert_eq!(outcome_range_prefixes(&a, &b), None);
+        assert_eq!(outcome_set_prefixes(&[a, b]), None);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{Attestation, Descriptor, Event, EventId, Group};
+use crate::{
+    tlv::{self, TlvReader, TlvWriter},
+    Attestation, Descriptor, Event, EventId, Group, SignatureKind,
+};
 use chrono::NaiveDateTime;
 use core::{convert::TryFrom, marker::PhantomData};
 
@@ -24,7 +27,8 @@ impl<C: Group> RawOracleEvent<C> {
         oracle_public_key: &C::PublicKey,
         announcement_signature: &C::Signature,
     ) -> bool {
-        C::verify_announcement_signature(
+        C::verify_announcement_key_signature(
+            self.signature_kind(),
             oracle_public_key,
             self.payload.as_bytes(),
             announcement_signature,
@@ -36,7 +40,15 @@ impl<C: Group> RawOracleEvent<C> {
     }
 
     pub fn sign(&self, keypair: &C::KeyPair) -> C::Signature {
-        C::sign_announcement(keypair, self.payload.as_bytes())
+        C::sign_with_announcement_key(self.signature_kind(), keypair, self.payload.as_bytes())
+    }
+
+    /// TLV encoded events are signed the way the DLC spec says.
+    pub fn signature_kind(&self) -> SignatureKind {
+        match self.payload {
+            RawOracleEventEncoding::Json(_) => SignatureKind::Announcement,
+            RawOracleEventEncoding::Tlv(_) => SignatureKind::DlcAnnouncement,
+        }
     }
 
     pub fn as_bytes(&self) -> &[u8] {
@@ -49,12 +61,36 @@ impl<C: Group> RawOracleEvent<C> {
             curve: PhantomData,
         }
     }
+
+    /// Interprets the bytes as a dlcspecs `oracle_event` TLV.
+    pub fn from_tlv_bytes(bytes: Vec<u8>) -> Self {
+        Self {
+            payload: RawOracleEventEncoding::Tlv(bytes),
+            curve: PhantomData,
+        }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        match self.payload {
+            RawOracleEventEncoding::Json(_) => Encoding::Json,
+            RawOracleEventEncoding::Tlv(_) => Encoding::Tlv,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Encoding {
+    Json,
+    /// The dlcspecs `oracle_event` TLV
+    Tlv,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "kebab-case", tag = "encoding", content = "data")]
 enum RawOracleEventEncoding {
     Json(String),
+    Tlv(#[serde(with = "crate::hex::serde_bytes")] Vec<u8>),
 }
 
 impl RawOracleEventEncoding {
@@ -62,6 +98,7 @@ impl RawOracleEventEncoding {
         use RawOracleEventEncoding::*;
         match self {
             Json(string) => serde_json::from_str(string).ok(),
+            Tlv(bytes) => OracleEvent::decode_tlv(bytes),
         }
     }
 
@@ -69,6 +106,7 @@ impl RawOracleEventEncoding {
         use RawOracleEventEncoding::*;
         match self {
             Json(string) => string.as_bytes(),
+            Tlv(bytes) => bytes.as_ref(),
         }
     }
 }
@@ -92,6 +130,8 @@ pub struct AnnouncementSchemes<C: Group> {
     pub olivia_v1: Option<announce::OliviaV1<C>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ecdsa_v1: Option<announce::EcdsaV1>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dlc_v1: Option<announce::DlcV1<C>>,
 }
 
 pub mod announce {
@@ -105,6 +145,13 @@ pub mod announce {
     #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
     #[serde(rename_all = "kebab-case")]
     pub struct EcdsaV1 {}
+
+    /// Nonces for BIP340 signatures on the outcomes as specified by the DLC spec.
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct DlcV1<C: Group> {
+        pub nonces: Vec<C::PublicNonce>,
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -130,6 +177,12 @@ impl<C: Group> TryFrom<OracleEventWithDescriptor<C>> for OracleEvent<C> {
             }
         }
 
+        if let Some(dlc_v1) = &schemes.dlc_v1 {
+            if dlc_v1.nonces.len() != oracle_event.id.n_nonces() as usize {
+                return Err("oracle event has the wrong number of dlc-v1 nonces".into());
+            }
+        }
+
         if oracle_event.id.descriptor() == oracle_event.descriptor {
             Ok(OracleEvent {
                 event: Event {
@@ -157,13 +210,92 @@ impl<C: Group> From<OracleEvent<C>> for OracleEventWithDescriptor<C> {
 }
 
 impl<C: Group> OracleEvent<C> {
-    fn encode_json(&self) -> RawOracleEvent<C> {
+    /// Encodes the event as JSON. This is what [`RawAnnouncement::create`] signs.
+    pub fn encode_json(&self) -> RawOracleEvent<C> {
         RawOracleEvent {
             payload: RawOracleEventEncoding::Json(serde_json::to_string(self).unwrap()),
             curve: PhantomData,
         }
     }
 
+    /// Encodes the event as a dlcspecs `oracle_event` TLV.
+    ///
+    /// Returns `None` if the event can't be represented in the format e.g. it has no descriptor or
+    /// its expected outcome time doesn't fit in the `u32` maturity epoch. Only the `dlc_v1`
+    /// nonces are encoded since they are the only ones the format's attestations can use.
+    pub fn encode_tlv(&self) -> Option<Vec<u8>> {
+        let nonces = self
+            .schemes
+            .dlc_v1
+            .as_ref()
+            .map(|dlc_v1| &dlc_v1.nonces[..])
+            .unwrap_or(&[]);
+        let n_nonces = u16::try_from(nonces.len()).ok()?;
+        let maturity = match self.event.expected_outcome_time {
+            Some(expected_outcome_time) => u32::try_from(expected_outcome_time.timestamp()).ok()?,
+            None => 0,
+        };
+        let mut descriptor = TlvWriter::new();
+        descriptor.write_descriptor(&self.event.id.descriptor())?;
+
+        let mut w = TlvWriter::new();
+        w.write_tlv(tlv::ORACLE_EVENT_TYPE, |w| {
+            w.write_u16(n_nonces);
+            for nonce in nonces {
+                w.write_object(nonce);
+            }
+            w.write_u32(maturity);
+            w.write_bytes(&descriptor.into_bytes());
+            w.write_string(self.event.id.as_str());
+        });
+        Some(w.into_bytes())
+    }
+
+    /// Decodes a dlcspecs `oracle_event` TLV.
+    ///
+    /// A maturity epoch of `0` is interpreted as the event not having an expected outcome time.
+    pub fn decode_tlv(bytes: &[u8]) -> Option<Self> {
+        let mut r = TlvReader::new(bytes);
+        let mut oracle_event = r.read_tlv(tlv::ORACLE_EVENT_TYPE)?;
+        r.finish()?;
+
+        let n_nonces = oracle_event.read_u16()?;
+        let nonces = (0..n_nonces)
+            .map(|_| oracle_event.read_object())
+            .collect::<Option<Vec<C::PublicNonce>>>()?;
+        let maturity = oracle_event.read_u32()?;
+        let descriptor = oracle_event.read_descriptor()?;
+        let id = oracle_event.read_string()?.parse::<EventId>().ok()?;
+        oracle_event.finish()?;
+
+        let expected_outcome_time = match maturity {
+            0 => None,
+            maturity => Some(NaiveDateTime::from_timestamp_opt(maturity as i64, 0)?),
+        };
+
+        OracleEvent::try_from(OracleEventWithDescriptor {
+            id,
+            expected_outcome_time,
+            descriptor,
+            schemes: AnnouncementSchemes {
+                olivia_v1: None,
+                ecdsa_v1: None,
+                dlc_v1: match nonces.is_empty() {
+                    true => None,
+                    false => Some(announce::DlcV1 { nonces }),
+                },
+            },
+        })
+        .ok()
+    }
+
+    fn encode_raw_tlv(&self) -> Option<RawOracleEvent<C>> {
+        Some(RawOracleEvent {
+            payload: RawOracleEventEncoding::Tlv(self.encode_tlv()?),
+            curve: PhantomData,
+        })
+    }
+
     pub fn anticipate_attestations_olivia_v1(
         &self,
         public_key: &C::PublicKey,
@@ -177,6 +309,23 @@ impl<C: Group> OracleEvent<C> {
             )
         })
     }
+
+    /// The anticipated `olivia_v1` attestation for the nonce at `nonce_index` if the event is
+    /// voided.
+    pub fn anticipate_void_attestation_olivia_v1(
+        &self,
+        public_key: &C::PublicKey,
+        nonce_index: usize,
+    ) -> Option<C::AnticipatedAttestation> {
+        self.schemes.olivia_v1.as_ref().and_then(|olivia_v1| {
+            C::anticipate_attestations(
+                public_key,
+                &olivia_v1.nonces[nonce_index],
+                self.event.id.n_outcomes_for_nonce(nonce_index) + 1,
+            )
+            .pop()
+        })
+    }
 }
 
 impl<C: Group> RawAnnouncement<C> {
@@ -213,6 +362,70 @@ impl<C: Group> RawAnnouncement<C> {
         }
     }
 
+    /// Like [`create`](Self::create) but the oracle event is encoded as a dlcspecs `oracle_event`
+    /// TLV. Returns `None` if the event can't be represented in that format (see
+    /// [`OracleEvent::encode_tlv`]).
+    pub fn create_tlv(
+        event: Event,
+        keypair: &C::KeyPair,
+        schemes: AnnouncementSchemes<C>,
+    ) -> Option<Self> {
+        let oracle_event = OracleEvent::<C> { event, schemes };
+
+        let encoded_oracle_event = oracle_event.encode_raw_tlv()?;
+        let signature = encoded_oracle_event.sign(keypair);
+        Some(Self {
+            signature,
+            oracle_event: encoded_oracle_event,
+        })
+    }
+
+    /// Serializes the announcement as a dlcspecs `oracle_announcement` TLV.
+    ///
+    /// Returns `None` if the oracle event is not TLV encoded.
+    pub fn to_tlv(&self, oracle_public_key: &C::PublicKey) -> Option<Vec<u8>> {
+        let oracle_event = match &self.oracle_event.payload {
+            RawOracleEventEncoding::Tlv(bytes) => bytes,
+            _ => return None,
+        };
+        let mut w = TlvWriter::new();
+        w.write_tlv(tlv::ORACLE_ANNOUNCEMENT_TYPE, |w| {
+            w.write_object(&self.signature);
+            w.write_object(oracle_public_key);
+            w.write_bytes(oracle_event);
+        });
+        Some(w.into_bytes())
+    }
+
+    /// Parses a dlcspecs `oracle_announcement` TLV returning the announcement and the oracle's
+    /// announcement public key embedded in it.
+    ///
+    /// Note this doesn't check the signature. Use [`verify_against_id`](Self::verify_against_id)
+    /// with a public key you trust.
+    pub fn from_tlv(bytes: &[u8]) -> Option<(Self, C::PublicKey)> {
+        let mut r = TlvReader::new(bytes);
+        let mut announcement = r.read_tlv(tlv::ORACLE_ANNOUNCEMENT_TYPE)?;
+        r.finish()?;
+        let signature = announcement.read_object()?;
+        let oracle_public_key = announcement.read_object()?;
+        let oracle_event = {
+            let remaining = announcement;
+            announcement.read_tlv(tlv::ORACLE_EVENT_TYPE)?;
+            announcement.finish()?;
+            remaining
+        };
+
+        Some((
+            Self {
+                oracle_event: RawOracleEvent::from_tlv_bytes(
+                    oracle_event.into_remaining().to_vec(),
+                ),
+                signature,
+            },
+            oracle_public_key,
+        ))
+    }
+
     pub fn test_instance(event: Event) -> Self {
         let nonces: Vec<_> = (0..event.id.event_kind().n_nonces())
             .map(|_| C::test_nonce_keypair().into())
@@ -223,9 +436,17 @@ impl<C: Group> RawAnnouncement<C> {
             AnnouncementSchemes {
                 olivia_v1: match nonces.is_empty() {
                     true => None,
-                    false => Some(announce::OliviaV1 { nonces }),
+                    false => Some(announce::OliviaV1 {
+                        nonces: nonces.clone(),
+                    }),
                 },
                 ecdsa_v1: Some(announce::EcdsaV1 {}),
+                dlc_v1: match nonces.is_empty()
+                    || event.id.descriptor() == Descriptor::MissingDescriptor
+                {
+                    true => None,
+                    false => Some(announce::DlcV1 { nonces }),
+                },
             },
         )
     }
@@ -237,6 +458,9 @@ pub struct AnnouncedEvent<C: Group> {
     pub event: Event,
     pub announcement: RawAnnouncement<C>,
     pub attestation: Option<Attestation<C>>,
+    /// The epoch of the oracle keys the event was announced with.
+    #[serde(default)]
+    pub epoch: u32,
 }
 
 impl<C: Group> AnnouncedEvent<C> {
@@ -245,6 +469,7 @@ impl<C: Group> AnnouncedEvent<C> {
             event: event.clone(),
             announcement: RawAnnouncement::test_instance(event.clone()),
             attestation: Some(Attestation::test_instance(&event.id)),
+            epoch: 0,
         }
     }
 
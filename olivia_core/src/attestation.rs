@@ -1,4 +1,11 @@
-use crate::{EventId, Group, OracleEvent, OracleKeys, Outcome};
+use crate::{
+    tlv::{self, TlvWriter},
+    EventId, Group, OracleEvent, OracleKeys, Outcome,
+};
+
+/// The outcome of an attestation that voids (cancels) an event. Real outcome strings never contain
+/// a `/` so it can't be mistaken for one of them.
+pub const VOID_OUTCOME: &str = "/void";
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(bound = "C: Group")]
@@ -14,6 +21,7 @@ pub struct Attestation<C: Group> {
 pub struct AttestationSchemes<C: Group> {
     pub olivia_v1: Option<attest::OliviaV1<C>>,
     pub ecdsa_v1: Option<attest::EcdsaV1<C>>,
+    pub dlc_v1: Option<attest::DlcV1<C>>,
 }
 
 pub mod attest {
@@ -27,6 +35,11 @@ pub mod attest {
     pub struct EcdsaV1<C: Group> {
         pub signature: C::EcdsaSignature,
     }
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+    pub struct DlcV1<C: Group> {
+        pub signatures: Vec<C::Signature>,
+    }
 }
 
 #[derive(thiserror::Error, Debug, PartialEq)]
@@ -35,6 +48,8 @@ pub enum AttestationInvalid {
     OliviaV1,
     #[error("ecdsa-v1 attestation was invalid")]
     EcdsaV1,
+    #[error("dlc-v1 attestation was invalid")]
+    DlcV1,
     #[error("outcome is invalid")]
     Outcome,
     #[error("outcome is missing")]
@@ -58,16 +73,33 @@ impl<C: crate::Group> Attestation<C> {
         }
     }
 
+    /// Creates an attestation that the event was voided rather than having one of its outcomes.
+    pub fn new_void(time: chrono::NaiveDateTime, schemes: AttestationSchemes<C>) -> Self {
+        Self::new(VOID_OUTCOME.to_string(), time, schemes)
+    }
+
+    /// Whether this attestation voids the event.
+    pub fn is_void(&self) -> bool {
+        self.outcome == VOID_OUTCOME
+    }
+
+    /// The outcome index revealed under each of the event's `olivia_v1` nonces.
+    pub fn attestation_indexes(&self, event_id: &EventId) -> Result<Vec<u32>, AttestationInvalid> {
+        match self.is_void() {
+            true => Ok(event_id.void_attestation_indexes()),
+            false => Outcome::try_from_id_and_outcome(event_id.clone(), &self.outcome)
+                .map(|outcome| outcome.attestation_indexes())
+                .map_err(|_| AttestationInvalid::Outcome),
+        }
+    }
+
     pub fn verify_olivia_v1_attestation(
         &self,
         oracle_event: &OracleEvent<C>,
         oracle_keys: &OracleKeys<C>,
     ) -> Result<(), AttestationInvalid> {
-        let outcome =
-            match Outcome::try_from_id_and_outcome(oracle_event.event.id.clone(), &self.outcome) {
-                Ok(outcome) => outcome,
-                Err(_) => return Err(AttestationInvalid::Outcome),
-            };
+        let event_id = &oracle_event.event.id;
+        let indexes = self.attestation_indexes(event_id)?;
 
         let attestation_key = match &oracle_keys.olivia_v1 {
             Some(key) => key,
@@ -76,17 +108,21 @@ impl<C: crate::Group> Attestation<C> {
 
         match (&oracle_event.schemes.olivia_v1, &self.schemes.olivia_v1) {
             (Some(ann_olivia_v1), Some(att_olivia_v1)) => {
-                if ann_olivia_v1.nonces.len() != att_olivia_v1.scalars.len() {
+                if ann_olivia_v1.nonces.len() != att_olivia_v1.scalars.len()
+                    || indexes.len() != ann_olivia_v1.nonces.len()
+                {
                     return Err(AttestationInvalid::OliviaV1);
                 }
 
-                for (frag_index, index) in outcome.attestation_indexes().iter().enumerate() {
-                    if !C::verify_attest_scalar(
-                        attestation_key,
-                        &ann_olivia_v1.nonces[frag_index],
-                        *index as u32,
-                        &att_olivia_v1.scalars[frag_index],
-                    ) {
+                for (frag_index, index) in indexes.iter().enumerate() {
+                    if (!self.is_void() && *index >= event_id.n_outcomes_for_nonce(frag_index))
+                        || !C::verify_attest_scalar(
+                            attestation_key,
+                            &ann_olivia_v1.nonces[frag_index],
+                            *index as u32,
+                            &att_olivia_v1.scalars[frag_index],
+                        )
+                    {
                         return Err(AttestationInvalid::OliviaV1);
                     }
                 }
@@ -103,11 +139,14 @@ impl<C: crate::Group> Attestation<C> {
         oracle_event: &OracleEvent<C>,
         oracle_keys: &OracleKeys<C>,
     ) -> Result<(), AttestationInvalid> {
-        let outcome =
-            match Outcome::try_from_id_and_outcome(oracle_event.event.id.clone(), &self.outcome) {
-                Ok(outcome) => outcome,
+        let event_id = &oracle_event.event.id;
+        let attestation_string = match self.is_void() {
+            true => event_id.void_attestation_string(),
+            false => match Outcome::try_from_id_and_outcome(event_id.clone(), &self.outcome) {
+                Ok(outcome) => outcome.attestation_string(),
                 Err(_) => return Err(AttestationInvalid::Outcome),
-            };
+            },
+        };
 
         let attestation_key = match &oracle_keys.ecdsa_v1 {
             Some(key) => key,
@@ -116,11 +155,7 @@ impl<C: crate::Group> Attestation<C> {
 
         match (&oracle_event.schemes.ecdsa_v1, &self.schemes.ecdsa_v1) {
             (Some(_), Some(attest::EcdsaV1 { signature })) => {
-                if !C::ecdsa_verify(
-                    attestation_key,
-                    outcome.attestation_string().as_ref(),
-                    signature,
-                ) {
+                if !C::ecdsa_verify(attestation_key, &attestation_string, signature) {
                     return Err(AttestationInvalid::EcdsaV1);
                 }
             }
@@ -131,6 +166,90 @@ impl<C: crate::Group> Attestation<C> {
         Ok(())
     }
 
+    /// Verifies the `dlc_v1` signatures against the oracle's announcement key. Void attestations
+    /// have no `dlc_v1` representation so they are only valid if the event has no `dlc_v1` nonces.
+    pub fn verify_dlc_v1_attestation(
+        &self,
+        oracle_event: &OracleEvent<C>,
+        oracle_keys: &OracleKeys<C>,
+    ) -> Result<(), AttestationInvalid> {
+        let ann_dlc_v1 = match &oracle_event.schemes.dlc_v1 {
+            Some(ann_dlc_v1) => ann_dlc_v1,
+            None => return Ok(()),
+        };
+        if self.is_void() {
+            return Err(AttestationInvalid::Missing);
+        }
+        let outcomes =
+            Outcome::try_from_id_and_outcome(oracle_event.event.id.clone(), &self.outcome)
+                .ok()
+                .and_then(|outcome| outcome.dlc_outcomes())
+                .ok_or(AttestationInvalid::Outcome)?;
+
+        match &self.schemes.dlc_v1 {
+            Some(att_dlc_v1) => {
+                if ann_dlc_v1.nonces.len() != att_dlc_v1.signatures.len()
+                    || outcomes.len() != ann_dlc_v1.nonces.len()
+                {
+                    return Err(AttestationInvalid::DlcV1);
+                }
+
+                for ((nonce, signature), outcome) in ann_dlc_v1
+                    .nonces
+                    .iter()
+                    .zip(&att_dlc_v1.signatures)
+                    .zip(&outcomes)
+                {
+                    if !C::dlc_verify_outcome(&oracle_keys.announcement, nonce, outcome, signature)
+                    {
+                        return Err(AttestationInvalid::DlcV1);
+                    }
+                }
+            }
+            None => return Err(AttestationInvalid::Missing),
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the `dlc_v1` attestation as a dlcspecs `oracle_attestation` TLV.
+    ///
+    /// The outcomes are those given by [`Outcome::dlc_outcomes`]. Returns `None` if the event or
+    /// the attestation has no `dlc_v1` scheme. Void attestations have no dlcspecs representation
+    /// so they return `None` too.
+    pub fn to_tlv(
+        &self,
+        oracle_event: &OracleEvent<C>,
+        oracle_keys: &OracleKeys<C>,
+    ) -> Option<Vec<u8>> {
+        if self.is_void() {
+            return None;
+        }
+        oracle_event.schemes.dlc_v1.as_ref()?;
+        let signatures = &self.schemes.dlc_v1.as_ref()?.signatures;
+        let outcomes =
+            Outcome::try_from_id_and_outcome(oracle_event.event.id.clone(), &self.outcome)
+                .ok()?
+                .dlc_outcomes()?;
+        let n_signatures = u16::try_from(signatures.len()).ok()?;
+        let n_outcomes = u16::try_from(outcomes.len()).ok()?;
+
+        let mut w = TlvWriter::new();
+        w.write_tlv(tlv::ORACLE_ATTESTATION_TYPE, |w| {
+            w.write_string(oracle_event.event.id.as_str());
+            w.write_object(&oracle_keys.announcement);
+            w.write_u16(n_signatures);
+            for signature in signatures {
+                w.write_object(signature);
+            }
+            w.write_u16(n_outcomes);
+            for outcome in &outcomes {
+                w.write_string(outcome);
+            }
+        });
+        Some(w.into_bytes())
+    }
+
     pub fn test_instance(event_id: &EventId) -> Self {
         let outcome = Outcome::test_instance(event_id);
 
@@ -143,6 +262,14 @@ impl<C: crate::Group> Attestation<C> {
             ecdsa_v1: Some(attest::EcdsaV1 {
                 signature: C::ecdsa_sign(&C::test_keypair(), &outcome.to_string().as_bytes()),
             }),
+            dlc_v1: outcome.dlc_outcomes().map(|outcomes| attest::DlcV1 {
+                signatures: outcomes
+                    .iter()
+                    .map(|outcome| {
+                        C::dlc_sign_outcome(&C::test_keypair(), C::test_nonce_keypair(), outcome)
+                    })
+                    .collect(),
+            }),
         };
 
         Attestation::new(outcome.to_string(), chrono::Utc::now().naive_utc(), schemes)
@@ -5,10 +5,19 @@ pub enum Descriptor {
         outcomes: Vec<String>,
     },
     DigitDecomposition {
+        #[serde(default = "binary")]
+        base: u16,
         is_signed: bool,
         n_digits: u8,
         unit: Option<String>,
+        /// The outcome is the number made from the digits multiplied by `10^precision`.
+        #[serde(default)]
+        precision: i32,
     },
     /// If the DLC spec doesn't support this
     MissingDescriptor,
 }
+
+fn binary() -> u16 {
+    2
+}
@@ -0,0 +1,142 @@
+use crate::{Attestation, AttestationInvalid, EventId, Group, OracleKeys, RawAnnouncement};
+
+/// Proof that an oracle attested to two different outcomes of the same event.
+///
+/// Revealing `olivia_v1` attestation scalars for two different outcome indexes under one nonce
+/// leaks the oracle's attestation key. The proof holds the signed announcement and the two
+/// conflicting scalars so anyone with the oracle's public keys can check it and recover the key
+/// themselves.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", bound = "C: Group")]
+pub struct EquivocationProof<C: Group> {
+    pub event_id: EventId,
+    pub announcement: RawAnnouncement<C>,
+    /// The index of the `olivia_v1` nonce both scalars were revealed under.
+    pub nonce_index: u32,
+    pub first: RevealedScalar<C>,
+    pub second: RevealedScalar<C>,
+}
+
+/// An attestation scalar along with the outcome index it was revealed for.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", bound = "C: Group")]
+pub struct RevealedScalar<C: Group> {
+    pub outcome_index: u32,
+    pub scalar: C::AttestScalar,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum EquivocationInvalid {
+    #[error("announcement was invalid")]
+    Announcement,
+    #[error("attestation was invalid: {0}")]
+    Attestation(AttestationInvalid),
+    #[error("the attestations don't reveal different outcomes under any nonce")]
+    NoConflict,
+    #[error("attestation scalar was invalid")]
+    Scalar,
+    #[error("oracle's attestation key isn't known")]
+    MissingKey,
+}
+
+impl<C: Group> EquivocationProof<C> {
+    /// Creates a proof from two valid attestations to different outcomes of the event announced by
+    /// `announcement`. Returns the attestation key they leak along with the proof.
+    pub fn from_attestations(
+        event_id: &EventId,
+        announcement: &RawAnnouncement<C>,
+        oracle_keys: &OracleKeys<C>,
+        first: &Attestation<C>,
+        second: &Attestation<C>,
+    ) -> Result<(Self, C::KeyPair), EquivocationInvalid> {
+        let oracle_event = announcement
+            .verify_against_id(event_id, &oracle_keys.announcement)
+            .ok_or(EquivocationInvalid::Announcement)?;
+
+        for attestation in [first, second] {
+            attestation
+                .verify_olivia_v1_attestation(&oracle_event, oracle_keys)
+                .map_err(EquivocationInvalid::Attestation)?;
+        }
+
+        let (first_scalars, second_scalars) =
+            match (&first.schemes.olivia_v1, &second.schemes.olivia_v1) {
+                (Some(first), Some(second)) => (&first.scalars, &second.scalars),
+                _ => return Err(EquivocationInvalid::NoConflict),
+            };
+        let first_indexes = first
+            .attestation_indexes(event_id)
+            .map_err(EquivocationInvalid::Attestation)?;
+        let second_indexes = second
+            .attestation_indexes(event_id)
+            .map_err(EquivocationInvalid::Attestation)?;
+
+        let nonce_index = first_indexes
+            .iter()
+            .zip(&second_indexes)
+            .position(|(first, second)| first != second)
+            .ok_or(EquivocationInvalid::NoConflict)?;
+
+        let proof = EquivocationProof {
+            event_id: event_id.clone(),
+            announcement: announcement.clone(),
+            nonce_index: nonce_index as u32,
+            first: RevealedScalar {
+                outcome_index: first_indexes[nonce_index],
+                scalar: first_scalars[nonce_index].clone(),
+            },
+            second: RevealedScalar {
+                outcome_index: second_indexes[nonce_index],
+                scalar: second_scalars[nonce_index].clone(),
+            },
+        };
+        let attestation_key = proof.verify(oracle_keys)?;
+        Ok((proof, attestation_key))
+    }
+
+    /// Checks the proof against the oracle's public keys and returns the attestation key it
+    /// recovers.
+    pub fn verify(&self, oracle_keys: &OracleKeys<C>) -> Result<C::KeyPair, EquivocationInvalid> {
+        let attestation_key = oracle_keys
+            .olivia_v1
+            .as_ref()
+            .ok_or(EquivocationInvalid::MissingKey)?;
+        let oracle_event = self
+            .announcement
+            .verify_against_id(&self.event_id, &oracle_keys.announcement)
+            .ok_or(EquivocationInvalid::Announcement)?;
+        let nonce = oracle_event
+            .schemes
+            .olivia_v1
+            .as_ref()
+            .and_then(|olivia_v1| olivia_v1.nonces.get(self.nonce_index as usize))
+            .ok_or(EquivocationInvalid::Announcement)?;
+
+        if self.first.outcome_index == self.second.outcome_index {
+            return Err(EquivocationInvalid::NoConflict);
+        }
+
+        for revealed in [&self.first, &self.second] {
+            if !C::verify_attest_scalar(
+                attestation_key,
+                nonce,
+                revealed.outcome_index,
+                &revealed.scalar,
+            ) {
+                return Err(EquivocationInvalid::Scalar);
+            }
+        }
+
+        let keypair = C::recover_attestation_key(
+            (self.first.outcome_index, &self.first.scalar),
+            (self.second.outcome_index, &self.second.scalar),
+        )
+        .ok_or(EquivocationInvalid::NoConflict)?;
+
+        if &keypair.clone().into() != attestation_key {
+            return Err(EquivocationInvalid::Scalar);
+        }
+
+        Ok(keypair)
+    }
+}
@@ -1,11 +1,24 @@
-use crate::{Descriptor, Outcome, OutcomeError, Path, PathError, PathRef, PrefixPath};
+use crate::{
+    outcome::{parse_price, write_price},
+    Descriptor, Outcome, OutcomeError, Path, PathError, PathRef, PrefixPath,
+};
 use chrono::NaiveDateTime;
 use core::{convert::TryFrom, fmt, str::FromStr};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum EventKind {
     VsMatch(VsMatchKind),
+    /// A competition between three or more competitors listed in the last segment of the path
+    /// e.g. `/s/F1/race/2021-08-29/HAM_VER_BOT.first`
+    Competition(CompetitionKind),
+    /// The score of a match between the two parties in the last segment of the path e.g.
+    /// `/s/EPL/match/2021-08-29/ARS_CHE.score`
+    Score(ScoreKind),
     SingleOccurrence,
+    /// One of a list of outcomes e.g. `enum?o=RED,GREEN,BLUE`
+    Enum {
+        outcomes: Vec<String>,
+    },
     Predicate {
         inner: Box<EventKind>,
         predicate: Predicate,
@@ -14,6 +27,15 @@ pub enum EventKind {
         /// the number of nonces the oracle will use if using nonce based attestation.
         /// Can't be more than 64.
         n_digits: u8,
+        /// The base of the digits. Each digit's nonce has `base` possible outcomes.
+        base: u16,
+        /// Whether the price can be negative. Signed prices use an extra nonce to attest to the
+        /// sign.
+        is_signed: bool,
+        unit: Option<String>,
+        /// The price is the outcome value multiplied by `10^exp`. Can't be more than 64 in
+        /// magnitude.
+        exp: i32,
     },
 }
 
@@ -22,8 +44,21 @@ impl EventKind {
     /// know the outcome of the other.
     pub fn eq_fuzzy(&self, rhs: &EventKind) -> bool {
         match (self, rhs) {
-            // we don't care about the number of digits
-            (EventKind::Price { .. }, EventKind::Price { .. }) => true,
+            // we don't care about the number of digits or their base
+            (
+                EventKind::Price {
+                    is_signed,
+                    unit,
+                    exp,
+                    ..
+                },
+                EventKind::Price {
+                    is_signed: rhs_is_signed,
+                    unit: rhs_unit,
+                    exp: rhs_exp,
+                    ..
+                },
+            ) => is_signed == rhs_is_signed && unit == rhs_unit && exp == rhs_exp,
             _ => self == rhs,
         }
     }
@@ -33,6 +68,8 @@ impl EventKind {
 pub enum Predicate {
     Eq(String),
     Bound(BoundKind, u64),
+    /// Whether the outcome is within the (inclusive) range
+    Range(u64, u64),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -44,20 +81,43 @@ pub enum PredicateKind {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum BoundKind {
     Gt,
+    Lt,
+    Ge,
+    Le,
+}
+
+impl BoundKind {
+    /// What goes between the event kind and the bound in the event id e.g. `price_lt_30000`.
+    pub fn separator(&self) -> &'static str {
+        match self {
+            BoundKind::Gt => "_",
+            BoundKind::Lt => "_lt_",
+            BoundKind::Ge => "_ge_",
+            BoundKind::Le => "_le_",
+        }
+    }
 }
 
 impl Predicate {
-    pub fn predicate_outcome(&self, outcome: &str) -> u64 {
+    /// The outcome of the predicate given the outcome of the event it was applied to.
+    pub fn predicate_outcome(&self, inner_outcome: &Outcome) -> u64 {
+        let cmp = |target: &u64| match inner_outcome.id.event_kind().price_args() {
+            Some((true, _)) => (inner_outcome.value as i64).cmp(&(*target as i64)),
+            Some((false, _)) => inner_outcome.value.cmp(target),
+            None => panic!("can't get predicate outcome for outcome that wasn't numeric"),
+        };
         match self {
-            Predicate::Eq(target) => (outcome == *target) as u64,
+            Predicate::Eq(target) => (inner_outcome.outcome_string() == *target) as u64,
             Predicate::Bound(bound_kind, target) => {
-                let value = outcome
-                    .parse::<u64>()
-                    .expect("can't get predicate outcome for outcome that wasn't numeric");
+                let ordering = cmp(target);
                 match bound_kind {
-                    BoundKind::Gt => (value > *target) as u64,
+                    BoundKind::Gt => ordering.is_gt() as u64,
+                    BoundKind::Lt => ordering.is_lt() as u64,
+                    BoundKind::Ge => ordering.is_ge() as u64,
+                    BoundKind::Le => ordering.is_le() as u64,
                 }
             }
+            Predicate::Range(lower, upper) => (cmp(lower).is_ge() && cmp(upper).is_le()) as u64,
         }
     }
 }
@@ -68,6 +128,35 @@ pub enum VsMatchKind {
     Win,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompetitionKind {
+    /// Who comes first
+    Winner,
+    /// Who comes first, second and third (in that order) e.g. `VER,HAM,BOT`
+    Podium,
+}
+
+/// The most competitors a podium event can have. The descriptor lists every podium so it grows
+/// with the cube of the number of competitors: 20 competitors have 6,840 podiums.
+pub const MAX_PODIUM_COMPETITORS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ScoreKind {
+    /// The score of each side e.g. `2-1`. Each side is attested to with `n_digits` binary digits.
+    Full { n_digits: u8 },
+    /// The sum of the scores of each side. Used as the subject of predicates e.g. `total_2`.
+    Total,
+    /// The score of the left side minus the score of the right side e.g. `margin_ge_2`.
+    Margin,
+}
+
+impl ScoreKind {
+    /// The number of digits per side if the event id doesn't say otherwise.
+    pub const DEFAULT_N_DIGITS: u8 = 8;
+    /// The maximum number of digits per side.
+    pub const MAX_N_DIGITS: u8 = 16;
+}
+
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum EventKindError {
     #[error("{0} is an unknown event kind")]
@@ -78,16 +167,28 @@ pub enum EventKindError {
     UnexpectedArgs,
     #[error("the predicate = was applied to was not a valid outcome")]
     PredEqToInvalidOutcome(OutcomeError),
-    #[error("the RHS of the bound predicate wasn't numeric")]
+    #[error("the RHS of the bound predicate wasn't a valid value for the event")]
     PredBoundWithNonNumericRhs,
     #[error("a bound predicate cannot be placed on a non-numeric event")]
     PredBoundOnNonNumericEvent,
+    #[error("the lower end of the range predicate was greater than the upper end")]
+    PredRangeEmpty,
 }
 
 impl EventKind {
     pub fn n_nonces(&self) -> u8 {
         match self {
-            &EventKind::Price { n_digits } => n_digits,
+            &EventKind::Price {
+                n_digits,
+                is_signed,
+                ..
+            } => match n_digits {
+                0 => 0,
+                n_digits => n_digits + is_signed as u8,
+            },
+            &EventKind::Score(ScoreKind::Full { n_digits }) => 2 * n_digits,
+            // these are only attested to via predicates
+            EventKind::Score(_) => 0,
             _ => 1,
         }
     }
@@ -100,26 +201,75 @@ impl fmt::Display for EventKind {
                 VsMatchKind::Win => write!(f, "winner"),
                 VsMatchKind::WinOrDraw => write!(f, "vs"),
             },
+            EventKind::Competition(kind) => match kind {
+                CompetitionKind::Winner => write!(f, "first"),
+                CompetitionKind::Podium => write!(f, "podium"),
+            },
+            EventKind::Score(kind) => match kind {
+                ScoreKind::Full { n_digits } if *n_digits == ScoreKind::DEFAULT_N_DIGITS => {
+                    write!(f, "score")
+                }
+                ScoreKind::Full { n_digits } => write!(f, "score?n={}", n_digits),
+                ScoreKind::Total => write!(f, "total"),
+                ScoreKind::Margin => write!(f, "margin"),
+            },
             EventKind::SingleOccurrence => write!(f, "occur"),
+            EventKind::Enum { outcomes } => write!(f, "enum?o={}", outcomes.join(",")),
             EventKind::Predicate {
                 inner,
                 predicate: kind,
-            } => match kind {
-                Predicate::Eq(value) => write!(f, "{}={}", inner, value),
-                Predicate::Bound(bound_kind, bound) => write!(
-                    f,
-                    "{}{}{}",
-                    inner,
-                    match bound_kind {
-                        BoundKind::Gt => '_',
-                    },
-                    bound
-                ),
-            },
-            EventKind::Price { n_digits } => {
+            } => {
+                // the arguments of the inner kind go after the predicate
+                let inner = inner.to_string();
+                let (inner_name, inner_args) = match inner.find('?') {
+                    Some(opener) => inner.split_at(opener),
+                    None => (&inner[..], ""),
+                };
+                let write_value = |f: &mut fmt::Formatter<'_>, value: u64| match self.price_args() {
+                    Some((is_signed, exp)) => write_price(f, value, is_signed, exp),
+                    None => write!(f, "{}", value),
+                };
+                match kind {
+                    Predicate::Eq(value) => write!(f, "{}={}", inner_name, value)?,
+                    Predicate::Bound(bound_kind, bound) => {
+                        write!(f, "{}{}", inner_name, bound_kind.separator())?;
+                        write_value(f, *bound)?;
+                    }
+                    Predicate::Range(lower, upper) => {
+                        write!(f, "{}_in_", inner_name)?;
+                        write_value(f, *lower)?;
+                        write!(f, "..")?;
+                        write_value(f, *upper)?;
+                    }
+                }
+                write!(f, "{}", inner_args)
+            }
+            EventKind::Price {
+                n_digits,
+                base,
+                is_signed,
+                unit,
+                exp,
+            } => {
                 write!(f, "price")?;
+                let mut args = vec![];
                 if *n_digits > 0 {
-                    write!(f, "?n={}", n_digits)?;
+                    args.push(format!("n={}", n_digits));
+                }
+                if *base != 2 {
+                    args.push(format!("base={}", base));
+                }
+                if *is_signed {
+                    args.push("signed".into());
+                }
+                if let Some(unit) = unit {
+                    args.push(format!("unit={}", unit));
+                }
+                if *exp != 0 {
+                    args.push(format!("exp={}", exp));
+                }
+                if !args.is_empty() {
+                    write!(f, "?{}", args.join("&"))?;
                 }
                 Ok(())
             }
@@ -127,6 +277,19 @@ impl fmt::Display for EventKind {
     }
 }
 
+impl EventKind {
+    /// The sign and exponent of the numeric values of this kind (if it has numeric values).
+    fn price_args(&self) -> Option<(bool, i32)> {
+        match self {
+            EventKind::Price { is_signed, exp, .. } => Some((*is_signed, *exp)),
+            EventKind::Score(ScoreKind::Total) => Some((false, 0)),
+            EventKind::Score(ScoreKind::Margin) => Some((true, 0)),
+            EventKind::Predicate { inner, .. } => inner.price_args(),
+            _ => None,
+        }
+    }
+}
+
 impl FromStr for EventKind {
     type Err = EventKindError;
 
@@ -138,16 +301,27 @@ impl FromStr for EventKind {
                 Err(EventKindError::UnexpectedArgs)
             }
         }
-        let (event_kind, args) = match event_kind.find('?') {
-            Some(opener) => (
-                &event_kind[..opener],
-                event_kind[opener + 1..]
-                    .split('&')
-                    .map(|arg| arg.split_once('=').unwrap_or((arg, "")))
-                    .collect(),
-            ),
-            None => (event_kind, vec![]),
+        let (event_kind, query) = match event_kind.find('?') {
+            Some(opener) => event_kind.split_at(opener),
+            None => (event_kind, ""),
+        };
+        let args: Vec<(&str, &str)> = match query.strip_prefix('?') {
+            Some(query) => query
+                .split('&')
+                .map(|arg| arg.split_once('=').unwrap_or((arg, "")))
+                .collect(),
+            None => vec![],
         };
+        // predicates pass their arguments through to the kind they are applied to
+        fn predicated_kind(lhs: &str, query: &str) -> Result<EventKind, EventKindError> {
+            let inner = EventKind::from_str(&format!("{}{}", lhs, query))?;
+            if let EventKind::Price { n_digits, .. } = inner {
+                if n_digits != 0 {
+                    return Err(EventKindError::UnexpectedArgs);
+                }
+            }
+            Ok(inner)
+        }
 
         Ok(match (event_kind, args) {
             ("vs", args) => {
@@ -158,13 +332,67 @@ impl FromStr for EventKind {
                 check_no_args(args)?;
                 EventKind::VsMatch(VsMatchKind::Win)
             }
+            ("first", args) => {
+                check_no_args(args)?;
+                EventKind::Competition(CompetitionKind::Winner)
+            }
+            ("podium", args) => {
+                check_no_args(args)?;
+                EventKind::Competition(CompetitionKind::Podium)
+            }
+            ("score", args) => {
+                let n_digits = match &args[..] {
+                    [] => ScoreKind::DEFAULT_N_DIGITS,
+                    [("n", n_digits)] => match u8::from_str(n_digits) {
+                        Ok(value)
+                            if value > 0
+                                && value <= ScoreKind::MAX_N_DIGITS
+                                && value != ScoreKind::DEFAULT_N_DIGITS
+                                && value.to_string() == *n_digits =>
+                        {
+                            value
+                        }
+                        _ => return Err(EventKindError::ArgsBadFormat),
+                    },
+                    _ => return Err(EventKindError::ArgsBadFormat),
+                };
+                EventKind::Score(ScoreKind::Full { n_digits })
+            }
+            ("total", args) => {
+                check_no_args(args)?;
+                EventKind::Score(ScoreKind::Total)
+            }
+            ("margin", args) => {
+                check_no_args(args)?;
+                EventKind::Score(ScoreKind::Margin)
+            }
             ("occur", args) => {
                 check_no_args(args)?;
                 EventKind::SingleOccurrence
             }
+            ("enum", args) => {
+                let outcomes = match &args[..] {
+                    [("o", outcomes)] => outcomes.split(',').map(String::from).collect::<Vec<_>>(),
+                    _ => return Err(EventKindError::ArgsBadFormat),
+                };
+                for (i, outcome) in outcomes.iter().enumerate() {
+                    let is_valid = !outcome.is_empty()
+                        && outcome
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                        && !outcomes[..i].contains(outcome);
+                    if !is_valid {
+                        return Err(EventKindError::ArgsBadFormat);
+                    }
+                }
+                EventKind::Enum { outcomes }
+            }
             ("price", args) => {
-                let n_digits = match &args[..] {
-                    [("n", n_digits)] => u8::from_str(n_digits)
+                // arguments must be in this order and only present if they aren't the default so
+                // that each price event has exactly one id.
+                let mut args = args.into_iter().peekable();
+                let n_digits = match args.next_if(|(key, _)| *key == "n") {
+                    Some((_, n_digits)) => u8::from_str(n_digits)
                         .map_err(|_| EventKindError::ArgsBadFormat)
                         .and_then(|n_digits| {
                             if n_digits == 0 || n_digits > 64 {
@@ -173,33 +401,100 @@ impl FromStr for EventKind {
                                 Ok(n_digits)
                             }
                         })?,
-                    [] => 0,
-                    _ => return Err(EventKindError::UnexpectedArgs),
+                    None => 0,
                 };
-                EventKind::Price { n_digits }
+                let base = match args.next_if(|(key, _)| *key == "base") {
+                    Some((_, base)) => match u16::from_str(base) {
+                        // the base only matters if there are digits
+                        Ok(value) if value > 2 && n_digits > 0 && value.to_string() == base => {
+                            value
+                        }
+                        _ => return Err(EventKindError::ArgsBadFormat),
+                    },
+                    None => 2,
+                };
+                let is_signed = match args.next_if(|(key, _)| *key == "signed") {
+                    Some((_, "")) => true,
+                    Some(_) => return Err(EventKindError::ArgsBadFormat),
+                    None => false,
+                };
+                let unit = match args.next_if(|(key, _)| *key == "unit") {
+                    Some((_, unit)) => {
+                        if unit.is_empty() || !unit.chars().all(|c| c.is_ascii_alphanumeric()) {
+                            return Err(EventKindError::ArgsBadFormat);
+                        }
+                        Some(unit.to_string())
+                    }
+                    None => None,
+                };
+                let exp = match args.next_if(|(key, _)| *key == "exp") {
+                    Some((_, exp)) => match i32::from_str(exp) {
+                        Ok(value)
+                            if value != 0 && value.abs() <= 64 && value.to_string() == exp =>
+                        {
+                            value
+                        }
+                        _ => return Err(EventKindError::ArgsBadFormat),
+                    },
+                    None => 0,
+                };
+                if args.next().is_some() {
+                    return Err(EventKindError::UnexpectedArgs);
+                }
+                EventKind::Price {
+                    n_digits,
+                    base,
+                    is_signed,
+                    unit,
+                    exp,
+                }
             }
-            (pred, args) if pred.contains('=') => {
-                check_no_args(args)?;
+            (pred, _) if pred.contains('=') => {
                 let (lhs, rhs) = pred.split_once('=').expect("we checked this already");
-                let inner = Self::from_str(lhs)?;
+                let inner = predicated_kind(lhs, query)?;
                 EventKind::Predicate {
                     inner: Box::new(inner),
                     predicate: Predicate::Eq(rhs.into()),
                 }
             }
-            (pred, args) if pred.contains('_') => {
-                check_no_args(args)?;
+            (pred, _) if pred.contains('_') => {
                 let (lhs, rhs) = pred.split_once('_').expect("we checked this already");
-                let rhs = rhs
-                    .parse()
-                    .map_err(|_| EventKindError::PredBoundWithNonNumericRhs)?;
-                let inner = Self::from_str(lhs)?;
-                if !matches!(inner, EventKind::Price { .. }) {
-                    return Err(EventKindError::PredBoundOnNonNumericEvent);
-                }
+                let inner = predicated_kind(lhs, query)?;
+                let (is_signed, exp) = inner
+                    .price_args()
+                    .ok_or(EventKindError::PredBoundOnNonNumericEvent)?;
+                let parse_value = |value: &str| {
+                    parse_price(value, is_signed, exp)
+                        .filter(|parsed| {
+                            let mut canonical = String::new();
+                            write_price(&mut canonical, *parsed, is_signed, exp).unwrap();
+                            canonical == value
+                        })
+                        .ok_or(EventKindError::PredBoundWithNonNumericRhs)
+                };
+                let predicate = match rhs.split_once('_') {
+                    Some(("lt", rhs)) => Predicate::Bound(BoundKind::Lt, parse_value(rhs)?),
+                    Some(("ge", rhs)) => Predicate::Bound(BoundKind::Ge, parse_value(rhs)?),
+                    Some(("le", rhs)) => Predicate::Bound(BoundKind::Le, parse_value(rhs)?),
+                    Some(("in", rhs)) => {
+                        let (lower, upper) = rhs
+                            .split_once("..")
+                            .ok_or(EventKindError::PredBoundWithNonNumericRhs)?;
+                        let (lower, upper) = (parse_value(lower)?, parse_value(upper)?);
+                        let is_empty = match is_signed {
+                            true => (lower as i64) > (upper as i64),
+                            false => lower > upper,
+                        };
+                        if is_empty {
+                            return Err(EventKindError::PredRangeEmpty);
+                        }
+                        Predicate::Range(lower, upper)
+                    }
+                    _ => Predicate::Bound(BoundKind::Gt, parse_value(rhs)?),
+                };
                 EventKind::Predicate {
                     inner: Box::new(inner),
-                    predicate: Predicate::Bound(BoundKind::Gt, rhs),
+                    predicate,
                 }
             }
             _ => return Err(EventKindError::Unknown(event_kind.into())),
@@ -229,7 +524,7 @@ impl EventId {
     }
 
     pub fn parties(&self) -> Option<(&str, &str)> {
-        if let EventKind::VsMatch(_) = self.event_kind() {
+        if let EventKind::VsMatch(_) | EventKind::Score(_) = self.event_kind() {
             let mut parties = self.path().last().split('_');
             Some((parties.next().unwrap(), parties.next().unwrap()))
         } else {
@@ -237,6 +532,14 @@ impl EventId {
         }
     }
 
+    pub fn competitors(&self) -> Option<Vec<&str>> {
+        if let EventKind::Competition(_) = self.event_kind() {
+            Some(self.path().last().split('_').collect())
+        } else {
+            None
+        }
+    }
+
     pub fn event_kind(&self) -> EventKind {
         let (_, event_kind) = self
             .0
@@ -247,21 +550,38 @@ impl EventId {
             .expect("Event kind must be valid since this is a valid event id")
     }
 
-    pub fn n_outcomes_for_nonce(&self, _nonce_index: usize) -> u32 {
+    pub fn n_outcomes_for_nonce(&self, nonce_index: usize) -> u32 {
         match self.event_kind() {
             EventKind::VsMatch(kind) => match kind {
                 VsMatchKind::WinOrDraw => 3,
                 _ => 2,
             },
+            EventKind::Competition(kind) => {
+                let n = self.competitors().expect("it's a competition").len() as u32;
+                match kind {
+                    CompetitionKind::Winner => n,
+                    CompetitionKind::Podium => n * (n - 1) * (n - 2),
+                }
+            }
+            EventKind::Score(_) => 2,
             EventKind::SingleOccurrence => 1,
+            EventKind::Enum { outcomes } => outcomes.len() as u32,
             EventKind::Predicate { .. } => 2,
-            EventKind::Price { .. } => 2,
+            EventKind::Price {
+                base, is_signed, ..
+            } => match (is_signed, nonce_index) {
+                // the sign is either "+" or "-"
+                (true, 0) => 2,
+                _ => base as u32,
+            },
         }
     }
 
     pub fn n_outcomes(&self) -> u64 {
         match self.event_kind() {
             EventKind::Price { .. } => u64::MAX,
+            EventKind::Score(ScoreKind::Full { n_digits }) => 1 << (2 * n_digits),
+            EventKind::Score(_) => u64::MAX,
             _ => self.n_outcomes_for_nonce(0) as u64,
         }
     }
@@ -287,15 +607,37 @@ impl EventId {
                     },
                 }
             }
+            EventKind::Enum { outcomes } => Descriptor::Enum { outcomes },
+            EventKind::Competition(_) => Descriptor::Enum {
+                outcomes: (0..self.n_outcomes())
+                    .map(|value| {
+                        Outcome {
+                            id: self.clone(),
+                            value,
+                        }
+                        .outcome_string()
+                    })
+                    .collect(),
+            },
             EventKind::SingleOccurrence => Descriptor::Enum {
                 outcomes: vec!["true".into()],
             },
-            EventKind::Price { n_digits } => match n_digits {
+            // a pair of numbers has no dlcspecs representation
+            EventKind::Score(_) => Descriptor::MissingDescriptor,
+            EventKind::Price {
+                n_digits,
+                base,
+                is_signed,
+                unit,
+                exp,
+            } => match n_digits {
                 0 => Descriptor::MissingDescriptor,
                 n_digits => Descriptor::DigitDecomposition {
-                    is_signed: false,
+                    base,
+                    is_signed,
                     n_digits,
-                    unit: None,
+                    unit,
+                    precision: exp,
                 },
             },
             EventKind::Predicate { .. } => Descriptor::Enum {
@@ -322,17 +664,27 @@ impl EventId {
         self.event_kind().n_nonces()
     }
 
+    /// The index revealed under each nonce when the event is voided. It is one past the last
+    /// real outcome of the nonce so a void attestation can never be confused with a real one.
+    pub fn void_attestation_indexes(&self) -> Vec<u32> {
+        (0..self.n_nonces() as usize)
+            .map(|i| self.n_outcomes_for_nonce(i))
+            .collect()
+    }
+
+    /// The message signed under `ecdsa_v1` when the event is voided. Real attestation strings
+    /// always start with the `/` of the event id so the two can't collide.
+    pub fn void_attestation_string(&self) -> Vec<u8> {
+        let mut att_string = b"void!".to_vec();
+        att_string.extend_from_slice(self.as_bytes());
+        att_string
+    }
+
     pub fn occur_from_dt(dt: NaiveDateTime) -> EventId {
         Self::from_path_and_kind(Path::from_dt(dt), EventKind::SingleOccurrence)
     }
 
     pub fn predicate(&self, predicate_kind: PredicateKind, value: u64) -> EventId {
-        let mut event_kind = self.event_kind();
-
-        assert!(
-            !matches!(event_kind, EventKind::Predicate { .. }),
-            "you must not predicate a already predicated event"
-        );
         let predicate = match predicate_kind {
             PredicateKind::Eq => {
                 let outcome_string = Outcome {
@@ -344,10 +696,31 @@ impl EventId {
             }
             PredicateKind::Bound(bound) => Predicate::Bound(bound, value),
         };
+        self.apply_predicate(predicate)
+    }
+
+    /// Predicate that the outcome will be between `lower` and `upper` (inclusive).
+    pub fn range_predicate(&self, lower: u64, upper: u64) -> EventId {
+        self.apply_predicate(Predicate::Range(lower, upper))
+    }
+
+    fn apply_predicate(&self, predicate: Predicate) -> EventId {
+        let mut event_kind = self.event_kind();
+
+        assert!(
+            !matches!(event_kind, EventKind::Predicate { .. }),
+            "you must not predicate a already predicated event"
+        );
 
-        if let EventKind::Price { ref mut n_digits } = event_kind {
+        if let EventKind::Price {
+            ref mut n_digits,
+            ref mut base,
+            ..
+        } = event_kind
+        {
             // The number of nonces is irrelevant to the predicate so set it to 0
             *n_digits = 0;
+            *base = 2;
         }
 
         self.replace_kind(EventKind::Predicate {
@@ -421,13 +794,27 @@ impl TryFrom<Path> for EventId {
         let event_kind = EventKind::from_str(event_kind)?;
 
         match event_kind {
-            EventKind::VsMatch(_) => {
+            EventKind::VsMatch(_) | EventKind::Score(_) => {
                 let teams: Vec<_> = path.last().split('_').collect();
                 if teams.len() != 2 || teams[0] == teams[1] {
                     return Err(EventIdError::BadFormat);
                 }
             }
-            EventKind::SingleOccurrence => (),
+            EventKind::Competition(kind) => {
+                let competitors: Vec<_> = path.last().split('_').collect();
+                let too_many =
+                    kind == CompetitionKind::Podium && competitors.len() > MAX_PODIUM_COMPETITORS;
+                // two competitors should be a VsMatch
+                if competitors.len() < 3
+                    || too_many
+                    || competitors.iter().enumerate().any(|(i, competitor)| {
+                        competitor.is_empty() || competitors[..i].contains(competitor)
+                    })
+                {
+                    return Err(EventIdError::BadFormat);
+                }
+            }
+            EventKind::SingleOccurrence | EventKind::Enum { .. } => (),
             EventKind::Predicate {
                 inner,
                 predicate: kind,
@@ -435,13 +822,27 @@ impl TryFrom<Path> for EventId {
                 match kind {
                     Predicate::Eq(value) => {
                         let id = EventId::from_path_and_kind(path.to_path(), *inner);
-                        if let Err(e) = Outcome::try_from_id_and_outcome(id, &value) {
-                            return Err(EventIdError::Kind(
-                                EventKindError::PredEqToInvalidOutcome(e),
-                            ));
+                        match Outcome::try_from_id_and_outcome(id, &value) {
+                            Err(e) => {
+                                return Err(EventIdError::Kind(
+                                    EventKindError::PredEqToInvalidOutcome(e),
+                                ))
+                            }
+                            // so we don't end up with two ids for the same predicate e.g. `1.5`
+                            // and `1.50`
+                            Ok(outcome) if outcome.outcome_string() != value => {
+                                return Err(EventIdError::Kind(
+                                    EventKindError::PredEqToInvalidOutcome(OutcomeError::Invalid {
+                                        outcome: value,
+                                    }),
+                                ))
+                            }
+                            Ok(_) => {}
                         }
                     }
-                    Predicate::Bound(..) => { /* validity was checked in kind parsing */ }
+                    Predicate::Bound(..) | Predicate::Range(..) => {
+                        /* validity was checked in kind parsing */
+                    }
                 }
             }
             _ => { /*everything is fine */ }
@@ -628,6 +1029,131 @@ mod test {
         assert!(EventId::from_str("/foo/bar.price?n=20_5").is_err());
         assert!(EventId::from_str("/foo/bar.winner_5").is_err());
         assert!(EventId::from_str("/foo/bar.price_foo").is_err());
+        assert!(EventId::from_str("/foo/bar.price?n=20&signed&unit=usd&exp=-2").is_ok());
+        assert!(EventId::from_str("/foo/bar.price?signed&exp=3").is_ok());
+        assert!(EventId::from_str("/foo/bar.price?signed&n=20").is_err());
+        assert!(EventId::from_str("/foo/bar.price?n=20&signed=true").is_err());
+        assert!(EventId::from_str("/foo/bar.price?n=20&exp=0").is_err());
+        assert!(EventId::from_str("/foo/bar.price?n=20&exp=+2").is_err());
+        assert!(EventId::from_str("/foo/bar.price?n=20&exp=65").is_err());
+        assert!(EventId::from_str("/foo/bar.price?n=20&unit=").is_err());
+        assert!(EventId::from_str("/foo/bar.price?n=20&unit=us_d").is_err());
+        assert!(EventId::from_str("/foo/bar.price_-5?signed").is_ok());
+        assert!(EventId::from_str("/foo/bar.price_-5").is_err());
+        assert!(EventId::from_str("/foo/bar.price_1.50?exp=-2").is_ok());
+        assert!(EventId::from_str("/foo/bar.price_1.5?exp=-2").is_err());
+        assert!(EventId::from_str("/foo/bar.price_1.505?exp=-2").is_err());
+        assert!(EventId::from_str("/foo/bar.price_5?n=20&signed").is_err());
+        assert!(EventId::from_str("/foo/bar.price=-1.00?signed&exp=-2").is_ok());
+        assert!(EventId::from_str("/foo/bar.price=-1?signed&exp=-2").is_err());
+        assert!(EventId::from_str("/foo/bar.price?n=5&base=10").is_ok());
+        assert!(EventId::from_str("/foo/bar.price?n=5&base=2").is_err());
+        assert!(EventId::from_str("/foo/bar.price?n=5&base=1").is_err());
+        assert!(EventId::from_str("/foo/bar.price?base=10").is_err());
+        assert!(EventId::from_str("/foo/bar.price?base=10&n=5").is_err());
+        assert!(EventId::from_str("/foo/bar.price_5?base=10").is_err());
+        assert!(EventId::from_str("/foo/bar.price_lt_5").is_ok());
+        assert!(EventId::from_str("/foo/bar.price_ge_5").is_ok());
+        assert!(EventId::from_str("/foo/bar.price_le_5").is_ok());
+        assert!(EventId::from_str("/foo/bar.price_gt_5").is_err());
+        assert!(EventId::from_str("/foo/bar.price_lt_").is_err());
+        assert!(EventId::from_str("/foo/bar.winner_lt_5").is_err());
+        assert!(EventId::from_str("/foo/bar.price_in_5..10").is_ok());
+        assert!(EventId::from_str("/foo/bar.price_in_5..5").is_ok());
+        assert!(EventId::from_str("/foo/bar.price_in_10..5").is_err());
+        assert!(EventId::from_str("/foo/bar.price_in_-10..-5?signed").is_ok());
+        assert!(EventId::from_str("/foo/bar.price_in_-5..-10?signed").is_err());
+        assert!(EventId::from_str("/foo/bar.price_in_5").is_err());
+        assert!(EventId::from_str("/foo/bar.price_in_5..").is_err());
+        assert!(EventId::from_str("/foo/bar.enum?o=RED,GREEN,BLUE").is_ok());
+        assert!(EventId::from_str("/foo/bar.enum?o=yes").is_ok());
+        assert!(EventId::from_str("/foo/bar.enum").is_err());
+        assert!(EventId::from_str("/foo/bar.enum?o=").is_err());
+        assert!(EventId::from_str("/foo/bar.enum?o=RED,,BLUE").is_err());
+        assert!(EventId::from_str("/foo/bar.enum?o=RED,RED").is_err());
+        assert!(EventId::from_str("/foo/bar.enum?o=RED&n=2").is_err());
+        assert!(EventId::from_str("/foo/bar.enum?o=R.D,BLUE").is_err());
+        assert!(EventId::from_str("/foo/bar.enum=RED?o=RED,GREEN,BLUE").is_ok());
+        assert!(EventId::from_str("/foo/bar.enum=PINK?o=RED,GREEN,BLUE").is_err());
+        assert!(EventId::from_str("/foo/bar.enum_5?o=RED,GREEN,BLUE").is_err());
+        assert!(EventId::from_str("/foo/HAM_VER_BOT.first").is_ok());
+        assert!(EventId::from_str("/foo/HAM_VER_BOT.podium").is_ok());
+        assert!(EventId::from_str("/foo/HAM_VER.first").is_err());
+        assert!(EventId::from_str("/foo/HAM_VER_HAM.first").is_err());
+        assert!(EventId::from_str("/foo/HAM__VER.podium").is_err());
+        assert!(EventId::from_str("/foo/HAM_VER_BOT.first=VER").is_ok());
+        assert!(EventId::from_str("/foo/HAM_VER_BOT.first=NOR").is_err());
+        assert!(EventId::from_str("/foo/HAM_VER_BOT.podium=VER,BOT,HAM").is_ok());
+        assert!(EventId::from_str("/foo/HAM_VER_BOT.podium=VER,VER,HAM").is_err());
+    }
+
+    #[test]
+    fn price_event_kind_roundtrip() {
+        for kind in [
+            "price",
+            "price?n=20",
+            "price?n=20&signed&unit=usd&exp=-2",
+            "price?n=5&base=10&signed",
+            "price?unit=C&exp=1",
+            "price_5",
+            "price_-0.05?signed&exp=-2",
+            "price=1000?exp=3",
+            "price_lt_5",
+            "price_ge_-1.5?signed&exp=-1",
+            "price_le_5?unit=usd",
+            "price_in_0.5..1.5?exp=-1",
+        ] {
+            assert_eq!(EventKind::from_str(kind).unwrap().to_string(), kind);
+        }
+
+        assert_eq!(
+            EventKind::from_str("price?n=20&signed&unit=usd&exp=-2").unwrap(),
+            EventKind::Price {
+                n_digits: 20,
+                base: 2,
+                is_signed: true,
+                unit: Some("usd".into()),
+                exp: -2
+            }
+        );
+        assert_eq!(
+            EventKind::from_str("price_-0.05?signed&exp=-2").unwrap(),
+            EventKind::Predicate {
+                inner: Box::new(EventKind::Price {
+                    n_digits: 0,
+                    base: 2,
+                    is_signed: true,
+                    unit: None,
+                    exp: -2
+                }),
+                predicate: Predicate::Bound(BoundKind::Gt, -5i64 as u64)
+            }
+        );
+    }
+
+    #[test]
+    fn signed_price_predicate() {
+        let id = EventId::from_str("/foo/bar.price?n=10&signed&unit=usd&exp=-2").unwrap();
+        let predicated = id.predicate(PredicateKind::Bound(BoundKind::Gt), -150i64 as u64);
+        assert_eq!(predicated, "/foo/bar.price_-1.50?signed&unit=usd&exp=-2");
+        assert_eq!(
+            id.range_predicate(-150i64 as u64, 0),
+            "/foo/bar.price_in_-1.50..0?signed&unit=usd&exp=-2"
+        );
+        assert_eq!(
+            id.predicate(PredicateKind::Bound(BoundKind::Le), 150),
+            "/foo/bar.price_le_1.50?signed&unit=usd&exp=-2"
+        );
+        assert_eq!(
+            id.descriptor(),
+            Descriptor::DigitDecomposition {
+                base: 2,
+                is_signed: true,
+                n_digits: 10,
+                unit: Some("usd".into()),
+                precision: -2
+            }
+        );
     }
 
     #[test]
@@ -641,11 +1167,170 @@ mod test {
             EventId::from_str("/foo/bar.price?n=64").unwrap().n_nonces(),
             64
         );
+        // signed prices need a nonce for the sign
+        assert_eq!(
+            EventId::from_str("/foo/bar.price?n=64&signed")
+                .unwrap()
+                .n_nonces(),
+            65
+        );
         // price without a nonce specifer just assumes that we are not doing nonce based
         // attestations
         assert_eq!(EventId::from_str("/foo/bar.price").unwrap().n_nonces(), 0);
     }
 
+    #[test]
+    fn enum_event() {
+        let id = EventId::from_str("/foo/bar.enum?o=RED,GREEN,BLUE").unwrap();
+        assert_eq!(id.n_nonces(), 1);
+        assert_eq!(id.n_outcomes_for_nonce(0), 3);
+        assert_eq!(id.n_outcomes(), 3);
+        assert_eq!(
+            id.descriptor(),
+            Descriptor::Enum {
+                outcomes: vec!["RED".into(), "GREEN".into(), "BLUE".into()]
+            }
+        );
+        assert_eq!(
+            id.predicate(PredicateKind::Eq, 2),
+            "/foo/bar.enum=BLUE?o=RED,GREEN,BLUE"
+        );
+        let outcome = Outcome::try_from_id_and_outcome(id.clone(), "GREEN").unwrap();
+        assert_eq!(outcome.value, 1);
+        assert_eq!(outcome.outcome_string(), "GREEN");
+        assert!(Outcome::try_from_id_and_outcome(id, "PINK").is_err());
+    }
+
+    #[test]
+    fn competition_event() {
+        let id = EventId::from_str("/foo/HAM_VER_BOT_NOR.first").unwrap();
+        assert_eq!(id.competitors().unwrap(), vec!["HAM", "VER", "BOT", "NOR"]);
+        assert_eq!(id.n_outcomes(), 4);
+        assert_eq!(
+            id.descriptor(),
+            Descriptor::Enum {
+                outcomes: vec!["HAM".into(), "VER".into(), "BOT".into(), "NOR".into()]
+            }
+        );
+
+        let id = EventId::from_str("/foo/HAM_VER_BOT_NOR.podium").unwrap();
+        assert_eq!(id.n_outcomes(), 24);
+        let outcomes = match id.descriptor() {
+            Descriptor::Enum { outcomes } => outcomes,
+            _ => panic!("should be an enum"),
+        };
+        assert_eq!(outcomes[0], "HAM,VER,BOT");
+        assert_eq!(outcomes[1], "HAM,VER,NOR");
+        assert_eq!(outcomes[2], "HAM,BOT,VER");
+        assert_eq!(outcomes[23], "NOR,BOT,VER");
+        for (value, outcome) in outcomes.iter().enumerate() {
+            assert_eq!(
+                Outcome::try_from_id_and_outcome(id.clone(), outcome)
+                    .unwrap()
+                    .value,
+                value as u64
+            );
+        }
+        let mut deduped = outcomes.clone();
+        deduped.sort();
+        deduped.dedup();
+        assert_eq!(deduped.len(), outcomes.len());
+
+        assert!(Outcome::try_from_id_and_outcome(id.clone(), "HAM,VER").is_err());
+        assert!(Outcome::try_from_id_and_outcome(id.clone(), "HAM,HAM,VER").is_err());
+        assert!(Outcome::try_from_id_and_outcome(id, "HAM,VER,BOT,NOR").is_err());
+
+        let competitors = |n: usize| {
+            (0..n)
+                .map(|i| format!("C{}", i))
+                .collect::<Vec<_>>()
+                .join("_")
+        };
+        let id = EventId::from_str(&format!("/foo/{}.podium", competitors(20))).unwrap();
+        assert_eq!(id.n_outcomes(), 20 * 19 * 18);
+        // every announcement carries the descriptor so the biggest one has to stay small
+        let descriptor = serde_json::to_string(&id.descriptor()).unwrap();
+        assert!(
+            descriptor.len() < 128 * 1024,
+            "descriptor is {} bytes",
+            descriptor.len()
+        );
+        assert!(EventId::from_str(&format!("/foo/{}.podium", competitors(21))).is_err());
+        assert!(EventId::from_str(&format!("/foo/{}.podium", competitors(1600))).is_err());
+        assert!(EventId::from_str(&format!("/foo/{}.first", competitors(1600))).is_ok());
+    }
+
+    #[test]
+    fn score_event() {
+        let id = EventId::from_str("/s/EPL/match/2021-08-13/BRE_ARS.score").unwrap();
+        assert_eq!(
+            id.event_kind(),
+            EventKind::Score(ScoreKind::Full { n_digits: 8 })
+        );
+        assert_eq!(id.parties(), Some(("BRE", "ARS")));
+        assert_eq!(id.n_nonces(), 16);
+        assert_eq!(id.n_outcomes(), 1 << 16);
+        assert_eq!(id.descriptor(), Descriptor::MissingDescriptor);
+        let id = EventId::from_str("/s/EPL/match/2021-08-13/BRE_ARS.score?n=4").unwrap();
+        assert_eq!(id.n_nonces(), 8);
+        assert_eq!(id.to_string(), "/s/EPL/match/2021-08-13/BRE_ARS.score?n=4");
+
+        for invalid in [
+            "/s/EPL/match/2021-08-13/BRE_ARS.score?n=8",
+            "/s/EPL/match/2021-08-13/BRE_ARS.score?n=0",
+            "/s/EPL/match/2021-08-13/BRE_ARS.score?n=17",
+            "/s/EPL/match/2021-08-13/BRE_ARS.score?n=04",
+            "/s/EPL/match/2021-08-13/BRE_BRE.score",
+            "/s/EPL/match/2021-08-13/BRE_ARS_CHE.score",
+            "/s/EPL/match/2021-08-13/BRE_ARS.total?n=4",
+            "/s/EPL/match/2021-08-13/BRE_ARS.total_-1",
+            "/s/EPL/match/2021-08-13/BRE_ARS.score_2",
+            "/s/EPL/match/2021-08-13/BRE_ARS.score=2-256",
+        ] {
+            assert!(EventId::from_str(invalid).is_err(), "{}", invalid);
+        }
+
+        let over = EventId::from_str("/s/EPL/match/2021-08-13/BRE_ARS.total_2").unwrap();
+        assert_eq!(
+            over.event_kind(),
+            EventKind::Predicate {
+                inner: Box::new(EventKind::Score(ScoreKind::Total)),
+                predicate: Predicate::Bound(BoundKind::Gt, 2),
+            }
+        );
+        let spread = EventId::from_str("/s/EPL/match/2021-08-13/BRE_ARS.margin_lt_-1").unwrap();
+        assert_eq!(
+            spread.event_kind(),
+            EventKind::Predicate {
+                inner: Box::new(EventKind::Score(ScoreKind::Margin)),
+                predicate: Predicate::Bound(BoundKind::Lt, -1i64 as u64),
+            }
+        );
+        let margin = Outcome::try_from_id_and_outcome(
+            spread.replace_kind(EventKind::Score(ScoreKind::Margin)),
+            "-2",
+        )
+        .unwrap();
+        assert_eq!(
+            Predicate::Bound(BoundKind::Lt, -1i64 as u64).predicate_outcome(&margin),
+            1
+        );
+    }
+
+    #[test]
+    fn test_n_outcomes_for_nonce() {
+        let id = EventId::from_str("/foo/bar.price?n=5&base=10").unwrap();
+        assert_eq!(id.n_nonces(), 5);
+        assert_eq!(id.n_outcomes_for_nonce(0), 10);
+        assert_eq!(id.n_outcomes_for_nonce(4), 10);
+        let id = EventId::from_str("/foo/bar.price?n=5&base=10&signed").unwrap();
+        assert_eq!(id.n_nonces(), 6);
+        assert_eq!(id.n_outcomes_for_nonce(0), 2);
+        assert_eq!(id.n_outcomes_for_nonce(1), 10);
+        let id = EventId::from_str("/foo/bar.price?n=5").unwrap();
+        assert_eq!(id.n_outcomes_for_nonce(0), 2);
+    }
+
     #[test]
     fn path_from_str() {
         assert!(Path::from_str("/foo/bar").is_ok());
@@ -696,25 +1381,80 @@ mod test {
 
     #[test]
     fn predicate_outcome_eq() {
+        let vs = EventId::from_str("/foo/bar/Foo_Bar.vs").unwrap();
+        let price = EventId::from_str("/foo/bar.price").unwrap();
+        let signed = EventId::from_str("/foo/bar.price?signed").unwrap();
+        let outcome = |id: &EventId, outcome: &str| {
+            Outcome::try_from_id_and_outcome(id.clone(), outcome).unwrap()
+        };
         assert_eq!(
-            Predicate::Eq("Foo_win".into()).predicate_outcome("Foo_win"),
+            Predicate::Eq("Foo_win".into()).predicate_outcome(&outcome(&vs, "Foo_win")),
             true as u64
         );
         assert_eq!(
-            Predicate::Eq("Foo_win".into()).predicate_outcome("Bar_win"),
+            Predicate::Eq("Foo_win".into()).predicate_outcome(&outcome(&vs, "Bar_win")),
             false as u64
         );
         assert_eq!(
-            Predicate::Bound(BoundKind::Gt, 10).predicate_outcome("11"),
+            Predicate::Bound(BoundKind::Gt, 10).predicate_outcome(&outcome(&price, "11")),
             true as u64
         );
         assert_eq!(
-            Predicate::Bound(BoundKind::Gt, 10).predicate_outcome("10"),
+            Predicate::Bound(BoundKind::Gt, 10).predicate_outcome(&outcome(&price, "10")),
+            false as u64
+        );
+        assert_eq!(
+            Predicate::Bound(BoundKind::Gt, 10).predicate_outcome(&outcome(&price, "9")),
             false as u64
         );
         assert_eq!(
-            Predicate::Bound(BoundKind::Gt, 10).predicate_outcome("9"),
+            Predicate::Bound(BoundKind::Gt, -10i64 as u64)
+                .predicate_outcome(&outcome(&signed, "-9")),
+            true as u64
+        );
+        assert_eq!(
+            Predicate::Bound(BoundKind::Gt, -10i64 as u64)
+                .predicate_outcome(&outcome(&signed, "-11")),
             false as u64
         );
     }
+
+    #[test]
+    fn predicate_outcome_bounds() {
+        let price = EventId::from_str("/foo/bar.price").unwrap();
+        let signed = EventId::from_str("/foo/bar.price?signed").unwrap();
+        let outcome = |id: &EventId, outcome: &str| {
+            Outcome::try_from_id_and_outcome(id.clone(), outcome).unwrap()
+        };
+        for (bound_kind, expected) in [
+            (BoundKind::Gt, [false, false, true]),
+            (BoundKind::Lt, [true, false, false]),
+            (BoundKind::Ge, [false, true, true]),
+            (BoundKind::Le, [true, true, false]),
+        ] {
+            for (value, expected) in ["9", "10", "11"].iter().zip(expected) {
+                assert_eq!(
+                    Predicate::Bound(bound_kind, 10).predicate_outcome(&outcome(&price, value)),
+                    expected as u64,
+                    "{:?} 10 with {}",
+                    bound_kind,
+                    value
+                );
+            }
+        }
+
+        let range = Predicate::Range(-5i64 as u64, 5);
+        for (value, expected) in [
+            ("-6", false),
+            ("-5", true),
+            ("0", true),
+            ("5", true),
+            ("6", false),
+        ] {
+            assert_eq!(
+                range.predicate_outcome(&outcome(&signed, value)),
+                expected as u64
+            );
+        }
+    }
 }
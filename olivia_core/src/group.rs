@@ -12,6 +12,7 @@ pub trait GroupObject:
     + core::fmt::Display
     + Send
     + Sync
+    + FixedBytes
     + 'static
 {
 }
@@ -28,10 +29,45 @@ pub trait GroupObject:
     + Sync
     + postgres_types::FromSqlOwned
     + postgres_types::ToSql
+    + FixedBytes
     + 'static
 {
 }
 
+/// A fixed length binary encoding for group objects.
+///
+/// This is what binary wire formats (like the DLC spec TLVs) use to encode keys, nonces and
+/// signatures.
+pub trait FixedBytes: Sized {
+    const LEN: usize;
+    fn to_bytes(&self) -> Vec<u8>;
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+/// What an oracle's announcement key is signing. Each kind of message is signed under its own tag
+/// so a signature on one can't be passed off as a signature on another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureKind {
+    Announcement,
+    /// An announcement whose oracle event is a dlcspecs `oracle_event` TLV. As the spec says this
+    /// is a BIP340 signature on the tagged hash of the TLV rather than on the TLV itself.
+    DlcAnnouncement,
+    KeySuccession,
+    NonceBatch,
+}
+
+impl SignatureKind {
+    pub fn tag(self) -> &'static str {
+        match self {
+            SignatureKind::Announcement => "DLC/announcement",
+            SignatureKind::DlcAnnouncement => "DLC/oracle/announcement/v0",
+            SignatureKind::KeySuccession => "olivia/key-succession",
+            SignatureKind::NonceBatch => "olivia/nonce-batch",
+        }
+    }
+}
+
 pub trait Group:
     Clone
     + Default
@@ -47,9 +83,9 @@ pub trait Group:
     type PublicKey: GroupObject;
     type PublicNonce: GroupObject;
     type Signature: GroupObject;
-    type AnticipatedAttestation;
+    type AnticipatedAttestation: Clone + Debug + PartialEq;
     type NonceKeyPair: Into<Self::PublicNonce> + Clone + Debug;
-    type KeyPair: Into<Self::PublicKey> + Clone;
+    type KeyPair: Into<Self::PublicKey> + Clone + Send + Sync;
     type EcdsaSignature: GroupObject;
 
     const KEY_MATERIAL_LEN: usize;
@@ -73,6 +109,21 @@ pub trait Group:
         public_key: &Self::PublicKey,
         message: &[u8],
         sig: &Self::Signature,
+    ) -> bool {
+        Self::verify_announcement_key_signature(
+            SignatureKind::Announcement,
+            public_key,
+            message,
+            sig,
+        )
+    }
+
+    /// Checks a signature made with [`sign_with_announcement_key`](Group::sign_with_announcement_key).
+    fn verify_announcement_key_signature(
+        kind: SignatureKind,
+        public_key: &Self::PublicKey,
+        message: &[u8],
+        sig: &Self::Signature,
     ) -> bool;
 
     fn anticipate_attestations(
@@ -81,7 +132,48 @@ pub trait Group:
         n_outcomes: u32,
     ) -> Vec<Self::AnticipatedAttestation>;
 
-    fn sign_announcement(keypair: &Self::KeyPair, announcement: &[u8]) -> Self::Signature;
+    /// Adds anticipated attestations together. The result is what the attestation scalars of
+    /// each of them combined with [`combine_attest_scalars`](Group::combine_attest_scalars) will
+    /// be the discrete log of.
+    fn combine_anticipated_attestations(
+        anticipated: &[Self::AnticipatedAttestation],
+    ) -> Self::AnticipatedAttestation;
+
+    /// Adds attestation scalars together.
+    fn combine_attest_scalars(scalars: &[Self::AttestScalar]) -> Self::AttestScalar;
+
+    /// Recovers the attestation key from two attestation scalars revealed under the same nonce for
+    /// different outcome indexes. Each scalar is given with the index it was revealed for.
+    ///
+    /// Returns `None` if the indexes are the same. The scalars aren't checked so the key is only
+    /// right if they both verify.
+    fn recover_attestation_key(
+        first: (u32, &Self::AttestScalar),
+        second: (u32, &Self::AttestScalar),
+    ) -> Option<Self::KeyPair>;
+
+    fn sign_announcement(keypair: &Self::KeyPair, announcement: &[u8]) -> Self::Signature {
+        Self::sign_with_announcement_key(SignatureKind::Announcement, keypair, announcement)
+    }
+    /// Signs `message` with the announcement key under the tag of `kind`.
+    fn sign_with_announcement_key(
+        kind: SignatureKind,
+        keypair: &Self::KeyPair,
+        message: &[u8],
+    ) -> Self::Signature;
+    /// Signs `outcome` with the pre-announced nonce as specified by the DLC spec.
+    fn dlc_sign_outcome(
+        signing_key: &Self::KeyPair,
+        nonce_key: Self::NonceKeyPair,
+        outcome: &str,
+    ) -> Self::Signature;
+    /// Checks that `sig` is a DLC spec signature on `outcome` that uses `public_nonce`.
+    fn dlc_verify_outcome(
+        public_key: &Self::PublicKey,
+        public_nonce: &Self::PublicNonce,
+        outcome: &str,
+        sig: &Self::Signature,
+    ) -> bool;
     fn keypair_from_secret_bytes(bytes: &[u8]) -> Self::KeyPair;
     fn nonce_keypair_from_secret_bytes(bytes: &[u8]) -> Self::NonceKeyPair;
     fn ecdsa_sign(keypair: &Self::KeyPair, message: &[u8]) -> Self::EcdsaSignature;
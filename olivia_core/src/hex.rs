@@ -34,3 +34,33 @@ pub fn hex_val(c: u8) -> Result<u8, HexError> {
         _ => Err(HexError::InvalidHex),
     }
 }
+
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn decode(hex: &str) -> Result<Vec<u8>, HexError> {
+    if hex.len() % 2 == 1 {
+        return Err(HexError::InvalidLength);
+    }
+    hex.as_bytes()
+        .chunks(2)
+        .map(|hex_byte| Ok(hex_val(hex_byte[0])? << 4 | hex_val(hex_byte[1])?))
+        .collect()
+}
+
+/// For `#[serde(with = "crate::hex::serde_bytes")]` on a `Vec<u8>` that should be a hex string.
+pub(crate) mod serde_bytes {
+    use serde::de::Error;
+
+    pub fn serialize<S: serde::Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        let hex = <String as serde::Deserialize>::deserialize(deserializer)?;
+        super::decode(&hex).map_err(D::Error::custom)
+    }
+}
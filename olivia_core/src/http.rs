@@ -1,22 +1,42 @@
-use crate::{AnnouncedEvent, Attestation, GetPath, Group, OracleKeys, RawAnnouncement};
+use crate::{
+    AnnouncedEvent, Attestation, GetPath, Group, NamespaceKeys, OracleKeys, RawAnnouncement,
+    RawNonceBatch,
+};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
 #[serde(bound = "C: Group")]
 pub struct EventResponse<C: Group> {
     pub announcement: RawAnnouncement<C>,
     pub attestation: Option<Attestation<C>>,
+    /// The epoch of the keys in the oracle's key history the event was announced with.
+    #[serde(default)]
+    pub epoch: u32,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
 #[serde(bound = "C: Group", rename_all = "kebab-case")]
 pub struct RootResponse<C: Group> {
     pub public_keys: OracleKeys<C>,
+    /// The paths whose events are signed with keys of their own rather than `public_keys`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub namespaces: Vec<NamespaceKeys<C>>,
     #[serde(flatten)]
     pub node: GetPath,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
-pub struct PathResponse {
+#[serde(bound = "C: Group", rename_all = "kebab-case")]
+pub struct NonceBatchesResponse<C: Group> {
+    pub nonce_batches: Vec<RawNonceBatch<C>>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+#[serde(bound = "C: Group", rename_all = "kebab-case")]
+pub struct PathResponse<C: Group> {
+    /// The keys the events under the path are signed with (missing if the oracle doesn't have
+    /// any yet).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_keys: Option<OracleKeys<C>>,
     #[serde(flatten)]
     pub node: GetPath,
 }
@@ -26,6 +46,7 @@ impl<C: Group> From<AnnouncedEvent<C>> for EventResponse<C> {
         EventResponse {
             announcement: ann.announcement,
             attestation: ann.attestation,
+            epoch: ann.epoch,
         }
     }
 }
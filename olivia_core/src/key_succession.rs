@@ -0,0 +1,139 @@
+use crate::{Group, NamespaceKeys, OracleKeys, SignatureKind};
+use chrono::NaiveDateTime;
+use core::marker::PhantomData;
+
+/// Hands the oracle over from its keys in one epoch to the keys in the next.
+///
+/// It's signed by the announcement key of the previous epoch so anyone who trusts the oracle's
+/// original keys can follow the chain of successions to its current ones.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", bound = "C: Group")]
+pub struct KeySuccession<C: Group> {
+    /// The new keys. Their `epoch` is one more than the keys they replace.
+    pub keys: OracleKeys<C>,
+    pub time: NaiveDateTime,
+    /// The keys of each namespace in the new epoch.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub namespaces: Vec<NamespaceKeys<C>>,
+}
+
+impl<C: Group> KeySuccession<C> {
+    /// Encodes the succession as JSON. This is what [`RawKeySuccession::create`] signs.
+    pub fn encode_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// The keys a signer is allowed to hand the oracle over to. Signers only sign a
+/// [`KeySuccession`] to the successor they've been given so whoever asks them to can't hand the
+/// oracle over to keys of their own.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", bound = "C: Group")]
+pub struct Successor<C: Group> {
+    pub keys: OracleKeys<C>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub namespaces: Vec<NamespaceKeys<C>>,
+}
+
+impl<C: Group> Successor<C> {
+    /// Whether `succession` hands over to these keys. The epochs aren't compared.
+    pub fn matches(&self, succession: &KeySuccession<C>) -> bool {
+        let epoch = succession.keys.epoch;
+        let with_epoch = |keys: &OracleKeys<C>| OracleKeys {
+            epoch,
+            ..keys.clone()
+        };
+        with_epoch(&self.keys) == succession.keys
+            && self.namespaces.len() == succession.namespaces.len()
+            && self
+                .namespaces
+                .iter()
+                .zip(&succession.namespaces)
+                .all(|(ours, theirs)| {
+                    ours.path == theirs.path && with_epoch(&ours.keys) == theirs.keys
+                })
+    }
+}
+
+/// A [`KeySuccession`] encoded as JSON and signed by the previous announcement key.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(bound = "C: Group")]
+pub struct RawKeySuccession<C: Group> {
+    pub succession: String,
+    pub signature: C::Signature,
+    #[serde(skip_serializing, default)]
+    curve: PhantomData<C>,
+}
+
+impl<C: Group> RawKeySuccession<C> {
+    pub fn create(succession: KeySuccession<C>, previous_keypair: &C::KeyPair) -> Self {
+        let succession = succession.encode_json();
+        let signature = C::sign_with_announcement_key(
+            SignatureKind::KeySuccession,
+            previous_keypair,
+            succession.as_bytes(),
+        );
+        Self::from_parts(succession, signature)
+    }
+
+    pub fn from_parts(succession: String, signature: C::Signature) -> Self {
+        Self {
+            succession,
+            signature,
+            curve: PhantomData,
+        }
+    }
+
+    /// Decodes the succession without checking the signature.
+    pub fn decode(&self) -> Option<KeySuccession<C>> {
+        serde_json::from_str(&self.succession).ok()
+    }
+
+    /// Checks that the succession was signed by `previous` and follows on from its epoch.
+    #[must_use]
+    pub fn verify(&self, previous: &OracleKeys<C>) -> Option<KeySuccession<C>> {
+        if !C::verify_announcement_key_signature(
+            SignatureKind::KeySuccession,
+            &previous.announcement,
+            self.succession.as_bytes(),
+            &self.signature,
+        ) {
+            return None;
+        }
+        self.decode()
+            .filter(|succession| Some(succession.keys.epoch) == previous.epoch.checked_add(1))
+    }
+}
+
+/// Every set of keys an oracle has had starting from its original ones.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", bound = "C: Group")]
+pub struct KeyHistory<C: Group> {
+    pub genesis: OracleKeys<C>,
+    pub successions: Vec<RawKeySuccession<C>>,
+}
+
+impl<C: Group> KeyHistory<C> {
+    /// Checks each succession against the keys before it and returns the keys of every epoch
+    /// (indexed by epoch). Only as trustworthy as `genesis`.
+    #[must_use]
+    pub fn verify(&self) -> Option<Vec<OracleKeys<C>>> {
+        if self.genesis.epoch != 0 {
+            return None;
+        }
+        let mut keys = vec![self.genesis.clone()];
+        for succession in &self.successions {
+            let next = succession.verify(keys.last().expect("never empty"))?;
+            keys.push(next.keys);
+        }
+        Some(keys)
+    }
+
+    /// The keys of the latest epoch without checking the successions.
+    pub fn current(&self) -> Option<OracleKeys<C>> {
+        match self.successions.last() {
+            Some(succession) => succession.decode().map(|succession| succession.keys),
+            None => Some(self.genesis.clone()),
+        }
+    }
+}
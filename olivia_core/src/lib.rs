@@ -1,21 +1,34 @@
+mod adaptor;
 mod announcement;
 mod attestation;
 mod descriptor;
+mod equivocation;
 mod event;
 mod group;
+#[doc(hidden)]
+pub mod hex;
 pub mod http;
+mod key_succession;
 mod macros;
+mod namespace;
 mod node;
+mod nonce_batch;
 mod oracle_info;
 mod outcome;
 mod path;
+pub mod tlv;
 
+pub use adaptor::*;
 pub use announcement::*;
 pub use attestation::*;
 pub use descriptor::*;
+pub use equivocation::*;
 pub use event::*;
 pub use group::*;
+pub use key_succession::*;
+pub use namespace::*;
 pub use node::*;
+pub use nonce_batch::*;
 pub use oracle_info::*;
 pub use outcome::*;
 pub use path::*;
@@ -23,6 +36,8 @@ pub use path::*;
 pub use chrono;
 #[cfg(feature = "postgres-types")]
 pub use postgres_types;
+#[doc(hidden)]
+pub use serde;
 
 pub trait PrefixPath {
     fn prefix_path(self, path: PathRef<'_>) -> Self;
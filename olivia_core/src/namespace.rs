@@ -0,0 +1,23 @@
+use crate::{Group, OracleKeys, Path, PathRef};
+
+/// Keys the oracle uses for the events under `path` instead of its main ones.
+///
+/// Someone who only trusts the events under `path` can trust these keys without trusting the
+/// oracle's main keys (or the keys of any other namespace).
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", bound = "C: Group")]
+pub struct NamespaceKeys<C: Group> {
+    pub path: Path,
+    pub keys: OracleKeys<C>,
+}
+
+impl<C: Group> NamespaceKeys<C> {
+    /// The most specific namespace `path` is in. Events outside of every namespace use the
+    /// oracle's main keys.
+    pub fn find<'a>(namespaces: &'a [Self], path: PathRef<'_>) -> Option<&'a Self> {
+        namespaces
+            .iter()
+            .filter(|namespace| namespace.path.as_path_ref().is_parent_of(path))
+            .max_by_key(|namespace| namespace.path.as_str().len())
+    }
+}
@@ -23,7 +23,18 @@ pub enum ChildDesc {
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "range-kind", rename_all = "kebab-case")]
 pub enum RangeKind {
-    Time { interval: u32 },
+    Time {
+        interval: u32,
+    },
+    /// Times picked out by a cron expression (minute hour day-of-month month day-of-week) in a time
+    /// zone, except on the `holidays` (dates in the time zone).
+    #[serde(rename_all = "kebab-case")]
+    Calendar {
+        cron: String,
+        time_zone: String,
+        #[serde(default)]
+        holidays: Vec<chrono::NaiveDate>,
+    },
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
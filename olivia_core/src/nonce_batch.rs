@@ -0,0 +1,329 @@
+use crate::{
+    AnnouncementSchemes, EventId, EventKind, Group, OracleEvent, Path, PrefixPath, SignatureKind,
+};
+use chrono::{Duration, NaiveDateTime};
+use core::{marker::PhantomData, str::FromStr};
+
+/// The events a [`NonceBatch`] has nonces for.
+///
+/// Slot `i` is for the event at `{path}/{start + i * interval}` with kind `event_kind`. For example
+/// a schedule with path `/x/BitMEX/BXBT`, kind `price?n=20`, a start of `2021-10-04T00:00:00`
+/// and an interval of `60` has a slot for the price at each minute from then on.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BatchSchedule {
+    pub path: Path,
+    pub event_kind: EventKind,
+    pub start: NaiveDateTime,
+    /// The number of seconds between each slot.
+    pub interval: u32,
+    pub n_slots: u32,
+    /// The epoch of the oracle keys the nonces are for (see [`KeySuccession`](crate::KeySuccession)).
+    #[serde(default)]
+    pub epoch: u32,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum InvalidSchedule {
+    #[error("nonce batch interval must be greater than 0")]
+    ZeroInterval,
+    #[error(
+        "nonce batch must have between 1 and {} slots",
+        BatchSchedule::MAX_SLOTS
+    )]
+    SlotCount,
+    #[error("nonce batch ends too far in the future")]
+    OutOfRange,
+}
+
+impl BatchSchedule {
+    /// The most slots a batch can have (a week of minutes). The nonces of each slot are derived
+    /// and signed over in one go so this keeps batches a reasonable size.
+    pub const MAX_SLOTS: u32 = 7 * 24 * 60;
+
+    /// Checks the schedule can be committed to.
+    pub fn validate(&self) -> Result<(), InvalidSchedule> {
+        if self.interval == 0 {
+            return Err(InvalidSchedule::ZeroInterval);
+        }
+        if self.n_slots == 0 || self.n_slots > Self::MAX_SLOTS {
+            return Err(InvalidSchedule::SlotCount);
+        }
+        self.end().ok_or(InvalidSchedule::OutOfRange)?;
+        Ok(())
+    }
+
+    /// The time of the event in `slot` or `None` if it is too far in the future to represent.
+    fn slot_time(&self, slot: u32) -> Option<NaiveDateTime> {
+        let offset = (self.interval as i64).checked_mul(slot as i64)?;
+        // Duration::seconds panics if the number of milliseconds doesn't fit in an i64
+        if offset > i64::MAX / 1_000 {
+            return None;
+        }
+        self.start.checked_add_signed(Duration::seconds(offset))
+    }
+
+    /// The id of the event that will use the nonces in `slot`.
+    pub fn slot_event_id(&self, slot: u32) -> Option<EventId> {
+        if slot >= self.n_slots {
+            return None;
+        }
+        let time = self.slot_time(slot)?;
+        let path = Path::from_dt(time).prefix_path(self.path.as_path_ref());
+        Some(EventId::from_path_and_kind(path, self.event_kind.clone()))
+    }
+
+    /// The slot `event_id` takes its nonces from if it belongs to one.
+    pub fn slot_for_event(&self, event_id: &EventId) -> Option<u32> {
+        if event_id.event_kind() != self.event_kind {
+            return None;
+        }
+        let path = event_id.path();
+        if path.parent()? != self.path.as_path_ref() {
+            return None;
+        }
+        let time = NaiveDateTime::from_str(path.last()).ok()?;
+        let offset = (time - self.start).num_seconds();
+        let interval = self.interval as i64;
+        if offset < 0 || interval == 0 || offset % interval != 0 {
+            return None;
+        }
+        u32::try_from(offset / interval)
+            .ok()
+            .filter(|slot| *slot < self.n_slots)
+    }
+
+    /// The slots of the schedule that are for events after `time` or `None` if there aren't any.
+    pub fn remaining_after(&self, time: NaiveDateTime) -> Option<BatchSchedule> {
+        if self.interval == 0 {
+            return None;
+        }
+        let first = match (time - self.start).num_seconds() {
+            offset if offset < 0 => 0,
+            offset => u32::try_from(offset / self.interval as i64 + 1).ok()?,
+        };
+        if first >= self.n_slots {
+            return None;
+        }
+        Some(BatchSchedule {
+            start: self.slot_time(first)?,
+            n_slots: self.n_slots - first,
+            ..self.clone()
+        })
+    }
+
+    /// The time of the last slot or `None` if it is too far in the future to represent.
+    pub fn end(&self) -> Option<NaiveDateTime> {
+        self.slot_time(self.n_slots.saturating_sub(1))
+    }
+}
+
+/// Nonces committed to ahead of time for events that haven't been created yet.
+///
+/// The nonces in each slot are exactly the ones the oracle will announce the slot's event with so
+/// a DLC can be set up on it before it exists.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", bound = "C: Group")]
+pub struct NonceBatch<C: Group> {
+    #[serde(flatten)]
+    pub schedule: BatchSchedule,
+    pub slots: Vec<AnnouncementSchemes<C>>,
+}
+
+impl<C: Group> NonceBatch<C> {
+    /// The nonces committed to for `event_id`.
+    pub fn nonces_for_event(&self, event_id: &EventId) -> Option<&AnnouncementSchemes<C>> {
+        let slot = self.schedule.slot_for_event(event_id)?;
+        self.slots.get(slot as usize)
+    }
+
+    /// Checks that the announced event uses the nonces committed to for it. Events outside of the
+    /// batch aren't bound by it so they are always consistent.
+    pub fn is_consistent_with(&self, oracle_event: &OracleEvent<C>) -> bool {
+        match self.nonces_for_event(&oracle_event.event.id) {
+            Some(schemes) => {
+                schemes.olivia_v1 == oracle_event.schemes.olivia_v1
+                    && schemes.dlc_v1 == oracle_event.schemes.dlc_v1
+            }
+            None => true,
+        }
+    }
+
+    /// Encodes the batch as JSON. This is what [`RawNonceBatch::create`] signs.
+    pub fn encode_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn is_well_formed(&self) -> bool {
+        self.schedule.validate().is_ok()
+            && self.slots.len() == self.schedule.n_slots as usize
+            && self.slots.iter().enumerate().all(|(slot, schemes)| {
+                let n_nonces = match self.schedule.slot_event_id(slot as u32) {
+                    Some(event_id) => event_id.n_nonces() as usize,
+                    None => return false,
+                };
+                let olivia_v1_ok = match &schemes.olivia_v1 {
+                    Some(olivia_v1) => olivia_v1.nonces.len() == n_nonces,
+                    None => true,
+                };
+                let dlc_v1_ok = match &schemes.dlc_v1 {
+                    Some(dlc_v1) => dlc_v1.nonces.len() == n_nonces,
+                    None => true,
+                };
+                olivia_v1_ok && dlc_v1_ok
+            })
+    }
+}
+
+/// A [`NonceBatch`] encoded as JSON and signed by the oracle's announcement key.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(bound = "C: Group")]
+pub struct RawNonceBatch<C: Group> {
+    pub batch: String,
+    pub signature: C::Signature,
+    #[serde(skip_serializing, default)]
+    curve: PhantomData<C>,
+}
+
+impl<C: Group> RawNonceBatch<C> {
+    pub fn create(batch: NonceBatch<C>, keypair: &C::KeyPair) -> Self {
+        let batch = batch.encode_json();
+        let signature =
+            C::sign_with_announcement_key(SignatureKind::NonceBatch, keypair, batch.as_bytes());
+        Self::from_parts(batch, signature)
+    }
+
+    pub fn from_parts(batch: String, signature: C::Signature) -> Self {
+        Self {
+            batch,
+            signature,
+            curve: PhantomData,
+        }
+    }
+
+    /// Decodes the batch without checking the signature.
+    pub fn decode(&self) -> Option<NonceBatch<C>> {
+        serde_json::from_str(&self.batch).ok()
+    }
+
+    #[must_use]
+    pub fn verify(&self, oracle_announcement_key: &C::PublicKey) -> Option<NonceBatch<C>> {
+        if !C::verify_announcement_key_signature(
+            SignatureKind::NonceBatch,
+            oracle_announcement_key,
+            self.batch.as_bytes(),
+            &self.signature,
+        ) {
+            return None;
+        }
+        self.decode().filter(NonceBatch::is_well_formed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn schedule() -> BatchSchedule {
+        BatchSchedule {
+            path: Path::from_str("/x/BitMEX/BXBT").unwrap(),
+            event_kind: EventKind::from_str("price?n=20").unwrap(),
+            start: NaiveDateTime::from_str("2021-10-04T00:00:00").unwrap(),
+            interval: 60,
+            n_slots: 7 * 24 * 60,
+            epoch: 0,
+        }
+    }
+
+    #[test]
+    fn slot_binding() {
+        let schedule = schedule();
+        let id = |id: &str| EventId::from_str(id).unwrap();
+        assert_eq!(
+            schedule.slot_event_id(61),
+            Some(id("/x/BitMEX/BXBT/2021-10-04T01:01:00.price?n=20"))
+        );
+        assert_eq!(schedule.slot_event_id(7 * 24 * 60), None);
+        assert_eq!(
+            schedule.end(),
+            Some(NaiveDateTime::from_str("2021-10-10T23:59:00").unwrap())
+        );
+
+        for slot in [0, 1, 61, 7 * 24 * 60 - 1] {
+            let event_id = schedule.slot_event_id(slot).unwrap();
+            assert_eq!(schedule.slot_for_event(&event_id), Some(slot));
+        }
+
+        for not_in_batch in [
+            // not on the minute
+            "/x/BitMEX/BXBT/2021-10-04T01:01:30.price?n=20",
+            // before and after the batch
+            "/x/BitMEX/BXBT/2021-10-03T23:59:00.price?n=20",
+            "/x/BitMEX/BXBT/2021-10-11T00:00:00.price?n=20",
+            // different kind of event
+            "/x/BitMEX/BXBT/2021-10-04T01:01:00.price?n=10",
+            "/x/BitMEX/BXBT/2021-10-04T01:01:00.price_30000",
+            // different path
+            "/x/BitMEX/ETHUSD/2021-10-04T01:01:00.price?n=20",
+            "/x/BitMEX/BXBT/foo/2021-10-04T01:01:00.price?n=20",
+            "/x/BitMEX/BXBT/not-a-time.price?n=20",
+        ] {
+            assert_eq!(
+                schedule.slot_for_event(&id(not_in_batch)),
+                None,
+                "{}",
+                not_in_batch
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        assert_eq!(schedule().validate(), Ok(()));
+        let invalid = |schedule: BatchSchedule| schedule.validate().unwrap_err();
+        assert_eq!(
+            invalid(BatchSchedule {
+                interval: 0,
+                ..schedule()
+            }),
+            InvalidSchedule::ZeroInterval
+        );
+        for n_slots in [0, BatchSchedule::MAX_SLOTS + 1, u32::MAX] {
+            assert_eq!(
+                invalid(BatchSchedule {
+                    n_slots,
+                    ..schedule()
+                }),
+                InvalidSchedule::SlotCount
+            );
+        }
+        let far = BatchSchedule {
+            interval: u32::MAX,
+            ..schedule()
+        };
+        assert_eq!(invalid(far.clone()), InvalidSchedule::OutOfRange);
+        assert_eq!(far.end(), None);
+        assert!(far.slot_event_id(far.n_slots - 1).is_none());
+        assert!(far.slot_event_id(0).is_some());
+    }
+
+    #[test]
+    fn remaining_slots() {
+        let schedule = schedule();
+        let time = |time: &str| NaiveDateTime::from_str(time).unwrap();
+        assert_eq!(
+            schedule.remaining_after(time("2021-10-01T00:00:00")),
+            Some(schedule.clone())
+        );
+        let remaining = schedule
+            .remaining_after(time("2021-10-04T01:00:00"))
+            .unwrap();
+        assert_eq!(remaining.start, time("2021-10-04T01:01:00"));
+        assert_eq!(remaining.end(), schedule.end());
+        let remaining = schedule
+            .remaining_after(time("2021-10-04T01:00:30"))
+            .unwrap();
+        assert_eq!(remaining.start, time("2021-10-04T01:01:00"));
+        assert_eq!(schedule.remaining_after(schedule.end().unwrap()), None);
+    }
+}
@@ -17,6 +17,9 @@ pub struct OracleKeys<C: Group> {
     pub ecdsa_v1: Option<C::PublicKey>,
     pub announcement: C::PublicKey,
     pub group: C,
+    /// How many times the keys have been rotated. See [`KeySuccession`](crate::KeySuccession).
+    #[serde(default)]
+    pub epoch: u32,
 }
 
 impl<C: Group> OracleInfo<C> {
@@ -28,6 +31,7 @@ impl<C: Group> OracleInfo<C> {
                 ecdsa_v1: Some(C::test_keypair().into()),
                 announcement: C::test_keypair().into(),
                 group: C::default(),
+                epoch: 0,
             },
         }
     }
@@ -1,4 +1,4 @@
-use crate::{EventId, EventKind, PrefixPath, VsMatchKind};
+use crate::{CompetitionKind, Descriptor, EventId, EventKind, PrefixPath, ScoreKind, VsMatchKind};
 use chrono::NaiveDateTime;
 use core::{
     convert::{TryFrom, TryInto},
@@ -35,6 +35,8 @@ impl StampedOutcome {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Outcome {
     pub id: EventId,
+    /// The index of the outcome. For signed price events this is the two's complement of the
+    /// (`i64`) price.
     pub value: u64,
 }
 
@@ -48,6 +50,53 @@ impl Outcome {
 
     pub fn try_from_id_and_outcome(id: EventId, outcome: &str) -> Result<Self, OutcomeError> {
         let value = match id.event_kind() {
+            EventKind::Competition(kind) => {
+                let competitors = id.competitors().expect("it's a competition");
+                let invalid = || OutcomeError::Invalid {
+                    outcome: outcome.to_string(),
+                };
+                let position = |competitor: &str| {
+                    competitors
+                        .iter()
+                        .position(|candidate| *candidate == competitor)
+                        .ok_or_else(invalid)
+                };
+                match kind {
+                    CompetitionKind::Winner => position(outcome)? as u64,
+                    CompetitionKind::Podium => {
+                        let podium = outcome
+                            .split(',')
+                            .map(position)
+                            .collect::<Result<Vec<_>, _>>()?;
+                        encode_podium(competitors.len(), &podium).ok_or_else(invalid)?
+                    }
+                }
+            }
+            EventKind::Enum { outcomes } => outcomes
+                .iter()
+                .position(|candidate| candidate == outcome)
+                .ok_or_else(|| OutcomeError::Invalid {
+                    outcome: outcome.to_string(),
+                })? as u64,
+            EventKind::Score(kind) => {
+                let invalid = || OutcomeError::Invalid {
+                    outcome: outcome.to_string(),
+                };
+                match kind {
+                    ScoreKind::Full { n_digits } => {
+                        let side = |side: &str| match u64::from_str(side) {
+                            Ok(value) if value >> n_digits == 0 && value.to_string() == side => {
+                                Ok(value)
+                            }
+                            _ => Err(invalid()),
+                        };
+                        let (left, right) = outcome.split_once('-').ok_or_else(invalid)?;
+                        side(left)? << n_digits | side(right)?
+                    }
+                    ScoreKind::Total => parse_price(outcome, false, 0).ok_or_else(invalid)?,
+                    ScoreKind::Margin => parse_price(outcome, true, 0).ok_or_else(invalid)?,
+                }
+            }
             EventKind::SingleOccurrence => {
                 if outcome == "true" {
                     0
@@ -100,11 +149,10 @@ impl Outcome {
                     outcome: outcome.to_string(),
                 })? as u64
             }
-            EventKind::Price { .. } => {
-                u64::from_str(outcome).map_err(|_| OutcomeError::Invalid {
+            EventKind::Price { is_signed, exp, .. } => parse_price(outcome, is_signed, exp)
+                .ok_or_else(|| OutcomeError::Invalid {
                     outcome: outcome.into(),
-                })?
-            }
+                })?,
         };
 
         Ok(Self { value, id })
@@ -118,6 +166,41 @@ impl Outcome {
 
     pub fn write_outcome_string(&self, f: &mut impl fmt::Write) -> fmt::Result {
         match (self.id.event_kind(), self.value) {
+            (EventKind::Competition(kind), o) => {
+                let competitors = self.id.competitors().unwrap();
+                match kind {
+                    CompetitionKind::Winner => write!(
+                        f,
+                        "{}",
+                        competitors
+                            .get(o as usize)
+                            .expect("outcome value should be less than the number of competitors")
+                    ),
+                    CompetitionKind::Podium => {
+                        let podium = decode_podium(competitors.len(), o)
+                            .expect("outcome value should be less than the number of podiums");
+                        write!(
+                            f,
+                            "{},{},{}",
+                            competitors[podium[0]], competitors[podium[1]], competitors[podium[2]]
+                        )
+                    }
+                }
+            }
+            (EventKind::Enum { outcomes }, o) => write!(
+                f,
+                "{}",
+                outcomes
+                    .get(o as usize)
+                    .expect("outcome value should be less than the number of outcomes")
+            ),
+            (EventKind::Score(kind), o) => match kind {
+                ScoreKind::Full { n_digits } => {
+                    write!(f, "{}-{}", o >> n_digits, o & ((1 << n_digits) - 1))
+                }
+                ScoreKind::Total => write_price(f, o, false, 0),
+                ScoreKind::Margin => write_price(f, o, true, 0),
+            },
             (EventKind::SingleOccurrence, o) => {
                 match Occur::try_from(o).expect("outcome value should be less than 1") {
                     Occur::Occurred => write!(f, "{}", "true"),
@@ -140,30 +223,207 @@ impl Outcome {
                 assert!(truth < 2);
                 write!(f, "{}", truth != 0)
             }
-            (EventKind::Price { .. }, price) => write!(f, "{}", price),
+            (EventKind::Price { is_signed, exp, .. }, price) => {
+                write_price(f, price, is_signed, exp)
+            }
         }
     }
 
     pub fn attestation_indexes(&self) -> Vec<u32> {
         match self.id.event_kind() {
-            EventKind::Price { n_digits } => {
-                let cap = u64::MAX.checked_shr(64 - n_digits as u32).unwrap_or(0);
-                let value = self.value.min(cap);
-                (0..n_digits)
-                    .map(|i| (value & (1 << i) != 0) as u32)
+            EventKind::Price {
+                n_digits,
+                base,
+                is_signed,
+                ..
+            } => {
+                let (is_negative, magnitude) = match is_signed {
+                    true => ((self.value as i64) < 0, (self.value as i64).unsigned_abs()),
+                    false => (false, self.value),
+                };
+                let base = base as u64;
+                // values that can't be represented with the digits are clamped to the maximum
+                let cap = base
+                    .checked_pow(n_digits as u32)
+                    .map(|max| max - 1)
+                    .unwrap_or(u64::MAX);
+                let mut magnitude = magnitude.min(cap);
+                let mut digits = (0..n_digits)
+                    .map(|_| {
+                        let digit = (magnitude % base) as u32;
+                        magnitude /= base;
+                        digit
+                    })
+                    .collect::<Vec<_>>();
+                // the sign is attested to first: 0 for "+" and 1 for "-"
+                if is_signed && n_digits > 0 {
+                    digits.push(is_negative as u32);
+                }
+                digits.reverse();
+                digits
+            }
+            EventKind::Score(ScoreKind::Full { n_digits }) => {
+                // the left side's digits followed by the right side's, most significant first
+                (0..2 * n_digits)
                     .rev()
+                    .map(|i| ((self.value >> i) & 1) as u32)
                     .collect()
             }
+            EventKind::Score(_) => vec![],
             _ => vec![self.value.try_into().unwrap()],
         }
     }
 
+    /// The score of the left and right side if this is the outcome of a `score` event.
+    pub fn score(&self) -> Option<(u64, u64)> {
+        match self.id.event_kind() {
+            EventKind::Score(ScoreKind::Full { n_digits }) => {
+                Some((self.value >> n_digits, self.value & ((1 << n_digits) - 1)))
+            }
+            _ => None,
+        }
+    }
+
     pub fn attestation_string(&self) -> Vec<u8> {
         let mut att_string = self.id.as_bytes().to_vec();
         att_string.push('!' as u8);
         att_string.append(&mut self.value.to_be_bytes().to_vec());
         att_string
     }
+
+    /// The outcome attested to by each nonce in the dlcspecs format.
+    ///
+    /// For `Enum` events there is a single outcome (the outcome string) and for
+    /// `DigitDecomposition` events there is one per digit (preceded by `+` or `-` if the event is
+    /// signed). Returns `None` if the event has no descriptor.
+    pub fn dlc_outcomes(&self) -> Option<Vec<String>> {
+        Some(match self.id.descriptor() {
+            Descriptor::Enum { .. } => vec![self.outcome_string()],
+            Descriptor::DigitDecomposition { is_signed, .. } => self
+                .attestation_indexes()
+                .iter()
+                .enumerate()
+                .map(|(i, index)| match (is_signed && i == 0, index) {
+                    (true, 0) => "+".to_string(),
+                    (true, _) => "-".to_string(),
+                    (false, digit) => digit.to_string(),
+                })
+                .collect(),
+            Descriptor::MissingDescriptor => return None,
+        })
+    }
+}
+
+/// Encodes the indexes of the first, second and third place competitors as the index of the
+/// podium in the lexicographic ordering of all possible podiums.
+fn encode_podium(n_competitors: usize, podium: &[usize]) -> Option<u64> {
+    let (first, second, third) = match *podium {
+        [first, second, third] if first != second && first != third && second != third => {
+            (first, second, third)
+        }
+        _ => return None,
+    };
+    // the index amongst the competitors that are left after the higher places are taken
+    let second = second - (second > first) as usize;
+    let third = third - (third > first) as usize - (third > podium[1]) as usize;
+    let n = n_competitors as u64;
+    Some((first as u64 * (n - 1) + second as u64) * (n - 2) + third as u64)
+}
+
+fn decode_podium(n_competitors: usize, value: u64) -> Option<[usize; 3]> {
+    let n = n_competitors as u64;
+    if value >= n * (n - 1) * (n - 2) {
+        return None;
+    }
+    let first = (value / ((n - 1) * (n - 2))) as usize;
+    let mut second = ((value / (n - 2)) % (n - 1)) as usize;
+    let mut third = (value % (n - 2)) as usize;
+    second += (second >= first) as usize;
+    // skip over the places that are already taken in ascending order
+    for taken in [first.min(second), first.max(second)] {
+        third += (third >= taken) as usize;
+    }
+    Some([first, second, third])
+}
+
+/// Parses a decimal price into an outcome value given the sign and exponent of the event.
+///
+/// The price must be exactly representable i.e. `1.234` is not a valid price if `exp` is `-2`.
+pub(crate) fn parse_price(price: &str, is_signed: bool, exp: i32) -> Option<u64> {
+    let (is_negative, price) = match price.strip_prefix('-') {
+        Some(price) if is_signed => (true, price),
+        Some(_) => return None,
+        None => (false, price),
+    };
+    let (integer, fraction) = price.split_once('.').unwrap_or((price, ""));
+    if integer.is_empty()
+        || (price.contains('.') && fraction.is_empty())
+        || !integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let mut mantissa = 0u128;
+    for digit in integer.chars().chain(fraction.chars()) {
+        mantissa = mantissa
+            .checked_mul(10)?
+            .checked_add(digit.to_digit(10)? as u128)?;
+    }
+    let shift = -(fraction.len() as i64) - exp as i64;
+    let magnitude = if shift >= 0 {
+        mantissa.checked_mul(10u128.checked_pow(u32::try_from(shift).ok()?)?)?
+    } else {
+        let divisor = match 10u128.checked_pow(u32::try_from(-shift).ok()?) {
+            Some(divisor) => divisor,
+            // the divisor is bigger than any mantissa so only zero is representable
+            None if mantissa == 0 => return Some(0),
+            None => return None,
+        };
+        let (magnitude, remainder) = (mantissa / divisor, mantissa % divisor);
+        if remainder != 0 {
+            return None;
+        }
+        magnitude
+    };
+
+    if is_signed {
+        let value = if is_negative {
+            i64::try_from(-i128::try_from(magnitude).ok()?).ok()?
+        } else {
+            i64::try_from(magnitude).ok()?
+        };
+        Some(value as u64)
+    } else {
+        u64::try_from(magnitude).ok()
+    }
+}
+
+/// Writes the canonical decimal form of the price represented by an outcome value.
+pub(crate) fn write_price(
+    f: &mut impl fmt::Write,
+    value: u64,
+    is_signed: bool,
+    exp: i32,
+) -> fmt::Result {
+    let (is_negative, magnitude) = match is_signed {
+        true => ((value as i64) < 0, (value as i64).unsigned_abs()),
+        false => (false, value),
+    };
+    if is_negative {
+        write!(f, "-")?;
+    }
+    match exp {
+        _ if magnitude == 0 => write!(f, "0"),
+        exp if exp >= 0 => write!(f, "{}{}", magnitude, "0".repeat(exp as usize)),
+        exp => {
+            let n_decimals = exp.unsigned_abs() as usize;
+            let digits = format!("{:0>width$}", magnitude, width = n_decimals + 1);
+            let (integer, fraction) = digits.split_at(digits.len() - n_decimals);
+            write!(f, "{}.{}", integer, fraction)
+        }
+    }
 }
 
 impl fmt::Display for Outcome {
@@ -334,4 +594,87 @@ mod test {
 
         assert_eq!(outcome.attestation_indexes(), vec![0u32; 0]);
     }
+
+    #[test]
+    fn base_10_attestation_indexes() {
+        let id = EventId::from_str("/foo/bar.price?n=4&base=10").unwrap();
+        let outcome = Outcome::try_from_id_and_outcome(id.clone(), "305").unwrap();
+        assert_eq!(outcome.attestation_indexes(), vec![0, 3, 0, 5]);
+        // overflow is clamped like binary
+        let outcome = Outcome::try_from_id_and_outcome(id, "123456").unwrap();
+        assert_eq!(outcome.attestation_indexes(), vec![9, 9, 9, 9]);
+
+        let id = EventId::from_str("/foo/bar.price?n=64&base=10").unwrap();
+        let outcome = Outcome::try_from_id_and_outcome(id, &u64::MAX.to_string()).unwrap();
+        let indexes = outcome.attestation_indexes();
+        assert_eq!(indexes.len(), 64);
+        assert!(indexes[..44].iter().all(|digit| *digit == 0));
+        assert_eq!(
+            indexes[44..],
+            [1, 8, 4, 4, 6, 7, 4, 4, 0, 7, 3, 7, 0, 9, 5, 5, 1, 6, 1, 5]
+        );
+
+        let id = EventId::from_str("/foo/bar.price?n=2&base=16&signed").unwrap();
+        let outcome = Outcome::try_from_id_and_outcome(id, "-255").unwrap();
+        assert_eq!(outcome.attestation_indexes(), vec![1, 15, 15]);
+    }
+
+    #[test]
+    fn signed_attestation_indexes() {
+        let id = EventId::from_str("/foo/bar.price?n=4&signed").unwrap();
+        let outcome = Outcome::try_from_id_and_outcome(id.clone(), "-5").unwrap();
+        assert_eq!(outcome.attestation_indexes(), vec![1, 0, 1, 0, 1]);
+        let outcome = Outcome::try_from_id_and_outcome(id.clone(), "5").unwrap();
+        assert_eq!(outcome.attestation_indexes(), vec![0, 0, 1, 0, 1]);
+        let outcome = Outcome::try_from_id_and_outcome(id, "-100").unwrap();
+        assert_eq!(outcome.attestation_indexes(), vec![1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn score_outcomes() {
+        let id = EventId::from_str("/s/EPL/match/2021-08-13/BRE_ARS.score?n=3").unwrap();
+        let outcome = Outcome::try_from_id_and_outcome(id.clone(), "5-2").unwrap();
+        assert_eq!(outcome.score(), Some((5, 2)));
+        assert_eq!(outcome.outcome_string(), "5-2");
+        assert_eq!(outcome.attestation_indexes(), vec![1, 0, 1, 0, 1, 0]);
+        for invalid in ["8-0", "0-8", "05-1", "-1-2", "1", "1-2-3", "a-b"] {
+            assert!(Outcome::try_from_id_and_outcome(id.clone(), invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn fixed_point_outcomes() {
+        let id = EventId::from_str("/foo/bar.price?n=20&signed&unit=usd&exp=-2").unwrap();
+        for (outcome, value, canonical) in [
+            ("1.5", 150, "1.50"),
+            ("-1.50", -150, "-1.50"),
+            ("0.01", 1, "0.01"),
+            ("-0.07", -7, "-0.07"),
+            ("3", 300, "3.00"),
+            ("0", 0, "0"),
+            ("-0", 0, "0"),
+        ] {
+            let outcome = Outcome::try_from_id_and_outcome(id.clone(), outcome).unwrap();
+            assert_eq!(outcome.value, value as i64 as u64);
+            assert_eq!(outcome.outcome_string(), canonical);
+        }
+        for invalid in [
+            "1.505",
+            "1.",
+            ".5",
+            "+1",
+            "1e2",
+            "--1",
+            "99999999999999999999",
+        ] {
+            assert!(Outcome::try_from_id_and_outcome(id.clone(), invalid).is_err());
+        }
+
+        let id = EventId::from_str("/foo/bar.price?exp=3").unwrap();
+        let outcome = Outcome::try_from_id_and_outcome(id.clone(), "42000").unwrap();
+        assert_eq!(outcome.value, 42);
+        assert_eq!(outcome.outcome_string(), "42000");
+        assert!(Outcome::try_from_id_and_outcome(id.clone(), "42500").is_err());
+        assert!(Outcome::try_from_id_and_outcome(id, "-42000").is_err());
+    }
 }
@@ -0,0 +1,360 @@
+//! Binary encoding of announcements and attestations in the [dlcspecs] TLV wire format.
+//!
+//! [dlcspecs]: https://github.com/discreetlogcontracts/dlcspecs/blob/master/Messaging.md
+use crate::{Descriptor, FixedBytes};
+
+pub const ORACLE_ANNOUNCEMENT_TYPE: u64 = 55332;
+pub const ORACLE_EVENT_TYPE: u64 = 55330;
+pub const ORACLE_ATTESTATION_TYPE: u64 = 55400;
+pub const ENUM_EVENT_DESCRIPTOR_TYPE: u64 = 55302;
+pub const DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE: u64 = 55306;
+
+#[derive(Default, Debug, Clone)]
+pub struct TlvWriter(Vec<u8>);
+
+impl TlvWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    pub fn write_bigsize(&mut self, value: u64) {
+        match value {
+            0..=0xfc => self.0.push(value as u8),
+            0xfd..=0xffff => {
+                self.0.push(0xfd);
+                self.0.extend_from_slice(&(value as u16).to_be_bytes());
+            }
+            0x10000..=0xffff_ffff => {
+                self.0.push(0xfe);
+                self.0.extend_from_slice(&(value as u32).to_be_bytes());
+            }
+            _ => {
+                self.0.push(0xff);
+                self.0.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.0.push(value as u8);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    pub fn write_string(&mut self, string: &str) {
+        self.write_bigsize(string.len() as u64);
+        self.write_bytes(string.as_bytes());
+    }
+
+    pub fn write_object<T: FixedBytes>(&mut self, object: &T) {
+        self.write_bytes(&object.to_bytes());
+    }
+
+    /// Writes a `type`, `length`, `value` record where the value is whatever `value` writes.
+    pub fn write_tlv(&mut self, tlv_type: u64, value: impl FnOnce(&mut TlvWriter)) {
+        let mut inner = TlvWriter::new();
+        value(&mut inner);
+        self.write_bigsize(tlv_type);
+        self.write_bigsize(inner.0.len() as u64);
+        self.write_bytes(&inner.0);
+    }
+
+    pub fn write_descriptor(&mut self, descriptor: &Descriptor) -> Option<()> {
+        match descriptor {
+            Descriptor::Enum { outcomes } => {
+                let n_outcomes = u16::try_from(outcomes.len()).ok()?;
+                self.write_tlv(ENUM_EVENT_DESCRIPTOR_TYPE, |w| {
+                    w.write_u16(n_outcomes);
+                    for outcome in outcomes {
+                        w.write_string(outcome);
+                    }
+                });
+            }
+            Descriptor::DigitDecomposition {
+                base,
+                is_signed,
+                n_digits,
+                unit,
+                precision,
+            } => self.write_tlv(DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE, |w| {
+                w.write_u16(*base);
+                w.write_bool(*is_signed);
+                w.write_string(unit.as_deref().unwrap_or(""));
+                w.write_i32(*precision);
+                w.write_u16(*n_digits as u16);
+            }),
+            Descriptor::MissingDescriptor => return None,
+        }
+        Some(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TlvReader<'a>(&'a [u8]);
+
+impl<'a> TlvReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn into_remaining(self) -> &'a [u8] {
+        self.0
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Some(array)
+    }
+
+    /// Reads a bigsize integer rejecting non-minimal encodings.
+    pub fn read_bigsize(&mut self) -> Option<u64> {
+        let value = match self.read_array::<1>()?[0] {
+            0xfd => {
+                let value = u16::from_be_bytes(self.read_array()?) as u64;
+                if value < 0xfd {
+                    return None;
+                }
+                value
+            }
+            0xfe => {
+                let value = u32::from_be_bytes(self.read_array()?) as u64;
+                if value < 0x10000 {
+                    return None;
+                }
+                value
+            }
+            0xff => {
+                let value = u64::from_be_bytes(self.read_array()?);
+                if value < 0x1_0000_0000 {
+                    return None;
+                }
+                value
+            }
+            value => value as u64,
+        };
+        Some(value)
+    }
+
+    pub fn read_u16(&mut self) -> Option<u16> {
+        self.read_array().map(u16::from_be_bytes)
+    }
+
+    pub fn read_u32(&mut self) -> Option<u32> {
+        self.read_array().map(u32::from_be_bytes)
+    }
+
+    pub fn read_i32(&mut self) -> Option<i32> {
+        self.read_array().map(i32::from_be_bytes)
+    }
+
+    pub fn read_bool(&mut self) -> Option<bool> {
+        match self.read_array::<1>()?[0] {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    pub fn read_string(&mut self) -> Option<String> {
+        let len = usize::try_from(self.read_bigsize()?).ok()?;
+        let bytes = self.read_bytes(len)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    pub fn read_object<T: FixedBytes>(&mut self) -> Option<T> {
+        T::from_bytes(self.read_bytes(T::LEN)?)
+    }
+
+    /// Reads a TLV record of type `tlv_type` and returns a reader over its value.
+    pub fn read_tlv(&mut self, tlv_type: u64) -> Option<TlvReader<'a>> {
+        if self.read_bigsize()? != tlv_type {
+            return None;
+        }
+        let len = usize::try_from(self.read_bigsize()?).ok()?;
+        self.read_bytes(len).map(TlvReader)
+    }
+
+    pub fn read_descriptor(&mut self) -> Option<Descriptor> {
+        let mut peek = *self;
+        let descriptor = match peek.read_bigsize()? {
+            ENUM_EVENT_DESCRIPTOR_TYPE => {
+                let mut r = self.read_tlv(ENUM_EVENT_DESCRIPTOR_TYPE)?;
+                let n_outcomes = r.read_u16()?;
+                let outcomes = (0..n_outcomes)
+                    .map(|_| r.read_string())
+                    .collect::<Option<Vec<_>>>()?;
+                r.finish()?;
+                Descriptor::Enum { outcomes }
+            }
+            DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE => {
+                let mut r = self.read_tlv(DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE)?;
+                let base = r.read_u16()?;
+                let is_signed = r.read_bool()?;
+                let unit = r.read_string()?;
+                let precision = r.read_i32()?;
+                let n_digits = u8::try_from(r.read_u16()?).ok()?;
+                r.finish()?;
+                if base < 2 {
+                    return None;
+                }
+                Descriptor::DigitDecomposition {
+                    base,
+                    is_signed,
+                    n_digits,
+                    unit: match unit.is_empty() {
+                        true => None,
+                        false => Some(unit),
+                    },
+                    precision,
+                }
+            }
+            _ => return None,
+        };
+        Some(descriptor)
+    }
+
+    /// Checks that everything has been read.
+    pub fn finish(self) -> Option<()> {
+        match self.is_empty() {
+            true => Some(()),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // test vectors from BOLT #1
+    const BIGSIZE_VECTORS: &[(u64, &[u8])] = &[
+        (0, &[0x00]),
+        (252, &[0xfc]),
+        (253, &[0xfd, 0x00, 0xfd]),
+        (65535, &[0xfd, 0xff, 0xff]),
+        (65536, &[0xfe, 0x00, 0x01, 0x00, 0x00]),
+        (4294967295, &[0xfe, 0xff, 0xff, 0xff, 0xff]),
+        (
+            4294967296,
+            &[0xff, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00],
+        ),
+        (
+            18446744073709551615,
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+        ),
+    ];
+
+    #[test]
+    fn bigsize_roundtrip() {
+        for (value, bytes) in BIGSIZE_VECTORS {
+            let mut w = TlvWriter::new();
+            w.write_bigsize(*value);
+            assert_eq!(&w.into_bytes()[..], *bytes);
+            let mut r = TlvReader::new(bytes);
+            assert_eq!(r.read_bigsize(), Some(*value));
+            assert!(r.is_empty());
+        }
+    }
+
+    #[test]
+    fn bigsize_not_minimal() {
+        for bytes in [
+            &[0xfd, 0x00, 0xfc][..],
+            &[0xfe, 0x00, 0x00, 0xff, 0xff][..],
+            &[0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff][..],
+        ] {
+            assert_eq!(TlvReader::new(bytes).read_bigsize(), None);
+        }
+    }
+
+    #[test]
+    fn enum_descriptor_encoding() {
+        let descriptor = Descriptor::Enum {
+            outcomes: vec!["a".into(), "bc".into()],
+        };
+        let mut w = TlvWriter::new();
+        w.write_descriptor(&descriptor).unwrap();
+        let bytes = w.into_bytes();
+        assert_eq!(
+            bytes,
+            vec![0xfd, 0xd8, 0x06, 0x07, 0x00, 0x02, 0x01, b'a', 0x02, b'b', b'c']
+        );
+        assert_eq!(TlvReader::new(&bytes).read_descriptor(), Some(descriptor));
+    }
+
+    #[test]
+    fn digit_decomposition_descriptor_encoding() {
+        let descriptor = Descriptor::DigitDecomposition {
+            base: 2,
+            is_signed: false,
+            n_digits: 20,
+            unit: None,
+            precision: 0,
+        };
+        let mut w = TlvWriter::new();
+        w.write_descriptor(&descriptor).unwrap();
+        let bytes = w.into_bytes();
+        assert_eq!(
+            bytes,
+            vec![
+                0xfd, 0xd8, 0x0a, 0x0a, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x14
+            ]
+        );
+        assert_eq!(TlvReader::new(&bytes).read_descriptor(), Some(descriptor));
+        assert_eq!(
+            TlvWriter::new().write_descriptor(&Descriptor::MissingDescriptor),
+            None
+        );
+
+        let descriptor = Descriptor::DigitDecomposition {
+            base: 10,
+            is_signed: true,
+            n_digits: 8,
+            unit: Some("usd".into()),
+            precision: -2,
+        };
+        let mut w = TlvWriter::new();
+        w.write_descriptor(&descriptor).unwrap();
+        let bytes = w.into_bytes();
+        assert_eq!(
+            bytes,
+            vec![
+                0xfd, 0xd8, 0x0a, 0x0d, 0x00, 0x0a, 0x01, 0x03, b'u', b's', b'd', 0xff, 0xff, 0xff,
+                0xfe, 0x00, 0x08
+            ]
+        );
+        assert_eq!(TlvReader::new(&bytes).read_descriptor(), Some(descriptor));
+    }
+}
@@ -3,7 +3,8 @@ extern crate alloc;
 use alloc::{string::String, vec::Vec};
 use core::str::FromStr;
 use olivia_core::{
-    BoundKind, EventId, EventKind, NodeKind, Outcome, Path, PathRef, Predicate, VsMatchKind,
+    BoundKind, CompetitionKind, EventId, EventKind, NodeKind, Outcome, Path, PathRef, Predicate,
+    ScoreKind, VsMatchKind,
 };
 
 #[cfg(feature = "wasm-bindgen")]
@@ -189,6 +190,54 @@ pub fn event_short(event_id: &EventId) -> String {
                 ),
             }
         }
+        (["s", competition, "match", date, _], EventKind::Score(kind)) => {
+            let (left, right) = event_id.parties().unwrap();
+            let left_long = lookup_team(competition, left);
+            let right_long = lookup_team(competition, right);
+            let competition = lookup_competition(competition);
+            match kind {
+                ScoreKind::Full { .. } => format!(
+                    "score of {} match {} vs {} on {}",
+                    competition, left_long, right_long, date
+                ),
+                ScoreKind::Total => format!(
+                    "total score of {} match {} vs {} on {}",
+                    competition, left_long, right_long, date
+                ),
+                ScoreKind::Margin => format!(
+                    "margin of {} over {} in their {} match on {}",
+                    left_long, right_long, competition, date
+                ),
+            }
+        }
+        ([..], EventKind::Score(kind)) => {
+            let (left, right) = event_id.parties().unwrap();
+            let parent = event_id.path().parent().unwrap();
+            match kind {
+                ScoreKind::Full { .. } => format!("score of {} vs {} in {}", left, right, parent),
+                ScoreKind::Total => format!("total score of {} vs {} in {}", left, right, parent),
+                ScoreKind::Margin => format!("margin of {} over {} in {}", left, right, parent),
+            }
+        }
+        (["s", competition, what, date, _], EventKind::Competition(kind)) => {
+            let competition = lookup_competition(competition);
+            match kind {
+                CompetitionKind::Winner => {
+                    format!("winner of the {} {} on {}", competition, what, date)
+                }
+                CompetitionKind::Podium => {
+                    format!("podium of the {} {} on {}", competition, what, date)
+                }
+            }
+        }
+        ([..], EventKind::Competition(kind)) => {
+            let competitors = event_id.competitors().unwrap().join(", ");
+            let parent = event_id.path().parent().unwrap();
+            match kind {
+                CompetitionKind::Winner => format!("winner of {} in {}", competitors, parent),
+                CompetitionKind::Podium => format!("podium of {} in {}", competitors, parent),
+            }
+        }
         (["time", datetime], EventKind::SingleOccurrence) => {
             format!("time {} has passed", datetime)
         }
@@ -198,20 +247,13 @@ pub fn event_short(event_id: &EventId) -> String {
             datetime
         ),
         (_, EventKind::SingleOccurrence) => format!("{} has transpired", event_id.path()),
-        (
-            ["x", exchange, instrument, time],
-            EventKind::Price {
-                n_digits: _n_digits,
-            },
-        ) => {
+        (_, EventKind::Enum { outcomes }) => {
+            format!("{} (one of {})", event_id.path(), outcomes.join(", "))
+        }
+        (["x", exchange, instrument, time], EventKind::Price { .. }) => {
             format!("price of {} on {} at {}", instrument, exchange, time,)
         }
-        (
-            [..],
-            EventKind::Price {
-                n_digits: _n_digits,
-            },
-        ) => format!("price of {}", event_id.path()),
+        ([..], EventKind::Price { .. }) => format!("price of {}", event_id.path()),
         ([..], EventKind::Predicate { inner, predicate }) => {
             let inner_id = event_id.replace_kind(*inner);
             match predicate {
@@ -220,15 +262,11 @@ pub fn event_short(event_id: &EventId) -> String {
                         .expect("this will be valid since predicate is valid");
                     format!("assertion that {}", crate::outcome(&outcome).positive,)
                 }
-                Predicate::Bound(bound_kind, bound) => match bound_kind {
-                    olivia_core::BoundKind::Gt => {
-                        format!(
-                            "assertion that the {} will be greater than {}",
-                            event_short(&inner_id),
-                            bound
-                        )
-                    }
-                },
+                predicate => format!(
+                    "assertion that the {} will be {}",
+                    event_short(&inner_id),
+                    bound_phrase(&inner_id, &predicate, false)
+                ),
             }
         }
     };
@@ -282,8 +320,8 @@ pub fn event_html(id: &EventId) -> Option<String> {
                                  Houtcome(outcome),
                                  Houtcome(Outcome { id: id.clone(), value: false as u64 }))
                 }
-                Predicate::Bound(BoundKind::Gt, bound) => {
-                    format!("Whether the {} is greater than <b>{}</b>", event_html(&inner_id).unwrap_or(event_short(&inner_id)), bound)
+                predicate => {
+                    format!("Whether the {} is {}", event_html(&inner_id).unwrap_or(event_short(&inner_id)), bound_phrase(&inner_id, &predicate, true))
                 }
             })
         },
@@ -291,6 +329,48 @@ pub fn event_html(id: &EventId) -> Option<String> {
     }
 }
 
+/// The decimal price for an outcome value of a price event followed by its unit (if it has one).
+fn price_with_unit(id: &EventId, value: u64) -> String {
+    let price = Outcome {
+        id: id.clone(),
+        value,
+    }
+    .outcome_string();
+    match id.event_kind() {
+        EventKind::Price {
+            unit: Some(unit), ..
+        } => format!("{} {}", price, unit),
+        _ => price,
+    }
+}
+
+/// Describes a bound or range predicate on a price e.g. "less than 10000".
+fn bound_phrase(inner_id: &EventId, predicate: &Predicate, html: bool) -> String {
+    let value = |value: u64| {
+        let price = price_with_unit(inner_id, value);
+        match html {
+            true => format!("<b>{}</b>", price),
+            false => price,
+        }
+    };
+    match *predicate {
+        Predicate::Bound(bound_kind, bound) => format!(
+            "{} {}",
+            match bound_kind {
+                BoundKind::Gt => "greater than",
+                BoundKind::Lt => "less than",
+                BoundKind::Ge => "greater than or equal to",
+                BoundKind::Le => "less than or equal to",
+            },
+            value(bound)
+        ),
+        Predicate::Range(lower, upper) => {
+            format!("between {} and {} (inclusive)", value(lower), value(upper))
+        }
+        Predicate::Eq(_) => unreachable!("equality predicates aren't bounds"),
+    }
+}
+
 pub struct OutcomeDesc {
     pub positive: String,
     pub negative: String,
@@ -356,10 +436,38 @@ pub fn outcome(outcome: &Outcome) -> OutcomeDesc {
                 ),
             }
         }
-        (_, EventKind::Price { .. }) => OutcomeDesc {
-            positive: format!("the price of {} is {}", event_short(id), outcome_str),
-            negative: format!("the price of {} is not {}", event_short(id), outcome_str),
-        },
+        (segments, EventKind::Competition(kind)) => {
+            let competition = match segments {
+                ["s", competition, ..] => *competition,
+                _ => "",
+            };
+            let name = |competitor: &str| lookup_competitor(competition, competitor).to_string();
+            let event = event_short(id);
+            match kind {
+                CompetitionKind::Winner => OutcomeDesc {
+                    positive: format!("{} is the {}", name(&outcome_str), event),
+                    negative: format!("{} is not the {}", name(&outcome_str), event),
+                },
+                CompetitionKind::Podium => {
+                    let podium = outcome_str.split(',').map(name).collect::<Vec<_>>();
+                    let podium = format!(
+                        "{} first, {} second and {} third",
+                        podium[0], podium[1], podium[2]
+                    );
+                    OutcomeDesc {
+                        positive: format!("the {} is {}", event, podium),
+                        negative: format!("the {} is not {}", event, podium),
+                    }
+                }
+            }
+        }
+        (_, EventKind::Price { .. }) => {
+            let price = price_with_unit(id, outcome.value);
+            OutcomeDesc {
+                positive: format!("the price of {} is {}", event_short(id), price),
+                negative: format!("the price of {} is not {}", event_short(id), price),
+            }
+        }
         (_, EventKind::Predicate { inner, predicate }) => {
             let inner_event_id = id.replace_kind(*inner);
             match predicate {
@@ -368,19 +476,14 @@ pub fn outcome(outcome: &Outcome) -> OutcomeDesc {
                         .expect("predicate is valid");
                     crate::outcome(&inner_outcome).maybe_negate(outcome_str == "false")
                 }
-                Predicate::Bound(BoundKind::Gt, upper_bound) => OutcomeDesc {
-                    positive: format!(
-                        "the {} is above {}",
-                        event_short(&inner_event_id),
-                        upper_bound
-                    ),
-                    negative: format!(
-                        "the {} is not above {}",
-                        event_short(&inner_event_id),
-                        upper_bound
-                    ),
+                predicate => {
+                    let bound = bound_phrase(&inner_event_id, &predicate, false);
+                    OutcomeDesc {
+                        positive: format!("the {} is {}", event_short(&inner_event_id), bound),
+                        negative: format!("the {} is not {}", event_short(&inner_event_id), bound),
+                    }
+                    .maybe_negate(outcome_str == "false")
                 }
-                .maybe_negate(outcome_str == "false"),
             }
         }
         _ => OutcomeDesc {
@@ -431,6 +534,7 @@ pub fn long_path_name_str(path: &str) -> Option<String> {
 fn lookup_competition(name: &str) -> &str {
     match name {
         "EPL" => "English Premier League",
+        "F1" => "Formula 1",
         _ => name,
     }
 }
@@ -461,6 +565,24 @@ fn lookup_team<'a>(competition: &str, name: &'a str) -> &'a str {
     }
 }
 
+fn lookup_competitor<'a>(competition: &str, name: &'a str) -> &'a str {
+    match (competition, name) {
+        ("F1", "HAM") => "Lewis Hamilton",
+        ("F1", "VER") => "Max Verstappen",
+        ("F1", "BOT") => "Valtteri Bottas",
+        ("F1", "PER") => "Sergio Perez",
+        ("F1", "NOR") => "Lando Norris",
+        ("F1", "RIC") => "Daniel Ricciardo",
+        ("F1", "LEC") => "Charles Leclerc",
+        ("F1", "SAI") => "Carlos Sainz",
+        ("F1", "GAS") => "Pierre Gasly",
+        ("F1", "ALO") => "Fernando Alonso",
+        ("F1", "OCO") => "Esteban Ocon",
+        ("F1", "VET") => "Sebastian Vettel",
+        _ => lookup_team(competition, name),
+    }
+}
+
 fn exchange_url(exchange: &str) -> Option<&'static str> {
     Some(match exchange {
         "BitMEX" => "https://bitmex.com",
@@ -538,6 +660,74 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_enum_event() {
+        let event_id = "/election/2024/president.enum?o=RED,GREEN,BLUE";
+        assert_eq!(
+            event_short_str(event_id).unwrap(),
+            "/election/2024/president (one of RED, GREEN, BLUE)"
+        );
+        assert_eq!(
+            outcome_str(event_id, "GREEN").unwrap(),
+            "the /election/2024/president (one of RED, GREEN, BLUE) is \"GREEN\""
+        );
+        assert_eq!(
+            outcome_str(
+                "/election/2024/president.enum=BLUE?o=RED,GREEN,BLUE",
+                "false"
+            )
+            .unwrap(),
+            "the /election/2024/president (one of RED, GREEN, BLUE) is not \"BLUE\""
+        );
+    }
+
+    #[test]
+    fn test_competition() {
+        let first = "/s/F1/race/2021-08-29/HAM_VER_RUS.first";
+        let podium = "/s/F1/race/2021-08-29/HAM_VER_RUS.podium";
+        assert_eq!(
+            event_short_str(first).unwrap(),
+            "winner of the Formula 1 race on 2021-08-29"
+        );
+        assert_eq!(
+            outcome_str(first, "VER").unwrap(),
+            "Max Verstappen is the winner of the Formula 1 race on 2021-08-29"
+        );
+        assert_eq!(
+            outcome_str(podium, "VER,RUS,HAM").unwrap(),
+            "the podium of the Formula 1 race on 2021-08-29 is Max Verstappen first, RUS second and Lewis Hamilton third"
+        );
+        assert_eq!(
+            outcome_str("/s/F1/race/2021-08-29/HAM_VER_RUS.first=HAM", "false").unwrap(),
+            "Lewis Hamilton is not the winner of the Formula 1 race on 2021-08-29"
+        );
+        assert_eq!(
+            event_short_str("/tournaments/chess/A_B_C.first").unwrap(),
+            "winner of A, B, C in /tournaments/chess"
+        );
+    }
+
+    #[test]
+    fn test_score() {
+        let score = "/s/EPL/match/2021-08-13/BRE_ARS.score";
+        assert_eq!(
+            event_short_str(score).unwrap(),
+            "score of English Premier League match Brentford vs Arsenal on 2021-08-13"
+        );
+        assert_eq!(
+            outcome_str(score, "2-0").unwrap(),
+            "the score of English Premier League match Brentford vs Arsenal on 2021-08-13 is \"2-0\""
+        );
+        assert_eq!(
+            outcome_str("/s/EPL/match/2021-08-13/BRE_ARS.total_2", "true").unwrap(),
+            "the total score of English Premier League match Brentford vs Arsenal on 2021-08-13 is greater than 2"
+        );
+        assert_eq!(
+            event_short_str("/s/EPL/match/2021-08-13/BRE_ARS.margin_le_-2").unwrap(),
+            "assertion that the margin of Brentford over Arsenal in their English Premier League match on 2021-08-13 will be less than or equal to -2"
+        );
+    }
+
     #[test]
     fn test_x_path() {
         assert_eq!(
@@ -573,5 +763,17 @@ mod test {
             event_short_str("/x/BitMEX/BXBT/2021-10-05T5:00:00.price_10000"),
             Some("assertion that the price of BXBT on BitMEX at 2021-10-05T5:00:00 will be greater than 10000".into())
         );
+        assert_eq!(
+            event_short_str("/x/BitMEX/BXBT/2021-10-05T5:00:00.price_le_10000"),
+            Some("assertion that the price of BXBT on BitMEX at 2021-10-05T5:00:00 will be less than or equal to 10000".into())
+        );
+        assert_eq!(
+            event_short_str("/x/BitMEX/BXBT/2021-10-05T5:00:00.price_in_40000..50000?unit=usd"),
+            Some("assertion that the price of BXBT on BitMEX at 2021-10-05T5:00:00 will be between 40000 usd and 50000 usd (inclusive)".into())
+        );
+        assert_eq!(
+            outcome_str("/x/BitMEX/BXBT/2021-10-05T5:00:00.price_lt_30000", "false"),
+            Some("the price of BXBT on BitMEX at 2021-10-05T5:00:00 is not less than 30000".into())
+        );
     }
 }
@@ -0,0 +1,646 @@
+#![allow(non_snake_case)]
+//! An implementation of olivia's [`Group`] over the [ristretto] prime order group.
+//!
+//! Since there is no ECDSA for ristretto the `ecdsa_v1` scheme uses a Schnorr signature with its
+//! own domain separation tag instead. It's still a plain signature on the attestation string
+//! under the `ecdsa_v1` key which is all the scheme requires.
+//!
+//! [`Group`]: olivia_core::Group
+//! [ristretto]: https://ristretto.group
+pub use curve25519_dalek;
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_TABLE,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use olivia_core::{FixedBytes, GroupObject, OracleKeys, SignatureKind};
+pub use serde;
+use sha2::{Digest, Sha512};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ristretto;
+
+/// Decodes a compressed point making sure it is a valid ristretto encoding.
+fn decompress(bytes: [u8; 32]) -> Option<CompressedRistretto> {
+    let point = CompressedRistretto(bytes);
+    point.decompress().map(|_| point)
+}
+
+fn scalar_from_bytes(bytes: [u8; 32]) -> Option<Scalar> {
+    Scalar::from_canonical_bytes(bytes)
+}
+
+#[derive(PartialEq, Clone)]
+pub struct PublicKey(CompressedRistretto);
+
+olivia_core::impl_display_debug_serialize_tosql! {
+    fn to_bytes(pk: &PublicKey) -> &[u8;32] {
+        pk.0.as_bytes()
+    }
+}
+
+olivia_core::impl_fromstr_deserialize_fromsql! {
+    name => "ristretto public key",
+    fn from_bytes(bytes: [u8;32]) ->  Option<PublicKey> {
+        decompress(bytes).map(PublicKey)
+    }
+}
+
+impl GroupObject for PublicKey {}
+
+impl FixedBytes for PublicKey {
+    const LEN: usize = 32;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        decompress(bytes.try_into().ok()?).map(PublicKey)
+    }
+}
+
+#[derive(PartialEq, Clone)]
+pub struct PublicNonce(CompressedRistretto);
+impl GroupObject for PublicNonce {}
+
+impl FixedBytes for PublicNonce {
+    const LEN: usize = 32;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        decompress(bytes.try_into().ok()?).map(PublicNonce)
+    }
+}
+
+olivia_core::impl_display_debug_serialize_tosql! {
+    fn to_bytes(pn: &PublicNonce) -> &[u8;32] {
+        pn.0.as_bytes()
+    }
+}
+
+olivia_core::impl_fromstr_deserialize_fromsql! {
+    name => "ristretto public nonce",
+    fn from_bytes(bytes: [u8;32]) ->  Option<PublicNonce> {
+        decompress(bytes).map(PublicNonce)
+    }
+}
+
+#[derive(PartialEq, Clone)]
+pub struct AttestScalar(Scalar);
+impl GroupObject for AttestScalar {}
+
+impl FixedBytes for AttestScalar {
+    const LEN: usize = 32;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        scalar_from_bytes(bytes.try_into().ok()?).map(AttestScalar)
+    }
+}
+
+olivia_core::impl_display_debug_serialize_tosql! {
+    fn to_bytes(scalar: &AttestScalar) -> &[u8;32] {
+        scalar.0.as_bytes()
+    }
+}
+
+olivia_core::impl_fromstr_deserialize_fromsql! {
+    name => "ristretto scalar",
+    fn from_bytes(bytes: [u8;32]) ->  Option<AttestScalar> {
+        scalar_from_bytes(bytes).map(AttestScalar)
+    }
+}
+
+/// A Schnorr signature `(R, s)` encoded as `R || s`.
+#[derive(PartialEq, Clone, Copy)]
+struct SchnorrSignature {
+    R: CompressedRistretto,
+    s: Scalar,
+}
+
+impl SchnorrSignature {
+    fn to_bytes(self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(self.R.as_bytes());
+        bytes[32..].copy_from_slice(self.s.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: [u8; 64]) -> Option<Self> {
+        let mut R = [0u8; 32];
+        let mut s = [0u8; 32];
+        R.copy_from_slice(&bytes[..32]);
+        s.copy_from_slice(&bytes[32..]);
+        Some(SchnorrSignature {
+            R: decompress(R)?,
+            s: scalar_from_bytes(s)?,
+        })
+    }
+
+    fn from_slice(bytes: &[u8]) -> Option<Self> {
+        Self::from_bytes(bytes.try_into().ok()?)
+    }
+}
+
+#[derive(PartialEq, Clone)]
+pub struct Signature(SchnorrSignature);
+
+olivia_core::impl_display_debug_serialize_tosql! {
+    fn to_bytes(sig: &Signature) -> [u8;64] {
+        sig.0.to_bytes()
+    }
+}
+
+olivia_core::impl_fromstr_deserialize_fromsql! {
+    name => "ristretto schnorr signature",
+    fn from_bytes(bytes: [u8;64]) ->  Option<Signature> {
+        SchnorrSignature::from_bytes(bytes).map(Signature)
+    }
+}
+
+impl GroupObject for Signature {}
+
+impl FixedBytes for Signature {
+    const LEN: usize = 64;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        SchnorrSignature::from_slice(bytes).map(Signature)
+    }
+}
+
+/// The signature used by the `ecdsa_v1` scheme.
+///
+/// It has the same encoding as [`Signature`] but is made under a different tag so the two can't be
+/// confused for one another.
+#[derive(PartialEq, Clone)]
+pub struct EcdsaSignature(SchnorrSignature);
+
+olivia_core::impl_display_debug_serialize_tosql! {
+    fn to_bytes(sig: &EcdsaSignature) -> [u8;64] {
+        sig.0.to_bytes()
+    }
+}
+
+olivia_core::impl_fromstr_deserialize_fromsql! {
+    name => "ristretto ecdsa-v1 signature",
+    fn from_bytes(bytes: [u8;64]) ->  Option<EcdsaSignature> {
+        SchnorrSignature::from_bytes(bytes).map(EcdsaSignature)
+    }
+}
+
+impl GroupObject for EcdsaSignature {}
+
+impl FixedBytes for EcdsaSignature {
+    const LEN: usize = 64;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        SchnorrSignature::from_slice(bytes).map(EcdsaSignature)
+    }
+}
+
+#[derive(Clone)]
+pub struct KeyPair {
+    secret_key: Scalar,
+    public_key: CompressedRistretto,
+}
+
+impl KeyPair {
+    pub fn new(secret_key: Scalar) -> Self {
+        let public_key = (&secret_key * &RISTRETTO_BASEPOINT_TABLE).compress();
+        KeyPair {
+            secret_key,
+            public_key,
+        }
+    }
+
+    pub fn secret_key(&self) -> &Scalar {
+        &self.secret_key
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(self.public_key)
+    }
+}
+
+impl From<KeyPair> for PublicKey {
+    fn from(kp: KeyPair) -> Self {
+        kp.public_key()
+    }
+}
+
+impl From<(Scalar, PublicNonce)> for PublicNonce {
+    fn from(kp: (Scalar, PublicNonce)) -> Self {
+        kp.1
+    }
+}
+
+impl From<PublicKey> for CompressedRistretto {
+    fn from(pk: PublicKey) -> Self {
+        pk.0
+    }
+}
+
+impl From<PublicNonce> for CompressedRistretto {
+    fn from(pn: PublicNonce) -> Self {
+        pn.0
+    }
+}
+
+impl From<AttestScalar> for Scalar {
+    fn from(att_scalar: AttestScalar) -> Self {
+        att_scalar.0
+    }
+}
+
+const DLC_ATTESTATION_TAG: &str = "DLC/oracle/attestation/v0";
+const ECDSA_V1_TAG: &str = "olivia/ecdsa-v1";
+
+fn tagged_hash(tag: &str) -> Sha512 {
+    let tag = Sha512::digest(tag.as_bytes());
+    Sha512::default().chain(tag).chain(tag)
+}
+
+fn challenge(
+    tag: &str,
+    R: &CompressedRistretto,
+    X: &CompressedRistretto,
+    message: &[u8],
+) -> Scalar {
+    Scalar::from_hash(
+        tagged_hash(tag)
+            .chain(R.as_bytes())
+            .chain(X.as_bytes())
+            .chain(message),
+    )
+}
+
+fn schnorr_sign(tag: &str, keypair: &KeyPair, message: &[u8]) -> SchnorrSignature {
+    let r = Scalar::from_hash(
+        tagged_hash("olivia/ristretto/nonce")
+            .chain(keypair.secret_key.as_bytes())
+            .chain(tag.as_bytes())
+            .chain(message),
+    );
+    let R = (&r * &RISTRETTO_BASEPOINT_TABLE).compress();
+    sign_with_nonce(tag, keypair, r, R, message)
+}
+
+fn sign_with_nonce(
+    tag: &str,
+    keypair: &KeyPair,
+    r: Scalar,
+    R: CompressedRistretto,
+    message: &[u8],
+) -> SchnorrSignature {
+    let c = challenge(tag, &R, &keypair.public_key, message);
+    SchnorrSignature {
+        R,
+        s: r + c * keypair.secret_key,
+    }
+}
+
+fn schnorr_verify(
+    tag: &str,
+    public_key: &CompressedRistretto,
+    message: &[u8],
+    sig: &SchnorrSignature,
+) -> bool {
+    let (X, R) = match (public_key.decompress(), sig.R.decompress()) {
+        (Some(X), Some(R)) => (X, R),
+        _ => return false,
+    };
+    let c = challenge(tag, &sig.R, public_key, message);
+    RistrettoPoint::vartime_double_scalar_mul_basepoint(&-c, &X, &sig.s) == R
+}
+
+impl olivia_core::Group for Ristretto {
+    type KeyPair = KeyPair;
+    type PublicKey = PublicKey;
+    type PublicNonce = PublicNonce;
+    type NonceKeyPair = (Scalar, PublicNonce);
+    type Signature = Signature;
+    type AttestScalar = AttestScalar;
+    type AnticipatedAttestation = RistrettoPoint;
+    type EcdsaSignature = EcdsaSignature;
+    const KEY_MATERIAL_LEN: usize = 64;
+
+    fn name() -> &'static str {
+        "ristretto"
+    }
+
+    fn verify_announcement_key_signature(
+        kind: SignatureKind,
+        public_key: &Self::PublicKey,
+        message: &[u8],
+        sig: &Self::Signature,
+    ) -> bool {
+        schnorr_verify(kind.tag(), &public_key.0, message, &sig.0)
+    }
+
+    fn reveal_attest_scalar(
+        signing_key: &Self::KeyPair,
+        nonce_key: Self::NonceKeyPair,
+        index: u32,
+    ) -> Self::AttestScalar {
+        let r = nonce_key.0;
+        let c = Scalar::from(index);
+        let x = signing_key.secret_key;
+        AttestScalar((c + Scalar::one()) * r + x)
+    }
+
+    fn anticipate_attestations(
+        public_key: &Self::PublicKey,
+        public_nonce: &Self::PublicNonce,
+        n_outcomes: u32,
+    ) -> Vec<Self::AnticipatedAttestation> {
+        let (X, R) = match (public_key.0.decompress(), public_nonce.0.decompress()) {
+            (Some(X), Some(R)) => (X, R),
+            _ => unreachable!("public keys and nonces are always valid points"),
+        };
+        (0..n_outcomes)
+            .scan(X, |C, _| {
+                *C += R;
+                Some(*C)
+            })
+            .collect()
+    }
+
+    fn combine_anticipated_attestations(
+        anticipated: &[Self::AnticipatedAttestation],
+    ) -> Self::AnticipatedAttestation {
+        anticipated.iter().sum()
+    }
+
+    fn combine_attest_scalars(scalars: &[Self::AttestScalar]) -> Self::AttestScalar {
+        AttestScalar(scalars.iter().map(|scalar| scalar.0).sum())
+    }
+
+    fn recover_attestation_key(
+        (i, s_i): (u32, &Self::AttestScalar),
+        (j, s_j): (u32, &Self::AttestScalar),
+    ) -> Option<Self::KeyPair> {
+        let (c_i, c_j) = (Scalar::from(i), Scalar::from(j));
+        if c_i == c_j {
+            return None;
+        }
+        // s_i - s_j = (i - j) * r
+        let r = (s_i.0 - s_j.0) * (c_i - c_j).invert();
+        Some(KeyPair::new(s_i.0 - (c_i + Scalar::one()) * r))
+    }
+
+    fn sign_with_announcement_key(
+        kind: SignatureKind,
+        keypair: &Self::KeyPair,
+        message: &[u8],
+    ) -> Self::Signature {
+        Signature(schnorr_sign(kind.tag(), keypair, message))
+    }
+
+    fn dlc_sign_outcome(
+        signing_key: &Self::KeyPair,
+        nonce_key: Self::NonceKeyPair,
+        outcome: &str,
+    ) -> Self::Signature {
+        let (r, R) = nonce_key;
+        Signature(sign_with_nonce(
+            DLC_ATTESTATION_TAG,
+            signing_key,
+            r,
+            R.0,
+            outcome.as_bytes(),
+        ))
+    }
+
+    fn dlc_verify_outcome(
+        public_key: &Self::PublicKey,
+        public_nonce: &Self::PublicNonce,
+        outcome: &str,
+        sig: &Self::Signature,
+    ) -> bool {
+        sig.0.R == public_nonce.0
+            && schnorr_verify(
+                DLC_ATTESTATION_TAG,
+                &public_key.0,
+                outcome.as_bytes(),
+                &sig.0,
+            )
+    }
+
+    fn verify_attest_scalar(
+        public_key: &Self::PublicKey,
+        public_nonce: &Self::PublicNonce,
+        index: u32,
+        attest_scalar: &Self::AttestScalar,
+    ) -> bool {
+        let (X, R) = match (public_key.0.decompress(), public_nonce.0.decompress()) {
+            (Some(X), Some(R)) => (X, R),
+            _ => return false,
+        };
+        let c = Scalar::from(index) + Scalar::one();
+        &attest_scalar.0 * &RISTRETTO_BASEPOINT_TABLE == c * R + X
+    }
+
+    fn test_keypair() -> Self::KeyPair {
+        KeyPair::new(Scalar::from_bytes_mod_order([42u8; 32]))
+    }
+
+    fn test_nonce_keypair() -> Self::NonceKeyPair {
+        nonce_keypair(Scalar::from_bytes_mod_order([84u8; 32]))
+    }
+
+    fn test_oracle_keys() -> OracleKeys<Self> {
+        let public_key = |byte| KeyPair::new(Scalar::from_bytes_mod_order([byte; 32])).public_key();
+        OracleKeys {
+            announcement: public_key(13u8),
+            ecdsa_v1: Some(public_key(14u8)),
+            olivia_v1: Some(public_key(16u8)),
+            group: Ristretto,
+            epoch: 0,
+        }
+    }
+
+    fn keypair_from_secret_bytes(bytes: &[u8]) -> Self::KeyPair {
+        KeyPair::new(scalar_from_wide_bytes(bytes))
+    }
+
+    fn nonce_keypair_from_secret_bytes(bytes: &[u8]) -> Self::NonceKeyPair {
+        nonce_keypair(scalar_from_wide_bytes(bytes))
+    }
+
+    fn ecdsa_sign(keypair: &Self::KeyPair, message: &[u8]) -> Self::EcdsaSignature {
+        EcdsaSignature(schnorr_sign(ECDSA_V1_TAG, keypair, message))
+    }
+
+    fn ecdsa_verify(
+        public_key: &Self::PublicKey,
+        message: &[u8],
+        sig: &Self::EcdsaSignature,
+    ) -> bool {
+        schnorr_verify(ECDSA_V1_TAG, &public_key.0, message, &sig.0)
+    }
+}
+
+fn scalar_from_wide_bytes(bytes: &[u8]) -> Scalar {
+    let bytes: &[u8; 64] = bytes.try_into().expect("will be 64 bytes long");
+    let scalar = Scalar::from_bytes_mod_order_wide(bytes);
+    assert!(scalar != Scalar::zero(), "will not be zero");
+    scalar
+}
+
+fn nonce_keypair(r: Scalar) -> (Scalar, PublicNonce) {
+    let R = &r * &RISTRETTO_BASEPOINT_TABLE;
+    debug_assert!(R != RistrettoPoint::identity());
+    (r, PublicNonce(R.compress()))
+}
+
+olivia_core::impl_deserialize_curve!(Ristretto);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::str::FromStr;
+    use olivia_core::Group;
+
+    #[test]
+    fn anticipate_vs_attest() {
+        let oracle_key = Ristretto::test_keypair();
+        let nonce_key = Ristretto::test_nonce_keypair();
+        let public_key = oracle_key.public_key();
+        let public_nonce: PublicNonce = nonce_key.clone().into();
+        let attestation_points = Ristretto::anticipate_attestations(&public_key, &public_nonce, 5);
+        for (i, point) in attestation_points.iter().enumerate() {
+            let scalar = Ristretto::reveal_attest_scalar(&oracle_key, nonce_key.clone(), i as u32);
+            assert_eq!(&(&scalar.0 * &RISTRETTO_BASEPOINT_TABLE), point);
+            assert!(Ristretto::verify_attest_scalar(
+                &public_key,
+                &public_nonce,
+                i as u32,
+                &scalar
+            ));
+            assert!(!Ristretto::verify_attest_scalar(
+                &public_key,
+                &public_nonce,
+                i as u32 + 1,
+                &scalar
+            ));
+        }
+    }
+
+    #[test]
+    fn two_outcomes_reveal_key() {
+        let oracle_key = Ristretto::test_keypair();
+        let nonce_key = Ristretto::test_nonce_keypair();
+        let s_1 = Ristretto::reveal_attest_scalar(&oracle_key, nonce_key.clone(), 1);
+        let s_4 = Ristretto::reveal_attest_scalar(&oracle_key, nonce_key, 4);
+        let recovered = Ristretto::recover_attestation_key((4, &s_4), (1, &s_1)).unwrap();
+        assert_eq!(recovered.secret_key(), oracle_key.secret_key());
+        assert!(Ristretto::recover_attestation_key((1, &s_1), (1, &s_1)).is_none());
+    }
+
+    #[test]
+    fn sign_vs_verify() {
+        let keypair = Ristretto::test_keypair();
+        let public_key = keypair.public_key();
+        let signature = Ristretto::sign_announcement(&keypair, b"announcement");
+        assert!(Ristretto::verify_announcement_signature(
+            &public_key,
+            b"announcement",
+            &signature
+        ));
+        assert!(!Ristretto::verify_announcement_signature(
+            &public_key,
+            b"another announcement",
+            &signature
+        ));
+
+        let ecdsa_signature = Ristretto::ecdsa_sign(&keypair, b"outcome");
+        assert!(Ristretto::ecdsa_verify(
+            &public_key,
+            b"outcome",
+            &ecdsa_signature
+        ));
+        assert!(!Ristretto::ecdsa_verify(
+            &public_key,
+            b"another outcome",
+            &ecdsa_signature
+        ));
+        // the same key signing the same message under different schemes can't be swapped around
+        let swapped = Signature(ecdsa_signature.0);
+        assert!(!Ristretto::verify_announcement_signature(
+            &public_key,
+            b"outcome",
+            &swapped
+        ));
+    }
+
+    #[test]
+    fn dlc_sign_vs_verify() {
+        let keypair = Ristretto::test_keypair();
+        let public_key = keypair.public_key();
+        let nonce_keypair = Ristretto::test_nonce_keypair();
+        let public_nonce: PublicNonce = nonce_keypair.clone().into();
+        let signature = Ristretto::dlc_sign_outcome(&keypair, nonce_keypair, "BRE_win");
+        assert!(Ristretto::dlc_verify_outcome(
+            &public_key,
+            &public_nonce,
+            "BRE_win",
+            &signature
+        ));
+        assert!(!Ristretto::dlc_verify_outcome(
+            &public_key,
+            &public_nonce,
+            "ARS_win",
+            &signature
+        ));
+        let other_nonce = Ristretto::test_oracle_keys().announcement.0;
+        assert!(!Ristretto::dlc_verify_outcome(
+            &public_key,
+            &PublicNonce(other_nonce),
+            "BRE_win",
+            &signature
+        ));
+    }
+
+    #[test]
+    fn encoding_roundtrip() {
+        let keypair = Ristretto::test_keypair();
+        let public_key = keypair.public_key();
+        let signature = Ristretto::sign_announcement(&keypair, b"announcement");
+        assert_eq!(
+            PublicKey::from_str(&public_key.to_string()).unwrap(),
+            public_key
+        );
+        assert_eq!(
+            Signature::from_str(&signature.to_string()).unwrap(),
+            signature
+        );
+        assert_eq!(
+            <Signature as FixedBytes>::from_bytes(&FixedBytes::to_bytes(&signature)),
+            Some(signature)
+        );
+        // not every 32 bytes is a valid ristretto encoding
+        assert!(PublicKey::from_str(&"ff".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn curve_serialization() {
+        assert_eq!(serde_json::to_string(&Ristretto).unwrap(), "\"ristretto\"");
+        assert!(serde_json::from_str::<Ristretto>("\"secp256k1\"").is_err());
+    }
+}
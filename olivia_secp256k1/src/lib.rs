@@ -1,8 +1,6 @@
 #![allow(non_snake_case)]
 pub use ecdsa_fun;
-use olivia_core::{GroupObject, OracleKeys};
-#[doc(hidden)]
-pub use schnorr_fun::fun::hex;
+use olivia_core::{FixedBytes, GroupObject, OracleKeys, SignatureKind};
 pub use schnorr_fun::{self, fun, KeyPair};
 use schnorr_fun::{
     fun::{g, marker::*, nonce::Deterministic, s, Point, Scalar, XOnly, G},
@@ -10,7 +8,6 @@ use schnorr_fun::{
 };
 pub use serde;
 use sha2::{Digest, Sha256};
-mod macros;
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Secp256k1;
@@ -18,13 +15,13 @@ pub struct Secp256k1;
 #[derive(PartialEq, Clone)]
 pub struct PublicKey(XOnly);
 
-crate::impl_display_debug_serialize_tosql! {
+olivia_core::impl_display_debug_serialize_tosql! {
     fn to_bytes(pk: &PublicKey) -> &[u8;32] {
         pk.0.as_bytes()
     }
 }
 
-crate::impl_fromstr_deserialize_fromsql! {
+olivia_core::impl_fromstr_deserialize_fromsql! {
     name => "secp256k1 xonly public key",
     fn from_bytes(bytes: [u8;32]) ->  Option<PublicKey> {
         XOnly::from_bytes(bytes).map(PublicKey)
@@ -33,17 +30,41 @@ crate::impl_fromstr_deserialize_fromsql! {
 
 impl GroupObject for PublicKey {}
 
+impl FixedBytes for PublicKey {
+    const LEN: usize = 32;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        XOnly::from_bytes(bytes.try_into().ok()?).map(PublicKey)
+    }
+}
+
 #[derive(PartialEq, Clone)]
 pub struct PublicNonce(XOnly);
 impl GroupObject for PublicNonce {}
 
-crate::impl_display_debug_serialize_tosql! {
+impl FixedBytes for PublicNonce {
+    const LEN: usize = 32;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        XOnly::from_bytes(bytes.try_into().ok()?).map(PublicNonce)
+    }
+}
+
+olivia_core::impl_display_debug_serialize_tosql! {
     fn to_bytes(pn: &PublicNonce) -> &[u8;32] {
         pn.0.as_bytes()
     }
 }
 
-crate::impl_fromstr_deserialize_fromsql! {
+olivia_core::impl_fromstr_deserialize_fromsql! {
     name => "secp256k1 xonly public nonce",
     fn from_bytes(bytes: [u8;32]) ->  Option<PublicNonce> {
         XOnly::from_bytes(bytes).map(PublicNonce)
@@ -54,13 +75,25 @@ crate::impl_fromstr_deserialize_fromsql! {
 pub struct AttestScalar(Scalar<Public, Zero>);
 impl GroupObject for AttestScalar {}
 
-crate::impl_display_debug_serialize_tosql! {
+impl FixedBytes for AttestScalar {
+    const LEN: usize = 32;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Scalar::from_bytes(bytes.try_into().ok()?).map(|s| AttestScalar(s.mark::<Public>()))
+    }
+}
+
+olivia_core::impl_display_debug_serialize_tosql! {
     fn to_bytes(scalar: &AttestScalar) -> [u8;32] {
         scalar.0.to_bytes()
     }
 }
 
-crate::impl_fromstr_deserialize_fromsql! {
+olivia_core::impl_fromstr_deserialize_fromsql! {
     name => "secp256k1 scalar",
     fn from_bytes(bytes: [u8;32]) ->  Option<AttestScalar> {
         Scalar::from_bytes(bytes).map(|s| AttestScalar(s.mark::<Public>()))
@@ -70,13 +103,13 @@ crate::impl_fromstr_deserialize_fromsql! {
 #[derive(PartialEq, Clone)]
 pub struct Signature(schnorr_fun::Signature);
 
-crate::impl_display_debug_serialize_tosql! {
+olivia_core::impl_display_debug_serialize_tosql! {
     fn to_bytes(sig: &Signature) -> [u8;64] {
         sig.0.to_bytes()
     }
 }
 
-crate::impl_fromstr_deserialize_fromsql! {
+olivia_core::impl_fromstr_deserialize_fromsql! {
     name => "bip340 schnorr signature",
     fn from_bytes(bytes: [u8;64]) ->  Option<Signature> {
         schnorr_fun::Signature::from_bytes(bytes).map(Signature)
@@ -85,16 +118,28 @@ crate::impl_fromstr_deserialize_fromsql! {
 
 impl GroupObject for Signature {}
 
+impl FixedBytes for Signature {
+    const LEN: usize = 64;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        schnorr_fun::Signature::from_bytes(bytes.try_into().ok()?).map(Signature)
+    }
+}
+
 #[derive(PartialEq, Clone)]
 pub struct EcdsaSignature(ecdsa_fun::Signature);
 
-crate::impl_display_debug_serialize_tosql! {
+olivia_core::impl_display_debug_serialize_tosql! {
     fn to_bytes(sig: &EcdsaSignature) -> [u8;64] {
         sig.0.to_bytes()
     }
 }
 
-crate::impl_fromstr_deserialize_fromsql! {
+olivia_core::impl_fromstr_deserialize_fromsql! {
     name => "ecdsa signature",
     fn from_bytes(bytes: [u8;64]) ->  Option<EcdsaSignature> {
         ecdsa_fun::Signature::from_bytes(bytes).map(EcdsaSignature)
@@ -103,6 +148,18 @@ crate::impl_fromstr_deserialize_fromsql! {
 
 impl GroupObject for EcdsaSignature {}
 
+impl FixedBytes for EcdsaSignature {
+    const LEN: usize = 64;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        ecdsa_fun::Signature::from_bytes(bytes.try_into().ok()?).map(EcdsaSignature)
+    }
+}
+
 lazy_static::lazy_static! {
     pub static ref SCHNORR: Schnorr<Sha256, Deterministic<Sha256>> = Schnorr::new(Deterministic::<Sha256>::default());
     pub static ref ECDSA: ecdsa_fun::ECDSA<Deterministic<Sha256>> = ecdsa_fun::ECDSA::<Deterministic<Sha256>>::default();
@@ -138,6 +195,18 @@ impl From<AttestScalar> for Scalar<Public, Zero> {
     }
 }
 
+impl From<Scalar<Public, Zero>> for AttestScalar {
+    fn from(scalar: Scalar<Public, Zero>) -> Self {
+        Self(scalar)
+    }
+}
+
+impl From<schnorr_fun::Signature> for Signature {
+    fn from(sig: schnorr_fun::Signature) -> Self {
+        Self(sig)
+    }
+}
+
 impl From<KeyPair> for PublicKey {
     fn from(kp: KeyPair) -> Self {
         let (_, pk) = kp.into();
@@ -165,18 +234,26 @@ impl olivia_core::Group for Secp256k1 {
         "secp256k1"
     }
 
-    fn verify_announcement_signature(
+    fn verify_announcement_key_signature(
+        kind: SignatureKind,
         public_key: &Self::PublicKey,
         message: &[u8],
         sig: &Self::Signature,
     ) -> bool {
         let public_key = public_key.0.clone();
         let verification_key = public_key.to_point();
-        SCHNORR.verify(
-            &verification_key,
-            Message::<Public>::plain("DLC/announcement", &message[..]),
-            &sig.0,
-        )
+        match kind {
+            SignatureKind::DlcAnnouncement => SCHNORR.verify(
+                &verification_key,
+                Message::<Public>::raw(&tagged_hash(kind.tag(), message)),
+                &sig.0,
+            ),
+            _ => SCHNORR.verify(
+                &verification_key,
+                Message::<Public>::plain(kind.tag(), message),
+                &sig.0,
+            ),
+        }
     }
 
     fn reveal_attest_scalar(
@@ -205,11 +282,84 @@ impl olivia_core::Group for Secp256k1 {
             .collect()
     }
 
-    fn sign_announcement(keypair: &Self::KeyPair, announcement: &[u8]) -> Self::Signature {
-        Signature(SCHNORR.sign(
-            keypair,
-            Message::<Public>::plain("DLC/announcement", announcement),
-        ))
+    fn combine_anticipated_attestations(
+        anticipated: &[Self::AnticipatedAttestation],
+    ) -> Self::AnticipatedAttestation {
+        anticipated
+            .iter()
+            .fold(Point::zero().mark::<Jacobian>(), |sum, point| {
+                g!(sum + point)
+            })
+    }
+
+    fn combine_attest_scalars(scalars: &[Self::AttestScalar]) -> Self::AttestScalar {
+        AttestScalar(
+            scalars
+                .iter()
+                .fold(Scalar::zero().mark::<Public>(), |sum, scalar| {
+                    s!(sum + { &scalar.0 }).mark::<Public>()
+                }),
+        )
+    }
+
+    fn recover_attestation_key(
+        (i, s_i): (u32, &Self::AttestScalar),
+        (j, s_j): (u32, &Self::AttestScalar),
+    ) -> Option<Self::KeyPair> {
+        let (c_i, c_j) = (Scalar::from(i), Scalar::from(j));
+        // s_i - s_j = (i - j) * r
+        let diff = s!(c_i - c_j).mark::<NonZero>()?;
+        let r = s!(({ &s_i.0 } - { &s_j.0 }) * { diff.invert() });
+        let x = s!({ &s_i.0 } - (c_i + 1) * r).mark::<(Secret, NonZero)>()?;
+        Some(SCHNORR.new_keypair(x))
+    }
+
+    fn sign_with_announcement_key(
+        kind: SignatureKind,
+        keypair: &Self::KeyPair,
+        message: &[u8],
+    ) -> Self::Signature {
+        Signature(match kind {
+            SignatureKind::DlcAnnouncement => SCHNORR.sign(
+                keypair,
+                Message::<Public>::raw(&tagged_hash(kind.tag(), message)),
+            ),
+            _ => SCHNORR.sign(keypair, Message::<Public>::plain(kind.tag(), message)),
+        })
+    }
+
+    fn dlc_sign_outcome(
+        signing_key: &Self::KeyPair,
+        nonce_key: Self::NonceKeyPair,
+        outcome: &str,
+    ) -> Self::Signature {
+        let (r, R) = nonce_key;
+        let message = dlc_attestation_message(outcome);
+        let c = SCHNORR.challenge(
+            R,
+            signing_key.public_key(),
+            Message::<Public>::raw(&message[..]),
+        );
+        let x = signing_key.secret_key();
+        Signature(schnorr_fun::Signature {
+            R,
+            s: s!(r + c * x).mark::<Public>(),
+        })
+    }
+
+    fn dlc_verify_outcome(
+        public_key: &Self::PublicKey,
+        public_nonce: &Self::PublicNonce,
+        outcome: &str,
+        sig: &Self::Signature,
+    ) -> bool {
+        let message = dlc_attestation_message(outcome);
+        sig.0.R == public_nonce.0
+            && SCHNORR.verify(
+                &public_key.0.to_point(),
+                Message::<Public>::raw(&message[..]),
+                &sig.0,
+            )
     }
 
     fn verify_attest_scalar(
@@ -247,6 +397,7 @@ impl olivia_core::Group for Secp256k1 {
             ecdsa_v1: Some(PublicKey(XOnly::from_bytes([14u8; 32]).unwrap())),
             olivia_v1: Some(PublicKey(XOnly::from_bytes([16u8; 32]).unwrap())),
             group: Secp256k1,
+            epoch: 0,
         }
     }
 
@@ -296,6 +447,24 @@ impl olivia_core::Group for Secp256k1 {
     }
 }
 
+/// The BIP340 tagged hash of `message`.
+fn tagged_hash(tag: &str, message: &[u8]) -> [u8; 32] {
+    let tag = Sha256::digest(tag.as_bytes());
+    let hash = Sha256::default()
+        .chain(tag)
+        .chain(tag)
+        .chain(message)
+        .finalize();
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(hash.as_ref());
+    digest
+}
+
+/// The BIP340 tagged hash of the outcome that the DLC spec says the oracle signs.
+fn dlc_attestation_message(outcome: &str) -> [u8; 32] {
+    tagged_hash("DLC/oracle/attestation/v0", outcome.as_bytes())
+}
+
 olivia_core::impl_deserialize_curve!(Secp256k1);
 
 #[cfg(test)]
@@ -322,8 +491,427 @@ mod test {
         assert_eq!(attestation_points, expected);
     }
 
+    #[test]
+    fn dlc_sign_vs_verify() {
+        let keypair = Secp256k1::test_keypair();
+        let public_key = keypair.clone().into();
+        let nonce_keypair = Secp256k1::test_nonce_keypair();
+        let public_nonce = nonce_keypair.clone().into();
+        let signature = Secp256k1::dlc_sign_outcome(&keypair, nonce_keypair, "BRE_win");
+        assert!(Secp256k1::dlc_verify_outcome(
+            &public_key,
+            &public_nonce,
+            "BRE_win",
+            &signature
+        ));
+        assert!(!Secp256k1::dlc_verify_outcome(
+            &public_key,
+            &public_nonce,
+            "ARS_win",
+            &signature
+        ));
+        let other_nonce = XOnly::from_bytes([13u8; 32]).unwrap().into();
+        assert!(!Secp256k1::dlc_verify_outcome(
+            &public_key,
+            &other_nonce,
+            "BRE_win",
+            &signature
+        ));
+    }
+
+    #[test]
+    fn adaptor_points_vs_adaptor_secret() {
+        use core::str::FromStr;
+        use olivia_core::{
+            announce, attest, chrono::NaiveDateTime, AnnouncementSchemes, Attestation,
+            AttestationSchemes, EventId, OracleEvent, Outcome,
+        };
+        let keypair = Secp256k1::test_keypair();
+        let oracle_keys = OracleKeys {
+            olivia_v1: Some(keypair.clone().into()),
+            ecdsa_v1: None,
+            announcement: keypair.clone().into(),
+            group: Secp256k1,
+            epoch: 0,
+        };
+        let event_id = EventId::from_str("/x/BitMEX/BXBT/2021-10-05T05:00:00.price?n=6").unwrap();
+        let oracle_event = OracleEvent::<Secp256k1> {
+            event: event_id.clone().into(),
+            schemes: AnnouncementSchemes {
+                olivia_v1: Some(announce::OliviaV1 {
+                    nonces: (0..6)
+                        .map(|_| Secp256k1::test_nonce_keypair().into())
+                        .collect(),
+                }),
+                ..Default::default()
+            },
+        };
+        let outcome =
+            |value: &str| Outcome::try_from_id_and_outcome(event_id.clone(), value).unwrap();
+        let adaptor_points = oracle_event
+            .adaptor_points_for_range_olivia_v1(&oracle_keys, &outcome("10"), &outcome("40"))
+            .unwrap();
+
+        for (value, in_range) in [("9", false), ("10", true), ("33", true), ("41", false)] {
+            let outcome = outcome(value);
+            let scalars = outcome
+                .attestation_indexes()
+                .iter()
+                .map(|index| {
+                    Secp256k1::reveal_attest_scalar(
+                        &keypair,
+                        Secp256k1::test_nonce_keypair(),
+                        *index,
+                    )
+                })
+                .collect();
+            let attestation = Attestation::<Secp256k1>::new(
+                outcome.outcome_string(),
+                NaiveDateTime::from_timestamp(0, 0),
+                AttestationSchemes {
+                    olivia_v1: Some(attest::OliviaV1 { scalars }),
+                    ..Default::default()
+                },
+            );
+            let completed = adaptor_points
+                .iter()
+                .filter_map(|adaptor_point| {
+                    let secret =
+                        attestation.olivia_v1_adaptor_secret(&event_id, &adaptor_point.prefix)?;
+                    assert_eq!(g!({ secret.0 } * G), adaptor_point.point);
+                    Some(())
+                })
+                .count();
+            assert_eq!(completed, in_range as usize);
+        }
+    }
+
+    #[test]
+    fn equivocation_leaks_attestation_key() {
+        use core::str::FromStr;
+        use olivia_core::{
+            announce, attest, chrono::NaiveDateTime, AnnouncementSchemes, Attestation,
+            AttestationSchemes, EquivocationInvalid, EquivocationProof, EventId, RawAnnouncement,
+        };
+        let announcement_keypair = Secp256k1::test_keypair();
+        let attestation_keypair = Secp256k1::keypair_from_secret_bytes(&[7u8; 32]);
+        let oracle_keys = OracleKeys {
+            olivia_v1: Some(attestation_keypair.clone().into()),
+            ecdsa_v1: None,
+            announcement: announcement_keypair.clone().into(),
+            group: Secp256k1,
+            epoch: 0,
+        };
+        let event_id = EventId::from_str("/foo/FOO_BAR.vs").unwrap();
+        let announcement = RawAnnouncement::<Secp256k1>::create(
+            event_id.clone().into(),
+            &announcement_keypair,
+            AnnouncementSchemes {
+                olivia_v1: Some(announce::OliviaV1 {
+                    nonces: vec![Secp256k1::test_nonce_keypair().into()],
+                }),
+                ..Default::default()
+            },
+        );
+        let attest_to = |outcome: &str| {
+            let mut attestation = Attestation::<Secp256k1>::new(
+                outcome.into(),
+                NaiveDateTime::from_timestamp(0, 0),
+                AttestationSchemes::default(),
+            );
+            let scalars = attestation
+                .attestation_indexes(&event_id)
+                .unwrap()
+                .into_iter()
+                .map(|index| {
+                    Secp256k1::reveal_attest_scalar(
+                        &attestation_keypair,
+                        Secp256k1::test_nonce_keypair(),
+                        index,
+                    )
+                })
+                .collect();
+            attestation.schemes.olivia_v1 = Some(attest::OliviaV1 { scalars });
+            attestation
+        };
+
+        for (first, second) in [("FOO_win", "BAR_win"), ("draw", olivia_core::VOID_OUTCOME)] {
+            let (proof, leaked) = EquivocationProof::from_attestations(
+                &event_id,
+                &announcement,
+                &oracle_keys,
+                &attest_to(first),
+                &attest_to(second),
+            )
+            .unwrap();
+            assert_eq!(leaked.secret_key(), attestation_keypair.secret_key());
+
+            let proof: EquivocationProof<Secp256k1> =
+                serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+            assert!(proof.verify(&oracle_keys).is_ok());
+
+            let mut bogus = proof.clone();
+            bogus.second.scalar = bogus.first.scalar.clone();
+            assert_eq!(
+                bogus.verify(&oracle_keys).unwrap_err(),
+                EquivocationInvalid::Scalar
+            );
+        }
+
+        assert_eq!(
+            EquivocationProof::from_attestations(
+                &event_id,
+                &announcement,
+                &oracle_keys,
+                &attest_to("draw"),
+                &attest_to("draw"),
+            )
+            .unwrap_err(),
+            EquivocationInvalid::NoConflict
+        );
+    }
+
     #[test]
     fn test_oracle_keys() {
         let _ = Secp256k1::test_oracle_keys();
     }
+
+    mod tlv {
+        use super::*;
+        use core::str::FromStr;
+        use olivia_core::{
+            announce, attest,
+            chrono::NaiveDateTime,
+            tlv::{TlvReader, ORACLE_ATTESTATION_TYPE},
+            AnnouncementSchemes, Attestation, AttestationSchemes, Encoding, Event, EventId,
+            Outcome, RawAnnouncement,
+        };
+
+        fn tlv_announcement(event: Event) -> RawAnnouncement<Secp256k1> {
+            let nonces = (0..event.id.n_nonces())
+                .map(|_| Secp256k1::test_nonce_keypair().into())
+                .collect();
+            RawAnnouncement::create_tlv(
+                event,
+                &Secp256k1::test_keypair(),
+                AnnouncementSchemes {
+                    olivia_v1: None,
+                    ecdsa_v1: None,
+                    dlc_v1: Some(announce::DlcV1 { nonces }),
+                },
+            )
+            .expect("event can be TLV encoded")
+        }
+
+        #[test]
+        fn announcement_roundtrip() {
+            let public_key: PublicKey = Secp256k1::test_keypair().into();
+            for id in [
+                "/s/EPL/match/2021-08-13/BRE_ARS.vs",
+                "/s/EPL/match/2021-08-13/BRE_ARS.winner",
+                "/time/2021-08-13T00:00:00.occur",
+                "/x/BitMEX/BXBT/2021-10-05T05:00:00.price?n=20",
+                "/x/BitMEX/BXBT/2021-10-05T05:00:00.price_30000",
+            ] {
+                let event = Event {
+                    id: EventId::from_str(id).unwrap(),
+                    expected_outcome_time: Some(
+                        NaiveDateTime::from_str("2021-10-05T05:00:00").unwrap(),
+                    ),
+                };
+                let announcement = tlv_announcement(event.clone());
+                assert_eq!(announcement.oracle_event.encoding(), Encoding::Tlv);
+
+                let oracle_event = announcement
+                    .verify_against_id(&event.id, &public_key)
+                    .expect("TLV announcement should verify");
+                assert_eq!(oracle_event.event, event);
+
+                let bytes = announcement.to_tlv(&public_key).unwrap();
+                // oracle_announcement type 55332
+                assert_eq!(&bytes[..3], &[0xfd, 0xd8, 0x24]);
+                let (decoded, decoded_key) =
+                    RawAnnouncement::<Secp256k1>::from_tlv(&bytes).unwrap();
+                assert_eq!(decoded, announcement);
+                assert_eq!(decoded_key, public_key);
+            }
+        }
+
+        #[test]
+        fn announcement_signature_is_bip340_over_tagged_hash() {
+            let public_key: PublicKey = Secp256k1::test_keypair().into();
+            let announcement = tlv_announcement(Event::from(
+                EventId::from_str("/time/2021-08-13T00:00:00.occur").unwrap(),
+            ));
+            let oracle_event = announcement.oracle_event.as_bytes();
+            let tag = Sha256::digest(b"DLC/oracle/announcement/v0");
+            let hash = Sha256::default()
+                .chain(tag)
+                .chain(tag)
+                .chain(oracle_event)
+                .finalize();
+            assert!(SCHNORR.verify(
+                &public_key.0.to_point(),
+                Message::<Public>::raw(hash.as_ref()),
+                &announcement.signature.0
+            ));
+            assert!(
+                !Secp256k1::verify_announcement_signature(
+                    &public_key,
+                    oracle_event,
+                    &announcement.signature
+                ),
+                "TLV announcements aren't signed like JSON ones"
+            );
+        }
+
+        #[test]
+        fn price_without_digits_cant_be_tlv_encoded() {
+            let event = Event::from(EventId::from_str("/foo/bar.price").unwrap());
+            assert!(RawAnnouncement::<Secp256k1>::create_tlv(
+                event,
+                &Secp256k1::test_keypair(),
+                AnnouncementSchemes::default()
+            )
+            .is_none());
+        }
+
+        #[test]
+        fn attestation_tlv() {
+            let keypair = Secp256k1::test_keypair();
+            let oracle_keys = OracleKeys {
+                olivia_v1: None,
+                ecdsa_v1: None,
+                announcement: keypair.clone().into(),
+                group: Secp256k1,
+                epoch: 0,
+            };
+            for (id, outcome, expected_outcomes) in [
+                (
+                    "/s/EPL/match/2021-08-13/BRE_ARS.vs",
+                    "ARS_win",
+                    vec!["ARS_win"],
+                ),
+                (
+                    "/x/BitMEX/BXBT/2021-10-05T05:00:00.price?n=4",
+                    "5",
+                    vec!["0", "1", "0", "1"],
+                ),
+                (
+                    "/x/BitMEX/BXBT/2021-10-05T05:00:00.price?n=4&signed&exp=-1",
+                    "-0.5",
+                    vec!["-", "0", "1", "0", "1"],
+                ),
+            ] {
+                let event_id = EventId::from_str(id).unwrap();
+                let oracle_event = tlv_announcement(event_id.clone().into())
+                    .verify_against_id(&event_id, &keypair.clone().into())
+                    .unwrap();
+                let outcome = Outcome::try_from_id_and_outcome(event_id.clone(), outcome).unwrap();
+                let signatures = outcome
+                    .dlc_outcomes()
+                    .unwrap()
+                    .iter()
+                    .map(|outcome| {
+                        Secp256k1::dlc_sign_outcome(
+                            &keypair,
+                            Secp256k1::test_nonce_keypair(),
+                            outcome,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let attestation = Attestation::<Secp256k1>::new(
+                    outcome.outcome_string(),
+                    NaiveDateTime::from_timestamp(0, 0),
+                    AttestationSchemes {
+                        olivia_v1: None,
+                        ecdsa_v1: None,
+                        dlc_v1: Some(attest::DlcV1 {
+                            signatures: signatures.clone(),
+                        }),
+                    },
+                );
+                assert_eq!(
+                    attestation.verify_dlc_v1_attestation(&oracle_event, &oracle_keys),
+                    Ok(())
+                );
+                let bytes = attestation.to_tlv(&oracle_event, &oracle_keys).unwrap();
+
+                let mut r = TlvReader::new(&bytes);
+                let mut att = r.read_tlv(ORACLE_ATTESTATION_TYPE).unwrap();
+                assert!(r.is_empty());
+                assert_eq!(att.read_string().unwrap(), id);
+                assert_eq!(
+                    att.read_object::<PublicKey>(),
+                    Some(oracle_keys.announcement.clone())
+                );
+                assert_eq!(att.read_u16().unwrap() as usize, signatures.len());
+                for signature in &signatures {
+                    assert_eq!(&att.read_object::<Signature>().unwrap(), signature);
+                }
+                assert_eq!(att.read_u16().unwrap() as usize, expected_outcomes.len());
+                for (expected, signature) in expected_outcomes.iter().zip(&signatures) {
+                    let outcome = att.read_string().unwrap();
+                    assert_eq!(&outcome, expected);
+                    // each signature is a plain BIP340 signature on the tagged outcome hash
+                    assert!(SCHNORR.verify(
+                        &oracle_keys.announcement.0.to_point(),
+                        Message::<Public>::raw(&dlc_attestation_message(&outcome)[..]),
+                        &signature.0
+                    ));
+                }
+                assert!(att.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn key_history_follows_successions() {
+        use olivia_core::{chrono::NaiveDateTime, KeyHistory, KeySuccession, RawKeySuccession};
+        let keypairs = (1u8..=3)
+            .map(|i| Secp256k1::keypair_from_secret_bytes(&[i; 32]))
+            .collect::<Vec<_>>();
+        let keys = keypairs
+            .iter()
+            .enumerate()
+            .map(|(epoch, keypair)| OracleKeys {
+                olivia_v1: Some(keypair.clone().into()),
+                ecdsa_v1: None,
+                announcement: keypair.clone().into(),
+                group: Secp256k1,
+                epoch: epoch as u32,
+            })
+            .collect::<Vec<_>>();
+        let succession = |epoch: usize, signer: usize| {
+            RawKeySuccession::create(
+                KeySuccession {
+                    keys: keys[epoch].clone(),
+                    time: NaiveDateTime::from_timestamp(0, 0),
+                    namespaces: vec![],
+                },
+                &keypairs[signer],
+            )
+        };
+
+        let history = KeyHistory {
+            genesis: keys[0].clone(),
+            successions: vec![succession(1, 0), succession(2, 1)],
+        };
+        assert_eq!(history.verify(), Some(keys.clone()));
+        assert_eq!(history.current(), Some(keys[2].clone()));
+
+        // each succession has to be signed by the keys it replaces
+        let forged = KeyHistory {
+            genesis: keys[0].clone(),
+            successions: vec![succession(1, 0), succession(2, 0)],
+        };
+        assert_eq!(forged.verify(), None);
+
+        // and can't skip an epoch
+        let skipped = KeyHistory {
+            genesis: keys[0].clone(),
+            successions: vec![succession(2, 0)],
+        };
+        assert_eq!(skipped.verify(), None);
+    }
 }